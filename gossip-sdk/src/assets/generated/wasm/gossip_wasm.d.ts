@@ -178,9 +178,9 @@ export class ReceiveMessageOutput {
    */
   readonly message: Uint8Array;
   /**
-   * Gets the sender's user id (32 bytes).
+   * Gets the sender's user id.
    */
-  readonly user_id: Uint8Array;
+  readonly user_id: UserId;
   /**
    * Gets the message timestamp (milliseconds since Unix epoch).
    */
@@ -234,11 +234,11 @@ export class SessionManagerWrapper {
   /**
    * Discards a peer and all associated session state.
    */
-  peer_discard(peer_id: Uint8Array): void;
+  peer_discard(peer_id: UserId): void;
   /**
    * Sends a message to a peer.
    */
-  send_message(peer_id: Uint8Array, message_contents: Uint8Array): SendMessageOutput | undefined;
+  send_message(peer_id: UserId, message_contents: Uint8Array): SendMessageOutput | undefined;
   /**
    * Serializes and encrypts the session manager into a blob.
    */
@@ -250,7 +250,7 @@ export class SessionManagerWrapper {
   /**
    * Gets the session status for a peer.
    */
-  peer_session_status(peer_id: Uint8Array): SessionStatus;
+  peer_session_status(peer_id: UserId): SessionStatus;
   /**
    * Establishes an outgoing session with a peer.
    *
@@ -346,11 +346,62 @@ export class SessionManagerWrapper {
   refresh(): Array<any>;
   /**
    * Gets the list of all peer IDs.
-   *
-   * JS-owned Uint8Arrays — same detached-view rationale as
-   * `get_message_board_read_keys`.
    */
-  peer_list(): Array<any>;
+  peer_list(): UserId[];
+}
+/**
+ * ID of a user, derived from their public keys by
+ * `UserPublicKeys.derive_id`.
+ *
+ * Peers are identified by `UserId` objects throughout this API, so an ID is
+ * validated once, when built from its 32 bytes, its hex encoding, or its
+ * base58check encoding: the form to show to users, whose checksum catches
+ * typos.
+ *
+ * # Example
+ *
+ * ```javascript
+ * const peer_id = UserId.from_base58(pasted);
+ * if (peer_id.equals(keys.public_keys().derive_id())) {
+ *   // that's us
+ * }
+ * ```
+ */
+export class UserId {
+  free(): void;
+  [Symbol.dispose](): void;
+  /**
+   * Creates a user ID from its 32 bytes.
+   */
+  constructor(bytes: Uint8Array);
+  /**
+   * Parses the hex encoding of a user ID, in either case.
+   */
+  static from_hex(hex: string): UserId;
+  /**
+   * Parses the base58check encoding of a user ID.
+   */
+  static from_base58(encoded: string): UserId;
+  /**
+   * Gets the 32 bytes of the user ID.
+   */
+  to_bytes(): Uint8Array;
+  /**
+   * Lowercase hex encoding of the user ID, as used for map keys.
+   */
+  to_hex(): string;
+  /**
+   * Base58check encoding of the user ID, to show to users.
+   */
+  to_base58(): string;
+  /**
+   * Whether both objects hold the same user ID.
+   */
+  equals(other: UserId): boolean;
+  /**
+   * Same as `to_hex`, so that template strings and map keys use it.
+   */
+  toString(): string;
 }
 /**
  * User keypair containing both public and secret keys.
@@ -394,7 +445,7 @@ export class UserPublicKeys {
   /**
    * Derives a unique user ID from the public keys.
    */
-  derive_id(): Uint8Array;
+  derive_id(): UserId;
   /**
    * Gets the EVM public key bytes (compressed, secp256k1).
    */
@@ -456,6 +507,7 @@ export interface InitOutput {
   readonly __wbg_sendmessageoutput_free: (a: number, b: number) => void;
   readonly __wbg_sessionconfig_free: (a: number, b: number) => void;
   readonly __wbg_sessionmanagerwrapper_free: (a: number, b: number) => void;
+  readonly __wbg_userid_free: (a: number, b: number) => void;
   readonly __wbg_userkeys_free: (a: number, b: number) => void;
   readonly __wbg_userpublickeys_free: (a: number, b: number) => void;
   readonly __wbg_usersecretkeys_free: (a: number, b: number) => void;
@@ -475,7 +527,7 @@ export interface InitOutput {
   readonly receivemessageoutput_acknowledged_seekers: (a: number) => any;
   readonly receivemessageoutput_message: (a: number) => [number, number];
   readonly receivemessageoutput_timestamp: (a: number) => number;
-  readonly receivemessageoutput_user_id: (a: number) => [number, number];
+  readonly receivemessageoutput_user_id: (a: number) => number;
   readonly sendmessageoutput_data: (a: number) => [number, number];
   readonly sendmessageoutput_seeker: (a: number) => [number, number];
   readonly sessionconfig_new: (a: number, b: number, c: number, d: number, e: number, f: number, g: bigint, h: bigint) => number;
@@ -486,17 +538,25 @@ export interface InitOutput {
   readonly sessionmanagerwrapper_from_encrypted_blob: (a: number, b: number, c: number) => [number, number, number];
   readonly sessionmanagerwrapper_get_message_board_read_keys: (a: number) => any;
   readonly sessionmanagerwrapper_new: (a: number) => number;
-  readonly sessionmanagerwrapper_peer_discard: (a: number, b: number, c: number) => [number, number];
+  readonly sessionmanagerwrapper_peer_discard: (a: number, b: number) => void;
   readonly sessionmanagerwrapper_peer_list: (a: number) => any;
-  readonly sessionmanagerwrapper_peer_session_status: (a: number, b: number, c: number) => [number, number, number];
+  readonly sessionmanagerwrapper_peer_session_status: (a: number, b: number) => number;
   readonly sessionmanagerwrapper_refresh: (a: number) => any;
-  readonly sessionmanagerwrapper_send_message: (a: number, b: number, c: number, d: number, e: number) => [number, number, number];
+  readonly sessionmanagerwrapper_send_message: (a: number, b: number, c: number, d: number) => number;
   readonly sessionmanagerwrapper_to_encrypted_blob: (a: number, b: number) => [number, number, number, number];
+  readonly userid_equals: (a: number, b: number) => number;
+  readonly userid_from_base58: (a: number, b: number) => [number, number, number];
+  readonly userid_from_hex: (a: number, b: number) => [number, number, number];
+  readonly userid_new: (a: number, b: number) => [number, number, number];
+  readonly userid_toString: (a: number) => [number, number];
+  readonly userid_to_base58: (a: number) => [number, number];
+  readonly userid_to_bytes: (a: number) => [number, number];
+  readonly userid_to_hex: (a: number) => [number, number];
   readonly userkeys_evm_address: (a: number) => [number, number];
   readonly userkeys_massa_address: (a: number) => [number, number];
   readonly userkeys_public_keys: (a: number) => [number, number, number];
   readonly userkeys_secret_keys: (a: number) => [number, number, number];
-  readonly userpublickeys_derive_id: (a: number) => [number, number];
+  readonly userpublickeys_derive_id: (a: number) => number;
   readonly userpublickeys_dsa_verification_key: (a: number) => [number, number];
   readonly userpublickeys_evm_public_key: (a: number) => [number, number];
   readonly userpublickeys_from_bytes: (a: number, b: number) => [number, number, number];
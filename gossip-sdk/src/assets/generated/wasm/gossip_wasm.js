@@ -529,14 +529,12 @@ export class ReceiveMessageOutput {
         return v1;
     }
     /**
-     * Gets the sender's user id (32 bytes).
-     * @returns {Uint8Array}
+     * Gets the sender's user id.
+     * @returns {UserId}
      */
     get user_id() {
         const ret = wasm.receivemessageoutput_user_id(this.__wbg_ptr);
-        var v1 = getArrayU8FromWasm0(ret[0], ret[1]).slice();
-        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
-        return v1;
+        return UserId.__wrap(ret);
     }
     /**
      * Gets the message timestamp (milliseconds since Unix epoch).
@@ -691,28 +689,26 @@ export class SessionManagerWrapper {
     }
     /**
      * Discards a peer and all associated session state.
-     * @param {Uint8Array} peer_id
+     * @param {UserId} peer_id
      */
     peer_discard(peer_id) {
-        const ptr0 = passArray8ToWasm0(peer_id, wasm.__wbindgen_malloc);
-        const len0 = WASM_VECTOR_LEN;
-        const ret = wasm.sessionmanagerwrapper_peer_discard(this.__wbg_ptr, ptr0, len0);
+        _assertClass(peer_id, UserId);
+        const ret = wasm.sessionmanagerwrapper_peer_discard(this.__wbg_ptr, peer_id.__wbg_ptr);
         if (ret[1]) {
             throw takeFromExternrefTable0(ret[0]);
         }
     }
     /**
      * Sends a message to a peer.
-     * @param {Uint8Array} peer_id
+     * @param {UserId} peer_id
      * @param {Uint8Array} message_contents
      * @returns {SendMessageOutput | undefined}
      */
     send_message(peer_id, message_contents) {
-        const ptr0 = passArray8ToWasm0(peer_id, wasm.__wbindgen_malloc);
+        _assertClass(peer_id, UserId);
+        const ptr0 = passArray8ToWasm0(message_contents, wasm.__wbindgen_malloc);
         const len0 = WASM_VECTOR_LEN;
-        const ptr1 = passArray8ToWasm0(message_contents, wasm.__wbindgen_malloc);
-        const len1 = WASM_VECTOR_LEN;
-        const ret = wasm.sessionmanagerwrapper_send_message(this.__wbg_ptr, ptr0, len0, ptr1, len1);
+        const ret = wasm.sessionmanagerwrapper_send_message(this.__wbg_ptr, peer_id.__wbg_ptr, ptr0, len0);
         if (ret[2]) {
             throw takeFromExternrefTable0(ret[1]);
         }
@@ -751,13 +747,12 @@ export class SessionManagerWrapper {
     }
     /**
      * Gets the session status for a peer.
-     * @param {Uint8Array} peer_id
+     * @param {UserId} peer_id
      * @returns {SessionStatus}
      */
     peer_session_status(peer_id) {
-        const ptr0 = passArray8ToWasm0(peer_id, wasm.__wbindgen_malloc);
-        const len0 = WASM_VECTOR_LEN;
-        const ret = wasm.sessionmanagerwrapper_peer_session_status(this.__wbg_ptr, ptr0, len0);
+        _assertClass(peer_id, UserId);
+        const ret = wasm.sessionmanagerwrapper_peer_session_status(this.__wbg_ptr, peer_id.__wbg_ptr);
         if (ret[2]) {
             throw takeFromExternrefTable0(ret[1]);
         }
@@ -912,10 +907,7 @@ export class SessionManagerWrapper {
     }
     /**
      * Gets the list of all peer IDs.
-     *
-     * JS-owned Uint8Arrays — same detached-view rationale as
-     * `get_message_board_read_keys`.
-     * @returns {Array<any>}
+     * @returns {UserId[]}
      */
     peer_list() {
         const ret = wasm.sessionmanagerwrapper_peer_list(this.__wbg_ptr);
@@ -924,6 +916,162 @@ export class SessionManagerWrapper {
 }
 if (Symbol.dispose) SessionManagerWrapper.prototype[Symbol.dispose] = SessionManagerWrapper.prototype.free;
 
+const UserIdFinalization = (typeof FinalizationRegistry === 'undefined')
+    ? { register: () => {}, unregister: () => {} }
+    : new FinalizationRegistry(ptr => wasm.__wbg_userid_free(ptr >>> 0, 1));
+/**
+ * ID of a user, derived from their public keys by
+ * `UserPublicKeys.derive_id`.
+ *
+ * Peers are identified by `UserId` objects throughout this API, so an ID is
+ * validated once, when built from its 32 bytes, its hex encoding, or its
+ * base58check encoding: the form to show to users, whose checksum catches
+ * typos.
+ *
+ * # Example
+ *
+ * ```javascript
+ * const peer_id = UserId.from_base58(pasted);
+ * if (peer_id.equals(keys.public_keys().derive_id())) {
+ *   // that's us
+ * }
+ * ```
+ */
+export class UserId {
+
+    static __wrap(ptr) {
+        ptr = ptr >>> 0;
+        const obj = Object.create(UserId.prototype);
+        obj.__wbg_ptr = ptr;
+        UserIdFinalization.register(obj, obj.__wbg_ptr, obj);
+        return obj;
+    }
+
+    __destroy_into_raw() {
+        const ptr = this.__wbg_ptr;
+        this.__wbg_ptr = 0;
+        UserIdFinalization.unregister(this);
+        return ptr;
+    }
+
+    free() {
+        const ptr = this.__destroy_into_raw();
+        wasm.__wbg_userid_free(ptr, 0);
+    }
+    /**
+     * Creates a user ID from its 32 bytes.
+     * @param {Uint8Array} bytes
+     */
+    constructor(bytes) {
+        const ptr0 = passArray8ToWasm0(bytes, wasm.__wbindgen_malloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.userid_new(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        this.__wbg_ptr = ret[0] >>> 0;
+        UserIdFinalization.register(this, this.__wbg_ptr, this);
+        return this;
+    }
+    /**
+     * Parses the hex encoding of a user ID, in either case.
+     * @param {string} hex
+     * @returns {UserId}
+     */
+    static from_hex(hex) {
+        const ptr0 = passStringToWasm0(hex, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.userid_from_hex(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return UserId.__wrap(ret[0]);
+    }
+    /**
+     * Parses the base58check encoding of a user ID.
+     * @param {string} encoded
+     * @returns {UserId}
+     */
+    static from_base58(encoded) {
+        const ptr0 = passStringToWasm0(encoded, wasm.__wbindgen_malloc, wasm.__wbindgen_realloc);
+        const len0 = WASM_VECTOR_LEN;
+        const ret = wasm.userid_from_base58(ptr0, len0);
+        if (ret[2]) {
+            throw takeFromExternrefTable0(ret[1]);
+        }
+        return UserId.__wrap(ret[0]);
+    }
+    /**
+     * Gets the 32 bytes of the user ID.
+     * @returns {Uint8Array}
+     */
+    to_bytes() {
+        const ret = wasm.userid_to_bytes(this.__wbg_ptr);
+        var v1 = getArrayU8FromWasm0(ret[0], ret[1]).slice();
+        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
+        return v1;
+    }
+    /**
+     * Lowercase hex encoding of the user ID, as used for map keys.
+     * @returns {string}
+     */
+    to_hex() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.userid_to_hex(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+    /**
+     * Base58check encoding of the user ID, to show to users.
+     * @returns {string}
+     */
+    to_base58() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.userid_to_base58(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+    /**
+     * Whether both objects hold the same user ID.
+     * @param {UserId} other
+     * @returns {boolean}
+     */
+    equals(other) {
+        _assertClass(other, UserId);
+        const ret = wasm.userid_equals(this.__wbg_ptr, other.__wbg_ptr);
+        return ret !== 0;
+    }
+    /**
+     * Same as `to_hex`, so that template strings and map keys use it.
+     * @returns {string}
+     */
+    toString() {
+        let deferred1_0;
+        let deferred1_1;
+        try {
+            const ret = wasm.userid_toString(this.__wbg_ptr);
+            deferred1_0 = ret[0];
+            deferred1_1 = ret[1];
+            return getStringFromWasm0(ret[0], ret[1]);
+        } finally {
+            wasm.__wbindgen_free(deferred1_0, deferred1_1, 1);
+        }
+    }
+}
+if (Symbol.dispose) UserId.prototype[Symbol.dispose] = UserId.prototype.free;
+
 const UserKeysFinalization = (typeof FinalizationRegistry === 'undefined')
     ? { register: () => {}, unregister: () => {} }
     : new FinalizationRegistry(ptr => wasm.__wbg_userkeys_free(ptr >>> 0, 1));
@@ -1104,13 +1252,11 @@ export class UserPublicKeys {
     }
     /**
      * Derives a unique user ID from the public keys.
-     * @returns {Uint8Array}
+     * @returns {UserId}
      */
     derive_id() {
         const ret = wasm.userpublickeys_derive_id(this.__wbg_ptr);
-        var v1 = getArrayU8FromWasm0(ret[0], ret[1]).slice();
-        wasm.__wbindgen_free(ret[0], ret[1] * 1, 1);
-        return v1;
+        return UserId.__wrap(ret);
     }
 }
 if (Symbol.dispose) UserPublicKeys.prototype[Symbol.dispose] = UserPublicKeys.prototype.free;
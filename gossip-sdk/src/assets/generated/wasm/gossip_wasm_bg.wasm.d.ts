@@ -8,6 +8,7 @@ export const __wbg_receivemessageoutput_free: (a: number, b: number) => void;
 export const __wbg_sendmessageoutput_free: (a: number, b: number) => void;
 export const __wbg_sessionconfig_free: (a: number, b: number) => void;
 export const __wbg_sessionmanagerwrapper_free: (a: number, b: number) => void;
+export const __wbg_userid_free: (a: number, b: number) => void;
 export const __wbg_userkeys_free: (a: number, b: number) => void;
 export const __wbg_userpublickeys_free: (a: number, b: number) => void;
 export const __wbg_usersecretkeys_free: (a: number, b: number) => void;
@@ -27,7 +28,7 @@ export const nonce_to_bytes: (a: number) => [number, number];
 export const receivemessageoutput_acknowledged_seekers: (a: number) => any;
 export const receivemessageoutput_message: (a: number) => [number, number];
 export const receivemessageoutput_timestamp: (a: number) => number;
-export const receivemessageoutput_user_id: (a: number) => [number, number];
+export const receivemessageoutput_user_id: (a: number) => number;
 export const sendmessageoutput_data: (a: number) => [number, number];
 export const sendmessageoutput_seeker: (a: number) => [number, number];
 export const sessionconfig_new: (a: number, b: number, c: number, d: number, e: number, f: number, g: bigint, h: bigint) => number;
@@ -38,17 +39,25 @@ export const sessionmanagerwrapper_feed_incoming_message_board_read: (a: number,
 export const sessionmanagerwrapper_from_encrypted_blob: (a: number, b: number, c: number) => [number, number, number];
 export const sessionmanagerwrapper_get_message_board_read_keys: (a: number) => any;
 export const sessionmanagerwrapper_new: (a: number) => number;
-export const sessionmanagerwrapper_peer_discard: (a: number, b: number, c: number) => [number, number];
+export const sessionmanagerwrapper_peer_discard: (a: number, b: number) => void;
 export const sessionmanagerwrapper_peer_list: (a: number) => any;
-export const sessionmanagerwrapper_peer_session_status: (a: number, b: number, c: number) => [number, number, number];
+export const sessionmanagerwrapper_peer_session_status: (a: number, b: number) => number;
 export const sessionmanagerwrapper_refresh: (a: number) => any;
-export const sessionmanagerwrapper_send_message: (a: number, b: number, c: number, d: number, e: number) => [number, number, number];
+export const sessionmanagerwrapper_send_message: (a: number, b: number, c: number, d: number) => number;
 export const sessionmanagerwrapper_to_encrypted_blob: (a: number, b: number) => [number, number, number, number];
+export const userid_equals: (a: number, b: number) => number;
+export const userid_from_base58: (a: number, b: number) => [number, number, number];
+export const userid_from_hex: (a: number, b: number) => [number, number, number];
+export const userid_new: (a: number, b: number) => [number, number, number];
+export const userid_toString: (a: number) => [number, number];
+export const userid_to_base58: (a: number) => [number, number];
+export const userid_to_bytes: (a: number) => [number, number];
+export const userid_to_hex: (a: number) => [number, number];
 export const userkeys_evm_address: (a: number) => [number, number];
 export const userkeys_massa_address: (a: number) => [number, number];
 export const userkeys_public_keys: (a: number) => [number, number, number];
 export const userkeys_secret_keys: (a: number) => [number, number, number];
-export const userpublickeys_derive_id: (a: number) => [number, number];
+export const userpublickeys_derive_id: (a: number) => number;
 export const userpublickeys_dsa_verification_key: (a: number) => [number, number];
 export const userpublickeys_evm_public_key: (a: number) => [number, number];
 export const userpublickeys_from_bytes: (a: number, b: number) => [number, number, number];
@@ -16,7 +16,11 @@ import { toDiscussion } from '../utils/discussions.js';
 import { decodeUserId, encodeUserId } from '../utils/userId.js';
 import { IMessageProtocol } from '../api/messageProtocol/index.js';
 import { UserPublicKeys, SessionStatus } from '../wasm/bindings.js';
-import { SessionModule, sessionStatusToString } from '../wasm/session.js';
+import {
+  SessionModule,
+  sessionStatusToString,
+  userIdBytes,
+} from '../wasm/session.js';
 import { Logger } from '../utils/logs.js';
 import { BulletinItem } from '../api/messageProtocol/types.js';
 import { SdkConfig, defaultSdkConfig } from '../config/sdk.js';
@@ -115,7 +119,9 @@ export class AnnouncementService {
   ): Promise<Result<Uint8Array, Error>> {
     const log = logger.forMethod('establishSession');
 
-    const contactUserId = encodeUserId(contactPublicKeys.derive_id());
+    const contactUserId = encodeUserId(
+      userIdBytes(contactPublicKeys.derive_id())
+    );
 
     // CRITICAL: await to ensure session state is persisted before returning
     const announcement = await this.session.establishOutgoingSession(
@@ -467,7 +473,7 @@ export class AnnouncementService {
     const { username, message } = decodeAnnouncementPayload(result.user_data);
 
     const announcerPkeys = result.announcer_public_keys;
-    const contactUserIdRaw = announcerPkeys.derive_id();
+    const contactUserIdRaw = userIdBytes(announcerPkeys.derive_id());
     const contactUserId = encodeUserId(contactUserIdRaw);
 
     const sessionStatus = this.session.peerSessionStatus(contactUserIdRaw);
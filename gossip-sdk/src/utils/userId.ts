@@ -6,7 +6,7 @@
  */
 
 import { bech32 } from '@scure/base';
-import { generateUserKeys } from '../wasm/index.js';
+import { generateUserKeys, userIdBytes } from '../wasm/index.js';
 
 const GOSSIP_PREFIX = 'gossip';
 const USER_ID_BYTE_LENGTH = 32;
@@ -109,6 +109,6 @@ export function formatUserId(
  */
 export async function generate(password?: string): Promise<string> {
   const identity = await generateUserKeys(password || '');
-  const userId = identity.public_keys().derive_id();
+  const userId = userIdBytes(identity.public_keys().derive_id());
   return encodeUserId(userId);
 }
@@ -6,7 +6,11 @@
  */
 
 // Export modules
-export { SessionModule, sessionStatusToString } from './session.js';
+export {
+  SessionModule,
+  sessionStatusToString,
+  userIdBytes,
+} from './session.js';
 
 // Export initialization functions
 export {
@@ -26,4 +30,5 @@ export {
   SendMessageOutput,
   ReceiveMessageOutput,
   AnnouncementResult,
+  UserId,
 } from './bindings.js';
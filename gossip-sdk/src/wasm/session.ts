@@ -9,6 +9,10 @@
  * subsequent WASM allocation that grows linear memory detaches the original
  * `wasm.memory.buffer` and any Uint8Array view sitting on top of it — leading
  * to "detached ArrayBuffer" failures on the next read.
+ *
+ * Peer ids cross the boundary as WASM `UserId` objects: `withUserId` wraps
+ * the bytes callers pass in, and `userIdBytes` unwraps (and frees) the ones
+ * WASM returns.
  */
 
 import {
@@ -21,12 +25,37 @@ import {
   SessionConfig,
   AnnouncementResult,
   UserKeys,
+  UserId,
 } from './bindings.js';
 import { encodeUserId } from '../utils/userId.js';
 
 const copyOut = (a: Uint8Array): Uint8Array => new Uint8Array(a);
 const copyOutMany = (xs: Uint8Array[]): Uint8Array[] => xs.map(copyOut);
 
+/**
+ * Takes the 32 bytes out of a WASM `UserId` and frees it. The SDK keeps
+ * peer ids as bytes (Bech32-encoded for storage), so every `UserId` returned
+ * from WASM is converted here.
+ */
+export const userIdBytes = (id: UserId): Uint8Array => {
+  const bytes = id.to_bytes();
+  id.free();
+  return bytes;
+};
+
+/**
+ * Calls into WASM with the peer id as the `UserId` it expects, freeing the
+ * wrapper afterwards. Throws if `peerId` is not 32 bytes long.
+ */
+const withUserId = <T>(peerId: Uint8Array, f: (id: UserId) => T): T => {
+  const id = new UserId(peerId);
+  try {
+    return f(id);
+  } finally {
+    id.free();
+  }
+};
+
 export interface ReceivedMessage {
   message: Uint8Array;
   user_id: Uint8Array;
@@ -49,7 +78,7 @@ export class SessionModule {
   ) {
     this.ourPk = userKeys.public_keys();
     this.ourSk = userKeys.secret_keys();
-    this.userId = copyOut(this.ourPk.derive_id());
+    this.userId = userIdBytes(this.ourPk.derive_id());
     this.userIdEncoded = encodeUserId(this.userId);
 
     const sessionConfig = config ?? SessionConfig.new_default();
@@ -213,7 +242,7 @@ export class SessionModule {
     // yields) can grow linear memory and detach views.
     const snapshot: ReceivedMessage = {
       message: copyOut(result.message),
-      user_id: copyOut(result.user_id),
+      user_id: userIdBytes(result.user_id),
       timestamp: result.timestamp,
       acknowledged_seekers: copyOutMany(
         result.acknowledged_seekers as Uint8Array[]
@@ -238,7 +267,10 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    const result = this.sessionManager.send_message(peerId, message);
+    const sessionManager = this.sessionManager;
+    const result = withUserId(peerId, id =>
+      sessionManager.send_message(id, message)
+    );
     // CRITICAL: Persist session state BEFORE returning
     // This ensures state is saved before the encrypted message goes on the network
     await this.persistIfNeeded();
@@ -253,7 +285,7 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    return copyOutMany(this.sessionManager.peer_list());
+    return this.sessionManager.peer_list().map(userIdBytes);
   }
 
   /**
@@ -264,7 +296,8 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    return this.sessionManager.peer_session_status(peerId);
+    const sessionManager = this.sessionManager;
+    return withUserId(peerId, id => sessionManager.peer_session_status(id));
   }
 
   /**
@@ -275,7 +308,8 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    this.sessionManager.peer_discard(peerId);
+    const sessionManager = this.sessionManager;
+    withUserId(peerId, id => sessionManager.peer_discard(id));
     await this.persistIfNeeded();
   }
 
@@ -287,7 +321,9 @@ export class SessionModule {
       throw new Error('Session manager is not initialized');
     }
 
-    const result = copyOutMany(this.sessionManager.refresh());
+    const report = this.sessionManager.refresh();
+    const result = (report.keep_alive_needed as UserId[]).map(userIdBytes);
+    report.free();
     await this.persistIfNeeded();
     return result;
   }
@@ -0,0 +1,69 @@
+#!/usr/bin/env bash
+# Regenerate wasm/sessions/tests/fixtures/session_manager_v0.bin, the
+# untagged SessionManager blob written before state format versioning, from
+# a build of the sessions crate at that revision: Alice's manager with an
+# active session with Bob, encrypted with the blob_compat fixture key.
+#
+# Usage: bash scripts/generate-session-fixture-v0.sh [revision]
+
+set -Eeuo pipefail
+
+SCRIPT_DIR="$(cd -P "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+ROOT_DIR="$(cd -P "$SCRIPT_DIR/.." && pwd)"
+REVISION="${1:-aea3cd8}"
+FIXTURE="$ROOT_DIR/wasm/sessions/tests/fixtures/session_manager_v0.bin"
+
+WORKTREE="$(mktemp -d)"
+cleanup() {
+  git -C "$ROOT_DIR" worktree remove --force "$WORKTREE"
+}
+trap cleanup EXIT
+
+git -C "$ROOT_DIR" worktree add --detach "$WORKTREE" "$REVISION"
+mkdir -p "$(dirname "$FIXTURE")"
+
+mkdir -p "$WORKTREE/wasm/sessions/tests"
+cat >"$WORKTREE/wasm/sessions/tests/generate_fixture.rs" <<'EOF'
+use auth::{StaticRootSecret, derive_keys_from_static_root_secret};
+use sessions::{SessionManager, SessionManagerConfig};
+
+#[test]
+fn generate_fixture() {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(|| {
+            let keypair = |passphrase: &[u8]| {
+                derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(passphrase))
+            };
+            let config = || SessionManagerConfig {
+                max_incoming_announcement_age_millis: u128::MAX,
+                max_incoming_announcement_future_millis: 60_000,
+                max_incoming_message_age_millis: u128::MAX,
+                max_incoming_message_future_millis: 60_000,
+                max_session_inactivity_millis: u128::MAX,
+                keep_alive_interval_millis: 60_000,
+                max_session_lag_length: 100,
+                max_keep_alive_peer_lag_length: 8,
+            };
+            let (alice_pk, alice_sk) = keypair(b"alice");
+            let (bob_pk, bob_sk) = keypair(b"bob");
+            let mut alice_manager = SessionManager::new(config());
+            let mut bob_manager = SessionManager::new(config());
+
+            let to_bob = alice_manager.establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![]);
+            let to_alice = bob_manager.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![]);
+            bob_manager.feed_incoming_announcement(&to_bob, &bob_pk, &bob_sk);
+            alice_manager.feed_incoming_announcement(&to_alice, &alice_pk, &alice_sk);
+
+            let key = crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE]);
+            let blob = alice_manager.to_encrypted_blob(&key).unwrap();
+            std::fs::write(std::env::var("FIXTURE").unwrap(), blob).unwrap();
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+EOF
+
+(cd "$WORKTREE/wasm" && FIXTURE="$FIXTURE" cargo test -p sessions --test generate_fixture)
+echo "Wrote $FIXTURE"
@@ -0,0 +1,204 @@
+//! Password-encrypted backups of secret keys.
+//!
+//! `UserSecretKeys::export_encrypted` wraps the secret keys in an envelope
+//! that can be stored or transferred as is:
+//!
+//! ```text
+//! magic "GSKS" (4) | version (1) | salt (16) | nonce (16) | ciphertext
+//! ```
+//!
+//! The encryption key is derived from the password and the random salt with
+//! Argon2id, and the ciphertext is the AEAD encryption of the serialized keys
+//! with the header as associated data. The version fixes the KDF parameters
+//! and the plaintext format, so envelopes written today stay readable when
+//! they change.
+
+use crate::types::UserSecretKeys;
+use zeroize::Zeroizing;
+
+/// Current version of the keystore envelope.
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// First bytes of a keystore envelope.
+const MAGIC: [u8; 4] = *b"GSKS";
+
+/// Size of the random salt of the password KDF.
+const SALT_SIZE: usize = 16;
+
+/// Size of the header: magic, version and salt.
+const HEADER_SIZE: usize = MAGIC.len() + 1 + SALT_SIZE;
+
+/// Error returned when a keystore envelope cannot be opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeystoreError {
+    /// The bytes are not a keystore envelope
+    Malformed,
+    /// The envelope was written by a newer version
+    UnsupportedVersion(u8),
+    /// The password is wrong or the envelope was tampered with
+    Decryption,
+}
+
+impl core::fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed keystore"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported keystore version {version}")
+            }
+            Self::Decryption => write!(f, "wrong password or corrupted keystore"),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl KeystoreError {
+    /// Stable, machine-discriminable code for this error variant, for use at
+    /// the FFI boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Malformed => "MALFORMED",
+            Self::UnsupportedVersion(_) => "UNSUPPORTED_VERSION",
+            Self::Decryption => "DECRYPTION",
+        }
+    }
+}
+
+/// Derives the envelope key from the password and salt.
+fn derive_key(password: &[u8], salt: &[u8]) -> crypto_aead::Key {
+    let mut key_bytes = Zeroizing::new([0u8; crypto_aead::KEY_SIZE]);
+    crypto_password_kdf::derive(password, salt, key_bytes.as_mut());
+    crypto_aead::Key::from_ref(&key_bytes)
+}
+
+impl UserSecretKeys {
+    /// Encrypts the secret keys with a password into a keystore envelope.
+    ///
+    /// Every call uses a fresh salt and nonce, so exporting the same keys
+    /// twice yields different envelopes. Open it with
+    /// [`import_encrypted`](Self::import_encrypted).
+    ///
+    /// # Panics
+    ///
+    /// Panics if serialization fails (should never happen in practice).
+    #[must_use]
+    pub fn export_encrypted(&self, password: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; HEADER_SIZE];
+        header[..MAGIC.len()].copy_from_slice(&MAGIC);
+        header[MAGIC.len()] = KEYSTORE_VERSION;
+        crypto_rng::fill_buffer(&mut header[MAGIC.len() + 1..]);
+        let key = derive_key(password, &header[MAGIC.len() + 1..]);
+
+        let mut nonce_bytes = [0u8; crypto_aead::NONCE_SIZE];
+        crypto_rng::fill_buffer(&mut nonce_bytes);
+        let nonce = crypto_aead::Nonce::from(nonce_bytes);
+
+        let serialized = Zeroizing::new(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Failed to serialize UserSecretKeys"),
+        );
+        let ciphertext = crypto_aead::encrypt(&key, &nonce, &serialized, &header);
+        [&header[..], nonce.as_bytes(), &ciphertext].concat()
+    }
+
+    /// Decrypts secret keys from a keystore envelope written by
+    /// [`export_encrypted`](Self::export_encrypted).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KeystoreError::Decryption`] if the password is wrong or the
+    /// envelope was modified.
+    pub fn import_encrypted(envelope: &[u8], password: &[u8]) -> Result<Self, KeystoreError> {
+        if envelope.len() < HEADER_SIZE + crypto_aead::NONCE_SIZE
+            || envelope[..MAGIC.len()] != MAGIC
+        {
+            return Err(KeystoreError::Malformed);
+        }
+        let version = envelope[MAGIC.len()];
+        if version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(version));
+        }
+        let (header, rest) = envelope.split_at(HEADER_SIZE);
+        let (nonce_bytes, ciphertext) = rest.split_at(crypto_aead::NONCE_SIZE);
+        let key = derive_key(password, &header[MAGIC.len() + 1..]);
+        let nonce = crypto_aead::Nonce::from(
+            <[u8; crypto_aead::NONCE_SIZE]>::try_from(nonce_bytes)
+                .map_err(|_| KeystoreError::Malformed)?,
+        );
+
+        let serialized = Zeroizing::new(
+            crypto_aead::decrypt(&key, &nonce, ciphertext, header)
+                .ok_or(KeystoreError::Decryption)?,
+        );
+        bincode::serde::decode_from_slice(&serialized, bincode::config::standard())
+            .map(|(secret_keys, _)| secret_keys)
+            .map_err(|_| KeystoreError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StaticRootSecret, derive_keys_from_static_root_secret};
+
+    fn secret_keys() -> UserSecretKeys {
+        let root_secret = StaticRootSecret::from_passphrase(b"keystore test");
+        derive_keys_from_static_root_secret(&root_secret).1
+    }
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let secret_keys = secret_keys();
+        let envelope = secret_keys.export_encrypted(b"password");
+        assert_eq!(&envelope[..4], b"GSKS");
+        assert_eq!(envelope[4], KEYSTORE_VERSION);
+
+        let imported = UserSecretKeys::import_encrypted(&envelope, b"password").unwrap();
+        assert_eq!(
+            imported.dsa_signing_key.as_bytes(),
+            secret_keys.dsa_signing_key.as_bytes()
+        );
+        assert_eq!(
+            imported.kem_secret_key.as_bytes(),
+            secret_keys.kem_secret_key.as_bytes()
+        );
+        assert_eq!(
+            imported.massa_keypair.to_bytes(),
+            secret_keys.massa_keypair.to_bytes()
+        );
+        assert_eq!(imported.evm_secret_key, secret_keys.evm_secret_key);
+
+        // a fresh salt and nonce every time
+        assert_ne!(envelope, secret_keys.export_encrypted(b"password"));
+    }
+
+    #[test]
+    fn test_keystore_rejects_bad_input() {
+        let envelope = secret_keys().export_encrypted(b"password");
+
+        assert!(matches!(
+            UserSecretKeys::import_encrypted(&envelope, b"wrong"),
+            Err(KeystoreError::Decryption)
+        ));
+
+        let mut tampered = envelope.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            UserSecretKeys::import_encrypted(&tampered, b"password"),
+            Err(KeystoreError::Decryption)
+        ));
+
+        let mut newer = envelope.clone();
+        newer[4] = KEYSTORE_VERSION + 1;
+        assert!(matches!(
+            UserSecretKeys::import_encrypted(&newer, b"password"),
+            Err(KeystoreError::UnsupportedVersion(_))
+        ));
+
+        assert!(matches!(
+            UserSecretKeys::import_encrypted(&envelope[..20], b"password"),
+            Err(KeystoreError::Malformed)
+        ));
+    }
+}
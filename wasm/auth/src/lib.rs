@@ -11,6 +11,23 @@
 //! 2. The `StaticRootSecret` is used to deterministically derive all user keys
 //! 3. A unique `UserId` is derived from the public keys
 //!
+//! `derive_keys_for_purpose` derives separate identities from the same root secret along
+//! derivation paths such as `gossip/chat/0`, so one passphrase can serve several communities
+//! without linking them.
+//!
+//! # Key Backups
+//!
+//! `UserSecretKeys::export_encrypted` seals the secret keys under a password (Argon2id and AEAD,
+//! behind a versioned header), and `UserSecretKeys::import_encrypted` opens the envelope again.
+//!
+//! `generate_mnemonic` creates a 24-word BIP39 phrase and `root_secret_from_mnemonic` turns it
+//! back into a `StaticRootSecret`, so that a whole identity can be written down on paper.
+//!
+//! # Passkeys
+//!
+//! `encryption_key_from_prf_output` derives AEAD keys from the output of a WebAuthn PRF, so that
+//! local state can be unlocked with a passkey; each purpose label gets an unrelated key.
+//!
 //! # Authentication Blob
 //!
 //! The `AuthBlob` type provides single-round sender authentication for Agraphon announcements,
@@ -22,10 +39,16 @@
 //! securely erased from memory when no longer needed.
 
 mod auth_blob;
+mod keystore;
+mod mnemonic;
+mod prf;
 mod types;
 
 pub use auth_blob::AuthBlob;
+pub use keystore::{KEYSTORE_VERSION, KeystoreError};
+pub use mnemonic::{MNEMONIC_WORD_COUNT, generate_mnemonic, root_secret_from_mnemonic};
+pub use prf::{MIN_PRF_OUTPUT_SIZE, encryption_key_from_prf_output};
 pub use types::{
     STATIC_ROOT_SECRET_SIZE, StaticRootSecret, USER_ID_SIZE, UserId, UserPublicKeys,
-    UserSecretKeys, derive_keys_from_static_root_secret,
+    UserSecretKeys, derive_keys_for_purpose, derive_keys_from_static_root_secret,
 };
@@ -0,0 +1,84 @@
+//! BIP39 mnemonic backups of identities.
+//!
+//! A 24-word English BIP39 mnemonic encodes 256 bits of entropy, protected
+//! by the BIP39 checksum so that a mistyped word is caught before any key is
+//! derived. The entropy is fed to the KDF to get the [`StaticRootSecret`] of
+//! the identity: writing the words down on paper backs up every key derived
+//! from it, post-quantum ones included.
+//!
+//! Unlike a passphrase, the mnemonic is random, so no password KDF is needed
+//! to slow down guessing it.
+
+use zeroize::Zeroizing;
+
+use crate::{STATIC_ROOT_SECRET_SIZE, StaticRootSecret};
+
+/// Number of words of an identity mnemonic.
+pub const MNEMONIC_WORD_COUNT: usize = 24;
+
+/// Bytes of entropy encoded by an identity mnemonic.
+const MNEMONIC_ENTROPY_SIZE: usize = 32;
+
+/// Generates a fresh 24-word English mnemonic.
+#[must_use]
+pub fn generate_mnemonic() -> Zeroizing<String> {
+    let mut entropy = Zeroizing::new([0u8; MNEMONIC_ENTROPY_SIZE]);
+    crypto_rng::fill_buffer(entropy.as_mut_slice());
+    let mnemonic = bip39::Mnemonic::from_entropy(entropy.as_slice())
+        .expect("32 bytes is a valid BIP39 entropy size");
+    Zeroizing::new(mnemonic.to_string())
+}
+
+/// Derives the root secret backed up by a mnemonic.
+///
+/// Words may be separated by any whitespace. Returns `None` if the phrase is
+/// not 24 words of the English list with a valid checksum.
+#[must_use]
+pub fn root_secret_from_mnemonic(phrase: &str) -> Option<StaticRootSecret> {
+    let words = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" "));
+    let mnemonic = bip39::Mnemonic::parse_in(bip39::Language::English, words.as_str()).ok()?;
+    if mnemonic.word_count() != MNEMONIC_WORD_COUNT {
+        return None;
+    }
+    let entropy = Zeroizing::new(mnemonic.to_entropy());
+    let mut kdf = crypto_kdf::Extract::new(b"auth.mnemonic.kdf.salt----------");
+    kdf.input_item(&entropy);
+    let mut root_secret = Zeroizing::new([0u8; STATIC_ROOT_SECRET_SIZE]);
+    kdf.finalize()
+        .expand(b"auth.mnemonic.kdf.root_secret", root_secret.as_mut_slice());
+    Some(StaticRootSecret::from_bytes(*root_secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mnemonic_roundtrip() {
+        let phrase = generate_mnemonic();
+        assert_eq!(phrase.split(' ').count(), MNEMONIC_WORD_COUNT);
+
+        let root_secret = root_secret_from_mnemonic(&phrase).unwrap();
+        let spaced = format!("  {}\n", phrase.replace(' ', "\n  "));
+        let recovered = root_secret_from_mnemonic(&spaced).unwrap();
+        assert_eq!(root_secret.as_slice(), recovered.as_slice());
+
+        let other = root_secret_from_mnemonic(&generate_mnemonic()).unwrap();
+        assert_ne!(root_secret.as_slice(), other.as_slice());
+    }
+
+    #[test]
+    fn test_mnemonic_rejects_invalid_phrases() {
+        // all-zero entropy: "abandon" 23 times, then the checksum word "art"
+        let valid = format!("{}art", "abandon ".repeat(23));
+        assert!(root_secret_from_mnemonic(&valid).is_some());
+
+        let bad_checksum = format!("{}abandon", "abandon ".repeat(23));
+        assert!(root_secret_from_mnemonic(&bad_checksum).is_none());
+        let unknown_word = format!("{}gossip", "abandon ".repeat(23));
+        assert!(root_secret_from_mnemonic(&unknown_word).is_none());
+        // valid BIP39, but 12 words
+        let short = format!("{}about", "abandon ".repeat(11));
+        assert!(root_secret_from_mnemonic(&short).is_none());
+    }
+}
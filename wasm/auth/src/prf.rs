@@ -0,0 +1,58 @@
+//! Encryption keys derived from the output of a WebAuthn PRF.
+//!
+//! The `prf` extension of WebAuthn makes a passkey evaluate a pseudo-random
+//! function over a salt given by the relying party, returning a 32-byte
+//! secret that only that passkey can reproduce. This module turns it into
+//! AEAD keys, so storage can be unlocked with a passkey instead of a
+//! password.
+//!
+//! The PRF output is fed to the KDF along with a purpose label, and each
+//! purpose (e.g. `storage`, `sessions`) gets an unrelated key: a key leaked
+//! for one purpose reveals neither the others nor the PRF output. Use a fixed
+//! PRF salt per application and tell purposes apart with the label; the
+//! passkey itself never leaves the authenticator, so losing it loses the key,
+//! and a recovery path (password or mnemonic) is still needed.
+
+use zeroize::Zeroizing;
+
+/// Minimum size of a PRF output: WebAuthn PRF outputs are 32 bytes.
+pub const MIN_PRF_OUTPUT_SIZE: usize = 32;
+
+/// Derives the AEAD key of `purpose` from the output of a WebAuthn PRF.
+///
+/// Returns `None` if `prf_output` is shorter than [`MIN_PRF_OUTPUT_SIZE`].
+#[must_use]
+pub fn encryption_key_from_prf_output(
+    prf_output: &[u8],
+    purpose: &str,
+) -> Option<crypto_aead::Key> {
+    if prf_output.len() < MIN_PRF_OUTPUT_SIZE {
+        return None;
+    }
+    let mut kdf = crypto_kdf::Extract::new(b"auth.prf.kdf.salt---------------");
+    kdf.input_item(prf_output);
+    kdf.input_item(purpose.as_bytes());
+    let mut key_bytes = Zeroizing::new([0u8; crypto_aead::KEY_SIZE]);
+    kdf.finalize()
+        .expand(b"auth.prf.kdf.encryption_key", key_bytes.as_mut_slice());
+    Some(crypto_aead::Key::from_ref(&key_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encryption_key_from_prf_output() {
+        let prf_output = [7u8; MIN_PRF_OUTPUT_SIZE];
+        let storage = encryption_key_from_prf_output(&prf_output, "storage").unwrap();
+        let again = encryption_key_from_prf_output(&prf_output, "storage").unwrap();
+        let sessions = encryption_key_from_prf_output(&prf_output, "sessions").unwrap();
+        let other_passkey = encryption_key_from_prf_output(&[8u8; 32], "storage").unwrap();
+
+        assert_eq!(storage.as_bytes(), again.as_bytes());
+        assert_ne!(storage.as_bytes(), sessions.as_bytes());
+        assert_ne!(storage.as_bytes(), other_passkey.as_bytes());
+        assert!(encryption_key_from_prf_output(&prf_output[1..], "storage").is_none());
+    }
+}
@@ -34,6 +34,52 @@ impl UserId {
     pub const fn from_bytes(bytes: [u8; USER_ID_SIZE]) -> Self {
         Self(bytes)
     }
+
+    /// Creates a `UserId` from a slice, or `None` if it is not
+    /// [`USER_ID_SIZE`] bytes long.
+    #[must_use]
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(Self)
+    }
+
+    /// Returns the lowercase hex encoding of the user ID.
+    #[must_use]
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Parses the hex encoding of a user ID, in either case. Returns `None`
+    /// if it is not `2 * USER_ID_SIZE` hex digits.
+    #[must_use]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 2 * USER_ID_SIZE || !hex.is_ascii() {
+            return None;
+        }
+        let mut bytes = [0u8; USER_ID_SIZE];
+        for (byte, digits) in bytes.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            // ASCII was checked above, so the digits are valid UTF-8
+            let digits = std::str::from_utf8(digits).ok()?;
+            *byte = u8::from_str_radix(digits, 16).ok()?;
+        }
+        Some(Self(bytes))
+    }
+
+    /// Returns the base58check encoding of the user ID, meant to be shown to
+    /// and typed by users: its checksum makes [`from_base58`](Self::from_base58)
+    /// reject mistyped IDs.
+    #[must_use]
+    pub fn to_base58(&self) -> String {
+        bs58::encode(self.0).with_check().into_string()
+    }
+
+    /// Parses the base58check encoding of a user ID. Returns `None` if it is
+    /// malformed, its checksum does not match, or it does not hold
+    /// [`USER_ID_SIZE`] bytes.
+    #[must_use]
+    pub fn from_base58(encoded: &str) -> Option<Self> {
+        let bytes = bs58::decode(encoded).with_check(None).into_vec().ok()?;
+        Self::from_slice(&bytes)
+    }
 }
 
 impl AsRef<[u8]> for UserId {
@@ -62,7 +108,27 @@ pub struct UserPublicKeys {
     pub evm_public_key: Vec<u8>,
 }
 
+/// Domain separation context of application signatures, so that they can
+/// never pass for protocol signatures or the other way around.
+const APP_SIGNATURE_CONTEXT: &[u8] = b"auth.app.signature";
+
 impl UserPublicKeys {
+    /// Verifies an application signature made with
+    /// [`UserSecretKeys::sign_message`].
+    ///
+    /// # Returns
+    ///
+    /// `true` if `signature` was made by the owner of these keys over `message`.
+    #[must_use]
+    pub fn verify_message(&self, message: &[u8], signature: &crypto_dsa::Signature) -> bool {
+        crypto_dsa::verify(
+            &self.dsa_verification_key,
+            message,
+            APP_SIGNATURE_CONTEXT,
+            signature,
+        )
+    }
+
     /// Serializes the public keys to bytes using bincode.
     ///
     /// # Returns
@@ -191,8 +257,24 @@ impl StaticRootSecret {
     /// ```
     #[must_use]
     pub fn from_passphrase(passphrase: &[u8]) -> Self {
+        Self::from_passphrase_with_params(passphrase, &crypto_password_kdf::Params::DEFAULT)
+    }
+
+    /// Same as [`from_passphrase`](Self::from_passphrase), with a custom KDF
+    /// cost. The passphrase only gives the same root secret again with the
+    /// same parameters.
+    #[must_use]
+    pub fn from_passphrase_with_params(
+        passphrase: &[u8],
+        params: &crypto_password_kdf::Params,
+    ) -> Self {
         let mut output = [0u8; STATIC_ROOT_SECRET_SIZE];
-        crypto_password_kdf::derive(passphrase, b"auth.pwd.kdf.salt---------------", &mut output);
+        crypto_password_kdf::derive_with_params(
+            passphrase,
+            b"auth.pwd.kdf.salt---------------",
+            params,
+            &mut output,
+        );
         Self(output)
     }
 
@@ -209,6 +291,30 @@ impl StaticRootSecret {
     pub const fn from_bytes(bytes: [u8; STATIC_ROOT_SECRET_SIZE]) -> Self {
         Self(bytes)
     }
+
+    /// Derives the child root secret of a label.
+    ///
+    /// Children of different labels are unrelated, and a child reveals
+    /// nothing about its parent, so a child can be handed to code that must
+    /// only manage one purpose.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - One segment of a derivation path, such as `chat`
+    ///
+    /// # Returns
+    ///
+    /// The `StaticRootSecret` of the label.
+    #[must_use]
+    pub fn derive_child(&self, label: &str) -> Self {
+        let mut kdf = crypto_kdf::Extract::new(b"auth.child.kdf.salt-------------");
+        kdf.input_item(self.as_slice());
+        kdf.input_item(label.as_bytes());
+        let mut output = [0u8; STATIC_ROOT_SECRET_SIZE];
+        kdf.finalize()
+            .expand(b"auth.child.kdf.root_secret", &mut output);
+        Self(output)
+    }
 }
 
 /// A collection of all secret keys associated with a user.
@@ -229,6 +335,26 @@ pub struct UserSecretKeys {
     pub evm_secret_key: [u8; 32],
 }
 
+impl UserSecretKeys {
+    /// Signs an application payload, such as a profile update or a registry
+    /// entry, with the ML-DSA key of the identity.
+    ///
+    /// Application signatures use their own domain separation context, so
+    /// they cannot be replayed as signatures of the messaging protocol.
+    /// Check them with [`UserPublicKeys::verify_message`].
+    #[must_use]
+    pub fn sign_message(&self, message: &[u8]) -> crypto_dsa::Signature {
+        let mut randomness = [0u8; crypto_dsa::SIGNING_RANDOMNESS_SIZE];
+        crypto_rng::fill_buffer(&mut randomness);
+        crypto_dsa::sign(
+            &self.dsa_signing_key,
+            message,
+            APP_SIGNATURE_CONTEXT,
+            randomness,
+        )
+    }
+}
+
 /// Derives all user keys from a static root secret.
 ///
 /// This function implements a hierarchical deterministic key derivation scheme.
@@ -327,6 +453,46 @@ pub fn derive_keys_from_static_root_secret(
     )
 }
 
+/// Derives the user keys of a purpose from a static root secret.
+///
+/// The purpose is a `/`-separated derivation path such as `gossip/chat/0`:
+/// each segment derives a child root secret with
+/// [`StaticRootSecret::derive_child`], and the keys come from the last one.
+/// One passphrase thus yields separate, unlinkable identities, one per path,
+/// and the same path always yields the same identity. The keys of
+/// `gossip/chat/0` are also those of `"0"` under the child of `gossip/chat`.
+///
+/// # Arguments
+///
+/// * `static_root_secret` - The root secret from which to derive keys
+/// * `purpose` - The derivation path of the identity
+///
+/// # Returns
+///
+/// A tuple containing the `UserPublicKeys` and `UserSecretKeys` of the purpose.
+///
+/// # Example
+///
+/// ```ignore
+/// use auth::{StaticRootSecret, derive_keys_for_purpose};
+///
+/// let root_secret = StaticRootSecret::from_passphrase(b"my secure passphrase");
+/// let (chat_public_keys, chat_secret_keys) = derive_keys_for_purpose(&root_secret, "gossip/chat/0");
+/// ```
+#[must_use]
+pub fn derive_keys_for_purpose(
+    static_root_secret: &StaticRootSecret,
+    purpose: &str,
+) -> (UserPublicKeys, UserSecretKeys) {
+    let mut segments = purpose.split('/');
+    // `split` yields at least one segment
+    let mut child = static_root_secret.derive_child(segments.next().unwrap_or_default());
+    for label in segments {
+        child = child.derive_child(label);
+    }
+    derive_keys_from_static_root_secret(&child)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -346,6 +512,34 @@ mod tests {
         assert_eq!(as_ref, &bytes);
     }
 
+    #[test]
+    fn test_user_id_string_encodings() {
+        let mut bytes = [0u8; USER_ID_SIZE];
+        bytes[0] = 0xab;
+        bytes[USER_ID_SIZE - 1] = 0x01;
+        let user_id = UserId::from_bytes(bytes);
+
+        let hex = user_id.to_hex();
+        assert_eq!(hex.len(), 2 * USER_ID_SIZE);
+        assert!(hex.starts_with("ab00") && hex.ends_with("0001"));
+        assert_eq!(UserId::from_hex(&hex), Some(user_id.clone()));
+        assert_eq!(UserId::from_hex(&hex.to_uppercase()), Some(user_id.clone()));
+        assert_eq!(UserId::from_hex(&hex[2..]), None);
+        assert_eq!(UserId::from_hex(&format!("zz{}", &hex[2..])), None);
+
+        let base58 = user_id.to_base58();
+        assert_eq!(UserId::from_base58(&base58), Some(user_id.clone()));
+        let mut mistyped = base58.into_bytes();
+        mistyped[3] = if mistyped[3] == b'2' { b'3' } else { b'2' };
+        assert_eq!(
+            UserId::from_base58(&String::from_utf8(mistyped).unwrap()),
+            None
+        );
+
+        assert_eq!(UserId::from_slice(&bytes), Some(user_id));
+        assert_eq!(UserId::from_slice(&bytes[1..]), None);
+    }
+
     #[test]
     fn test_user_id_equality() {
         let user_id1 = UserId::from_bytes([1u8; USER_ID_SIZE]);
@@ -533,6 +727,49 @@ mod tests {
         assert_ne!(sec_keys.evm_secret_key, [0u8; 32]);
     }
 
+    #[test]
+    fn test_derive_keys_for_purpose() {
+        let root_secret = StaticRootSecret::from_bytes([7u8; STATIC_ROOT_SECRET_SIZE]);
+        let (root_keys, _) = derive_keys_from_static_root_secret(&root_secret);
+        let (chat0, _) = derive_keys_for_purpose(&root_secret, "gossip/chat/0");
+        let (chat1, _) = derive_keys_for_purpose(&root_secret, "gossip/chat/1");
+
+        // distinct identities, stable per path
+        assert_ne!(chat0.derive_id(), root_keys.derive_id());
+        assert_ne!(chat0.derive_id(), chat1.derive_id());
+        assert_eq!(
+            chat0.derive_id(),
+            derive_keys_for_purpose(&root_secret, "gossip/chat/0")
+                .0
+                .derive_id()
+        );
+        // the path is hierarchical, not a flat label
+        assert_ne!(
+            chat0.derive_id(),
+            derive_keys_for_purpose(&root_secret, "gossip/chat0")
+                .0
+                .derive_id()
+        );
+        let chat = root_secret.derive_child("gossip").derive_child("chat");
+        assert_eq!(
+            chat0.derive_id(),
+            derive_keys_for_purpose(&chat, "0").0.derive_id()
+        );
+    }
+
+    #[test]
+    fn test_sign_message() {
+        let (pub_keys, sec_keys) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_bytes([5u8; 32]));
+        let (other_pub_keys, _) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_bytes([6u8; 32]));
+
+        let signature = sec_keys.sign_message(b"profile v2");
+        assert!(pub_keys.verify_message(b"profile v2", &signature));
+        assert!(!pub_keys.verify_message(b"profile v3", &signature));
+        assert!(!other_pub_keys.verify_message(b"profile v2", &signature));
+    }
+
     #[test]
     fn test_user_public_keys_serialization() {
         let root_secret = StaticRootSecret::from_passphrase(b"serialization test");
@@ -0,0 +1,128 @@
+//! # clock
+//!
+//! The time source shared by the `sessions` and `secureStorage` crates.
+//!
+//! Announcement and message age windows, session expiry, keep-alives, rate
+//! limiting and storage leases all compare timestamps with the current time.
+//! They read it from a [`Clock`], the system clock by default, so that tests
+//! can move time forward without sleeping and platforms with an unreliable
+//! clock, or none at all without `std`, can provide their own.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// Returns the current Unix time in milliseconds.
+    fn now_millis(&self) -> u128;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now_millis(&self) -> u128 {
+        (**self).now_millis()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now_millis(&self) -> u128 {
+        (**self).now_millis()
+    }
+}
+
+/// The system clock (JS `Date` on wasm32).
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u128 {
+        // web_time provides a std::time-compatible API that uses JS Date on wasm32
+        // and std::time on native platforms automatically
+        web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .expect("Failed to get timestamp")
+            .as_millis()
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Clones share the same time, so a test can keep one clone and hand the
+/// other to the code under test. Only available on targets with 64-bit
+/// atomics.
+#[cfg(target_has_atomic = "64")]
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<AtomicU64>);
+
+#[cfg(target_has_atomic = "64")]
+impl MockClock {
+    /// Creates a clock stopped at `now_millis`.
+    pub fn new(now_millis: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(now_millis)))
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, now_millis: u64) {
+        self.0.store(now_millis, Ordering::SeqCst);
+    }
+
+    /// Moves the current time forward, saturating at `u64::MAX`.
+    pub fn advance(&self, millis: u64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |now| {
+                Some(now.saturating_add(millis))
+            });
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Clock for MockClock {
+    fn now_millis(&self) -> u128 {
+        self.0.load(Ordering::SeqCst) as u128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the system clock reads a plausible time
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_system_clock_is_after_2020() {
+        // 2020-01-01T00:00:00Z
+        assert!(SystemClock.now_millis() > 1_577_836_800_000);
+    }
+
+    /// Tests that clones of a mock clock share the same time
+    #[cfg(target_has_atomic = "64")]
+    #[test]
+    fn test_mock_clock_shared() {
+        let clock = MockClock::new(1_000);
+        let handle = clock.clone();
+        handle.advance(500);
+        assert_eq!(clock.now_millis(), 1_500);
+        handle.set(10);
+        assert_eq!(clock.now_millis(), 10);
+
+        let shared: Arc<dyn Clock> = Arc::new(clock.clone());
+        clock.advance(32);
+        assert_eq!(shared.now_millis(), 42);
+    }
+
+    /// Tests that advancing a mock clock saturates instead of wrapping
+    #[cfg(target_has_atomic = "64")]
+    #[test]
+    fn test_mock_clock_advance_saturates() {
+        let clock = MockClock::new(u64::MAX - 1);
+        clock.advance(10);
+        assert_eq!(clock.now_millis(), u64::MAX as u128);
+    }
+}
@@ -0,0 +1,109 @@
+//! Encryption with nonces derived from a message counter.
+//!
+//! [`SivEncryptor`] prefixes every ciphertext with the counter its nonce was
+//! derived from (see [`Nonce::derive`]):
+//!
+//! ```text
+//! counter (8, big-endian) | ciphertext
+//! ```
+//!
+//! so the receiver needs only the key and the context to decrypt, and the
+//! sender only has to persist the next counter between runs.
+
+use crate::{Key, Nonce, decrypt, encrypt};
+
+/// Size of the counter prefix of a message.
+pub const COUNTER_SIZE: usize = 8;
+
+/// Encrypts messages of one context, deriving the nonce of each from a
+/// counter increased by every message.
+///
+/// # Examples
+///
+/// ```rust
+/// use crypto_aead::{Key, SivEncryptor, KEY_SIZE};
+///
+/// let key = Key::from([42u8; KEY_SIZE]);
+/// let mut sender = SivEncryptor::new(&key, b"drafts", 0);
+/// let message = sender.encrypt(b"hello", b"");
+/// assert_eq!(sender.counter(), 1);
+///
+/// let receiver = SivEncryptor::new(&key, b"drafts", 0);
+/// assert_eq!(receiver.decrypt(&message, b"").unwrap(), b"hello");
+/// ```
+pub struct SivEncryptor {
+    key: Key,
+    context: Vec<u8>,
+    counter: u64,
+}
+
+impl SivEncryptor {
+    /// Starts encrypting for `context` at `counter`: 0 the first time, then
+    /// the value of [`counter`](Self::counter) saved at the end of the last
+    /// run. Starting again from a counter already used reuses its nonce.
+    pub fn new(key: &Key, context: &[u8], counter: u64) -> Self {
+        Self {
+            key: Key::from_ref(key.as_bytes()),
+            context: context.to_vec(),
+            counter,
+        }
+    }
+
+    /// The counter of the next message.
+    #[must_use]
+    pub const fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Encrypts a message under the next counter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the counter is exhausted, after 2^64 messages.
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let counter = self.counter;
+        self.counter = counter.checked_add(1).expect("message counter exhausted");
+        let nonce = Nonce::derive(&self.key, &self.context, counter);
+        let mut message = counter.to_be_bytes().to_vec();
+        message.extend(encrypt(&self.key, &nonce, plaintext, aad));
+        message
+    }
+
+    /// Decrypts a message encrypted by an encryptor of the same key and
+    /// context. Returns `None` if it was modified or belongs to another key
+    /// or context.
+    #[must_use]
+    pub fn decrypt(&self, message: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        let (counter, ciphertext) = message.split_first_chunk::<COUNTER_SIZE>()?;
+        let nonce = Nonce::derive(&self.key, &self.context, u64::from_be_bytes(*counter));
+        decrypt(&self.key, &nonce, ciphertext, aad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KEY_SIZE;
+
+    #[test]
+    fn test_encryptor_uses_a_new_nonce_per_message() {
+        let key = Key::from([42u8; KEY_SIZE]);
+        let mut encryptor = SivEncryptor::new(&key, b"context", 0);
+        let first = encryptor.encrypt(b"same", b"");
+        let second = encryptor.encrypt(b"same", b"");
+        assert_ne!(first, second);
+        assert_eq!(encryptor.counter(), 2);
+
+        // resumes where the last run stopped
+        let mut resumed = SivEncryptor::new(&key, b"context", encryptor.counter());
+        let third = resumed.encrypt(b"same", b"");
+        assert_eq!(&third[..COUNTER_SIZE], &2u64.to_be_bytes());
+
+        for message in [&first, &second, &third] {
+            assert_eq!(encryptor.decrypt(message, b"").unwrap(), b"same");
+        }
+        let other_context = SivEncryptor::new(&key, b"other", 0);
+        assert!(other_context.decrypt(&first, b"").is_none());
+        assert!(encryptor.decrypt(&first[..COUNTER_SIZE - 1], b"").is_none());
+    }
+}
@@ -45,6 +45,12 @@
 //! assert_eq!(&decrypted, plaintext);
 //! ```
 //!
+//! Payloads too large to hold in memory at once are encrypted chunk by chunk
+//! with [`StreamEncryptor`] and [`StreamDecryptor`]. [`wrap_key`] encrypts a
+//! key under another one, to store it alongside the data it protects.
+//! [`SivEncryptor`] derives a fresh nonce for every message from a counter,
+//! so callers never pick nonces themselves.
+//!
 //! ## Nonce and AAD Considerations
 //!
 //! - **Nonce**: 32 bytes, should be unique per encryption for maximum security
@@ -58,6 +64,14 @@ use aes_siv::{
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+mod encryptor;
+mod stream;
+mod wrap;
+
+pub use encryptor::{COUNTER_SIZE, SivEncryptor};
+pub use stream::{StreamDecryptor, StreamEncryptor};
+pub use wrap::{WRAPPED_KEY_SIZE, unwrap_key, wrap_key};
+
 /// AES-256-SIV key size in bytes (512 bits total: 256 for encryption + 256 for MAC)
 ///
 /// Note: AES-SIV uses a double-length key internally (two 256-bit keys),
@@ -70,6 +84,10 @@ pub const KEY_SIZE: usize = 64;
 /// AES block operations and provides sufficient nonce space.
 pub const NONCE_SIZE: usize = 16;
 
+/// Size in bytes of the authentication tag: a ciphertext is this much longer
+/// than its plaintext.
+pub const TAG_SIZE: usize = 16;
+
 /// A nonce for AES-256-SIV encryption.
 ///
 /// This wraps a 128-bit nonce and provides safe byte array conversions.
@@ -112,6 +130,34 @@ impl Nonce {
     pub const fn as_bytes(&self) -> &[u8; NONCE_SIZE] {
         &self.0
     }
+
+    /// Derives the nonce of message `counter` of `context` under `key`.
+    ///
+    /// Distinct `(context, counter)` pairs give unrelated nonces, so a
+    /// sender only needs to keep a counter to never reuse one; the receiver
+    /// derives the same nonce from the counter sent along with the message.
+    /// [`SivEncryptor`] manages the counter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use crypto_aead::{Key, Nonce, KEY_SIZE};
+    ///
+    /// let key = Key::from([42u8; KEY_SIZE]);
+    /// let first = Nonce::derive(&key, b"drafts", 0);
+    /// let second = Nonce::derive(&key, b"drafts", 1);
+    /// assert_ne!(first.as_bytes(), second.as_bytes());
+    /// ```
+    #[must_use]
+    pub fn derive(key: &Key, context: &[u8], counter: u64) -> Self {
+        let mut kdf = crypto_kdf::Extract::new(b"crypto_aead.nonce.kdf.salt------");
+        kdf.input_item(key.as_bytes());
+        kdf.input_item(context);
+        kdf.input_item(&counter.to_be_bytes());
+        let mut nonce = [0u8; NONCE_SIZE];
+        kdf.finalize().expand(b"crypto_aead.nonce", &mut nonce);
+        Self(nonce)
+    }
 }
 
 /// A key for AES-256-SIV encryption.
@@ -0,0 +1,199 @@
+//! Chunked encryption of payloads too large to hold in memory at once.
+//!
+//! A stream is a sequence of chunks, each encrypted on its own with
+//! [`encrypt`](crate::encrypt) under the same key and nonce. The associated
+//! data of a chunk is its index and whether it is the last one, followed by
+//! the associated data of the stream:
+//!
+//! ```text
+//! chunk index (8, big-endian) | last (1) | stream aad
+//! ```
+//!
+//! so chunks cannot be reordered, dropped, or moved between streams, and a
+//! stream cut short is detected because its last chunk is missing. Each
+//! ciphertext chunk is [`TAG_SIZE`](crate::TAG_SIZE) bytes longer than its
+//! plaintext: with plaintext chunks of a fixed size, the reader splits the
+//! ciphertext at that size plus the tag.
+//!
+//! # Example
+//!
+//! ```rust
+//! use crypto_aead::{Key, Nonce, StreamDecryptor, StreamEncryptor, KEY_SIZE, NONCE_SIZE};
+//!
+//! let key = Key::from([42u8; KEY_SIZE]);
+//! let nonce = Nonce::from([1u8; NONCE_SIZE]);
+//!
+//! let mut encryptor = StreamEncryptor::new(&key, &nonce, b"file.bin");
+//! let first = encryptor.encrypt_chunk(b"first chunk ");
+//! let last = encryptor.finalize(b"last chunk");
+//!
+//! let mut decryptor = StreamDecryptor::new(&key, &nonce, b"file.bin");
+//! let mut plaintext = decryptor.decrypt_chunk(&first).unwrap();
+//! plaintext.extend(decryptor.finalize(&last).unwrap());
+//! assert_eq!(plaintext, b"first chunk last chunk");
+//! ```
+
+use crate::{Key, Nonce, decrypt, encrypt};
+
+/// Associated data of a chunk.
+fn chunk_aad(index: u64, last: bool, aad: &[u8]) -> Vec<u8> {
+    let mut chunk_aad = Vec::with_capacity(9 + aad.len());
+    chunk_aad.extend_from_slice(&index.to_be_bytes());
+    chunk_aad.push(u8::from(last));
+    chunk_aad.extend_from_slice(aad);
+    chunk_aad
+}
+
+/// Encrypts a stream chunk by chunk.
+pub struct StreamEncryptor {
+    key: Key,
+    nonce: Nonce,
+    aad: Vec<u8>,
+    index: u64,
+}
+
+impl StreamEncryptor {
+    /// Starts a stream. Use a fresh nonce for every stream encrypted with
+    /// the same key: two streams sharing both reveal which of their chunks
+    /// are equal.
+    pub fn new(key: &Key, nonce: &Nonce, aad: &[u8]) -> Self {
+        Self {
+            key: Key::from_ref(key.as_bytes()),
+            nonce: Nonce::from(*nonce.as_bytes()),
+            aad: aad.to_vec(),
+            index: 0,
+        }
+    }
+
+    /// Encrypts the next chunk, which must not be the last one.
+    pub fn encrypt_chunk(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let ciphertext = encrypt(
+            &self.key,
+            &self.nonce,
+            chunk,
+            &chunk_aad(self.index, false, &self.aad),
+        );
+        self.index += 1;
+        ciphertext
+    }
+
+    /// Encrypts the last chunk, which may be empty, and ends the stream.
+    pub fn finalize(self, chunk: &[u8]) -> Vec<u8> {
+        encrypt(
+            &self.key,
+            &self.nonce,
+            chunk,
+            &chunk_aad(self.index, true, &self.aad),
+        )
+    }
+}
+
+/// Decrypts a stream written by [`StreamEncryptor`], chunk by chunk.
+///
+/// Every chunk is authenticated before it is returned, but the stream is
+/// only complete once [`finalize`](Self::finalize) accepted its last chunk:
+/// until then, treat the plaintext as possibly truncated.
+pub struct StreamDecryptor {
+    key: Key,
+    nonce: Nonce,
+    aad: Vec<u8>,
+    index: u64,
+}
+
+impl StreamDecryptor {
+    /// Starts reading a stream encrypted with this key, nonce and associated
+    /// data.
+    pub fn new(key: &Key, nonce: &Nonce, aad: &[u8]) -> Self {
+        Self {
+            key: Key::from_ref(key.as_bytes()),
+            nonce: Nonce::from(*nonce.as_bytes()),
+            aad: aad.to_vec(),
+            index: 0,
+        }
+    }
+
+    /// Decrypts the next chunk, which must not be the last one. Returns
+    /// `None` if it was modified, is out of place, or is the last chunk; the
+    /// decryptor can then only be dropped.
+    pub fn decrypt_chunk(&mut self, chunk: &[u8]) -> Option<Vec<u8>> {
+        let plaintext = decrypt(
+            &self.key,
+            &self.nonce,
+            chunk,
+            &chunk_aad(self.index, false, &self.aad),
+        )?;
+        self.index += 1;
+        Some(plaintext)
+    }
+
+    /// Decrypts the last chunk and ends the stream. Returns `None` if it was
+    /// modified, is out of place, or is not the last chunk.
+    pub fn finalize(self, chunk: &[u8]) -> Option<Vec<u8>> {
+        decrypt(
+            &self.key,
+            &self.nonce,
+            chunk,
+            &chunk_aad(self.index, true, &self.aad),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KEY_SIZE, NONCE_SIZE, TAG_SIZE};
+
+    fn encrypt_stream(chunks: &[&[u8]], last: &[u8]) -> Vec<Vec<u8>> {
+        let key = Key::from([42u8; KEY_SIZE]);
+        let nonce = Nonce::from([1u8; NONCE_SIZE]);
+        let mut encryptor = StreamEncryptor::new(&key, &nonce, b"aad");
+        let mut ciphertexts: Vec<Vec<u8>> = chunks
+            .iter()
+            .map(|chunk| encryptor.encrypt_chunk(chunk))
+            .collect();
+        ciphertexts.push(encryptor.finalize(last));
+        ciphertexts
+    }
+
+    fn new_decryptor() -> StreamDecryptor {
+        let key = Key::from([42u8; KEY_SIZE]);
+        let nonce = Nonce::from([1u8; NONCE_SIZE]);
+        StreamDecryptor::new(&key, &nonce, b"aad")
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let ciphertexts = encrypt_stream(&[b"one", b"two"], b"");
+        assert_eq!(ciphertexts[0].len(), 3 + TAG_SIZE);
+
+        let mut decryptor = new_decryptor();
+        assert_eq!(decryptor.decrypt_chunk(&ciphertexts[0]).unwrap(), b"one");
+        assert_eq!(decryptor.decrypt_chunk(&ciphertexts[1]).unwrap(), b"two");
+        assert_eq!(decryptor.finalize(&ciphertexts[2]).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_stream_rejects_reordering_and_truncation() {
+        let ciphertexts = encrypt_stream(&[b"one", b"two"], b"three");
+
+        // swapped chunks
+        assert!(new_decryptor().decrypt_chunk(&ciphertexts[1]).is_none());
+
+        // truncated stream: the second chunk is not the last one
+        let mut decryptor = new_decryptor();
+        decryptor.decrypt_chunk(&ciphertexts[0]).unwrap();
+        assert!(decryptor.finalize(&ciphertexts[1]).is_none());
+
+        // the last chunk passed as an intermediate one
+        let mut decryptor = new_decryptor();
+        decryptor.decrypt_chunk(&ciphertexts[0]).unwrap();
+        decryptor.decrypt_chunk(&ciphertexts[1]).unwrap();
+        assert!(decryptor.decrypt_chunk(&ciphertexts[2]).is_none());
+
+        // other associated data
+        let key = Key::from([42u8; KEY_SIZE]);
+        let nonce = Nonce::from([1u8; NONCE_SIZE]);
+        let mut decryptor = StreamDecryptor::new(&key, &nonce, b"other");
+        assert!(decryptor.decrypt_chunk(&ciphertexts[0]).is_none());
+    }
+}
@@ -0,0 +1,64 @@
+//! Key wrapping: encrypting a [`Key`] under another one.
+//!
+//! A wrapped key can be stored next to the data it protects and unwrapped
+//! with the wrapping key when needed, so the data key itself never has to be
+//! stored in the clear. Changing the wrapping key only rewraps the data key,
+//! leaving the data untouched.
+//!
+//! SIV is a deterministic key wrap (RFC 5297, section 1.3.2): no nonce is
+//! needed, and the same key wrapped twice under the same wrapping key gives
+//! the same output, which reveals nothing but that equality.
+
+use crate::{KEY_SIZE, Key, NONCE_SIZE, Nonce, TAG_SIZE, decrypt, encrypt};
+use zeroize::Zeroizing;
+
+/// Size of a wrapped key.
+pub const WRAPPED_KEY_SIZE: usize = KEY_SIZE + TAG_SIZE;
+
+/// Associated data of a wrapped key, so that it cannot be mistaken for
+/// another ciphertext of the wrapping key.
+const WRAP_AAD: &[u8] = b"crypto_aead.key_wrap.v1";
+
+/// Wraps `key` under `wrapping_key`.
+pub fn wrap_key(wrapping_key: &Key, key: &Key) -> Vec<u8> {
+    encrypt(
+        wrapping_key,
+        &Nonce::from([0u8; NONCE_SIZE]),
+        key.as_bytes(),
+        WRAP_AAD,
+    )
+}
+
+/// Unwraps a key wrapped by [`wrap_key`]. Returns `None` if it was modified
+/// or wrapped under another key.
+pub fn unwrap_key(wrapping_key: &Key, wrapped: &[u8]) -> Option<Key> {
+    let key_bytes = Zeroizing::new(decrypt(
+        wrapping_key,
+        &Nonce::from([0u8; NONCE_SIZE]),
+        wrapped,
+        WRAP_AAD,
+    )?);
+    let key_bytes: &[u8; KEY_SIZE] = key_bytes.as_slice().try_into().ok()?;
+    Some(Key::from_ref(key_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_roundtrip() {
+        let wrapping_key = Key::from([1u8; KEY_SIZE]);
+        let key = Key::from([2u8; KEY_SIZE]);
+
+        let wrapped = wrap_key(&wrapping_key, &key);
+        assert_eq!(wrapped.len(), WRAPPED_KEY_SIZE);
+        let unwrapped = unwrap_key(&wrapping_key, &wrapped).unwrap();
+        assert_eq!(unwrapped.as_bytes(), key.as_bytes());
+
+        assert!(unwrap_key(&Key::from([3u8; KEY_SIZE]), &wrapped).is_none());
+        let mut tampered = wrapped.clone();
+        tampered[0] ^= 1;
+        assert!(unwrap_key(&wrapping_key, &tampered).is_none());
+    }
+}
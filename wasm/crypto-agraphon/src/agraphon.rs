@@ -10,6 +10,11 @@ use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
+/// Bytes a message adds to its payload: the message randomness, two KEM
+/// ciphertexts, the next public key and the AEAD tag.
+pub const MESSAGE_OVERHEAD: usize =
+    32 + 2 * kem::CIPHERTEXT_SIZE + kem::PUBLIC_KEY_SIZE + aead::TAG_SIZE;
+
 #[derive(Zeroize, ZeroizeOnDrop)]
 pub struct FeedIncomingMessageResult {
     pub message_bytes: Vec<u8>,
@@ -485,6 +490,43 @@ impl Agraphon {
             .expect("Self lag is negative")
     }
 
+    /// Returns the height of our latest message, the announcement being at
+    /// height 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the internal message history is empty, see
+    /// [`self_lag_length`](Self::self_lag_length).
+    #[must_use]
+    pub fn self_height(&self) -> u64 {
+        self.self_msg_history
+            .back()
+            .expect("Self message history unexpectedly empty")
+            .height
+    }
+
+    /// Returns the height of the latest message received from the peer, its
+    /// announcement being at height 1.
+    #[must_use]
+    pub fn peer_height(&self) -> u64 {
+        self.latest_peer_msg.height
+    }
+
+    /// Returns the seekers of our messages the peer has not acknowledged yet,
+    /// oldest first.
+    ///
+    /// Unlike [`self_lag_length`](Self::self_lag_length), this only counts
+    /// messages we actually sent, without the announcement offset.
+    #[must_use]
+    pub fn unacknowledged_self_seekers(&self) -> Vec<Vec<u8>> {
+        let acknowledged_height = self.latest_peer_msg.our_parent_height;
+        self.self_msg_history
+            .iter()
+            .filter(|msg| msg.height > acknowledged_height && !msg.seeker.is_empty())
+            .map(|msg| msg.seeker.clone())
+            .collect()
+    }
+
     /// Returns how many peer messages are still unacknowledged by our latest outgoing message.
     ///
     /// This value compares:
@@ -789,6 +831,7 @@ mod tests {
         // Send a large message (100KB)
         let large_msg = vec![42u8; 100_000];
         let result = alice_session.send_outgoing_message(b"seeker", &large_msg, &bob_pk);
+        assert_eq!(result.len(), large_msg.len() + MESSAGE_OVERHEAD);
 
         let decrypted = bob_session
             .try_feed_incoming_message(&bob_sk, &result)
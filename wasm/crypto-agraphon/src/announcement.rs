@@ -12,6 +12,11 @@ use crypto_rng as rng;
 use serde::{Deserialize, Serialize};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
+/// Bytes an announcement adds to its auth payload: the announcement
+/// randomness, the KEM ciphertext, the next public key and the AEAD tag.
+pub const ANNOUNCEMENT_OVERHEAD: usize =
+    32 + kem::CIPHERTEXT_SIZE + kem::PUBLIC_KEY_SIZE + cipher::TAG_SIZE;
+
 /// Intermediate state when receiving an announcement.
 ///
 /// After receiving and decrypting an announcement, this precursor allows the
@@ -603,6 +608,10 @@ mod tests {
 
         let precursor = OutgoingAnnouncementPrecursor::new(&bob_pk);
         let (announcement_bytes, _) = precursor.finalize(&large_payload);
+        assert_eq!(
+            announcement_bytes.len(),
+            large_payload.len() + ANNOUNCEMENT_OVERHEAD
+        );
 
         let bob_precursor = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
             &announcement_bytes,
@@ -163,9 +163,10 @@ mod announcement_auth_kdf;
 mod announcement_root_kdf;
 mod history;
 mod message_root_kdf;
+mod prekey;
 
-pub use agraphon::Agraphon;
+pub use agraphon::{Agraphon, MESSAGE_OVERHEAD};
 pub use announcement::{
-    IncomingAnnouncement, IncomingAnnouncementPrecursor, OutgoingAnnouncement,
-    OutgoingAnnouncementPrecursor,
+    ANNOUNCEMENT_OVERHEAD, IncomingAnnouncement, IncomingAnnouncementPrecursor,
+    OutgoingAnnouncement, OutgoingAnnouncementPrecursor,
 };
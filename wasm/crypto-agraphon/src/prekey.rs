@@ -0,0 +1,133 @@
+//! Session establishment from a published prekey.
+//!
+//! A session normally joins both parties' announcements, so neither side can
+//! send before the other has announced. A responder can instead publish a
+//! prekey ahead of time: a KEM key pair whose public half stands in for the
+//! `pk_next` of its announcement. The initiator encapsulates to the prekey and
+//! builds the responder's side of the handshake locally, so it can send right
+//! away. The responder rebuilds the matching side from the ciphertext, which
+//! the initiator delivers along with its own announcement.
+
+use crate::announcement::{IncomingAnnouncement, OutgoingAnnouncement};
+use crypto_kdf as kdf;
+use crypto_kem as kem;
+use crypto_rng as rng;
+
+impl IncomingAnnouncement {
+    /// Builds the responder's side of the handshake from its prekey, on the
+    /// initiator's side.
+    ///
+    /// # Arguments
+    ///
+    /// * `pk_peer` - The responder's static public key
+    /// * `pk_prekey` - The responder's published prekey
+    ///
+    /// # Returns
+    ///
+    /// The ciphertext to deliver to the responder, and the announcement to
+    /// join with our own outgoing announcement.
+    #[must_use]
+    pub fn from_prekey(
+        pk_peer: kem::PublicKey,
+        pk_prekey: &kem::PublicKey,
+    ) -> (kem::Ciphertext, Self) {
+        let (ct, ss) = {
+            let mut kem_randomness = [0u8; kem::ENCAPSULATION_RANDOMNESS_SIZE];
+            rng::fill_buffer(&mut kem_randomness);
+            kem::encapsulate(pk_prekey, kem_randomness)
+        };
+        let k_next = prekey_k_next(&ss, &ct, pk_prekey);
+        (
+            ct,
+            Self {
+                pk_peer,
+                pk_next: pk_prekey.clone(),
+                k_next,
+            },
+        )
+    }
+}
+
+impl OutgoingAnnouncement {
+    /// Rebuilds our side of a handshake the initiator built from our prekey.
+    ///
+    /// # Arguments
+    ///
+    /// * `sk_prekey` - Our prekey secret key
+    /// * `pk_prekey` - Our published prekey
+    /// * `ct` - The ciphertext the initiator encapsulated to the prekey
+    #[must_use]
+    pub fn from_prekey(
+        sk_prekey: &kem::SecretKey,
+        pk_prekey: &kem::PublicKey,
+        ct: &kem::Ciphertext,
+    ) -> Self {
+        let ss = kem::decapsulate(sk_prekey, ct);
+        Self {
+            k_next: prekey_k_next(&ss, ct, pk_prekey),
+            sk_next: sk_prekey.clone(),
+        }
+    }
+}
+
+/// Derives the `k_next` of a prekey handshake.
+fn prekey_k_next(ss: &kem::SharedSecret, ct: &kem::Ciphertext, pk: &kem::PublicKey) -> [u8; 32] {
+    let mut k_next = [0u8; 32];
+    let mut prekey_kdf = kdf::Extract::new("agraphon.prekey_kdf.V1".as_bytes());
+    prekey_kdf.input_item(ct.as_bytes());
+    prekey_kdf.input_item(ss.as_bytes());
+    prekey_kdf.input_item(pk.as_bytes());
+    prekey_kdf
+        .finalize()
+        .expand("agraphon.prekey_kdf.k_next".as_bytes(), &mut k_next);
+    k_next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agraphon, IncomingAnnouncementPrecursor, OutgoingAnnouncementPrecursor};
+
+    #[test]
+    fn test_prekey_session_roundtrip() {
+        let mut alice_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut alice_rand);
+        let (alice_sk, alice_pk) = kem::generate_key_pair(alice_rand);
+
+        let mut bob_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut bob_rand);
+        let (bob_sk, bob_pk) = kem::generate_key_pair(bob_rand);
+
+        // Bob publishes a prekey
+        let mut prekey_rand = [0u8; kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        rng::fill_buffer(&mut prekey_rand);
+        let (prekey_sk, prekey_pk) = kem::generate_key_pair(prekey_rand);
+
+        // Alice announces and builds Bob's side from the prekey
+        let (announcement_bytes, alice_announcement) =
+            OutgoingAnnouncementPrecursor::new(&bob_pk).finalize(b"");
+        let (ct, bob_side) = IncomingAnnouncement::from_prekey(bob_pk.clone(), &prekey_pk);
+        let mut alice = Agraphon::from_announcement_pair(&alice_announcement, &bob_side);
+
+        // Bob reads Alice's announcement and rebuilds his side
+        let alice_side = IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
+            &announcement_bytes,
+            &bob_pk,
+            &bob_sk,
+        )
+        .unwrap()
+        .finalize(alice_pk.clone())
+        .unwrap();
+        let bob_announcement = OutgoingAnnouncement::from_prekey(&prekey_sk, &prekey_pk, &ct);
+        assert_eq!(bob_announcement.k_next, bob_side.k_next);
+        let mut bob = Agraphon::from_announcement_pair(&bob_announcement, &alice_side);
+
+        // Alice sends first, Bob replies
+        let message = alice.send_outgoing_message(b"seeker", b"hello bob", &bob_pk);
+        let received = bob.try_feed_incoming_message(&bob_sk, &message).unwrap();
+        assert_eq!(received.message_bytes, b"hello bob");
+        let reply = bob.send_outgoing_message(b"seeker", b"hello alice", &alice_pk);
+        let received = alice.try_feed_incoming_message(&alice_sk, &reply).unwrap();
+        assert_eq!(received.message_bytes, b"hello alice");
+    }
+}
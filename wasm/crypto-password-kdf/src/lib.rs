@@ -33,9 +33,93 @@
 //!
 //! // The derived key can now be used for encryption or authentication
 //! ```
+//!
+//! [`derive_with_params`] takes a custom cost instead, e.g. a lower one for
+//! low-end devices, bounded below by [`Params::new`].
 
 use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
 
+/// Minimum memory cost in KiB (19 MiB, the OWASP minimum for Argon2id).
+pub const MIN_MEMORY_KIB: u32 = 19_456;
+
+/// Minimum number of iterations.
+pub const MIN_ITERATIONS: u32 = 2;
+
+/// Maximum degree of parallelism.
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// Cost parameters of the Argon2id derivation.
+///
+/// The same password and salt only give the same key with the same
+/// parameters: store them alongside the salt when they are not the default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Params {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Params {
+    /// The parameters of [`derive`]: 32 MiB, 4 iterations, 1 thread.
+    pub const DEFAULT: Self = Self {
+        memory_kib: 32_768,
+        iterations: 4,
+        parallelism: 1,
+    };
+
+    /// Creates parameters, or returns `None` if the memory is below
+    /// [`MIN_MEMORY_KIB`], the iterations below [`MIN_ITERATIONS`], or the
+    /// parallelism is 0 or above [`MAX_PARALLELISM`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use crypto_password_kdf::Params;
+    ///
+    /// assert!(Params::new(65_536, 3, 1).is_some());
+    /// assert!(Params::new(1_024, 3, 1).is_none());
+    /// ```
+    #[must_use]
+    pub const fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Option<Self> {
+        if memory_kib < MIN_MEMORY_KIB
+            || iterations < MIN_ITERATIONS
+            || parallelism == 0
+            || parallelism > MAX_PARALLELISM
+        {
+            return None;
+        }
+        Some(Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        })
+    }
+
+    /// Memory cost in KiB.
+    #[must_use]
+    pub const fn memory_kib(&self) -> u32 {
+        self.memory_kib
+    }
+
+    /// Number of iterations.
+    #[must_use]
+    pub const fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Degree of parallelism.
+    #[must_use]
+    pub const fn parallelism(&self) -> u32 {
+        self.parallelism
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Derives a cryptographic key from a password using Argon2id.
 ///
 /// This function uses parameters optimized for single-core WASM and mobile devices:
@@ -81,6 +165,15 @@ use argon2::{Algorithm, Argon2, ParamsBuilder, Version};
 /// assert_ne!(derived_key, [0u8; 32]);
 /// ```
 pub fn derive(password: &[u8], salt: &[u8], output_buffer: &mut [u8]) {
+    derive_with_params(password, salt, &Params::DEFAULT, output_buffer);
+}
+
+/// Same as [`derive`], with the cost given by `params`.
+///
+/// # Panics
+///
+/// Panics under the same conditions as [`derive`].
+pub fn derive_with_params(password: &[u8], salt: &[u8], params: &Params, output_buffer: &mut [u8]) {
     // Validate salt length (minimum 8 bytes per Argon2 spec)
     assert!(
         salt.len() >= 8,
@@ -88,12 +181,10 @@ pub fn derive(password: &[u8], salt: &[u8], output_buffer: &mut [u8]) {
         salt.len()
     );
 
-    // Parameters optimized for single-core WASM and mobile devices
-    // while maintaining high security (128-bit equivalent)
     let params = ParamsBuilder::new()
-        .m_cost(32768) // 32 MiB - Reasonable for mobile devices
-        .t_cost(4) // 4 iterations - Higher to compensate for single thread
-        .p_cost(1) // 1 thread - Single-core for WASM compatibility
+        .m_cost(params.memory_kib)
+        .t_cost(params.iterations)
+        .p_cost(params.parallelism)
         .build()
         .expect("Invalid Argon2 parameters");
 
@@ -202,6 +293,36 @@ mod tests {
         assert_ne!(key, [0u8; 32]);
     }
 
+    #[test]
+    fn test_params() {
+        let password = b"test-password";
+        let salt = b"test-salt-16bytes";
+
+        let mut default_key = [0u8; 32];
+        let mut explicit_key = [0u8; 32];
+        let mut custom_key = [0u8; 32];
+        derive(password, salt, &mut default_key);
+        derive_with_params(
+            password,
+            salt,
+            &Params::new(32_768, 4, 1).unwrap(),
+            &mut explicit_key,
+        );
+        derive_with_params(
+            password,
+            salt,
+            &Params::new(MIN_MEMORY_KIB, 2, 2).unwrap(),
+            &mut custom_key,
+        );
+        assert_eq!(default_key, explicit_key);
+        assert_ne!(default_key, custom_key);
+
+        assert!(Params::new(MIN_MEMORY_KIB - 1, 4, 1).is_none());
+        assert!(Params::new(32_768, MIN_ITERATIONS - 1, 1).is_none());
+        assert!(Params::new(32_768, 4, 0).is_none());
+        assert!(Params::new(32_768, 4, MAX_PARALLELISM + 1).is_none());
+    }
+
     #[test]
     fn test_empty_password() {
         let password = b"";
@@ -0,0 +1,183 @@
+//! Seeded random bytes, for reproducible test vectors.
+//!
+//! Inside [`with_seed`], [`fill_buffer`](crate::fill_buffer) returns the
+//! ChaCha20 keystream of RFC 8439 with the seed as key, an all-zero nonce and
+//! a block counter starting at 0, so implementations in other languages can
+//! reproduce the same bytes. The stream is per thread and consumed in order
+//! across calls.
+//!
+//! Never enable the `deterministic` feature outside tests: the bytes are only
+//! as secret as the seed.
+
+use std::cell::RefCell;
+
+/// ChaCha20 keystream position.
+struct KeystreamState {
+    key: [u32; 8],
+    counter: u32,
+    block: [u8; 64],
+    offset: usize,
+}
+
+impl KeystreamState {
+    fn new(seed: &[u8; 32]) -> Self {
+        let mut key = [0u32; 8];
+        for (word, bytes) in key.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        Self {
+            key,
+            counter: 0,
+            block: [0; 64],
+            offset: 64,
+        }
+    }
+
+    fn fill(&mut self, buffer: &mut [u8]) {
+        for byte in buffer {
+            if self.offset == 64 {
+                self.block = chacha20_block(&self.key, self.counter, &[0; 3]);
+                self.counter = self
+                    .counter
+                    .checked_add(1)
+                    .expect("deterministic keystream exhausted");
+                self.offset = 0;
+            }
+            *byte = self.block[self.offset];
+            self.offset += 1;
+        }
+    }
+}
+
+thread_local! {
+    static KEYSTREAM: RefCell<Option<KeystreamState>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous keystream when `with_seed` returns or unwinds.
+struct Restore(Option<KeystreamState>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        KEYSTREAM.with(|keystream| *keystream.borrow_mut() = self.0.take());
+    }
+}
+
+/// Runs `f` with [`fill_buffer`](crate::fill_buffer) returning the keystream
+/// of `seed` on this thread.
+///
+/// # Examples
+///
+/// ```rust
+/// let draw = || {
+///     let mut bytes = [0u8; 16];
+///     crypto_rng::fill_buffer(&mut bytes);
+///     bytes
+/// };
+/// let first = crypto_rng::with_seed([1; 32], draw);
+/// assert_eq!(crypto_rng::with_seed([1; 32], draw), first);
+/// ```
+pub fn with_seed<R>(seed: [u8; 32], f: impl FnOnce() -> R) -> R {
+    let previous = KEYSTREAM.with(|keystream| keystream.replace(Some(KeystreamState::new(&seed))));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Fills `buffer` from the seeded keystream, if one is set on this thread.
+pub(crate) fn fill(buffer: &mut [u8]) -> bool {
+    KEYSTREAM.with(|keystream| match keystream.borrow_mut().as_mut() {
+        Some(state) => {
+            state.fill(buffer);
+            true
+        }
+        None => false,
+    })
+}
+
+fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(12);
+    state[a] = state[a].wrapping_add(state[b]);
+    state[d] = (state[d] ^ state[a]).rotate_left(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_left(7);
+}
+
+/// The ChaCha20 block function of RFC 8439, section 2.3.
+fn chacha20_block(key: &[u32; 8], counter: u32, nonce: &[u32; 3]) -> [u8; 64] {
+    let mut initial = [0u32; 16];
+    initial[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    initial[4..12].copy_from_slice(key);
+    initial[12] = counter;
+    initial[13..].copy_from_slice(nonce);
+
+    let mut state = initial;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut block = [0u8; 64];
+    for (i, bytes) in block.chunks_exact_mut(4).enumerate() {
+        bytes.copy_from_slice(&state[i].wrapping_add(initial[i]).to_le_bytes());
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc8439_keystream() {
+        // RFC 8439, appendix A.1, test vectors #1 and #2 (blocks 0 and 1)
+        let mut keystream = [0u8; 128];
+        with_seed([0; 32], || crate::fill_buffer(&mut keystream));
+        assert_eq!(
+            keystream[..64],
+            [
+                0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86,
+                0xbd, 0x28, 0xbd, 0xd2, 0x19, 0xb8, 0xa0, 0x8d, 0xed, 0x1a, 0xa8, 0x36, 0xef, 0xcc,
+                0x8b, 0x77, 0x0d, 0xc7, 0xda, 0x41, 0x59, 0x7c, 0x51, 0x57, 0x48, 0x8d, 0x77, 0x24,
+                0xe0, 0x3f, 0xb8, 0xd8, 0x4a, 0x37, 0x6a, 0x43, 0xb8, 0xf4, 0x15, 0x18, 0xa1, 0x1c,
+                0xc3, 0x87, 0xb6, 0x69, 0xb2, 0xee, 0x65, 0x86,
+            ]
+        );
+        assert_eq!(
+            keystream[64..80],
+            [
+                0x9f, 0x07, 0xe7, 0xbe, 0x55, 0x51, 0x38, 0x7a, 0x98, 0xba, 0x97, 0x7c, 0x73, 0x2d,
+                0x08, 0x0d,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_continues_across_calls() {
+        let mut whole = [0u8; 100];
+        let mut split = [0u8; 100];
+        with_seed([9; 32], || crate::fill_buffer(&mut whole));
+        with_seed([9; 32], || {
+            crate::fill_buffer(&mut split[..30]);
+            crate::fill_buffer(&mut split[30..]);
+        });
+        assert_eq!(whole, split);
+    }
+
+    #[test]
+    fn test_seed_is_scoped() {
+        let mut inside = [0u8; 32];
+        let mut outside = [0u8; 32];
+        with_seed([0; 32], || crate::fill_buffer(&mut inside));
+        crate::fill_buffer(&mut outside);
+        assert_ne!(inside, outside);
+        assert!(!fill(&mut outside));
+    }
+}
@@ -106,6 +106,18 @@
 //! If you need error recovery, you can catch the panic using `std::panic::catch_unwind`
 //! (on platforms that support it), but this is generally not recommended for
 //! cryptographic code.
+//!
+//! ## Deterministic Output
+//!
+//! The `deterministic` feature adds `with_seed`, which makes `fill_buffer` return a seeded
+//! ChaCha20 keystream on the current thread while a closure runs. It exists to produce
+//! reproducible test vectors and must never be enabled in production builds.
+
+#[cfg(feature = "deterministic")]
+mod deterministic;
+
+#[cfg(feature = "deterministic")]
+pub use deterministic::with_seed;
 
 /// Fills a buffer with cryptographically secure random bytes.
 ///
@@ -167,6 +179,10 @@
 /// - Session tokens
 /// - Any other security-critical random data
 pub fn fill_buffer(buffer: &mut [u8]) {
+    #[cfg(feature = "deterministic")]
+    if deterministic::fill(buffer) {
+        return;
+    }
     getrandom::getrandom(buffer)
         .expect("Failed to generate random bytes: system random source unavailable");
 }
@@ -5,12 +5,37 @@
 //!
 //! # Features
 //!
-//! - **Session Management**: Create and manage encrypted messaging sessions
+//! - **Session Management**: Create and manage encrypted messaging sessions, optionally
+//!   saved to encrypted storage as they change
 //! - **Authentication**: Generate cryptographic keys from passphrases
-//! - **AEAD Encryption**: Direct access to AES-256-SIV authenticated encryption
+//! - **AEAD Encryption**: Direct access to AES-256-SIV authenticated encryption, in one
+//!   call or streamed chunk by chunk for large payloads
 //! - **Post-Quantum Security**: Uses ML-KEM and ML-DSA for quantum resistance
+//!
+//! # Errors
+//!
+//! Fallible functions throw a [`GossipError`], whose `code` identifies the
+//! failure without parsing the message.
+//!
+//! # Secrets
+//!
+//! `UserSecretKeys`, `UserKeys`, `EncryptionKey` and `Nonce` have a
+//! `dispose()` method that zeroizes the secret they hold in WASM memory while
+//! keeping the object usable, unlike `free()`: their getters then return
+//! `undefined`, and functions given them throw a `GossipError` with code
+//! `DISPOSED`.
+//!
+//! # Logging
+//!
+//! [`set_log_level`] turns on a console log of session events, off by
+//! default. Like peer transcripts, it never holds keys, seekers, peer IDs or
+//! message contents.
+
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
 
 // Set up panic hook for better error messages in the browser
 #[wasm_bindgen(start)]
@@ -18,6 +43,267 @@ pub fn start() {
     console_error_panic_hook::set_once();
 }
 
+/// Writes `log` records to the browser console.
+struct ConsoleLogger;
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = JsValue::from(format!("[{}] {}", record.target(), record.args()));
+        match record.level() {
+            log::Level::Error => web_sys::console::error_1(&line),
+            log::Level::Warn => web_sys::console::warn_1(&line),
+            log::Level::Info => web_sys::console::info_1(&line),
+            log::Level::Debug | log::Level::Trace => web_sys::console::debug_1(&line),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CONSOLE_LOGGER: ConsoleLogger = ConsoleLogger;
+
+/// Sets the level of the session log written to the console: `"off"` (the
+/// default), `"error"`, `"warn"`, `"info"`, `"debug"` or `"trace"`.
+///
+/// The log holds the same redacted events as peer transcripts, without
+/// peer IDs.
+#[wasm_bindgen]
+pub fn set_log_level(level: &str) -> Result<(), GossipError> {
+    let filter: log::LevelFilter = level
+        .parse()
+        .map_err(|_| GossipError::invalid_argument(format!("unknown log level: {level}")))?;
+    // only fails when already installed by a previous call
+    let _ = log::set_logger(&CONSOLE_LOGGER);
+    log::set_max_level(filter);
+    Ok(())
+}
+
+/// Error thrown by every fallible function of this module.
+///
+/// `code` is a stable identifier to switch on (e.g. `"NO_SESSION"`,
+/// `"INVALID_ARGUMENT"`), `kind` the family it belongs to, and `message` a
+/// human-readable description whose wording may change.
+///
+/// # Example
+///
+/// ```javascript
+/// try {
+///   manager.send_large_message(peer_id, payload);
+/// } catch (e) {
+///   if (e instanceof GossipError && e.code === "THROTTLED") {
+///     // retry later
+///   }
+/// }
+/// ```
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct GossipError {
+    code: &'static str,
+    kind: &'static str,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl GossipError {
+    /// Stable code of the error.
+    #[wasm_bindgen(getter, unchecked_return_type = "GossipErrorCode")]
+    pub fn code(&self) -> String {
+        self.code.to_string()
+    }
+
+    /// Family of the error: `"argument"` for invalid arguments, `"session"`
+    /// for session protocol errors, `"crypto"` for failed encryption or
+    /// decryption, `"serialization"`, `"keystore"` and `"storage"`.
+    #[wasm_bindgen(getter, unchecked_return_type = "GossipErrorKind")]
+    pub fn kind(&self) -> String {
+        self.kind.to_string()
+    }
+
+    /// Human-readable description of the error.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// Formats the error as `"<code>: <message>"`.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        format!("{}: {}", self.code, self.message)
+    }
+}
+
+impl GossipError {
+    /// An argument has the wrong size or shape.
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self {
+            code: "INVALID_ARGUMENT",
+            kind: "argument",
+            message: message.into(),
+        }
+    }
+
+    /// Encryption or decryption failed.
+    fn crypto(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            kind: "crypto",
+            message: message.into(),
+        }
+    }
+
+    /// A secret object was used after `dispose()`.
+    fn disposed() -> Self {
+        Self {
+            code: "DISPOSED",
+            kind: "argument",
+            message: "Object was disposed".to_string(),
+        }
+    }
+
+    /// Keys or state could not be encoded or decoded.
+    fn serialization(message: impl Into<String>) -> Self {
+        Self {
+            code: "SERIALIZATION",
+            kind: "serialization",
+            message: message.into(),
+        }
+    }
+
+    /// The storage of a `PersistedSessionManager` failed; keeps the message
+    /// of the JS error it threw, if any.
+    fn storage(error: &JsValue) -> Self {
+        let message = error
+            .as_string()
+            .or_else(|| {
+                js_sys::Reflect::get(error, &JsValue::from_str("message"))
+                    .ok()
+                    .and_then(|message| message.as_string())
+            })
+            .unwrap_or_else(|| "Storage error".to_string());
+        Self {
+            code: "STORAGE",
+            kind: "storage",
+            message,
+        }
+    }
+}
+
+impl From<sessions::SessionError> for GossipError {
+    fn from(error: sessions::SessionError) -> Self {
+        Self {
+            code: error.code(),
+            kind: "session",
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<auth::KeystoreError> for GossipError {
+    fn from(error: auth::KeystoreError) -> Self {
+        Self {
+            code: error.code(),
+            kind: "keystore",
+            message: error.to_string(),
+        }
+    }
+}
+
+// Hand-written TypeScript types completing the generated definitions: byte
+// arrays get named aliases and JS arrays get element types, referenced by the
+// `unchecked_param_type` / `unchecked_return_type` annotations below.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** Message board key under which a session entry is stored. */
+export type Seeker = Uint8Array;
+
+/** `[peer_id, announcement]`: an announcement to publish for a peer. */
+export type PeerAnnouncement = [UserId, Uint8Array];
+
+/** `[seeker, peer_id]`: a seeker to read and the peer it belongs to. */
+export type SeekerPeer = [Seeker, UserId];
+
+/** Entry of a batch result: the output, or `undefined` if that item failed. */
+export type BatchResult<T> = T | undefined;
+
+/**
+ * Cost of the Argon2id password KDF of `generate_user_keys` and
+ * `EncryptionKey.from_seed`; the default is 32768 KiB, 4 iterations, 1
+ * thread. At least 19456 KiB and 2 iterations, and 1 to 16 threads. A
+ * passphrase only gives the same keys again with the same parameters.
+ */
+export interface KdfParams {
+  memory_kib: number;
+  iterations: number;
+  parallelism: number;
+}
+
+/** Family of a `GossipError`. */
+export type GossipErrorKind =
+  | "argument"
+  | "session"
+  | "crypto"
+  | "serialization"
+  | "keystore"
+  | "storage";
+
+/** Code of a `GossipError` of kind `"session"`. */
+export type SessionErrorCode =
+  | "UNDECRYPTABLE"
+  | "INVALID_SIGNATURE"
+  | "TOO_OLD"
+  | "TOO_FAR_IN_FUTURE"
+  | "REPLAY"
+  | "OWN_ANNOUNCEMENT"
+  | "UNKNOWN_PEER"
+  | "NO_SESSION"
+  | "CLOSED"
+  | "SATURATED"
+  | "UNKNOWN_SEEKER"
+  | "INVALID_TRANSFER"
+  | "BLOCKED"
+  | "RATE_LIMITED"
+  | "BUFFERED"
+  | "KEY_CHANGED"
+  | "REVOKED"
+  | "USER_DATA_TOO_LARGE"
+  | "NO_REQUEST"
+  | "NOT_ALLOWED"
+  | "TOO_MANY_PEERS"
+  | "THROTTLED";
+
+/** Code of a `GossipError` of kind `"keystore"`. */
+export type KeystoreErrorCode = "MALFORMED" | "UNSUPPORTED_VERSION" | "DECRYPTION";
+
+/** Code of a `GossipError`. */
+export type GossipErrorCode =
+  | SessionErrorCode
+  | KeystoreErrorCode
+  | "INVALID_ARGUMENT"
+  | "DISPOSED"
+  | "ENCRYPTION"
+  | "DECRYPTION"
+  | "SERIALIZATION"
+  | "STORAGE";
+
+/**
+ * Storage a `PersistedSessionManager` keeps its state in, such as the
+ * `StorageHandle` of the secure storage module.
+ */
+export interface SessionStorage {
+  readData(namespace: number, offset: number, len: number): Uint8Array;
+  writeData(namespace: number, offset: number, data: Uint8Array): void;
+  dataLength(namespace: number): number;
+  flushData(): Promise<void>;
+}
+"#;
+
 /// Session manager configuration for controlling session behavior.
 #[wasm_bindgen]
 pub struct SessionConfig {
@@ -28,6 +314,7 @@ pub struct SessionConfig {
 impl SessionConfig {
     /// Creates a new session configuration with the given parameters.
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)] // mirrors the positional JS constructor
     pub fn new(
         max_incoming_announcement_age_millis: f64,
         max_incoming_announcement_future_millis: f64,
@@ -49,6 +336,7 @@ impl SessionConfig {
                 keep_alive_interval_millis: keep_alive_interval_millis as u128,
                 max_session_lag_length,
                 max_keep_alive_peer_lag_length,
+                ..Default::default()
             },
         }
     }
@@ -62,20 +350,207 @@ impl SessionConfig {
     /// - Keep-alive interval: 1 day
     /// - Max lag: 10000 messages
     /// - Max peer lag before keep-alive: 8 messages
+    /// - Duplicate announcement cache: 10000 entries for 1 week
     pub fn new_default() -> Self {
         Self {
-            inner: sessions::SessionManagerConfig {
-                max_incoming_announcement_age_millis: 604_800_000, // 1 week
-                max_incoming_announcement_future_millis: 60_000,   // 1 minute
-                max_incoming_message_age_millis: 604_800_000,      // 1 week
-                max_incoming_message_future_millis: 60_000,        // 1 minute
-                max_session_inactivity_millis: 604_800_000,        // 1 week
-                keep_alive_interval_millis: 86_400_000,            // 1 day
-                max_session_lag_length: 10000,
-                max_keep_alive_peer_lag_length: 8,
-            },
+            inner: sessions::SessionManagerConfig::default(),
         }
     }
+
+    /// Sets the lag from which an active session reports `NearSaturation`,
+    /// before sends are blocked at the maximum lag, or disables it with
+    /// `undefined`.
+    pub fn set_near_saturation_lag_length(&mut self, lag: Option<f64>) {
+        self.inner.near_saturation_lag_length = lag.map(|lag| lag as u64);
+    }
+
+    /// Sets how many recently processed announcements are remembered, and for
+    /// how long, to reject duplicates without cryptographic work. A size of 0
+    /// disables the cache.
+    pub fn set_announcement_cache(&mut self, size: usize, max_age_millis: f64) {
+        self.inner.announcement_cache_size = size;
+        self.inner.announcement_cache_max_age_millis = max_age_millis as u128;
+    }
+
+    /// Sets how many seekers beyond the next one are announced to peers with
+    /// every message, so they can fetch entries ahead of time.
+    pub fn set_seeker_lookahead(&mut self, window: usize) {
+        self.inner.seeker_lookahead = window;
+    }
+
+    /// Sets the domain separation bytes of message seekers, so that
+    /// applications sharing a message board neither collide nor can be told
+    /// apart by their seekers. Peers must use the same bytes; empty ones (the
+    /// default) keep the plain gossip seekers.
+    pub fn set_seeker_namespace(&mut self, domain: Vec<u8>) {
+        self.inner.seeker_namespace = sessions::SeekerNamespace::new(domain);
+    }
+
+    /// Sets the maximum size of an incoming announcement in bytes, checked
+    /// before any cryptographic work, or removes the limit with `undefined`.
+    pub fn set_max_incoming_announcement_bytes(&mut self, max_bytes: Option<usize>) {
+        self.inner.max_incoming_announcement_bytes = max_bytes;
+    }
+
+    /// Sets whether `refresh_with_rehandshakes` announces again to peers whose
+    /// session was killed.
+    pub fn set_rehandshake_killed_sessions(&mut self, enabled: bool) {
+        self.inner.rehandshake_killed_sessions = enabled;
+    }
+
+    /// Sets how long a saturated session may go without incoming messages
+    /// before `refresh_with_rehandshakes` announces again to the peer, or
+    /// disables it with `undefined`.
+    pub fn set_rehandshake_saturated_after_millis(&mut self, millis: Option<f64>) {
+        self.inner.rehandshake_saturated_after_millis = millis.map(|millis| millis as u128);
+    }
+
+    /// Sets how long before an unanswered announcement expires
+    /// `refresh_with_rehandshakes` announces again with the same user data,
+    /// or lets announcements expire with `undefined`.
+    pub fn set_renew_requests_before_expiry_millis(&mut self, millis: Option<f64>) {
+        self.inner.renew_requests_before_expiry_millis = millis.map(|millis| millis as u128);
+    }
+
+    /// Sets the largest peer clock offset compensated when checking message
+    /// timestamps, or disables compensation with `undefined`. The offset of
+    /// every peer is estimated from the timestamps of its messages.
+    pub fn set_max_clock_skew_compensation_millis(&mut self, millis: Option<f64>) {
+        self.inner.max_clock_skew_compensation_millis = millis.map(|millis| millis as u128);
+    }
+
+    /// Sets whether announcements from unknown peers are rejected unless the
+    /// peer was allowed with `allow_peer` (error code `NOT_ALLOWED`).
+    pub fn set_allowlist_only(&mut self, enabled: bool) {
+        self.inner.allowlist_only = enabled;
+    }
+
+    /// Sets whether new contacts are announced to under an alias of their
+    /// own instead of our identity, so peers cannot link us across
+    /// conversations.
+    pub fn set_alias_identities(&mut self, enabled: bool) {
+        self.inner.alias_identities = enabled;
+    }
+
+    /// Sets the maximum number of peers, or removes the limit with
+    /// `undefined`. With the table full, a new peer evicts the least recent
+    /// unanswered request, or is rejected (error code `TOO_MANY_PEERS`).
+    pub fn set_max_peers(&mut self, max_peers: Option<usize>) {
+        self.inner.max_peers = max_peers;
+    }
+
+    /// Sets the maximum size of announcement user data in bytes, or removes
+    /// the limit with `undefined`. Larger user data fails to be sent and
+    /// incoming announcements carrying it are rejected.
+    pub fn set_max_announcement_user_data_bytes(&mut self, max_bytes: Option<usize>) {
+        self.inner.max_announcement_user_data_bytes = max_bytes;
+    }
+
+    /// Pads outgoing announcements to the smallest of the given sizes in
+    /// bytes that fits, or to a multiple of the largest, so that their size
+    /// does not reveal their user data. An empty list disables padding.
+    pub fn set_announcement_padding_buckets(&mut self, buckets: Vec<u32>) {
+        self.inner.announcement_padding = sessions::PaddingPolicy::Buckets(buckets);
+    }
+
+    /// Lets `burst` messages be sent to a peer back to back, then at most one
+    /// per `interval_millis`, or removes the limit with `undefined`. Sends
+    /// beyond that fail until the budget refills.
+    pub fn set_send_throttle(&mut self, burst: Option<u64>, interval_millis: f64) {
+        self.inner.send_burst_size = burst;
+        self.inner.send_interval_millis = interval_millis as u128;
+    }
+
+    /// Limits the number of announcements validated per interval, or removes
+    /// the limit with `undefined`. Rate-limited announcements are rejected
+    /// unexamined and can be fed again later.
+    pub fn set_announcement_rate_limit(
+        &mut self,
+        max_announcements: Option<u64>,
+        interval_millis: f64,
+    ) {
+        self.inner.max_incoming_announcements_per_interval = max_announcements;
+        self.inner.announcement_rate_interval_millis = interval_millis as u128;
+    }
+}
+
+/// ID of a user, derived from their public keys by
+/// `UserPublicKeys.derive_id`.
+///
+/// Peers are identified by `UserId` objects throughout this API, so an ID is
+/// validated once, when built from its 32 bytes, its hex encoding, or its
+/// base58check encoding: the form to show to users, whose checksum catches
+/// typos.
+///
+/// # Example
+///
+/// ```javascript
+/// const peer_id = UserId.from_base58(pasted);
+/// if (peer_id.equals(keys.public_keys().derive_id())) {
+///   // that's us
+/// }
+/// ```
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct UserId {
+    inner: auth::UserId,
+}
+
+impl From<auth::UserId> for UserId {
+    fn from(inner: auth::UserId) -> Self {
+        Self { inner }
+    }
+}
+
+#[wasm_bindgen]
+impl UserId {
+    /// Creates a user ID from its 32 bytes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<UserId, GossipError> {
+        auth::UserId::from_slice(bytes)
+            .map(Self::from)
+            .ok_or_else(|| GossipError::invalid_argument("User ID must be 32 bytes"))
+    }
+
+    /// Parses the hex encoding of a user ID, in either case.
+    pub fn from_hex(hex: &str) -> Result<UserId, GossipError> {
+        auth::UserId::from_hex(hex)
+            .map(Self::from)
+            .ok_or_else(|| GossipError::invalid_argument("User ID must be 64 hex digits"))
+    }
+
+    /// Parses the base58check encoding of a user ID.
+    pub fn from_base58(encoded: &str) -> Result<UserId, GossipError> {
+        auth::UserId::from_base58(encoded)
+            .map(Self::from)
+            .ok_or_else(|| GossipError::invalid_argument("Invalid base58 user ID"))
+    }
+
+    /// Gets the 32 bytes of the user ID.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.as_bytes().to_vec()
+    }
+
+    /// Lowercase hex encoding of the user ID, as used for map keys.
+    pub fn to_hex(&self) -> String {
+        self.inner.to_hex()
+    }
+
+    /// Base58check encoding of the user ID, to show to users.
+    pub fn to_base58(&self) -> String {
+        self.inner.to_base58()
+    }
+
+    /// Whether both objects hold the same user ID.
+    pub fn equals(&self, other: &UserId) -> bool {
+        self.inner == other.inner
+    }
+
+    /// Same as `to_hex`, so that template strings and map keys use it.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.inner.to_hex()
+    }
 }
 
 /// User public keys for authentication and encryption.
@@ -87,8 +562,8 @@ pub struct UserPublicKeys {
 #[wasm_bindgen]
 impl UserPublicKeys {
     /// Derives a unique user ID from the public keys.
-    pub fn derive_id(&self) -> Vec<u8> {
-        self.inner.derive_id().as_bytes().to_vec()
+    pub fn derive_id(&self) -> UserId {
+        self.inner.derive_id().into()
     }
 
     /// Gets the DSA verification key bytes.
@@ -116,14 +591,14 @@ impl UserPublicKeys {
     }
 
     /// Serializes the public keys to bytes.
-    pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GossipError> {
         Ok(self.inner.to_bytes())
     }
 
     /// Deserializes public keys from bytes.
-    pub fn from_bytes(bytes: &[u8]) -> Result<UserPublicKeys, JsValue> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<UserPublicKeys, GossipError> {
         let inner = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
-            .map_err(|e| JsValue::from_str(&format!("Deserialization error: {}", e)))?
+            .map_err(|e| GossipError::serialization(format!("Deserialization error: {}", e)))?
             .0;
         Ok(UserPublicKeys { inner })
     }
@@ -132,47 +607,97 @@ impl UserPublicKeys {
 /// User secret keys for signing and decryption.
 #[wasm_bindgen]
 pub struct UserSecretKeys {
-    inner: auth::UserSecretKeys,
+    /// `None` once disposed
+    inner: Option<auth::UserSecretKeys>,
+}
+
+impl UserSecretKeys {
+    fn new(inner: auth::UserSecretKeys) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    /// The secret keys, unless disposed.
+    fn inner(&self) -> Result<&auth::UserSecretKeys, GossipError> {
+        self.inner.as_ref().ok_or_else(GossipError::disposed)
+    }
 }
 
 #[wasm_bindgen]
 impl UserSecretKeys {
     /// Serializes the secret keys to bytes for secure storage.
-    pub fn to_bytes(&self) -> Result<Vec<u8>, JsValue> {
-        bincode::serde::encode_to_vec(&self.inner, bincode::config::standard())
-            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GossipError> {
+        bincode::serde::encode_to_vec(self.inner()?, bincode::config::standard())
+            .map_err(|e| GossipError::serialization(format!("Serialization error: {}", e)))
     }
 
     /// Deserializes secret keys from bytes.
-    pub fn from_bytes(bytes: &[u8]) -> Result<UserSecretKeys, JsValue> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<UserSecretKeys, GossipError> {
         let inner = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
-            .map_err(|e| JsValue::from_str(&format!("Deserialization error: {}", e)))?
+            .map_err(|e| GossipError::serialization(format!("Deserialization error: {}", e)))?
             .0;
-        Ok(UserSecretKeys { inner })
+        Ok(UserSecretKeys::new(inner))
+    }
+
+    /// Encrypts the secret keys with a password, for backups and storage.
+    pub fn export_encrypted(&self, password: &[u8]) -> Result<Vec<u8>, GossipError> {
+        Ok(self.inner()?.export_encrypted(password))
+    }
+
+    /// Decrypts secret keys exported with `export_encrypted`.
+    pub fn import_encrypted(
+        envelope: &[u8],
+        password: &[u8],
+    ) -> Result<UserSecretKeys, GossipError> {
+        let inner = auth::UserSecretKeys::import_encrypted(envelope, password)
+            .map_err(GossipError::from)?;
+        Ok(UserSecretKeys::new(inner))
+    }
+
+    /// Gets the DSA signing key bytes, or `undefined` once disposed.
+    #[wasm_bindgen(getter)]
+    pub fn dsa_signing_key(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.dsa_signing_key.as_bytes().to_vec())
     }
 
-    /// Gets the DSA signing key bytes.
+    /// Gets the KEM secret key bytes, or `undefined` once disposed.
     #[wasm_bindgen(getter)]
-    pub fn dsa_signing_key(&self) -> Vec<u8> {
-        self.inner.dsa_signing_key.as_bytes().to_vec()
+    pub fn kem_secret_key(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.kem_secret_key.as_bytes().to_vec())
     }
 
-    /// Gets the KEM secret key bytes.
+    /// Gets only the Massa secret key bytes, or `undefined` once disposed.
     #[wasm_bindgen(getter)]
-    pub fn kem_secret_key(&self) -> Vec<u8> {
-        self.inner.kem_secret_key.as_bytes().to_vec()
+    pub fn massa_secret_key(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.massa_keypair.to_bytes().to_vec())
     }
 
-    /// Gets only the Massa secret key bytes
+    /// Gets the EVM secret key bytes (raw 32-byte scalar), or `undefined`
+    /// once disposed.
     #[wasm_bindgen(getter)]
-    pub fn massa_secret_key(&self) -> Vec<u8> {
-        self.inner.massa_keypair.to_bytes().to_vec()
+    pub fn evm_secret_key(&self) -> Option<Vec<u8>> {
+        let inner = self.inner.as_ref()?;
+        Some(inner.evm_secret_key.to_vec())
+    }
+
+    /// Zeroizes the secret keys. The object stays valid but holds nothing:
+    /// getters return `undefined` and functions given it throw a
+    /// `GossipError` with code `DISPOSED`. Unlike `free()`, calling it twice
+    /// or using the object afterwards is safe.
+    ///
+    /// Copies already handed to JS by getters or `to_bytes` are not affected;
+    /// overwrite them with `fill(0)`.
+    pub fn dispose(&mut self) {
+        // zeroized on drop
+        self.inner = None;
     }
 
-    /// Gets the EVM secret key bytes (raw 32-byte scalar).
+    /// Whether `dispose()` was called.
     #[wasm_bindgen(getter)]
-    pub fn evm_secret_key(&self) -> Vec<u8> {
-        self.inner.evm_secret_key.to_vec()
+    pub fn disposed(&self) -> bool {
+        self.inner.is_none()
     }
 }
 
@@ -180,7 +705,10 @@ impl UserSecretKeys {
 #[wasm_bindgen]
 pub struct UserKeys {
     public_keys_bytes: Vec<u8>,
-    secret_keys_bytes: Vec<u8>,
+    /// `None` once disposed
+    secret_keys_bytes: Option<Zeroizing<Vec<u8>>>,
+    /// Set by `generate_user_keys_with_mnemonic`, `None` once disposed
+    mnemonic: Option<Zeroizing<String>>,
     evm_address: String,
     massa_address: String,
 }
@@ -188,13 +716,32 @@ pub struct UserKeys {
 #[wasm_bindgen]
 impl UserKeys {
     /// Gets the public keys.
-    pub fn public_keys(&self) -> Result<UserPublicKeys, JsValue> {
+    pub fn public_keys(&self) -> Result<UserPublicKeys, GossipError> {
         UserPublicKeys::from_bytes(&self.public_keys_bytes)
     }
 
     /// Gets the secret keys.
-    pub fn secret_keys(&self) -> Result<UserSecretKeys, JsValue> {
-        UserSecretKeys::from_bytes(&self.secret_keys_bytes)
+    pub fn secret_keys(&self) -> Result<UserSecretKeys, GossipError> {
+        let secret_keys_bytes = self
+            .secret_keys_bytes
+            .as_ref()
+            .ok_or_else(GossipError::disposed)?;
+        UserSecretKeys::from_bytes(secret_keys_bytes)
+    }
+
+    /// The 24-word mnemonic the keys were generated from, if they come from
+    /// `generate_user_keys_with_mnemonic` and were not disposed.
+    pub fn mnemonic(&self) -> Option<String> {
+        Some(self.mnemonic.as_ref()?.to_string())
+    }
+
+    /// Zeroizes the secret keys and mnemonic held by this object, keeping the
+    /// public ones. `secret_keys()` throws a `GossipError` with code
+    /// `DISPOSED` afterwards.
+    pub fn dispose(&mut self) {
+        // zeroized on drop
+        self.secret_keys_bytes = None;
+        self.mnemonic = None;
     }
 
     /// EIP-55 checksummed EVM address (0x…) derived from the EVM public key.
@@ -208,22 +755,193 @@ impl UserKeys {
     }
 }
 
-/// Generates user keys from a passphrase.
+/// Fields of a `KdfParams` object.
+#[derive(serde::Deserialize)]
+struct KdfParamsObject {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+/// Reads an optional `KdfParams` object, the default parameters if absent.
+fn kdf_params(params: Option<js_sys::Object>) -> Result<crypto_password_kdf::Params, GossipError> {
+    let Some(params) = params else {
+        return Ok(crypto_password_kdf::Params::DEFAULT);
+    };
+    let params: KdfParamsObject = serde_wasm_bindgen::from_value(params.into())
+        .map_err(|e| GossipError::invalid_argument(format!("Invalid KDF parameters: {}", e)))?;
+    crypto_password_kdf::Params::new(params.memory_kib, params.iterations, params.parallelism)
+        .ok_or_else(|| {
+            GossipError::invalid_argument(format!(
+                "KDF parameters must have at least {} KiB of memory, {} iterations, and 1 to {} threads",
+                crypto_password_kdf::MIN_MEMORY_KIB,
+                crypto_password_kdf::MIN_ITERATIONS,
+                crypto_password_kdf::MAX_PARALLELISM,
+            ))
+        })
+}
+
+/// Generates user keys from a passphrase, with the default KDF cost unless
+/// `params` are given.
 ///
 /// Derives all gossip keys (DSA, KEM, Massa, EVM) in a single WASM call so
-/// the passphrase crosses the JS boundary only once.
+/// the passphrase crosses the JS boundary only once. Throws if `params` are
+/// below the minimums of `KdfParams`.
+///
+/// # Example
+///
+/// ```javascript
+/// // slower to unlock than the default, harder to brute-force
+/// const desktop = { memory_kib: 65536, iterations: 3, parallelism: 1 };
+/// const keys = generate_user_keys(passphrase, desktop);
+/// ```
+#[wasm_bindgen]
+pub fn generate_user_keys(
+    passphrase: &str,
+    #[wasm_bindgen(unchecked_param_type = "KdfParams")] params: Option<js_sys::Object>,
+) -> Result<UserKeys, GossipError> {
+    let root_secret = auth::StaticRootSecret::from_passphrase_with_params(
+        passphrase.as_bytes(),
+        &kdf_params(params)?,
+    );
+    let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+    user_keys(public_keys, secret_keys)
+}
+
+/// Generates the user keys of a purpose, such as `gossip/chat/0`, from a
+/// passphrase.
+///
+/// Each purpose yields a separate identity that cannot be linked to the
+/// others or to the one of `generate_user_keys`.
+#[wasm_bindgen]
+pub fn generate_user_keys_for_purpose(
+    passphrase: &str,
+    purpose: &str,
+    #[wasm_bindgen(unchecked_param_type = "KdfParams")] params: Option<js_sys::Object>,
+) -> Result<UserKeys, GossipError> {
+    let root_secret = auth::StaticRootSecret::from_passphrase_with_params(
+        passphrase.as_bytes(),
+        &kdf_params(params)?,
+    );
+    let (public_keys, secret_keys) = auth::derive_keys_for_purpose(&root_secret, purpose);
+    user_keys(public_keys, secret_keys)
+}
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// Lets the event loop run pending tasks (rendering, input) before
+/// continuing. A resolved promise would only drain microtasks.
+async fn yield_to_event_loop() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, 0);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Calls the progress callback, if any. A throwing callback does not abort
+/// the operation it reports on.
+fn report_progress(on_progress: Option<&js_sys::Function>, progress: f64) {
+    if let Some(on_progress) = on_progress {
+        let _ = on_progress.call1(&JsValue::NULL, &JsValue::from_f64(progress));
+    }
+}
+
+/// Generates user keys from a fresh 24-word BIP39 mnemonic, to be written
+/// down as a paper backup of the identity. Get the words with
+/// `mnemonic()`, show them once, then `dispose()` the keys.
+///
+/// # Example
+///
+/// ```javascript
+/// const keys = generate_user_keys_with_mnemonic();
+/// showBackupSheet(keys.mnemonic());
+/// // later, on another device
+/// const recovered = recover_user_keys_from_mnemonic(words);
+/// ```
+#[wasm_bindgen]
+pub fn generate_user_keys_with_mnemonic() -> Result<UserKeys, GossipError> {
+    let mnemonic = auth::generate_mnemonic();
+    let root_secret =
+        auth::root_secret_from_mnemonic(&mnemonic).expect("generated mnemonics are valid");
+    let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+    let mut user_keys = user_keys(public_keys, secret_keys)?;
+    user_keys.mnemonic = Some(mnemonic);
+    Ok(user_keys)
+}
+
+/// Recovers the user keys backed up by a mnemonic of
+/// `generate_user_keys_with_mnemonic`.
+///
+/// Words may be separated by any whitespace. Throws an `INVALID_ARGUMENT`
+/// error if the phrase is not 24 English BIP39 words with a valid checksum,
+/// so a mistyped word is reported instead of recovering another identity.
+#[wasm_bindgen]
+pub fn recover_user_keys_from_mnemonic(words: &str) -> Result<UserKeys, GossipError> {
+    let root_secret = auth::root_secret_from_mnemonic(words).ok_or_else(|| {
+        GossipError::invalid_argument("Mnemonic must be 24 BIP39 words with a valid checksum")
+    })?;
+    let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+    user_keys(public_keys, secret_keys)
+}
+
+/// Share of `generate_user_keys_async` progress reported once the password
+/// KDF, which takes most of the time, is done.
+const KEYGEN_KDF_PROGRESS: f64 = 0.9;
+
+/// Same as `generate_user_keys`, yielding to the event loop before the
+/// password KDF and before deriving the key pairs, and reporting progress
+/// from 0 to 1 to `on_progress`.
+///
+/// The KDF itself runs in one block, as Argon2 offers no way to pause
+/// between its passes: the page still stalls for its duration (1 to 3
+/// seconds), but can render a progress indicator first. Call this from a
+/// worker to keep the page responsive throughout.
+///
+/// # Example
+///
+/// ```javascript
+/// const keys = await generate_user_keys_async(passphrase, (progress) => {
+///   progressBar.value = progress;
+/// });
+/// ```
 #[wasm_bindgen]
-pub fn generate_user_keys(passphrase: &str) -> Result<UserKeys, JsValue> {
-    let root_secret = auth::StaticRootSecret::from_passphrase(passphrase.as_bytes());
+pub async fn generate_user_keys_async(
+    passphrase: String,
+    on_progress: Option<js_sys::Function>,
+    #[wasm_bindgen(unchecked_param_type = "KdfParams")] params: Option<js_sys::Object>,
+) -> Result<UserKeys, GossipError> {
+    let passphrase = Zeroizing::new(passphrase);
+    let params = kdf_params(params)?;
+    report_progress(on_progress.as_ref(), 0.0);
+    yield_to_event_loop().await;
+    let root_secret =
+        auth::StaticRootSecret::from_passphrase_with_params(passphrase.as_bytes(), &params);
+    report_progress(on_progress.as_ref(), KEYGEN_KDF_PROGRESS);
+    yield_to_event_loop().await;
     let (public_keys, secret_keys) = auth::derive_keys_from_static_root_secret(&root_secret);
+    let user_keys = user_keys(public_keys, secret_keys)?;
+    report_progress(on_progress.as_ref(), 1.0);
+    Ok(user_keys)
+}
 
+fn user_keys(
+    public_keys: auth::UserPublicKeys,
+    secret_keys: auth::UserSecretKeys,
+) -> Result<UserKeys, GossipError> {
     let evm_address = public_keys.evm_address();
     let massa_address = public_keys.massa_address();
 
     Ok(UserKeys {
         public_keys_bytes: public_keys.to_bytes(),
-        secret_keys_bytes: bincode::serde::encode_to_vec(&secret_keys, bincode::config::standard())
-            .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))?,
+        secret_keys_bytes: Some(Zeroizing::new(
+            bincode::serde::encode_to_vec(&secret_keys, bincode::config::standard())
+                .map_err(|e| GossipError::serialization(format!("Serialization error: {}", e)))?,
+        )),
+        mnemonic: None,
         evm_address,
         massa_address,
     })
@@ -234,7 +952,19 @@ pub fn generate_user_keys(passphrase: &str) -> Result<UserKeys, JsValue> {
 /// AES-256-SIV uses a 64-byte (512-bit) key: two 256-bit keys for encryption and MAC.
 #[wasm_bindgen]
 pub struct EncryptionKey {
-    inner: crypto_aead::Key,
+    /// `None` once disposed
+    inner: Option<crypto_aead::Key>,
+}
+
+impl EncryptionKey {
+    fn new(inner: crypto_aead::Key) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    /// The key, unless disposed.
+    fn inner(&self) -> Result<&crypto_aead::Key, GossipError> {
+        self.inner.as_ref().ok_or_else(GossipError::disposed)
+    }
 }
 
 #[wasm_bindgen]
@@ -243,9 +973,7 @@ impl EncryptionKey {
     pub fn generate() -> Self {
         let mut key_bytes = [0u8; 64];
         crypto_rng::fill_buffer(&mut key_bytes);
-        Self {
-            inner: crypto_aead::Key::from(key_bytes),
-        }
+        Self::new(crypto_aead::Key::from(key_bytes))
     }
 
     /// Generates a deterministic encryption key (64 bytes) from a seed and salt.
@@ -255,33 +983,110 @@ impl EncryptionKey {
     ///
     /// - `seed`: application-provided seed string (treat like a password)
     /// - `salt`: unique, random salt (minimum 8 bytes, recommended 16+ bytes)
-    pub fn from_seed(seed: &str, salt: &[u8]) -> Result<EncryptionKey, JsValue> {
+    /// - `params`: KDF cost, the default one if omitted
+    pub fn from_seed(
+        seed: &str,
+        salt: &[u8],
+        #[wasm_bindgen(unchecked_param_type = "KdfParams")] params: Option<js_sys::Object>,
+    ) -> Result<EncryptionKey, GossipError> {
         if salt.len() < 8 {
-            return Err(JsValue::from_str("Salt must be at least 8 bytes"));
+            return Err(GossipError::invalid_argument(
+                "Salt must be at least 8 bytes",
+            ));
         }
 
         let mut key_bytes = [0u8; 64];
-        crypto_password_kdf::derive(seed.as_bytes(), salt, &mut key_bytes);
-        Ok(Self {
-            inner: crypto_aead::Key::from(key_bytes),
-        })
+        crypto_password_kdf::derive_with_params(
+            seed.as_bytes(),
+            salt,
+            &kdf_params(params)?,
+            &mut key_bytes,
+        );
+        Ok(Self::new(crypto_aead::Key::from(key_bytes)))
+    }
+
+    /// Derives an encryption key from the output of a passkey's WebAuthn
+    /// `prf` extension, for passwordless unlock.
+    ///
+    /// Each `purpose` (e.g. `"storage"`, `"sessions"`) gets an unrelated key
+    /// from the same PRF output. Evaluate the PRF with a salt fixed by the
+    /// application, and keep a password or mnemonic as a fallback: the key
+    /// cannot be recovered without the passkey.
+    ///
+    /// # Example
+    ///
+    /// ```javascript
+    /// const credential = await navigator.credentials.get({
+    ///   publicKey: { challenge, extensions: { prf: { eval: { first: appSalt } } } },
+    /// });
+    /// const prfOutput = new Uint8Array(credential.getClientExtensionResults().prf.results.first);
+    /// const key = EncryptionKey.from_prf_output(prfOutput, "sessions");
+    /// prfOutput.fill(0);
+    /// ```
+    pub fn from_prf_output(prf_output: &[u8], purpose: &str) -> Result<EncryptionKey, GossipError> {
+        auth::encryption_key_from_prf_output(prf_output, purpose)
+            .map(Self::new)
+            .ok_or_else(|| {
+                GossipError::invalid_argument(format!(
+                    "PRF output must be at least {} bytes",
+                    auth::MIN_PRF_OUTPUT_SIZE
+                ))
+            })
+    }
+
+    /// Encrypts this key under `wrapping_key`, to store it without exposing
+    /// it. The result is 80 bytes.
+    pub fn wrap(&self, wrapping_key: &EncryptionKey) -> Result<Vec<u8>, GossipError> {
+        Ok(crypto_aead::wrap_key(wrapping_key.inner()?, self.inner()?))
+    }
+
+    /// Decrypts a key wrapped with `wrap`.
+    pub fn from_wrapped(
+        wrapped: &[u8],
+        wrapping_key: &EncryptionKey,
+    ) -> Result<EncryptionKey, GossipError> {
+        crypto_aead::unwrap_key(wrapping_key.inner()?, wrapped)
+            .map(Self::new)
+            .ok_or_else(|| GossipError::crypto("DECRYPTION", "Failed to unwrap key"))
+    }
+
+    /// Wraps a key wrapped under `old_wrapping_key` under `new_wrapping_key`
+    /// instead, without the key ever leaving WASM memory.
+    pub fn rewrap(
+        wrapped: &[u8],
+        old_wrapping_key: &EncryptionKey,
+        new_wrapping_key: &EncryptionKey,
+    ) -> Result<Vec<u8>, GossipError> {
+        let key = Self::from_wrapped(wrapped, old_wrapping_key)?;
+        key.wrap(new_wrapping_key)
     }
 
     /// Creates an encryption key from raw bytes (must be 64 bytes).
-    pub fn from_bytes(bytes: &[u8]) -> Result<EncryptionKey, JsValue> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<EncryptionKey, GossipError> {
         if bytes.len() != 64 {
-            return Err(JsValue::from_str("Key must be 64 bytes"));
+            return Err(GossipError::invalid_argument("Key must be 64 bytes"));
         }
         let mut key_bytes = [0u8; 64];
         key_bytes.copy_from_slice(bytes);
-        Ok(Self {
-            inner: crypto_aead::Key::from(key_bytes),
-        })
+        Ok(Self::new(crypto_aead::Key::from(key_bytes)))
     }
 
-    /// Gets the raw bytes of the encryption key.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        self.inner.as_bytes().to_vec()
+    /// Gets the raw bytes of the encryption key, or `undefined` once disposed.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.inner.as_ref()?.as_bytes().to_vec())
+    }
+
+    /// Zeroizes the key. Functions given it afterwards throw a `GossipError`
+    /// with code `DISPOSED`, and `to_bytes` returns `undefined`.
+    pub fn dispose(&mut self) {
+        // zeroized on drop
+        self.inner = None;
+    }
+
+    /// Whether `dispose()` was called.
+    #[wasm_bindgen(getter)]
+    pub fn disposed(&self) -> bool {
+        self.inner.is_none()
     }
 }
 
@@ -291,7 +1096,19 @@ impl EncryptionKey {
 /// per encryption for maximum security, though SIV mode is nonce-misuse resistant.
 #[wasm_bindgen]
 pub struct Nonce {
-    inner: crypto_aead::Nonce,
+    /// `None` once disposed
+    inner: Option<crypto_aead::Nonce>,
+}
+
+impl Nonce {
+    fn new(inner: crypto_aead::Nonce) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    /// The nonce, unless disposed.
+    fn inner(&self) -> Result<&crypto_aead::Nonce, GossipError> {
+        self.inner.as_ref().ok_or_else(GossipError::disposed)
+    }
 }
 
 #[wasm_bindgen]
@@ -300,26 +1117,48 @@ impl Nonce {
     pub fn generate() -> Self {
         let mut nonce_bytes = [0u8; 16];
         crypto_rng::fill_buffer(&mut nonce_bytes);
-        Self {
-            inner: crypto_aead::Nonce::from(nonce_bytes),
-        }
+        Self::new(crypto_aead::Nonce::from(nonce_bytes))
     }
 
     /// Creates a nonce from raw bytes (must be 16 bytes).
-    pub fn from_bytes(bytes: &[u8]) -> Result<Nonce, JsValue> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Nonce, GossipError> {
         if bytes.len() != 16 {
-            return Err(JsValue::from_str("Nonce must be 16 bytes"));
+            return Err(GossipError::invalid_argument("Nonce must be 16 bytes"));
         }
         let mut nonce_bytes = [0u8; 16];
         nonce_bytes.copy_from_slice(bytes);
-        Ok(Self {
-            inner: crypto_aead::Nonce::from(nonce_bytes),
-        })
+        Ok(Self::new(crypto_aead::Nonce::from(nonce_bytes)))
     }
 
-    /// Gets the raw bytes of the nonce.
-    pub fn to_bytes(&self) -> Vec<u8> {
-        self.inner.as_bytes().to_vec()
+    /// Derives the nonce of message `counter` of `context` under `key`.
+    ///
+    /// Distinct `(context, counter)` pairs give unrelated nonces, so keeping
+    /// a counter is enough to never reuse one. `SivEncryptor` manages the
+    /// counter for you.
+    pub fn derive(key: &EncryptionKey, context: &[u8], counter: u64) -> Result<Nonce, GossipError> {
+        Ok(Self::new(crypto_aead::Nonce::derive(
+            key.inner()?,
+            context,
+            counter,
+        )))
+    }
+
+    /// Gets the raw bytes of the nonce, or `undefined` once disposed.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.inner.as_ref()?.as_bytes().to_vec())
+    }
+
+    /// Zeroizes the nonce. Functions given it afterwards throw a
+    /// `GossipError` with code `DISPOSED`, and `to_bytes` returns `undefined`.
+    pub fn dispose(&mut self) {
+        // zeroized on drop
+        self.inner = None;
+    }
+
+    /// Whether `dispose()` was called.
+    #[wasm_bindgen(getter)]
+    pub fn disposed(&self) -> bool {
+        self.inner.is_none()
     }
 }
 
@@ -354,8 +1193,18 @@ impl Nonce {
 /// const ciphertext = aead_encrypt(key, nonce, plaintext, aad);
 /// ```
 #[wasm_bindgen]
-pub fn aead_encrypt(key: &EncryptionKey, nonce: &Nonce, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
-    crypto_aead::encrypt(&key.inner, &nonce.inner, plaintext, aad)
+pub fn aead_encrypt(
+    key: &EncryptionKey,
+    nonce: &Nonce,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>, GossipError> {
+    Ok(crypto_aead::encrypt(
+        key.inner()?,
+        nonce.inner()?,
+        plaintext,
+        aad,
+    ))
 }
 
 /// Decrypts data using AES-256-SIV authenticated encryption.
@@ -395,8 +1244,269 @@ pub fn aead_decrypt(
     nonce: &Nonce,
     ciphertext: &[u8],
     aad: &[u8],
-) -> Option<Vec<u8>> {
-    crypto_aead::decrypt(&key.inner, &nonce.inner, ciphertext, aad)
+) -> Result<Option<Vec<u8>>, GossipError> {
+    Ok(crypto_aead::decrypt(
+        key.inner()?,
+        nonce.inner()?,
+        ciphertext,
+        aad,
+    ))
+}
+
+/// Encrypts a large payload chunk by chunk, with bounded memory.
+///
+/// Each call to `update` returns the ciphertext of one chunk, `TAG_SIZE`
+/// (16) bytes longer than the chunk; `finalize` encrypts the last chunk,
+/// which may be empty, and consumes the encryptor. Decrypt with
+/// `AeadStreamDecryptor`, passing the ciphertext chunks as they were
+/// returned: with plaintext chunks of a fixed size, split the ciphertext at
+/// that size plus 16.
+///
+/// Chunks are bound to their position and to the end of the stream, so
+/// reordered, dropped or truncated chunks fail to decrypt. Use a fresh nonce
+/// for every stream encrypted with the same key.
+///
+/// # Example
+///
+/// ```javascript
+/// const encryptor = new AeadStreamEncryptor(key, nonce, aad);
+/// for (const chunk of chunks) upload(encryptor.update(chunk));
+/// upload(encryptor.finalize(new Uint8Array()));
+/// ```
+#[wasm_bindgen]
+pub struct AeadStreamEncryptor {
+    inner: crypto_aead::StreamEncryptor,
+}
+
+#[wasm_bindgen]
+impl AeadStreamEncryptor {
+    /// Starts a stream encrypted with `key` and `nonce`, authenticating
+    /// `aad` along with every chunk.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &EncryptionKey,
+        nonce: &Nonce,
+        aad: &[u8],
+    ) -> Result<AeadStreamEncryptor, GossipError> {
+        Ok(Self {
+            inner: crypto_aead::StreamEncryptor::new(key.inner()?, nonce.inner()?, aad),
+        })
+    }
+
+    /// Encrypts the next chunk, which must not be the last one.
+    pub fn update(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.inner.encrypt_chunk(chunk)
+    }
+
+    /// Encrypts the last chunk and ends the stream.
+    pub fn finalize(self, chunk: &[u8]) -> Vec<u8> {
+        self.inner.finalize(chunk)
+    }
+}
+
+/// Decrypts, chunk by chunk, a payload encrypted with `AeadStreamEncryptor`.
+///
+/// Every chunk is authenticated before it is returned, but the payload is
+/// only complete once `finalize` accepted the last chunk: until then, treat
+/// it as possibly truncated. After a failed chunk the decryptor is unusable.
+#[wasm_bindgen]
+pub struct AeadStreamDecryptor {
+    inner: crypto_aead::StreamDecryptor,
+}
+
+#[wasm_bindgen]
+impl AeadStreamDecryptor {
+    /// Starts reading a stream encrypted with this key, nonce and `aad`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &EncryptionKey,
+        nonce: &Nonce,
+        aad: &[u8],
+    ) -> Result<AeadStreamDecryptor, GossipError> {
+        Ok(Self {
+            inner: crypto_aead::StreamDecryptor::new(key.inner()?, nonce.inner()?, aad),
+        })
+    }
+
+    /// Decrypts the next chunk, which must not be the last one. Throws if it
+    /// was modified or is out of place.
+    pub fn update(&mut self, chunk: &[u8]) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .decrypt_chunk(chunk)
+            .ok_or_else(|| GossipError::crypto("DECRYPTION", "Failed to decrypt stream chunk"))
+    }
+
+    /// Decrypts the last chunk and ends the stream. Throws if it was
+    /// modified, is out of place, or is not the last chunk.
+    pub fn finalize(self, chunk: &[u8]) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .finalize(chunk)
+            .ok_or_else(|| GossipError::crypto("DECRYPTION", "Failed to decrypt stream chunk"))
+    }
+}
+
+/// Encrypts messages of one context with nonces derived from a counter, so
+/// you never pick nonces yourself.
+///
+/// Each message starts with the 8-byte big-endian counter its nonce was
+/// derived from. Save `counter` after encrypting and pass it back when
+/// creating the encryptor again: starting over from a used counter reuses
+/// its nonce.
+///
+/// # Example
+///
+/// ```javascript
+/// const encryptor = new SivEncryptor(key, context, loadCounter() ?? 0n);
+/// const message = encryptor.encrypt(plaintext, aad);
+/// saveCounter(encryptor.counter);
+/// const decrypted = encryptor.decrypt(message, aad);
+/// ```
+#[wasm_bindgen]
+pub struct SivEncryptor {
+    inner: crypto_aead::SivEncryptor,
+}
+
+#[wasm_bindgen]
+impl SivEncryptor {
+    /// Starts encrypting for `context` with `key`, at `counter` (0 if
+    /// omitted).
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &EncryptionKey,
+        context: &[u8],
+        counter: Option<u64>,
+    ) -> Result<SivEncryptor, GossipError> {
+        Ok(Self {
+            inner: crypto_aead::SivEncryptor::new(key.inner()?, context, counter.unwrap_or(0)),
+        })
+    }
+
+    /// The counter of the next message.
+    #[wasm_bindgen(getter)]
+    pub fn counter(&self) -> u64 {
+        self.inner.counter()
+    }
+
+    /// Encrypts a message under the next counter.
+    pub fn encrypt(&mut self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        self.inner.encrypt(plaintext, aad)
+    }
+
+    /// Decrypts a message encrypted with the same key and context. Throws if
+    /// it was modified or belongs to another key or context.
+    pub fn decrypt(&self, message: &[u8], aad: &[u8]) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .decrypt(message, aad)
+            .ok_or_else(|| GossipError::crypto("DECRYPTION", "Failed to decrypt message"))
+    }
+}
+
+/// Signs an application payload, such as a profile update or a registry
+/// entry, with the ML-DSA key of an identity.
+///
+/// The signature is bound to an application-specific context: it cannot be
+/// mistaken for a signature of the messaging protocol.
+///
+/// # Example
+///
+/// ```javascript
+/// const signature = sign_message(keys.secret_keys(), payload);
+/// const valid = verify_signature(keys.public_keys(), payload, signature);
+/// ```
+#[wasm_bindgen]
+pub fn sign_message(secret_keys: &UserSecretKeys, message: &[u8]) -> Result<Vec<u8>, GossipError> {
+    Ok(secret_keys
+        .inner()?
+        .sign_message(message)
+        .as_bytes()
+        .to_vec())
+}
+
+/// Verifies a signature made with `sign_message`.
+///
+/// Returns `false` if the signature is invalid, was made by another identity
+/// or over another payload, or does not have the size of an ML-DSA signature.
+#[wasm_bindgen]
+pub fn verify_signature(public_keys: &UserPublicKeys, message: &[u8], signature: &[u8]) -> bool {
+    let Ok(signature) = <[u8; crypto_dsa::SIGNATURE_SIZE]>::try_from(signature) else {
+        return false;
+    };
+    public_keys
+        .inner
+        .verify_message(message, &crypto_dsa::Signature::from(signature))
+}
+
+/// Result of `kem_encapsulate`: a ciphertext for the peer and the shared
+/// secret it carries.
+#[wasm_bindgen]
+pub struct KemEncapsulation {
+    ciphertext: Vec<u8>,
+    shared_secret: crypto_kem::SharedSecret,
+}
+
+#[wasm_bindgen]
+impl KemEncapsulation {
+    /// Ciphertext to send to the peer, who recovers the shared secret with
+    /// `kem_decapsulate`.
+    #[wasm_bindgen(getter)]
+    pub fn ciphertext(&self) -> Vec<u8> {
+        self.ciphertext.clone()
+    }
+
+    /// The 32-byte shared secret.
+    #[wasm_bindgen(getter)]
+    pub fn shared_secret(&self) -> Vec<u8> {
+        self.shared_secret.as_bytes().to_vec()
+    }
+}
+
+/// Encapsulates a fresh shared secret to the ML-KEM key of a peer, for
+/// one-off post-quantum sealed boxes outside the session protocol, such as
+/// encrypting a backup to a contact.
+///
+/// The shared secret is 32 bytes: derive encryption keys from it with a KDF
+/// rather than using it directly. Sealed boxes give no forward secrecy and
+/// do not authenticate the sender; sign the payload with `sign_message` if
+/// the peer must know who sent it.
+///
+/// # Example
+///
+/// ```javascript
+/// const { ciphertext, shared_secret } = kem_encapsulate(peer_public_keys);
+/// // ...and on the peer's side
+/// const same_secret = kem_decapsulate(peer_secret_keys, ciphertext);
+/// ```
+#[wasm_bindgen]
+pub fn kem_encapsulate(peer_pk: &UserPublicKeys) -> KemEncapsulation {
+    let mut randomness = [0u8; crypto_kem::ENCAPSULATION_RANDOMNESS_SIZE];
+    crypto_rng::fill_buffer(&mut randomness);
+    let (ciphertext, shared_secret) =
+        crypto_kem::encapsulate(&peer_pk.inner.kem_public_key, randomness);
+    KemEncapsulation {
+        ciphertext: ciphertext.as_bytes().to_vec(),
+        shared_secret,
+    }
+}
+
+/// Recovers the shared secret of a ciphertext from `kem_encapsulate`.
+///
+/// ML-KEM decapsulation does not fail on a forged or corrupted ciphertext:
+/// it yields an unrelated secret, so authenticate whatever the secret
+/// protects. Fails only if the ciphertext does not have the size of an
+/// ML-KEM ciphertext.
+#[wasm_bindgen]
+pub fn kem_decapsulate(our_sk: &UserSecretKeys, ciphertext: &[u8]) -> Result<Vec<u8>, GossipError> {
+    let ciphertext = <[u8; crypto_kem::CIPHERTEXT_SIZE]>::try_from(ciphertext).map_err(|_| {
+        GossipError::invalid_argument(format!(
+            "Ciphertext must be {} bytes",
+            crypto_kem::CIPHERTEXT_SIZE
+        ))
+    })?;
+    let shared_secret = crypto_kem::decapsulate(
+        &our_sk.inner()?.kem_secret_key,
+        &crypto_kem::Ciphertext::from(ciphertext),
+    );
+    Ok(shared_secret.as_bytes().to_vec())
 }
 
 /// Session status indicating the state of a peer session.
@@ -408,20 +1518,137 @@ pub enum SessionStatus {
     PeerRequested,
     SelfRequested,
     Killed,
+    Closed,
     Saturated,
+    LinkedDevice,
+    NearSaturation,
+}
+
+impl From<sessions::SessionStatus> for SessionStatus {
+    fn from(status: sessions::SessionStatus) -> Self {
+        match status {
+            sessions::SessionStatus::Active => SessionStatus::Active,
+            sessions::SessionStatus::UnknownPeer => SessionStatus::UnknownPeer,
+            sessions::SessionStatus::NoSession => SessionStatus::NoSession,
+            sessions::SessionStatus::PeerRequested => SessionStatus::PeerRequested,
+            sessions::SessionStatus::SelfRequested => SessionStatus::SelfRequested,
+            sessions::SessionStatus::Killed => SessionStatus::Killed,
+            sessions::SessionStatus::Closed => SessionStatus::Closed,
+            sessions::SessionStatus::NearSaturation => SessionStatus::NearSaturation,
+            sessions::SessionStatus::Saturated => SessionStatus::Saturated,
+            sessions::SessionStatus::LinkedDevice => SessionStatus::LinkedDevice,
+        }
+    }
+}
+
+/// How the user data of an outgoing announcement travels.
+#[wasm_bindgen]
+pub enum UserDataMode {
+    /// In the announcement, without post-compromise secrecy
+    Announcement,
+    /// As the first message of the session once established
+    Session,
+}
+
+/// Delivery status of a sent message.
+#[wasm_bindgen]
+pub enum MessageStatus {
+    Pending,
+    Delivered,
+}
+
+/// Protocol traffic received in place of a user payload.
+#[wasm_bindgen]
+#[derive(Clone, Copy)]
+pub enum ControlMessage {
+    /// Empty message sent by the peer to keep the session alive
+    KeepAlive,
+    /// Liveness probe, answered automatically
+    Ping,
+    /// Answer to one of our pings
+    Pong,
+    /// The peer read our messages, up to `read_up_to_message_id`
+    ReadReceipt,
+    /// The peer closed the session
+    Close,
+    /// Control message of a kind introduced by a later version
+    Unknown,
+}
+
+impl From<sessions::ControlMessage> for ControlMessage {
+    fn from(control: sessions::ControlMessage) -> Self {
+        match control {
+            sessions::ControlMessage::KeepAlive => ControlMessage::KeepAlive,
+            sessions::ControlMessage::Ping => ControlMessage::Ping,
+            sessions::ControlMessage::Pong => ControlMessage::Pong,
+            sessions::ControlMessage::ReadReceipt { .. } => ControlMessage::ReadReceipt,
+            sessions::ControlMessage::Close => ControlMessage::Close,
+            _ => ControlMessage::Unknown,
+        }
+    }
+}
+
+/// Copies `bytes` into `target` from `offset`, returning the number of bytes
+/// written.
+fn copy_into(bytes: &[u8], target: &js_sys::Uint8Array, offset: u32) -> Result<u32, GossipError> {
+    let end = u32::try_from(bytes.len())
+        .ok()
+        .and_then(|len| offset.checked_add(len))
+        .filter(|end| *end <= target.length())
+        .ok_or_else(|| GossipError::invalid_argument("Target array is too small"))?;
+    target.subarray(offset, end).copy_from(bytes);
+    Ok(end - offset)
+}
+
+/// View of `bytes` in WASM memory, without copying.
+fn view(bytes: &[u8]) -> js_sys::Uint8Array {
+    // SAFETY: the view is handed to JS, which is documented to use it before
+    // any other call into the module may move or free `bytes`
+    unsafe { js_sys::Uint8Array::view(bytes) }
 }
 
 /// Output from sending a message.
+///
+/// The `data` and `seeker` getters copy the bytes on every access. For
+/// high-throughput sync, read the lengths and either copy the bytes into a
+/// buffer of your own with `copy_data_into` / `copy_seeker_into`, or read them
+/// in place with `data_view` / `seeker_view`.
 #[wasm_bindgen]
 pub struct SendMessageOutput {
+    message_id: f64,
     seeker: Vec<u8>,
     data: Vec<u8>,
+    handle: Option<u64>,
+    session_epoch: Option<f64>,
+    lag_length: Option<f64>,
+    remaining_lag_budget: Option<f64>,
+}
+
+impl SendMessageOutput {
+    fn from_output(output: &sessions::SendOutgoingMessageOutput) -> Self {
+        Self {
+            message_id: output.message_id as f64,
+            seeker: output.seeker.clone(),
+            data: output.data.clone(),
+            handle: output.handle.map(sessions::MessageHandle::to_u64),
+            session_epoch: output.session_epoch.map(|epoch| epoch as f64),
+            lag_length: output.lag_length.map(|lag| lag as f64),
+            remaining_lag_budget: output.remaining_lag_budget.map(|budget| budget as f64),
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl SendMessageOutput {
-    /// Gets the seeker (identifier for message board lookup).
+    /// Gets the message ID, increasing by one with every message of the
+    /// session.
     #[wasm_bindgen(getter)]
+    pub fn message_id(&self) -> f64 {
+        self.message_id
+    }
+
+    /// Gets the seeker (identifier for message board lookup).
+    #[wasm_bindgen(getter, unchecked_return_type = "Seeker")]
     pub fn seeker(&self) -> Vec<u8> {
         self.seeker.clone()
     }
@@ -431,18 +1658,135 @@ impl SendMessageOutput {
     pub fn data(&self) -> Vec<u8> {
         self.data.clone()
     }
+
+    /// Gets the length of the seeker in bytes.
+    #[wasm_bindgen(getter)]
+    pub fn seeker_len(&self) -> usize {
+        self.seeker.len()
+    }
+
+    /// Gets the length of the encrypted message data in bytes.
+    #[wasm_bindgen(getter)]
+    pub fn data_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Copies the seeker into `target` from `offset`, without allocating.
+    /// Returns the number of bytes written; throws if `target` is too small.
+    pub fn copy_seeker_into(
+        &self,
+        target: &js_sys::Uint8Array,
+        offset: u32,
+    ) -> Result<u32, GossipError> {
+        copy_into(&self.seeker, target, offset)
+    }
+
+    /// Copies the encrypted message data into `target` from `offset`, without
+    /// allocating. Returns the number of bytes written; throws if `target` is
+    /// too small.
+    pub fn copy_data_into(
+        &self,
+        target: &js_sys::Uint8Array,
+        offset: u32,
+    ) -> Result<u32, GossipError> {
+        copy_into(&self.data, target, offset)
+    }
+
+    /// Returns a view of the seeker in WASM memory, without copying.
+    ///
+    /// The view is only valid until the next call into this module or until
+    /// this object is freed: memory growth detaches it, and later calls may
+    /// overwrite the bytes it shows. Read or copy it right away.
+    #[wasm_bindgen(unchecked_return_type = "Seeker")]
+    pub fn seeker_view(&self) -> js_sys::Uint8Array {
+        view(&self.seeker)
+    }
+
+    /// Returns a view of the encrypted message data in WASM memory, without
+    /// copying. Same validity as `seeker_view`.
+    pub fn data_view(&self) -> js_sys::Uint8Array {
+        view(&self.data)
+    }
+
+    /// Gets the delivery tracking handle, to pass to `message_status`.
+    #[wasm_bindgen(getter)]
+    pub fn handle(&self) -> Option<u64> {
+        self.handle
+    }
+
+    /// Gets the epoch of the session the message was sent in.
+    #[wasm_bindgen(getter)]
+    pub fn session_epoch(&self) -> Option<f64> {
+        self.session_epoch
+    }
+
+    /// Gets the number of our messages the peer has not acknowledged yet,
+    /// this one included.
+    #[wasm_bindgen(getter)]
+    pub fn lag_length(&self) -> Option<f64> {
+        self.lag_length
+    }
+
+    /// Gets the number of messages that can still be sent before the
+    /// session saturates; warn the user as it nears 0, when sends start
+    /// failing until the peer replies.
+    #[wasm_bindgen(getter)]
+    pub fn remaining_lag_budget(&self) -> Option<f64> {
+        self.remaining_lag_budget
+    }
 }
 
 /// Output from receiving a message.
+///
+/// The `message` getter copies the contents on every access; see
+/// `copy_message_into` and `message_view` to avoid the allocation.
 #[wasm_bindgen]
 pub struct ReceiveMessageOutput {
     message: Vec<u8>,
     timestamp: f64,
+    message_id: f64,
     acknowledged_seekers: js_sys::Array,
-    user_id: Vec<u8>,
+    user_id: auth::UserId,
+    ttl_millis: Option<f64>,
+    transfer: Option<sessions::TransferProgress>,
+    announcement_user_data: bool,
+    control: Option<ControlMessage>,
+    read_up_to_message_id: Option<f64>,
+    session_epoch: Option<f64>,
+}
+
+impl ReceiveMessageOutput {
+    fn from_output(output: &sessions::FeedIncomingMessageOutput) -> Self {
+        let acknowledged_seekers = js_sys::Array::new();
+        for ack_seeker in &output.newly_acknowledged_self_seekers {
+            let js_seeker = js_sys::Uint8Array::new_with_length(ack_seeker.len() as u32);
+            js_seeker.copy_from(&ack_seeker[..]);
+            acknowledged_seekers.push(&js_seeker);
+        }
+
+        Self {
+            message: output.message.clone(),
+            timestamp: output.timestamp as f64,
+            message_id: output.message_id as f64,
+            acknowledged_seekers,
+            user_id: auth::UserId::from_slice(&output.user_id)
+                .expect("sessions build sender IDs from a UserId"),
+            ttl_millis: output.ttl_millis.map(|ttl| ttl as f64),
+            transfer: output.transfer,
+            announcement_user_data: output.announcement_user_data,
+            control: output.control.map(ControlMessage::from),
+            read_up_to_message_id: match output.control {
+                Some(sessions::ControlMessage::ReadReceipt { up_to_message_id }) => {
+                    Some(up_to_message_id as f64)
+                }
+                _ => None,
+            },
+            session_epoch: output.session_epoch.map(|epoch| epoch as f64),
+        }
+    }
 }
 
-/// Result from feeding an incoming announcement.
+/// Result from feeding or peeking at an incoming announcement.
 #[wasm_bindgen]
 pub struct AnnouncementResult {
     inner: sessions::AnnouncementResult,
@@ -458,6 +1802,12 @@ impl AnnouncementResult {
         }
     }
 
+    /// Gets the announcer's user ID.
+    #[wasm_bindgen(getter)]
+    pub fn announcer_id(&self) -> UserId {
+        self.inner.announcer_public_keys.derive_id().into()
+    }
+
     /// Gets the announcement timestamp in milliseconds since Unix epoch.
     #[wasm_bindgen(getter)]
     pub fn timestamp(&self) -> f64 {
@@ -471,6 +1821,34 @@ impl AnnouncementResult {
     }
 }
 
+/// Decrypts an announcement and verifies its signature without feeding it to
+/// a session manager, so that a contact request can be shown ("X wants to
+/// connect") before any state is allocated for the peer.
+///
+/// Freshness, replay, blocking and the other checks of
+/// `feed_incoming_announcement` depend on the manager and are not made:
+/// feeding the announcement once accepted can still reject it.
+///
+/// # Example
+///
+/// ```javascript
+/// const peek = peek_announcement(announcement, our_pk, our_sk);
+/// const profile = AnnouncementUserData.from_bytes(peek.user_data);
+/// if (await confirm(`${profile?.display_name ?? peek.announcer_id.to_base58()} wants to connect`)) {
+///   manager.feed_incoming_announcement(announcement, our_pk, our_sk);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn peek_announcement(
+    announcement_bytes: &[u8],
+    our_pk: &UserPublicKeys,
+    our_sk: &UserSecretKeys,
+) -> Result<AnnouncementResult, GossipError> {
+    let inner =
+        sessions::parse_announcement_peek(announcement_bytes, &our_pk.inner, our_sk.inner()?)?;
+    Ok(AnnouncementResult { inner })
+}
+
 #[wasm_bindgen]
 impl ReceiveMessageOutput {
     /// Gets the received message contents.
@@ -479,66 +1857,874 @@ impl ReceiveMessageOutput {
         self.message.clone()
     }
 
+    /// Gets the length of the message contents in bytes.
+    #[wasm_bindgen(getter)]
+    pub fn message_len(&self) -> usize {
+        self.message.len()
+    }
+
+    /// Copies the message contents into `target` from `offset`, without
+    /// allocating. Returns the number of bytes written; throws if `target` is
+    /// too small.
+    pub fn copy_message_into(
+        &self,
+        target: &js_sys::Uint8Array,
+        offset: u32,
+    ) -> Result<u32, GossipError> {
+        copy_into(&self.message, target, offset)
+    }
+
+    /// Returns a view of the message contents in WASM memory, without
+    /// copying.
+    ///
+    /// The view is only valid until the next call into this module or until
+    /// this object is freed: memory growth detaches it, and later calls may
+    /// overwrite the bytes it shows. Read or copy it right away.
+    pub fn message_view(&self) -> js_sys::Uint8Array {
+        view(&self.message)
+    }
+
     /// Gets the message timestamp (milliseconds since Unix epoch).
     #[wasm_bindgen(getter)]
     pub fn timestamp(&self) -> f64 {
         self.timestamp
     }
 
-    /// Gets the list of newly acknowledged seekers.
+    /// Gets the message ID assigned by the sender, increasing by one with
+    /// every message of the session.
     #[wasm_bindgen(getter)]
+    pub fn message_id(&self) -> f64 {
+        self.message_id
+    }
+
+    /// Gets the list of newly acknowledged seekers.
+    #[wasm_bindgen(getter, unchecked_return_type = "Seeker[]")]
     pub fn acknowledged_seekers(&self) -> js_sys::Array {
         self.acknowledged_seekers.clone()
     }
 
-    /// Gets the sender's user id (32 bytes).
+    /// Gets the sender's user id.
     #[wasm_bindgen(getter)]
-    pub fn user_id(&self) -> Vec<u8> {
-        self.user_id.clone()
+    pub fn user_id(&self) -> UserId {
+        self.user_id.clone().into()
     }
-}
 
-/// Session manager wrapper for WebAssembly.
-#[wasm_bindgen]
-pub struct SessionManagerWrapper {
-    inner: sessions::SessionManager,
-}
+    /// Gets the agreed lifetime of the message plaintext in milliseconds
+    /// from its timestamp, if any.
+    #[wasm_bindgen(getter)]
+    pub fn ttl_millis(&self) -> Option<f64> {
+        self.ttl_millis
+    }
 
-#[wasm_bindgen]
-impl SessionManagerWrapper {
-    /// Creates a new session manager with the given configuration.
-    #[wasm_bindgen(constructor)]
-    pub fn new(config: SessionConfig) -> Self {
-        Self {
-            inner: sessions::SessionManager::new(config.inner),
-        }
+    /// Gets the chunked transfer the message belongs to, if any.
+    #[wasm_bindgen(getter)]
+    pub fn transfer_id(&self) -> Option<u64> {
+        self.transfer.map(|progress| progress.transfer_id)
     }
 
-    /// Deserializes a session manager from an encrypted blob.
-    pub fn from_encrypted_blob(
-        encrypted_blob: &[u8],
-        key: &EncryptionKey,
-    ) -> Result<SessionManagerWrapper, JsValue> {
-        let inner = sessions::SessionManager::from_encrypted_blob(encrypted_blob, &key.inner)
-            .ok_or_else(|| JsValue::from_str("Failed to decrypt session manager"))?;
-        Ok(Self { inner })
+    /// Gets the bytes of the chunked transfer received so far. The message
+    /// holds the whole payload once this reaches `transfer_total_bytes`.
+    #[wasm_bindgen(getter)]
+    pub fn transfer_received_bytes(&self) -> Option<f64> {
+        self.transfer.map(|progress| progress.received_bytes as f64)
     }
 
-    /// Serializes and encrypts the session manager into a blob.
-    pub fn to_encrypted_blob(&self, key: &EncryptionKey) -> Result<Vec<u8>, JsValue> {
-        self.inner
-            .to_encrypted_blob(&key.inner)
-            .ok_or_else(|| JsValue::from_str("Failed to encrypt session manager"))
+    /// Gets the total payload size of the chunked transfer.
+    #[wasm_bindgen(getter)]
+    pub fn transfer_total_bytes(&self) -> Option<f64> {
+        self.transfer.map(|progress| progress.total_bytes as f64)
     }
 
-    /// Establishes an outgoing session with a peer.
-    ///
-    /// # Parameters
-    ///
-    /// - `peer_pk`: The peer's public keys
-    /// - `our_pk`: Our public keys
-    /// - `our_sk`: Our secret keys
-    /// - `user_data`: Arbitrary user data to include in the announcement (can be empty)
+    /// Whether the message is the user data of the peer's announcement, sent
+    /// in the session with `UserDataMode.Session`.
+    #[wasm_bindgen(getter)]
+    pub fn announcement_user_data(&self) -> bool {
+        self.announcement_user_data
+    }
+
+    /// Gets the kind of protocol traffic the message carries, or `undefined`
+    /// for a payload of the peer's application. Control messages have an
+    /// empty `message`.
+    #[wasm_bindgen(getter)]
+    pub fn control(&self) -> Option<ControlMessage> {
+        self.control
+    }
+
+    /// Gets the ID of the last of our messages the peer read, for a read
+    /// receipt.
+    #[wasm_bindgen(getter)]
+    pub fn read_up_to_message_id(&self) -> Option<f64> {
+        self.read_up_to_message_id
+    }
+
+    /// Gets the epoch of the session the message was received in, if known.
+    #[wasm_bindgen(getter)]
+    pub fn session_epoch(&self) -> Option<f64> {
+        self.session_epoch
+    }
+}
+
+/// Fingerprint two users compare out of band to verify each other's keys.
+#[wasm_bindgen]
+pub struct VerificationFingerprint {
+    inner: sessions::VerificationFingerprint,
+}
+
+#[wasm_bindgen]
+impl VerificationFingerprint {
+    /// Gets the fingerprint as 30 digits in groups of 5.
+    #[wasm_bindgen(getter)]
+    pub fn digits(&self) -> String {
+        self.inner.digits()
+    }
+
+    /// Gets the fingerprint as a sequence of 8 emoji.
+    #[wasm_bindgen(getter)]
+    pub fn emoji(&self) -> String {
+        self.inner.emoji()
+    }
+
+    /// Gets the binary encoding, to display as a QR code.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    /// Checks a scanned binary encoding against this fingerprint.
+    pub fn matches_bytes(&self, bytes: &[u8]) -> bool {
+        sessions::VerificationFingerprint::from_bytes(bytes) == Some(self.inner)
+    }
+}
+
+/// Profile information to carry in announcement user data, understood by all
+/// Gossip clients.
+#[wasm_bindgen]
+pub struct AnnouncementUserData {
+    inner: sessions::AnnouncementUserData,
+}
+
+#[wasm_bindgen]
+impl AnnouncementUserData {
+    /// Creates profile information. The display name is at most 64 bytes of
+    /// UTF-8, the avatar hash 32 bytes; `capabilities` is a bit set.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        protocol_version: u32,
+        display_name: String,
+        avatar_hash: Option<Vec<u8>>,
+        capabilities: u64,
+    ) -> Result<AnnouncementUserData, GossipError> {
+        let avatar_hash = avatar_hash
+            .map(|hash| {
+                <[u8; sessions::AVATAR_HASH_SIZE]>::try_from(hash.as_slice())
+                    .map_err(|_| GossipError::invalid_argument("Avatar hash must be 32 bytes"))
+            })
+            .transpose()?;
+        Ok(Self {
+            inner: sessions::AnnouncementUserData {
+                protocol_version,
+                display_name,
+                avatar_hash,
+                capabilities,
+            },
+        })
+    }
+
+    /// Gets the application protocol version of the announcer.
+    #[wasm_bindgen(getter)]
+    pub fn protocol_version(&self) -> u32 {
+        self.inner.protocol_version
+    }
+
+    /// Gets the display name of the announcer.
+    #[wasm_bindgen(getter)]
+    pub fn display_name(&self) -> String {
+        self.inner.display_name.clone()
+    }
+
+    /// Gets the hash of the announcer's avatar image, if any.
+    #[wasm_bindgen(getter)]
+    pub fn avatar_hash(&self) -> Option<Vec<u8>> {
+        self.inner.avatar_hash.map(|hash| hash.to_vec())
+    }
+
+    /// Gets the capability bits of the announcer.
+    #[wasm_bindgen(getter)]
+    pub fn capabilities(&self) -> u64 {
+        self.inner.capabilities
+    }
+
+    /// Encodes the profile, to pass as announcement user data. Fails if the
+    /// display name is too long.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GossipError> {
+        self.inner.to_bytes().map_err(GossipError::from)
+    }
+
+    /// Parses announcement user data, or returns `undefined` if it is not an
+    /// encoded profile.
+    pub fn from_bytes(bytes: &[u8]) -> Option<AnnouncementUserData> {
+        sessions::AnnouncementUserData::from_bytes(bytes).map(|inner| Self { inner })
+    }
+}
+
+/// Derives the verification fingerprint of our identity and a peer's; both
+/// sides get the same result.
+#[wasm_bindgen]
+pub fn verification_fingerprint(
+    our_pk: &UserPublicKeys,
+    peer_pk: &UserPublicKeys,
+) -> VerificationFingerprint {
+    VerificationFingerprint {
+        inner: sessions::SessionManager::verification_fingerprint(&our_pk.inner, &peer_pk.inner),
+    }
+}
+
+/// Counters of incoming announcements.
+#[wasm_bindgen]
+pub struct AnnouncementStats {
+    inner: sessions::AnnouncementStats,
+}
+
+#[wasm_bindgen]
+impl AnnouncementStats {
+    /// Announcements rejected by the size pre-filter.
+    #[wasm_bindgen(getter)]
+    pub fn rejected_size(&self) -> f64 {
+        self.inner.rejected_size as f64
+    }
+
+    /// Announcements rejected by the rate limit.
+    #[wasm_bindgen(getter)]
+    pub fn rate_limited(&self) -> f64 {
+        self.inner.rate_limited as f64
+    }
+
+    /// Duplicates of recently processed announcements.
+    #[wasm_bindgen(getter)]
+    pub fn duplicates(&self) -> f64 {
+        self.inner.duplicates as f64
+    }
+
+    /// Announcements rejected after cryptographic validation.
+    #[wasm_bindgen(getter)]
+    pub fn rejected(&self) -> f64 {
+        self.inner.rejected as f64
+    }
+
+    /// Announcements accepted.
+    #[wasm_bindgen(getter)]
+    pub fn accepted(&self) -> f64 {
+        self.inner.accepted as f64
+    }
+}
+
+/// Peer counts by status and delivery backlog across all peers.
+#[wasm_bindgen]
+pub struct PeerSummary {
+    inner: sessions::PeerSummary,
+}
+
+#[wasm_bindgen]
+impl PeerSummary {
+    /// Known peers, whatever their status.
+    #[wasm_bindgen(getter)]
+    pub fn peer_count(&self) -> f64 {
+        self.inner.peer_count as f64
+    }
+
+    /// Peers with an active session, near saturation and saturated ones
+    /// included.
+    #[wasm_bindgen(getter)]
+    pub fn active_count(&self) -> f64 {
+        self.inner.active_count as f64
+    }
+
+    /// Peers whose session is saturated by lag.
+    #[wasm_bindgen(getter)]
+    pub fn saturated_count(&self) -> f64 {
+        self.inner.saturated_count as f64
+    }
+
+    /// Peers with a session request pending on either side.
+    #[wasm_bindgen(getter)]
+    pub fn pending_count(&self) -> f64 {
+        self.inner.pending_count as f64
+    }
+
+    /// Peers without a session: never established, killed or closed.
+    #[wasm_bindgen(getter)]
+    pub fn inactive_count(&self) -> f64 {
+        self.inner.inactive_count as f64
+    }
+
+    /// Unacknowledged messages across all peers.
+    #[wasm_bindgen(getter)]
+    pub fn unacked_count(&self) -> f64 {
+        self.inner.unacked_count as f64
+    }
+
+    /// Largest number of unacknowledged messages of a single peer.
+    #[wasm_bindgen(getter)]
+    pub fn max_unacked_count(&self) -> f64 {
+        self.inner.max_unacked_count as f64
+    }
+}
+
+/// Message-board footprint of an announcement or a message, to predict its
+/// storage cost before posting it.
+#[wasm_bindgen]
+pub struct PostingCost {
+    inner: sessions::PostingCost,
+}
+
+#[wasm_bindgen]
+impl PostingCost {
+    /// Entries to post: one, or the manifest and chunks of a large message.
+    #[wasm_bindgen(getter)]
+    pub fn entry_count(&self) -> f64 {
+        self.inner.entry_count as f64
+    }
+
+    /// Chunks of a large message, 0 otherwise.
+    #[wasm_bindgen(getter)]
+    pub fn chunk_count(&self) -> f64 {
+        self.inner.chunk_count as f64
+    }
+
+    /// Bytes of the entry keys (seekers), 0 for an announcement.
+    #[wasm_bindgen(getter)]
+    pub fn key_bytes(&self) -> f64 {
+        self.inner.key_bytes as f64
+    }
+
+    /// Bytes of the posted data.
+    #[wasm_bindgen(getter)]
+    pub fn data_bytes(&self) -> f64 {
+        self.inner.data_bytes as f64
+    }
+
+    /// Bytes stored on the board, keys and data.
+    #[wasm_bindgen(getter)]
+    pub fn total_bytes(&self) -> f64 {
+        self.inner.total_bytes() as f64
+    }
+}
+
+/// Messages sent to a peer that it has not acknowledged yet.
+#[wasm_bindgen]
+pub struct PeerLag {
+    inner: sessions::PeerLag,
+}
+
+#[wasm_bindgen]
+impl PeerLag {
+    /// Number of unacknowledged messages.
+    #[wasm_bindgen(getter)]
+    pub fn unacked_count(&self) -> f64 {
+        self.inner.unacked_count as f64
+    }
+
+    /// When the oldest unacknowledged message was sent, in milliseconds since
+    /// the Unix epoch, if known.
+    #[wasm_bindgen(getter)]
+    pub fn oldest_unacked_timestamp(&self) -> Option<f64> {
+        self.inner
+            .oldest_unacked_timestamp
+            .map(|timestamp| timestamp as f64)
+    }
+
+    /// Seekers of the unacknowledged messages, oldest first (array of
+    /// Uint8Array).
+    #[wasm_bindgen(getter, unchecked_return_type = "Seeker[]")]
+    pub fn outstanding_seekers(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for seeker in &self.inner.outstanding_seekers {
+            array.push(&js_sys::Uint8Array::from(seeker.as_slice()));
+        }
+        array
+    }
+}
+
+/// Round-trip and last-seen estimates of a peer.
+#[wasm_bindgen]
+pub struct PeerLiveness {
+    inner: sessions::PeerLiveness,
+}
+
+#[wasm_bindgen]
+impl PeerLiveness {
+    /// Round-trip time of the latest answered ping in milliseconds, if any.
+    #[wasm_bindgen(getter)]
+    pub fn round_trip_millis(&self) -> Option<f64> {
+        self.inner.round_trip_millis.map(|millis| millis as f64)
+    }
+
+    /// When a message was last received from the peer, if ever.
+    #[wasm_bindgen(getter)]
+    pub fn last_seen_millis(&self) -> Option<f64> {
+        self.inner.last_seen_millis.map(|millis| millis as f64)
+    }
+
+    /// When the latest ping was sent, while it is not answered.
+    #[wasm_bindgen(getter)]
+    pub fn ping_pending_since_millis(&self) -> Option<f64> {
+        self.inner
+            .ping_pending_since_millis
+            .map(|millis| millis as f64)
+    }
+
+    /// Estimated offset of the peer's clock ahead of ours in milliseconds,
+    /// with clock skew compensation enabled.
+    #[wasm_bindgen(getter)]
+    pub fn clock_offset_millis(&self) -> Option<f64> {
+        self.inner.clock_offset_millis.map(|millis| millis as f64)
+    }
+}
+
+/// A peer discarded by `SessionManagerWrapper.prune_peers`.
+#[wasm_bindgen]
+pub struct PrunedPeer {
+    inner: sessions::PrunedPeer,
+}
+
+#[wasm_bindgen]
+impl PrunedPeer {
+    #[wasm_bindgen(getter)]
+    pub fn peer_id(&self) -> UserId {
+        UserId::from(self.inner.peer_id.clone())
+    }
+
+    /// Latest announcement or message time known for the peer, if any.
+    #[wasm_bindgen(getter)]
+    pub fn last_activity_millis(&self) -> Option<f64> {
+        self.inner.last_activity_millis.map(|millis| millis as f64)
+    }
+
+    /// Application data that was attached to the peer.
+    #[wasm_bindgen(getter)]
+    pub fn metadata(&self) -> Vec<u8> {
+        self.inner.metadata.clone()
+    }
+}
+
+/// Activity counters of a peer and its current session.
+#[wasm_bindgen]
+pub struct PeerStats {
+    inner: sessions::PeerStats,
+}
+
+#[wasm_bindgen]
+impl PeerStats {
+    /// Messages sent in the current session, keep-alives included.
+    #[wasm_bindgen(getter)]
+    pub fn messages_sent(&self) -> f64 {
+        self.inner.messages_sent as f64
+    }
+
+    /// Messages received in the current session, keep-alives included.
+    #[wasm_bindgen(getter)]
+    pub fn messages_received(&self) -> f64 {
+        self.inner.messages_received as f64
+    }
+
+    /// Age of the current session in milliseconds, if known.
+    #[wasm_bindgen(getter)]
+    pub fn session_age_millis(&self) -> Option<f64> {
+        self.inner.session_age_millis.map(|age| age as f64)
+    }
+
+    /// Timestamp of the latest incoming message of the current session.
+    #[wasm_bindgen(getter)]
+    pub fn last_incoming_timestamp(&self) -> Option<f64> {
+        self.inner
+            .last_incoming_timestamp
+            .map(|timestamp| timestamp as f64)
+    }
+
+    /// Timestamp of the latest outgoing message of the current session.
+    #[wasm_bindgen(getter)]
+    pub fn last_outgoing_timestamp(&self) -> Option<f64> {
+        self.inner
+            .last_outgoing_timestamp
+            .map(|timestamp| timestamp as f64)
+    }
+
+    /// Round trips completed in the current session.
+    #[wasm_bindgen(getter)]
+    pub fn ratchet_epochs(&self) -> f64 {
+        self.inner.ratchet_epochs as f64
+    }
+
+    /// Sessions established with the peer after the first one.
+    #[wasm_bindgen(getter)]
+    pub fn reestablishments(&self) -> f64 {
+        self.inner.reestablishments as f64
+    }
+
+    /// Epoch of the current session, if any.
+    #[wasm_bindgen(getter)]
+    pub fn session_epoch(&self) -> Option<f64> {
+        self.inner.session_epoch.map(|epoch| epoch as f64)
+    }
+}
+
+/// A session request waiting for our answer.
+#[wasm_bindgen]
+pub struct PendingRequest {
+    inner: sessions::PendingRequest,
+}
+
+#[wasm_bindgen]
+impl PendingRequest {
+    /// ID of the peer that announced itself.
+    #[wasm_bindgen(getter)]
+    pub fn peer_id(&self) -> UserId {
+        self.inner.peer_id.clone().into()
+    }
+
+    /// Timestamp of the peer's announcement.
+    #[wasm_bindgen(getter)]
+    pub fn timestamp_millis(&self) -> f64 {
+        self.inner.timestamp_millis as f64
+    }
+
+    /// User data of the peer's announcement.
+    #[wasm_bindgen(getter)]
+    pub fn user_data(&self) -> Vec<u8> {
+        self.inner.user_data.clone()
+    }
+}
+
+/// State changed since the previous incremental snapshot.
+#[wasm_bindgen]
+pub struct IncrementalSnapshot {
+    inner: sessions::IncrementalSnapshot,
+}
+
+#[wasm_bindgen]
+impl IncrementalSnapshot {
+    /// Encrypted manager state without the peers, replacing the stored one.
+    #[wasm_bindgen(getter)]
+    pub fn manifest(&self) -> Vec<u8> {
+        self.inner.manifest.clone()
+    }
+
+    /// IDs of the peers whose records changed (array of Uint8Array, to use
+    /// as storage keys), in the same order as `updated_peer_blobs`.
+    #[wasm_bindgen(getter, unchecked_return_type = "Uint8Array[]")]
+    pub fn updated_peer_ids(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for record in &self.inner.updated_peers {
+            array.push(&js_sys::Uint8Array::from(record.peer_id.as_bytes()));
+        }
+        array
+    }
+
+    /// Encrypted records of the peers that changed (array of Uint8Array).
+    #[wasm_bindgen(getter, unchecked_return_type = "Uint8Array[]")]
+    pub fn updated_peer_blobs(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for record in &self.inner.updated_peers {
+            array.push(&js_sys::Uint8Array::from(record.blob.as_slice()));
+        }
+        array
+    }
+
+    /// IDs of the peers whose stored records must be deleted (array of
+    /// Uint8Array).
+    #[wasm_bindgen(getter, unchecked_return_type = "Uint8Array[]")]
+    pub fn removed_peer_ids(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for peer_id in &self.inner.removed_peers {
+            array.push(&js_sys::Uint8Array::from(peer_id.as_bytes()));
+        }
+        array
+    }
+}
+
+/// Keep-alive message to post to the message board, returned by
+/// `refresh_with_keepalives`.
+#[wasm_bindgen]
+pub struct KeepAliveMessage {
+    peer_id: auth::UserId,
+    seeker: Vec<u8>,
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeepAliveMessage {
+    /// Gets the peer the keep-alive is for.
+    #[wasm_bindgen(getter)]
+    pub fn peer_id(&self) -> UserId {
+        self.peer_id.clone().into()
+    }
+
+    /// Gets the seeker to post the keep-alive under.
+    #[wasm_bindgen(getter, unchecked_return_type = "Seeker")]
+    pub fn seeker(&self) -> Vec<u8> {
+        self.seeker.clone()
+    }
+
+    /// Gets the encrypted keep-alive to post.
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+/// Outcome of a session refresh.
+#[wasm_bindgen]
+pub struct RefreshReport {
+    inner: sessions::RefreshReport,
+    keep_alives: js_sys::Array,
+    announcements: js_sys::Array,
+}
+
+/// Wraps peer IDs into an array of `UserId`.
+fn peer_ids_to_array<'a>(peer_ids: impl IntoIterator<Item = &'a auth::UserId>) -> js_sys::Array {
+    let array = js_sys::Array::new();
+    for peer_id in peer_ids {
+        array.push(&JsValue::from(UserId::from(peer_id.clone())));
+    }
+    array
+}
+
+#[wasm_bindgen]
+impl RefreshReport {
+    /// IDs of the peers whose session expired for inactivity.
+    #[wasm_bindgen(getter, unchecked_return_type = "UserId[]")]
+    pub fn expired_sessions(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.expired_sessions)
+    }
+
+    /// IDs of the peers whose unanswered announcement became too old to be
+    /// accepted.
+    #[wasm_bindgen(getter, unchecked_return_type = "UserId[]")]
+    pub fn expired_requests(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.expired_requests)
+    }
+
+    /// IDs of the peers that need a keep-alive message.
+    #[wasm_bindgen(getter, unchecked_return_type = "UserId[]")]
+    pub fn keep_alive_needed(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.keep_alive_needed)
+    }
+
+    /// IDs of the peers to announce again to, per the re-handshake policy.
+    #[wasm_bindgen(getter, unchecked_return_type = "UserId[]")]
+    pub fn rehandshake_needed(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.rehandshake_needed)
+    }
+
+    /// IDs of the peers whose unanswered announcement is about to expire and
+    /// is renewed, per the renewal policy.
+    #[wasm_bindgen(getter, unchecked_return_type = "UserId[]")]
+    pub fn renewal_needed(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.renewal_needed)
+    }
+
+    /// Keep-alive messages to post to the message board; empty unless
+    /// returned by `refresh_with_keepalives`.
+    #[wasm_bindgen(getter, unchecked_return_type = "KeepAliveMessage[]")]
+    pub fn keep_alives(&self) -> js_sys::Array {
+        self.keep_alives.clone()
+    }
+
+    /// Fresh announcements to publish, as `[peer_id, announcement]` pairs;
+    /// empty unless returned by `refresh_with_rehandshakes`.
+    #[wasm_bindgen(getter, unchecked_return_type = "PeerAnnouncement[]")]
+    pub fn announcements(&self) -> js_sys::Array {
+        self.announcements.clone()
+    }
+}
+
+/// Session manager wrapper for WebAssembly.
+#[wasm_bindgen]
+pub struct SessionManagerWrapper {
+    inner: sessions::SessionManager,
+}
+
+#[wasm_bindgen]
+impl SessionManagerWrapper {
+    /// Creates a new session manager with the given configuration.
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            inner: sessions::SessionManager::new(config.inner),
+        }
+    }
+
+    /// Deserializes a session manager from an encrypted blob.
+    pub fn from_encrypted_blob(
+        encrypted_blob: &[u8],
+        key: &EncryptionKey,
+    ) -> Result<SessionManagerWrapper, GossipError> {
+        let inner = sessions::SessionManager::from_encrypted_blob(encrypted_blob, key.inner()?)
+            .ok_or_else(|| {
+                GossipError::crypto("DECRYPTION", "Failed to decrypt session manager")
+            })?;
+        Ok(Self { inner })
+    }
+
+    /// Serializes and encrypts the session manager into a blob.
+    pub fn to_encrypted_blob(&self, key: &EncryptionKey) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .to_encrypted_blob(key.inner()?)
+            .ok_or_else(|| GossipError::crypto("ENCRYPTION", "Failed to encrypt session manager"))
+    }
+
+    /// Same as `from_encrypted_blob`, with the blob key wrapped under
+    /// `wrapping_key` (see `EncryptionKey.wrap`). The blob key is only
+    /// unwrapped for the duration of the call, so JavaScript never holds it.
+    pub fn from_encrypted_blob_with_wrapped_key(
+        encrypted_blob: &[u8],
+        wrapped_key: &[u8],
+        wrapping_key: &EncryptionKey,
+    ) -> Result<SessionManagerWrapper, GossipError> {
+        let key = EncryptionKey::from_wrapped(wrapped_key, wrapping_key)?;
+        Self::from_encrypted_blob(encrypted_blob, &key)
+    }
+
+    /// Same as `to_encrypted_blob`, with the blob key wrapped under
+    /// `wrapping_key`.
+    pub fn to_encrypted_blob_with_wrapped_key(
+        &self,
+        wrapped_key: &[u8],
+        wrapping_key: &EncryptionKey,
+    ) -> Result<Vec<u8>, GossipError> {
+        let key = EncryptionKey::from_wrapped(wrapped_key, wrapping_key)?;
+        self.to_encrypted_blob(&key)
+    }
+
+    /// Gets the size in bytes of the blob `to_encrypted_blob` would return
+    /// now, without building it: check it against the storage quota (see
+    /// `navigator.storage.estimate()`) before saving.
+    ///
+    /// # Example
+    ///
+    /// ```javascript
+    /// const { quota, usage } = await navigator.storage.estimate();
+    /// if (manager.estimate_blob_size() > (quota - usage) / 2) warnStorageFull();
+    /// ```
+    pub fn estimate_blob_size(&self) -> usize {
+        self.inner.estimate_blob_size()
+    }
+
+    /// Exports the replay protection state, encrypted. Save it after every
+    /// accepted announcement and merge it back after restoring an older blob.
+    pub fn export_replay_guard(&self, key: &EncryptionKey) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .export_replay_guard(key.inner()?)
+            .ok_or_else(|| GossipError::crypto("ENCRYPTION", "Failed to encrypt replay guard"))
+    }
+
+    /// Merges a replay guard exported with `export_replay_guard`, so that the
+    /// announcements it records stay rejected as replays.
+    pub fn merge_replay_guard(
+        &mut self,
+        encrypted_guard: &[u8],
+        key: &EncryptionKey,
+    ) -> Result<(), GossipError> {
+        if self.inner.merge_replay_guard(encrypted_guard, key.inner()?) {
+            Ok(())
+        } else {
+            Err(GossipError::crypto(
+                "DECRYPTION",
+                "Failed to decrypt replay guard",
+            ))
+        }
+    }
+
+    /// Exports one peer, including its session state, encrypted with `key`,
+    /// to archive the conversation or move it to another session manager.
+    /// Discard the peer here once a moved export is stored.
+    pub fn export_peer(
+        &self,
+        peer_id: &UserId,
+        key: &EncryptionKey,
+    ) -> Result<Vec<u8>, GossipError> {
+        let key = key.inner()?;
+        if matches!(
+            self.inner.peer_session_status(&peer_id.inner),
+            sessions::SessionStatus::UnknownPeer
+        ) {
+            return Err(sessions::SessionError::UnknownPeer.into());
+        }
+        self.inner
+            .export_peer(&peer_id.inner, key)
+            .ok_or_else(|| GossipError::serialization("Failed to serialize peer state"))
+    }
+
+    /// Imports a peer exported with `export_peer`, replacing any state held
+    /// for it. Returns the peer ID.
+    pub fn import_peer(
+        &mut self,
+        exported_peer: &[u8],
+        key: &EncryptionKey,
+    ) -> Result<UserId, GossipError> {
+        self.inner
+            .import_peer(exported_peer, key.inner()?)
+            .map(UserId::from)
+            .map_err(GossipError::from)
+    }
+
+    /// Returns the state changed since the previous call: a new manifest, the
+    /// records of the peers that changed and the peers whose records must be
+    /// deleted. Store them, then restore with `from_incremental_snapshot`.
+    pub fn take_incremental_snapshot(
+        &mut self,
+        key: &EncryptionKey,
+    ) -> Result<IncrementalSnapshot, GossipError> {
+        let inner = self
+            .inner
+            .take_incremental_snapshot(key.inner()?)
+            .ok_or_else(|| {
+                GossipError::crypto("ENCRYPTION", "Failed to encrypt session manager")
+            })?;
+        Ok(IncrementalSnapshot { inner })
+    }
+
+    /// Restores a session manager from the latest stored manifest and the
+    /// stored record of every peer, given as two arrays of Uint8Array of the
+    /// same length.
+    pub fn from_incremental_snapshot(
+        manifest: &[u8],
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] peer_ids: js_sys::Array,
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] peer_blobs: js_sys::Array,
+        key: &EncryptionKey,
+    ) -> Result<SessionManagerWrapper, GossipError> {
+        if peer_ids.length() != peer_blobs.length() {
+            return Err(GossipError::invalid_argument(
+                "Peer IDs and peer blobs must have the same length",
+            ));
+        }
+        let mut peer_records = Vec::new();
+        for (peer_id, blob) in peer_ids.iter().zip(peer_blobs.iter()) {
+            let peer_id: [u8; 32] = js_sys::Uint8Array::new(&peer_id)
+                .to_vec()
+                .try_into()
+                .map_err(|_| GossipError::invalid_argument("Peer ID must be 32 bytes"))?;
+            peer_records.push(sessions::PeerRecord {
+                peer_id: auth::UserId::from_bytes(peer_id),
+                blob: js_sys::Uint8Array::new(&blob).to_vec(),
+            });
+        }
+        let inner = sessions::SessionManager::from_incremental_snapshot(
+            manifest,
+            &peer_records,
+            key.inner()?,
+        )
+        .ok_or_else(|| GossipError::crypto("DECRYPTION", "Failed to decrypt session manager"))?;
+        Ok(Self { inner })
+    }
+
+    /// Establishes an outgoing session with a peer.
+    ///
+    /// # Parameters
+    ///
+    /// - `peer_pk`: The peer's public keys
+    /// - `our_pk`: Our public keys
+    /// - `our_sk`: Our secret keys
+    /// - `user_data`: Arbitrary user data to include in the announcement (can be empty)
     ///
     /// # Security Warning
     ///
@@ -551,7 +2737,8 @@ impl SessionManagerWrapper {
     ///
     /// **Recommendation**: Avoid including highly sensitive information in user_data. Use it for
     /// metadata like protocol version, public display names, or capability flags. Send truly
-    /// sensitive data through regular messages after the session is established.
+    /// sensitive data through regular messages after the session is established, or use
+    /// `establish_outgoing_session_with_mode` with `UserDataMode.Session`.
     ///
     /// # Returns
     ///
@@ -562,13 +2749,164 @@ impl SessionManagerWrapper {
         our_pk: &UserPublicKeys,
         our_sk: &UserSecretKeys,
         user_data: &[u8],
-    ) -> Vec<u8> {
-        self.inner.establish_outgoing_session(
-            &peer_pk.inner,
-            &our_pk.inner,
-            &our_sk.inner,
-            user_data.to_vec(),
-        )
+    ) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .establish_outgoing_session(
+                &peer_pk.inner,
+                &our_pk.inner,
+                our_sk.inner()?,
+                user_data.to_vec(),
+            )
+            .map_err(GossipError::from)
+    }
+
+    /// Establishes outgoing sessions with several peers at once, given their
+    /// serialized public keys (array of Uint8Array, see
+    /// `UserPublicKeys.to_bytes`). Returns the announcements to publish as
+    /// `[peer_id, announcement]` pairs, one per distinct peer.
+    #[wasm_bindgen(unchecked_return_type = "PeerAnnouncement[]")]
+    pub fn establish_outgoing_sessions(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] peer_pks: js_sys::Array,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+        user_data: &[u8],
+    ) -> Result<js_sys::Array, GossipError> {
+        let peer_pks = peer_pks
+            .iter()
+            .map(|peer_pk| {
+                UserPublicKeys::from_bytes(&js_sys::Uint8Array::new(&peer_pk).to_vec())
+                    .map(|peer_pk| peer_pk.inner)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let announcements = self
+            .inner
+            .establish_outgoing_sessions(
+                &peer_pks,
+                &our_pk.inner,
+                our_sk.inner()?,
+                user_data.to_vec(),
+            )
+            .map_err(GossipError::from)?;
+        let array = js_sys::Array::new();
+        for (peer_id, announcement) in announcements {
+            array.push(&js_sys::Array::of2(
+                &JsValue::from(UserId::from(peer_id)),
+                &js_sys::Uint8Array::from(&announcement[..]),
+            ));
+        }
+        Ok(array)
+    }
+
+    /// Same as `establish_outgoing_session`, choosing how `user_data` travels.
+    ///
+    /// With `UserDataMode.Session` the announcement carries no user data: it
+    /// is sent as the first message of the session once established, collected
+    /// with `take_outbox_messages`, and the peer receives it flagged
+    /// `announcement_user_data`.
+    pub fn establish_outgoing_session_with_mode(
+        &mut self,
+        peer_pk: &UserPublicKeys,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+        user_data: &[u8],
+        mode: UserDataMode,
+    ) -> Result<Vec<u8>, GossipError> {
+        let mode = match mode {
+            UserDataMode::Announcement => sessions::UserDataMode::Announcement,
+            UserDataMode::Session => sessions::UserDataMode::Session,
+        };
+        self.inner
+            .establish_outgoing_session_with(
+                &peer_pk.inner,
+                &our_pk.inner,
+                our_sk.inner()?,
+                user_data.to_vec(),
+                mode,
+            )
+            .map_err(GossipError::from)
+    }
+
+    /// Creates a prekey bundle to publish, so that peers can start a session
+    /// and send right away without waiting for our announcement.
+    pub fn create_prekey_bundle(
+        &mut self,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> Result<Vec<u8>, GossipError> {
+        Ok(self
+            .inner
+            .create_prekey_bundle(&our_pk.inner, our_sk.inner()?))
+    }
+
+    /// Establishes an outgoing session from a prekey bundle published by the
+    /// peer. The session is active right away; the returned announcement must
+    /// still be published so that the peer joins it.
+    pub fn establish_outgoing_session_with_prekey(
+        &mut self,
+        prekey_bundle: &[u8],
+        peer_pk: &UserPublicKeys,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+        user_data: &[u8],
+    ) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .establish_outgoing_session_with_prekey(
+                prekey_bundle,
+                &peer_pk.inner,
+                &our_pk.inner,
+                our_sk.inner()?,
+                user_data.to_vec(),
+            )
+            .map_err(GossipError::from)
+    }
+
+    /// Revokes every announcement we created up to now, e.g. after losing a
+    /// device, returning the signed revocation to publish. Announce again to
+    /// peers to resume sessions.
+    pub fn create_announcement_revocation(
+        &mut self,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> Result<Vec<u8>, GossipError> {
+        Ok(self
+            .inner
+            .create_announcement_revocation(&our_pk.inner, our_sk.inner()?))
+    }
+
+    /// Applies an announcement revocation published by a peer, returning the
+    /// peer ID. Their revoked announcements are rejected from now on.
+    pub fn feed_announcement_revocation(
+        &mut self,
+        revocation: &[u8],
+    ) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .feed_announcement_revocation(revocation)
+            .map(|peer_id| peer_id.as_bytes().to_vec())
+            .map_err(GossipError::from)
+    }
+
+    /// Moves to new long-term keys, returning the signed key transitions to
+    /// post to the message board (array of `SendMessageOutput`), one per peer
+    /// with an active session. The old secret keys are consumed and kept to
+    /// read messages sent before peers switch; use the new keys afterwards.
+    #[wasm_bindgen(unchecked_return_type = "SendMessageOutput[]")]
+    pub fn rotate_identity(
+        &mut self,
+        old_pk: &UserPublicKeys,
+        old_sk: UserSecretKeys,
+        new_pk: &UserPublicKeys,
+        new_sk: &UserSecretKeys,
+    ) -> Result<js_sys::Array, GossipError> {
+        let old_sk = old_sk.inner.ok_or_else(GossipError::disposed)?;
+        let array = js_sys::Array::new();
+        for output in
+            self.inner
+                .rotate_identity(&old_pk.inner, old_sk, &new_pk.inner, new_sk.inner()?)
+        {
+            array.push(&JsValue::from(SendMessageOutput::from_output(&output)));
+        }
+        Ok(array)
     }
 
     /// Feeds an incoming announcement from the blockchain.
@@ -605,53 +2943,273 @@ impl SessionManagerWrapper {
         announcement_bytes: &[u8],
         our_pk: &UserPublicKeys,
         our_sk: &UserSecretKeys,
-    ) -> Option<AnnouncementResult> {
+    ) -> Result<Option<AnnouncementResult>, GossipError> {
+        Ok(self
+            .inner
+            .feed_incoming_announcement(announcement_bytes, &our_pk.inner, our_sk.inner()?)
+            .map(|result| AnnouncementResult { inner: result }))
+    }
+
+    /// Processes a batch of announcements, such as a board scan, cheapest
+    /// checks first. Returns one entry per announcement: its
+    /// `AnnouncementResult`, or `undefined` if it was rejected.
+    #[wasm_bindgen(unchecked_return_type = "BatchResult<AnnouncementResult>[]")]
+    pub fn feed_incoming_announcements(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] announcements: js_sys::Array,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> Result<js_sys::Array, GossipError> {
+        let announcements: Vec<Vec<u8>> = announcements
+            .iter()
+            .map(|item| js_sys::Uint8Array::new(&item).to_vec())
+            .collect();
+        let announcements: Vec<&[u8]> = announcements.iter().map(Vec::as_slice).collect();
+
+        let array = js_sys::Array::new();
+        for result in
+            self.inner
+                .feed_incoming_announcements(&announcements, &our_pk.inner, our_sk.inner()?)
+        {
+            match result {
+                Ok(result) => array.push(&JsValue::from(AnnouncementResult { inner: result })),
+                Err(_) => array.push(&JsValue::UNDEFINED),
+            };
+        }
+        Ok(array)
+    }
+
+    /// Gets the list of message board seekers to monitor.
+    ///
+    /// Each seeker is materialised as a JS-owned Uint8Array via
+    /// `new_with_length` + `copy_from`. `Uint8Array::from(&[u8])` in older
+    /// js-sys returns an array whose buffer view sits over wasm linear
+    /// memory; subsequent wasm calls that grow the heap detach those
+    /// views, and the JS-side `SEEKERS_UPDATED` listener crashes with
+    /// "Cannot perform values on a detached ArrayBuffer". The
+    /// new-with-length path allocates a JS-side ArrayBuffer up front,
+    /// then copies — the result is decoupled from wasm memory.
+    #[wasm_bindgen(unchecked_return_type = "Seeker[]")]
+    pub fn get_message_board_read_keys(&self) -> js_sys::Array {
+        let seekers = self.inner.get_message_board_read_keys();
+        let array = js_sys::Array::new();
+        for seeker in seekers {
+            let js_seeker = js_sys::Uint8Array::new_with_length(seeker.len() as u32);
+            js_seeker.copy_from(&seeker);
+            array.push(&js_seeker);
+        }
+        array
+    }
+
+    /// Gets the seekers to read for peer sessions, each paired with the peer
+    /// it belongs to, as an array of `[seeker, peer_id]` pairs.
+    /// Linked device seekers are not included.
+    #[wasm_bindgen(unchecked_return_type = "SeekerPeer[]")]
+    pub fn get_message_board_read_keys_map(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for (seeker, peer_id) in self.inner.get_message_board_read_keys_map() {
+            let js_seeker = js_sys::Uint8Array::new_with_length(seeker.len() as u32);
+            js_seeker.copy_from(&seeker);
+            array.push(&js_sys::Array::of2(
+                &js_seeker,
+                &JsValue::from(UserId::from(peer_id)),
+            ));
+        }
+        array
+    }
+
+    /// Gets at most `max_reads` seekers to read, those most likely to hold a
+    /// message first: peers that wrote recently, then peers due for a
+    /// keep-alive, then the others.
+    #[wasm_bindgen(unchecked_return_type = "Seeker[]")]
+    pub fn plan_board_sync(&self, max_reads: usize) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for seeker in self.inner.plan_board_sync(max_reads) {
+            let js_seeker = js_sys::Uint8Array::new_with_length(seeker.len() as u32);
+            js_seeker.copy_from(&seeker);
+            array.push(&js_seeker);
+        }
+        array
+    }
+
+    /// Sends a message to a peer.
+    pub fn send_message(
+        &mut self,
+        peer_id: &UserId,
+        message_contents: &[u8],
+    ) -> Option<SendMessageOutput> {
+        self.inner
+            .send_message(&peer_id.inner, message_contents)
+            .map(|output| SendMessageOutput::from_output(&output))
+    }
+
+    /// Sends a payload too large for a single message-board entry as ordered
+    /// chunks of at most `chunk_size` bytes (32 KiB if `undefined`). Post
+    /// every returned message to the message board.
+    #[wasm_bindgen(unchecked_return_type = "SendMessageOutput[]")]
+    pub fn send_large_message(
+        &mut self,
+        peer_id: &UserId,
+        message_contents: &[u8],
+        chunk_size: Option<usize>,
+    ) -> Result<js_sys::Array, GossipError> {
+        let outputs = self
+            .inner
+            .send_large_message(
+                &peer_id.inner,
+                message_contents,
+                chunk_size.unwrap_or(sessions::DEFAULT_CHUNK_SIZE),
+            )
+            .map_err(GossipError::from)?;
+        let array = js_sys::Array::new();
+        for output in outputs {
+            array.push(&JsValue::from(SendMessageOutput::from_output(&output)));
+        }
+        Ok(array)
+    }
+
+    /// Estimates the size of the announcement `establish_outgoing_session`
+    /// builds for `user_data_len` bytes of user data, padding included.
+    pub fn estimate_announcement_cost(&self, user_data_len: usize) -> PostingCost {
+        PostingCost {
+            inner: self.inner.estimate_announcement_cost(user_data_len),
+        }
+    }
+
+    /// Estimates the size of a message of `payload_len` bytes sent with
+    /// `send_message`, or with `send_large_message` in chunks of at most
+    /// `chunk_size` bytes if set. Sizes are upper bounds, a few bytes over.
+    pub fn estimate_message_cost(
+        &self,
+        payload_len: usize,
+        chunk_size: Option<usize>,
+    ) -> PostingCost {
+        let inner = match chunk_size {
+            Some(chunk_size) => self
+                .inner
+                .estimate_large_message_cost(payload_len, chunk_size),
+            None => self.inner.estimate_message_cost(payload_len),
+        };
+        PostingCost { inner }
+    }
+
+    /// Sends several messages (an array of Uint8Array) to a peer, in order.
+    /// Returns one entry per message: its `SendMessageOutput`, or `undefined`
+    /// if that message could not be sent.
+    #[wasm_bindgen(unchecked_return_type = "BatchResult<SendMessageOutput>[]")]
+    pub fn send_messages(
+        &mut self,
+        peer_id: &UserId,
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] messages: js_sys::Array,
+    ) -> Result<js_sys::Array, GossipError> {
+        let messages: Vec<Vec<u8>> = messages
+            .iter()
+            .map(|message| js_sys::Uint8Array::new(&message).to_vec())
+            .collect();
+        let messages: Vec<&[u8]> = messages.iter().map(Vec::as_slice).collect();
+        let array = js_sys::Array::new();
+        for result in self.inner.send_messages(&peer_id.inner, &messages) {
+            match result {
+                Ok(output) => array.push(&JsValue::from(SendMessageOutput::from_output(&output))),
+                Err(_) => array.push(&JsValue::UNDEFINED),
+            };
+        }
+        Ok(array)
+    }
+
+    /// Sets the lifetime we request for message plaintexts exchanged with a
+    /// peer, or clears it with `undefined`.
+    pub fn set_message_ttl(
+        &mut self,
+        peer_id: &UserId,
+        ttl_millis: Option<f64>,
+    ) -> Result<(), GossipError> {
+        self.inner
+            .set_message_ttl(&peer_id.inner, ttl_millis.map(|ttl| ttl as u64))
+            .map_err(GossipError::from)
+    }
+
+    /// Attaches application data to a peer (display name, notes, ...),
+    /// persisted and encrypted with the session state.
+    pub fn set_peer_metadata(
+        &mut self,
+        peer_id: &UserId,
+        metadata: Vec<u8>,
+    ) -> Result<(), GossipError> {
         self.inner
-            .feed_incoming_announcement(announcement_bytes, &our_pk.inner, &our_sk.inner)
-            .map(|result| AnnouncementResult { inner: result })
+            .set_peer_metadata(&peer_id.inner, metadata)
+            .map_err(GossipError::from)
     }
 
-    /// Gets the list of message board seekers to monitor.
-    ///
-    /// Each seeker is materialised as a JS-owned Uint8Array via
-    /// `new_with_length` + `copy_from`. `Uint8Array::from(&[u8])` in older
-    /// js-sys returns an array whose buffer view sits over wasm linear
-    /// memory; subsequent wasm calls that grow the heap detach those
-    /// views, and the JS-side `SEEKERS_UPDATED` listener crashes with
-    /// "Cannot perform values on a detached ArrayBuffer". The
-    /// new-with-length path allocates a JS-side ArrayBuffer up front,
-    /// then copies — the result is decoupled from wasm memory.
-    pub fn get_message_board_read_keys(&self) -> js_sys::Array {
-        let seekers = self.inner.get_message_board_read_keys();
-        let array = js_sys::Array::new();
-        for seeker in seekers {
-            let js_seeker = js_sys::Uint8Array::new_with_length(seeker.len() as u32);
-            js_seeker.copy_from(&seeker);
-            array.push(&js_seeker);
-        }
-        array
+    /// Gets the application data attached to a peer, or `undefined` for an
+    /// unknown peer.
+    pub fn get_peer_metadata(&self, peer_id: &UserId) -> Option<Vec<u8>> {
+        self.inner
+            .get_peer_metadata(&peer_id.inner)
+            .map(<[u8]>::to_vec)
     }
 
-    /// Sends a message to a peer.
-    pub fn send_message(
+    /// Gets the lifetime currently agreed with a peer for message plaintexts.
+    pub fn message_ttl(&self, peer_id: &UserId) -> Option<f64> {
+        self.inner.message_ttl(&peer_id.inner).map(|ttl| ttl as f64)
+    }
+
+    /// Pads every outgoing message to the smallest of the given sizes that
+    /// fits it, hiding message sizes from the message board.
+    pub fn set_padding_buckets(&mut self, buckets: Vec<u32>) {
+        self.inner
+            .set_padding_policy(sessions::PaddingPolicy::Buckets(buckets));
+    }
+
+    /// Pads every outgoing message by a random Pareto-distributed amount
+    /// with the given scale in bytes and shape.
+    pub fn set_padding_pareto(&mut self, scale: u32, shape: f64) {
+        self.inner
+            .set_padding_policy(sessions::PaddingPolicy::Pareto { scale, shape });
+    }
+
+    /// Stops padding outgoing messages.
+    pub fn disable_padding(&mut self) {
+        self.inner.set_padding_policy(sessions::PaddingPolicy::None);
+    }
+
+    /// Gets the delivery status of a sent message from its handle, or
+    /// `undefined` if the handle is unknown.
+    pub fn message_status(&self, handle: u64) -> Option<MessageStatus> {
+        self.inner
+            .message_status(sessions::MessageHandle::from_u64(handle))
+            .map(|status| match status {
+                sessions::MessageStatus::Pending => MessageStatus::Pending,
+                sessions::MessageStatus::Delivered => MessageStatus::Delivered,
+            })
+    }
+
+    /// Queues a message for a peer, sent as soon as the session allows it.
+    /// Collect the encrypted messages with `take_outbox_messages`.
+    pub fn queue_message(
         &mut self,
-        peer_id: &[u8],
+        peer_id: &UserId,
         message_contents: &[u8],
-    ) -> Result<Option<SendMessageOutput>, JsValue> {
-        if peer_id.len() != 32 {
-            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
-        }
-        let mut peer_id_arr = [0u8; 32];
-        peer_id_arr.copy_from_slice(peer_id);
-        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+    ) -> Result<(), GossipError> {
+        self.inner
+            .queue_message(&peer_id.inner, message_contents)
+            .map_err(GossipError::from)
+    }
 
-        Ok(self
-            .inner
-            .send_message(&peer_id, message_contents)
-            .map(|output| SendMessageOutput {
-                seeker: output.seeker.clone(),
-                data: output.data.clone(),
-            }))
+    /// Gets the number of queued messages for a peer that are not sent yet.
+    pub fn outbox_len(&self, peer_id: &UserId) -> usize {
+        self.inner.outbox_len(&peer_id.inner)
+    }
+
+    /// Takes the queued messages encrypted since the last call; post each one
+    /// to the message board.
+    #[wasm_bindgen(unchecked_return_type = "SendMessageOutput[]")]
+    pub fn take_outbox_messages(&mut self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for output in self.inner.take_outbox_messages() {
+            array.push(&JsValue::from(SendMessageOutput::from_output(&output)));
+        }
+        array
     }
 
     /// Processes an incoming message from the message board.
@@ -662,91 +3220,696 @@ impl SessionManagerWrapper {
     /// before the JS side reads the buffer).
     pub fn feed_incoming_message_board_read(
         &mut self,
-        seeker: &[u8],
+        #[wasm_bindgen(unchecked_param_type = "Seeker")] seeker: &[u8],
         ciphertext: &[u8],
         our_sk: &UserSecretKeys,
-    ) -> Option<ReceiveMessageOutput> {
-        self.inner
-            .feed_incoming_message_board_read(seeker, ciphertext, &our_sk.inner)
-            .map(|output| {
-                let acknowledged_seekers = js_sys::Array::new();
-                for ack_seeker in &output.newly_acknowledged_self_seekers {
-                    let js_seeker = js_sys::Uint8Array::new_with_length(ack_seeker.len() as u32);
-                    js_seeker.copy_from(&ack_seeker[..]);
-                    acknowledged_seekers.push(&js_seeker);
-                }
+    ) -> Result<Option<ReceiveMessageOutput>, GossipError> {
+        Ok(self
+            .inner
+            .feed_incoming_message_board_read(seeker, ciphertext, our_sk.inner()?)
+            .map(|output| ReceiveMessageOutput::from_output(&output)))
+    }
+
+    /// Processes a batch of message board reads, given as two arrays of
+    /// Uint8Array of the same length. Messages of a session may be in any
+    /// order. Returns one entry per read: its `ReceiveMessageOutput`, or
+    /// `undefined` if it was rejected or held for later.
+    #[wasm_bindgen(unchecked_return_type = "BatchResult<ReceiveMessageOutput>[]")]
+    pub fn feed_incoming_message_board_reads(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "Seeker[]")] seekers: js_sys::Array,
+        #[wasm_bindgen(unchecked_param_type = "Uint8Array[]")] ciphertexts: js_sys::Array,
+        our_sk: &UserSecretKeys,
+    ) -> Result<js_sys::Array, GossipError> {
+        if seekers.length() != ciphertexts.length() {
+            return Err(GossipError::invalid_argument(
+                "Seekers and ciphertexts must have the same length",
+            ));
+        }
+        let to_vecs = |array: js_sys::Array| -> Vec<Vec<u8>> {
+            array
+                .iter()
+                .map(|item| js_sys::Uint8Array::new(&item).to_vec())
+                .collect()
+        };
+        let seekers = to_vecs(seekers);
+        let ciphertexts = to_vecs(ciphertexts);
+        let reads: Vec<(&[u8], &[u8])> = seekers
+            .iter()
+            .zip(&ciphertexts)
+            .map(|(seeker, ciphertext)| (seeker.as_slice(), ciphertext.as_slice()))
+            .collect();
 
-                ReceiveMessageOutput {
-                    message: output.message.clone(),
-                    timestamp: output.timestamp as f64,
-                    acknowledged_seekers,
-                    user_id: output.user_id.clone(),
+        let array = js_sys::Array::new();
+        for result in self
+            .inner
+            .feed_incoming_message_board_reads(&reads, our_sk.inner()?)
+        {
+            match result {
+                Ok(output) => {
+                    array.push(&JsValue::from(ReceiveMessageOutput::from_output(&output)))
                 }
-            })
+                Err(_) => array.push(&JsValue::UNDEFINED),
+            };
+        }
+        Ok(array)
     }
 
-    /// Gets the list of all peer IDs.
-    ///
-    /// JS-owned Uint8Arrays — same detached-view rationale as
-    /// `get_message_board_read_keys`.
-    pub fn peer_list(&self) -> js_sys::Array {
-        let peers = self.inner.peer_list();
+    /// Gets the seekers peers announced beyond the next one, to fetch entries
+    /// ahead of time. Entries fed early are held until their predecessors
+    /// arrive; collect them with `take_reordered_messages`.
+    #[wasm_bindgen(unchecked_return_type = "Seeker[]")]
+    pub fn get_message_board_lookahead_keys(&self) -> js_sys::Array {
         let array = js_sys::Array::new();
-        for peer_id in peers {
-            let bytes = peer_id.as_bytes();
-            let js_peer_id = js_sys::Uint8Array::new_with_length(bytes.len() as u32);
-            js_peer_id.copy_from(bytes);
-            array.push(&js_peer_id);
+        for seeker in self.inner.get_message_board_lookahead_keys() {
+            let js_seeker = js_sys::Uint8Array::new_with_length(seeker.len() as u32);
+            js_seeker.copy_from(&seeker);
+            array.push(&js_seeker);
         }
         array
     }
 
-    /// Gets the session status for a peer.
-    pub fn peer_session_status(&self, peer_id: &[u8]) -> Result<SessionStatus, JsValue> {
-        if peer_id.len() != 32 {
-            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
+    /// Takes the messages decrypted from entries that were fed ahead of time.
+    #[wasm_bindgen(unchecked_return_type = "ReceiveMessageOutput[]")]
+    pub fn take_reordered_messages(&mut self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for output in self.inner.take_reordered_messages() {
+            array.push(&JsValue::from(ReceiveMessageOutput::from_output(&output)));
         }
-        let mut peer_id_arr = [0u8; 32];
-        peer_id_arr.copy_from_slice(peer_id);
-        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+        array
+    }
 
-        Ok(match self.inner.peer_session_status(&peer_id) {
-            sessions::SessionStatus::Active => SessionStatus::Active,
-            sessions::SessionStatus::UnknownPeer => SessionStatus::UnknownPeer,
-            sessions::SessionStatus::NoSession => SessionStatus::NoSession,
-            sessions::SessionStatus::PeerRequested => SessionStatus::PeerRequested,
-            sessions::SessionStatus::SelfRequested => SessionStatus::SelfRequested,
-            sessions::SessionStatus::Killed => SessionStatus::Killed,
-            sessions::SessionStatus::Saturated => SessionStatus::Saturated,
+    /// Gets the incoming announcement counters.
+    pub fn announcement_stats(&self) -> AnnouncementStats {
+        AnnouncementStats {
+            inner: self.inner.announcement_stats(),
+        }
+    }
+
+    /// Blocks a peer: their session is discarded and their announcements are
+    /// dropped until unblocked.
+    pub fn block_peer(&mut self, peer_id: &UserId) {
+        self.inner.block_peer(&peer_id.inner);
+    }
+
+    /// Unblocks a peer.
+    pub fn unblock_peer(&mut self, peer_id: &UserId) {
+        self.inner.unblock_peer(&peer_id.inner);
+    }
+
+    /// Allows announcements from an unknown peer in allowlist-only mode.
+    pub fn allow_peer(&mut self, peer_id: &UserId) {
+        self.inner.allow_peer(&peer_id.inner);
+    }
+
+    /// Removes a peer from the allowlist.
+    pub fn disallow_peer(&mut self, peer_id: &UserId) {
+        self.inner.disallow_peer(&peer_id.inner);
+    }
+
+    /// Checks whether a peer is on the allowlist.
+    pub fn is_peer_allowed(&self, peer_id: &UserId) -> bool {
+        self.inner.is_peer_allowed(&peer_id.inner)
+    }
+
+    /// Gets the list of allowed peer IDs.
+    #[wasm_bindgen(unchecked_return_type = "UserId[]")]
+    pub fn allowed_peers(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.allowed_peers())
+    }
+
+    /// Accepts the next announcement of a new identity that reuses the wallet
+    /// key of a known peer (error code `KEY_CHANGED`). Feed the announcement
+    /// again afterwards.
+    pub fn approve_key_change(&mut self, peer_id: &UserId) {
+        self.inner.approve_key_change(&peer_id.inner);
+    }
+
+    /// Moves a peer who changed identity to `new_pk`, once the new identity
+    /// is authenticated, e.g. after error code `KEY_CHANGED`. Queued
+    /// messages, metadata and blocked status follow; the old session is
+    /// dropped, so feed the peer's announcement again. Returns the new ID.
+    pub fn migrate_peer(
+        &mut self,
+        old_peer_id: &UserId,
+        new_pk: &UserPublicKeys,
+    ) -> Result<UserId, GossipError> {
+        Ok(UserId::from(
+            self.inner.migrate_peer(&old_peer_id.inner, &new_pk.inner)?,
+        ))
+    }
+
+    /// Checks whether a peer is blocked.
+    pub fn is_peer_blocked(&self, peer_id: &UserId) -> bool {
+        self.inner.is_peer_blocked(&peer_id.inner)
+    }
+
+    /// Gets the list of blocked peer IDs.
+    #[wasm_bindgen(unchecked_return_type = "UserId[]")]
+    pub fn blocked_peers(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.blocked_peers())
+    }
+
+    /// Gets the list of all peer IDs.
+    #[wasm_bindgen(unchecked_return_type = "UserId[]")]
+    pub fn peer_list(&self) -> js_sys::Array {
+        peer_ids_to_array(&self.inner.peer_list())
+    }
+
+    /// Gets the peer counts by status and the delivery backlog across all
+    /// peers, e.g. to decide when to prune inactive peers with
+    /// `peer_discard`.
+    pub fn peer_summary(&self) -> PeerSummary {
+        PeerSummary {
+            inner: self.inner.peer_summary(),
+        }
+    }
+
+    /// Gets the messages sent to a peer that it has not acknowledged yet, or
+    /// `undefined` without an active session.
+    pub fn peer_lag(&self, peer_id: &UserId) -> Option<PeerLag> {
+        self.inner
+            .peer_lag(&peer_id.inner)
+            .map(|inner| PeerLag { inner })
+    }
+
+    /// Gets the activity counters of a peer, or `undefined` if the peer is
+    /// unknown.
+    pub fn peer_stats(&self, peer_id: &UserId) -> Option<PeerStats> {
+        self.inner
+            .peer_stats(&peer_id.inner)
+            .map(|inner| PeerStats { inner })
+    }
+
+    /// Sends a liveness probe to a peer, answered automatically by peers
+    /// advertising the pings capability. Post the output like a message.
+    pub fn ping(&mut self, peer_id: &UserId) -> Result<SendMessageOutput, GossipError> {
+        self.inner
+            .ping(&peer_id.inner)
+            .map(|output| SendMessageOutput::from_output(&output))
+            .map_err(GossipError::from)
+    }
+
+    /// Tells a peer we read its messages up to the given message ID. Post the
+    /// output like a message; only send receipts to peers advertising the
+    /// pings capability.
+    pub fn send_read_receipt(
+        &mut self,
+        peer_id: &UserId,
+        up_to_message_id: f64,
+    ) -> Result<SendMessageOutput, GossipError> {
+        self.inner
+            .send_read_receipt(&peer_id.inner, up_to_message_id as u64)
+            .map(|output| SendMessageOutput::from_output(&output))
+            .map_err(GossipError::from)
+    }
+
+    /// Closes the session with a peer, telling the peer it was closed on
+    /// purpose. Post the output like a message; the peer's status becomes
+    /// `Closed` until either side announces again.
+    pub fn close_session(&mut self, peer_id: &UserId) -> Result<SendMessageOutput, GossipError> {
+        self.inner
+            .close_session(&peer_id.inner)
+            .map(|output| SendMessageOutput::from_output(&output))
+            .map_err(GossipError::from)
+    }
+
+    /// Gets the round-trip and last-seen estimates of a peer, or `undefined`
+    /// if the peer is unknown.
+    pub fn peer_liveness(&self, peer_id: &UserId) -> Option<PeerLiveness> {
+        self.inner
+            .peer_liveness(&peer_id.inner)
+            .map(|inner| PeerLiveness { inner })
+    }
+
+    /// Gets the latest events about a peer as text, one per line, to attach
+    /// to bug reports, or `undefined` if the peer is unknown. Holds no keys or
+    /// message contents.
+    pub fn debug_transcript(&self, peer_id: &UserId) -> Option<String> {
+        self.inner.debug_transcript(&peer_id.inner).map(|entries| {
+            entries
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
         })
     }
 
-    /// Discards a peer and all associated session state.
-    pub fn peer_discard(&mut self, peer_id: &[u8]) -> Result<(), JsValue> {
-        if peer_id.len() != 32 {
-            return Err(JsValue::from_str("Peer ID must be 32 bytes"));
+    /// Gets the epoch of the active session with a peer, growing by one with
+    /// every session established with it, or `undefined` without a session.
+    pub fn session_epoch(&self, peer_id: &UserId) -> Option<f64> {
+        self.inner
+            .session_epoch(&peer_id.inner)
+            .map(|epoch| epoch as f64)
+    }
+
+    /// Gets the session status for a peer.
+    pub fn peer_session_status(&self, peer_id: &UserId) -> SessionStatus {
+        self.inner.peer_session_status(&peer_id.inner).into()
+    }
+
+    /// Gets the session status of every known peer, as a `Map` from the
+    /// lowercase hex peer ID to its `SessionStatus`. Prefer this over calling
+    /// `peer_session_status` for each peer.
+    #[wasm_bindgen(unchecked_return_type = "Map<string, SessionStatus>")]
+    pub fn peer_statuses(&self) -> js_sys::Map {
+        let map = js_sys::Map::new();
+        for (peer_id, status) in self.inner.peer_statuses() {
+            map.set(
+                &JsValue::from_str(&peer_id.to_hex()),
+                &JsValue::from(SessionStatus::from(status)),
+            );
         }
-        let mut peer_id_arr = [0u8; 32];
-        peer_id_arr.copy_from_slice(peer_id);
-        let peer_id = auth::UserId::from_bytes(peer_id_arr);
+        map
+    }
 
-        self.inner.peer_discard(&peer_id);
-        Ok(())
+    /// Gets the session requests waiting for our answer (array of
+    /// `PendingRequest`), oldest first. Ignored requests are left out.
+    #[wasm_bindgen(unchecked_return_type = "PendingRequest[]")]
+    pub fn pending_requests(&self) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for inner in self.inner.pending_requests() {
+            array.push(&JsValue::from(PendingRequest { inner }));
+        }
+        array
     }
 
-    /// Refreshes sessions and returns peer IDs that need keep-alive messages.
-    ///
-    /// JS-owned Uint8Arrays — same detached-view rationale as
-    /// `get_message_board_read_keys`.
-    pub fn refresh(&mut self) -> js_sys::Array {
-        let peers = self.inner.refresh();
+    /// Accepts the pending request of a peer. Returns our announcement to
+    /// publish, which establishes the session.
+    pub fn accept_request(
+        &mut self,
+        peer_id: &UserId,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+        user_data: &[u8],
+    ) -> Result<Vec<u8>, GossipError> {
+        self.inner
+            .accept_request(
+                &peer_id.inner,
+                &our_pk.inner,
+                our_sk.inner()?,
+                user_data.to_vec(),
+            )
+            .map_err(GossipError::from)
+    }
+
+    /// Rejects the pending request of a peer: the peer is discarded, and
+    /// blocked if `block` is set.
+    pub fn reject_request(&mut self, peer_id: &UserId, block: bool) -> Result<(), GossipError> {
+        self.inner
+            .reject_request(&peer_id.inner, block)
+            .map_err(GossipError::from)
+    }
+
+    /// Ignores the pending request of a peer until it announces itself again.
+    pub fn ignore_request(&mut self, peer_id: &UserId) -> Result<(), GossipError> {
+        self.inner
+            .ignore_request(&peer_id.inner)
+            .map_err(GossipError::from)
+    }
+
+    /// Marks a peer as ephemeral, or persistent again. Ephemeral peers are
+    /// left out of encrypted blobs and incremental snapshots, so they only
+    /// live in memory.
+    pub fn set_peer_ephemeral(
+        &mut self,
+        peer_id: &UserId,
+        ephemeral: bool,
+    ) -> Result<(), GossipError> {
+        self.inner
+            .set_peer_ephemeral(&peer_id.inner, ephemeral)
+            .map_err(GossipError::from)
+    }
+
+    /// Checks whether a peer is ephemeral.
+    pub fn is_peer_ephemeral(&self, peer_id: &UserId) -> bool {
+        self.inner.is_peer_ephemeral(&peer_id.inner)
+    }
+
+    /// Discards a peer and all associated session state.
+    pub fn peer_discard(&mut self, peer_id: &UserId) {
+        self.inner.peer_discard(&peer_id.inner);
+    }
+
+    /// Discards the peers without session, queued message or activity for
+    /// `older_than_millis`, and returns them, least recently active first,
+    /// for the application to archive.
+    #[wasm_bindgen(unchecked_return_type = "PrunedPeer[]")]
+    pub fn prune_peers(&mut self, older_than_millis: f64) -> js_sys::Array {
         let array = js_sys::Array::new();
-        for peer_id in peers {
-            let bytes = peer_id.as_bytes();
-            let js_peer_id = js_sys::Uint8Array::new_with_length(bytes.len() as u32);
-            js_peer_id.copy_from(bytes);
-            array.push(&js_peer_id);
+        for inner in self.inner.prune_peers(older_than_millis as u128) {
+            array.push(&JsValue::from(PrunedPeer { inner }));
         }
         array
     }
+
+    /// Refreshes sessions and reports expired sessions, expired requests and
+    /// the peers that need keep-alive messages.
+    ///
+    /// Peer IDs are JS-owned Uint8Arrays — same detached-view rationale as
+    /// `get_message_board_read_keys`.
+    pub fn refresh(&mut self) -> RefreshReport {
+        RefreshReport {
+            inner: self.inner.refresh(),
+            keep_alives: js_sys::Array::new(),
+            announcements: js_sys::Array::new(),
+        }
+    }
+
+    /// Same as `refresh`, also returning the keep-alive messages to post to
+    /// the message board in `keep_alives`. Saturated sessions are skipped
+    /// until their lag clears.
+    ///
+    /// # Example
+    ///
+    /// ```javascript
+    /// const report = manager.refresh_with_keepalives();
+    /// for (const { seeker, data } of report.keep_alives) {
+    ///   await board.post(seeker, data);
+    /// }
+    /// ```
+    pub fn refresh_with_keepalives(&mut self) -> RefreshReport {
+        let (report, outputs) = self.inner.refresh_with_keepalives();
+        let keep_alives = js_sys::Array::new();
+        for (peer_id, output) in outputs {
+            keep_alives.push(&JsValue::from(KeepAliveMessage {
+                peer_id,
+                seeker: output.seeker.clone(),
+                data: output.data.clone(),
+            }));
+        }
+        RefreshReport {
+            inner: report,
+            keep_alives,
+            announcements: js_sys::Array::new(),
+        }
+    }
+
+    /// Same as `refresh`, also announcing again to the peers that need a
+    /// re-handshake or a renewal of their unanswered announcement, and
+    /// returning the announcements to publish in `announcements`.
+    pub fn refresh_with_rehandshakes(
+        &mut self,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> Result<RefreshReport, GossipError> {
+        let (report, outputs) = self
+            .inner
+            .refresh_with_rehandshakes(&our_pk.inner, our_sk.inner()?);
+        let announcements = js_sys::Array::new();
+        for (peer_id, announcement) in outputs {
+            announcements.push(&js_sys::Array::of2(
+                &js_sys::Uint8Array::from(peer_id.as_bytes()),
+                &js_sys::Uint8Array::from(&announcement[..]),
+            ));
+        }
+        Ok(RefreshReport {
+            inner: report,
+            keep_alives: js_sys::Array::new(),
+            announcements,
+        })
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// Storage of a `PersistedSessionManager`, see the `SessionStorage`
+    /// interface.
+    #[wasm_bindgen(typescript_type = "SessionStorage")]
+    pub type SessionStorage;
+
+    #[wasm_bindgen(method, catch, js_name = readData)]
+    fn read_data(
+        this: &SessionStorage,
+        namespace: u8,
+        offset: f64,
+        len: usize,
+    ) -> Result<Vec<u8>, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = writeData)]
+    fn write_data(
+        this: &SessionStorage,
+        namespace: u8,
+        offset: f64,
+        data: &[u8],
+    ) -> Result<(), JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = dataLength)]
+    fn data_length(this: &SessionStorage, namespace: u8) -> Result<f64, JsValue>;
+
+    #[wasm_bindgen(method, catch, js_name = flushData)]
+    fn flush_data(this: &SessionStorage) -> Result<js_sys::Promise, JsValue>;
+}
+
+/// Delay between a change and the save it triggers, when not configured.
+const DEFAULT_SAVE_DEBOUNCE_MILLIS: u32 = 500;
+
+/// Size of the big-endian length prefix of the stored blob: a namespace
+/// cannot be truncated, so a shorter blob leaves stale bytes after it.
+const BLOB_LENGTH_PREFIX_SIZE: usize = 4;
+
+struct PersistedState {
+    manager: SessionManagerWrapper,
+    storage: SessionStorage,
+    namespace: u8,
+    key: crypto_aead::Key,
+    debounce_millis: u32,
+    /// Changed since the last save
+    dirty: bool,
+    save_scheduled: bool,
+}
+
+/// Session manager that keeps its encrypted state in a `SessionStorage`
+/// namespace, saving it after every change.
+///
+/// Changes made through this class are saved once no other change happened
+/// for `debounce_millis`, and are durable once the storage flushed them. A
+/// save pending when the object is freed still runs, but call `flush` before
+/// closing the storage, and after changes that must not be lost, such as a
+/// new session. A failed background save is retried by the next one, and
+/// `flush` throws its error.
+///
+/// Only the most common operations are available; for the others, use a
+/// `SessionManagerWrapper` and save it yourself.
+///
+/// # Example
+///
+/// ```javascript
+/// const storage = await StorageHandle.initStorage(domain, "idb");
+/// storage.unlockSession(password);
+/// const manager = PersistedSessionManager.load(storage, 1, key, SessionConfig.new_default());
+/// const announcement = manager.establish_outgoing_session(peer_pk, our_pk, our_sk, userData);
+/// await manager.flush();
+/// ```
+#[wasm_bindgen]
+pub struct PersistedSessionManager {
+    state: Rc<RefCell<PersistedState>>,
+}
+
+#[wasm_bindgen]
+impl PersistedSessionManager {
+    /// Loads the session manager saved in `namespace` of `storage`, or
+    /// creates one with `config` if the namespace is empty. `config` only
+    /// applies to a new manager.
+    pub fn load(
+        storage: SessionStorage,
+        namespace: u8,
+        key: &EncryptionKey,
+        config: SessionConfig,
+        debounce_millis: Option<u32>,
+    ) -> Result<PersistedSessionManager, GossipError> {
+        let key = crypto_aead::Key::from_ref(key.inner()?.as_bytes());
+        let stored_len = storage
+            .data_length(namespace)
+            .map_err(|e| GossipError::storage(&e))?;
+        let manager = if stored_len == 0.0 {
+            SessionManagerWrapper::new(config)
+        } else {
+            let prefix = storage
+                .read_data(namespace, 0.0, BLOB_LENGTH_PREFIX_SIZE)
+                .map_err(|e| GossipError::storage(&e))?;
+            let prefix: [u8; BLOB_LENGTH_PREFIX_SIZE] = prefix
+                .try_into()
+                .map_err(|_| GossipError::serialization("Truncated session manager record"))?;
+            let blob = storage
+                .read_data(
+                    namespace,
+                    BLOB_LENGTH_PREFIX_SIZE as f64,
+                    u32::from_be_bytes(prefix) as usize,
+                )
+                .map_err(|e| GossipError::storage(&e))?;
+            let inner =
+                sessions::SessionManager::from_encrypted_blob(&blob, &key).ok_or_else(|| {
+                    GossipError::crypto("DECRYPTION", "Failed to decrypt session manager")
+                })?;
+            SessionManagerWrapper { inner }
+        };
+        Ok(Self {
+            state: Rc::new(RefCell::new(PersistedState {
+                manager,
+                storage,
+                namespace,
+                key,
+                debounce_millis: debounce_millis.unwrap_or(DEFAULT_SAVE_DEBOUNCE_MILLIS),
+                dirty: false,
+                save_scheduled: false,
+            })),
+        })
+    }
+
+    /// Whether changes are not saved yet.
+    #[wasm_bindgen(getter)]
+    pub fn dirty(&self) -> bool {
+        self.state.borrow().dirty
+    }
+
+    /// Saves pending changes now; resolves once the storage flushed them.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn flush(&self) -> js_sys::Promise {
+        let state = Rc::clone(&self.state);
+        wasm_bindgen_futures::future_to_promise(async move {
+            save(&state).await.map_err(JsValue::from)?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// See `SessionManagerWrapper.establish_outgoing_session`.
+    pub fn establish_outgoing_session(
+        &self,
+        peer_pk: &UserPublicKeys,
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+        user_data: &[u8],
+    ) -> Result<Vec<u8>, GossipError> {
+        self.update(|manager| {
+            manager.establish_outgoing_session(peer_pk, our_pk, our_sk, user_data)
+        })
+    }
+
+    /// See `SessionManagerWrapper.feed_incoming_announcement`.
+    pub fn feed_incoming_announcement(
+        &self,
+        announcement_bytes: &[u8],
+        our_pk: &UserPublicKeys,
+        our_sk: &UserSecretKeys,
+    ) -> Result<Option<AnnouncementResult>, GossipError> {
+        self.update(|manager| {
+            manager.feed_incoming_announcement(announcement_bytes, our_pk, our_sk)
+        })
+    }
+
+    /// See `SessionManagerWrapper.send_message`.
+    pub fn send_message(
+        &self,
+        peer_id: &UserId,
+        message_contents: &[u8],
+    ) -> Option<SendMessageOutput> {
+        self.update(|manager| manager.send_message(peer_id, message_contents))
+    }
+
+    /// See `SessionManagerWrapper.feed_incoming_message_board_read`.
+    pub fn feed_incoming_message_board_read(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Seeker")] seeker: &[u8],
+        ciphertext: &[u8],
+        our_sk: &UserSecretKeys,
+    ) -> Result<Option<ReceiveMessageOutput>, GossipError> {
+        self.update(|manager| manager.feed_incoming_message_board_read(seeker, ciphertext, our_sk))
+    }
+
+    /// See `SessionManagerWrapper.refresh_with_keepalives`.
+    pub fn refresh_with_keepalives(&self) -> RefreshReport {
+        self.update(|manager| manager.refresh_with_keepalives())
+    }
+
+    /// See `SessionManagerWrapper.get_message_board_read_keys`.
+    #[wasm_bindgen(unchecked_return_type = "Seeker[]")]
+    pub fn get_message_board_read_keys(&self) -> js_sys::Array {
+        self.state.borrow().manager.get_message_board_read_keys()
+    }
+
+    /// See `SessionManagerWrapper.peer_list`.
+    #[wasm_bindgen(unchecked_return_type = "UserId[]")]
+    pub fn peer_list(&self) -> js_sys::Array {
+        self.state.borrow().manager.peer_list()
+    }
+
+    /// See `SessionManagerWrapper.peer_session_status`.
+    pub fn peer_session_status(&self, peer_id: &UserId) -> SessionStatus {
+        self.state.borrow().manager.peer_session_status(peer_id)
+    }
+}
+
+impl PersistedSessionManager {
+    /// Runs an operation that may change the manager, and schedules a save.
+    fn update<T>(&self, operation: impl FnOnce(&mut SessionManagerWrapper) -> T) -> T {
+        let output = operation(&mut self.state.borrow_mut().manager);
+        schedule_save(&self.state);
+        output
+    }
+}
+
+/// Marks the state changed and saves it after the debounce delay, unless a
+/// save is already scheduled.
+fn schedule_save(state: &Rc<RefCell<PersistedState>>) {
+    let mut state_ref = state.borrow_mut();
+    state_ref.dirty = true;
+    if state_ref.save_scheduled {
+        return;
+    }
+    state_ref.save_scheduled = true;
+    let debounce_millis = i32::try_from(state_ref.debounce_millis).unwrap_or(i32::MAX);
+    drop(state_ref);
+
+    let state = Rc::clone(state);
+    let callback = Closure::once_into_js(move || {
+        state.borrow_mut().save_scheduled = false;
+        wasm_bindgen_futures::spawn_local(async move {
+            // Left dirty on failure: the next save retries.
+            let _ = save(&state).await;
+        });
+    });
+    set_timeout(callback.unchecked_ref(), debounce_millis);
+}
+
+/// Writes the state to the storage if it changed and waits for the storage
+/// to flush it.
+async fn save(state: &RefCell<PersistedState>) -> Result<(), GossipError> {
+    let flushed = {
+        let mut state = state.borrow_mut();
+        if state.dirty {
+            let blob = state
+                .manager
+                .inner
+                .to_encrypted_blob(&state.key)
+                .ok_or_else(|| {
+                    GossipError::crypto("ENCRYPTION", "Failed to encrypt session manager")
+                })?;
+            let blob_len = u32::try_from(blob.len())
+                .map_err(|_| GossipError::serialization("Session manager too large"))?;
+            let mut record = Vec::with_capacity(BLOB_LENGTH_PREFIX_SIZE + blob.len());
+            record.extend_from_slice(&blob_len.to_be_bytes());
+            record.extend_from_slice(&blob);
+            state
+                .storage
+                .write_data(state.namespace, 0.0, &record)
+                .map_err(|e| GossipError::storage(&e))?;
+            state.dirty = false;
+        }
+        state
+            .storage
+            .flush_data()
+            .map_err(|e| GossipError::storage(&e))
+    };
+    let result = match flushed {
+        Ok(promise) => wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map(|_| ())
+            .map_err(|e| GossipError::storage(&e)),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        state.borrow_mut().dirty = true;
+    }
+    result
 }
@@ -146,6 +146,27 @@ pub fn root_aead_key_label(domain: &str) -> String {
     format!("{domain}:secure_storage:kdf:root_aead_key")
 }
 
+/// Salt for recovery-code KDF: `{root}:recovery_kdf`
+#[must_use]
+pub fn recovery_kdf_salt(domain: &str) -> String {
+    format!("{domain}:secure_storage:recovery_kdf")
+}
+
+/// Label for recovery wrap key derivation: `{root}:kdf:recovery_wrap_key`
+#[must_use]
+pub fn recovery_wrap_key_label(domain: &str) -> String {
+    format!("{domain}:secure_storage:kdf:recovery_wrap_key")
+}
+
+/// AAD for a recovery entry: `{session_scope}:recovery:c{code_index}`
+#[must_use]
+pub fn recovery_aad(domain: &str, version: u32, index: SessionIndex, code_index: usize) -> String {
+    format!(
+        "{domain}:secure_storage:session:v{version}:i{}:recovery:c{code_index}",
+        index.as_u8()
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,4 +358,13 @@ mod tests {
         block_aead_aad(&mut buf, "d", 0, idx, 0, 1);
         assert_ne!(first, buf, "buffer should be overwritten on reuse");
     }
+
+    #[test]
+    fn test_recovery_aad() {
+        let idx = SessionIndex::new(1).unwrap();
+        assert_eq!(
+            recovery_aad("app:ns", 0, idx, 3),
+            "app:ns:secure_storage:session:v0:i1:recovery:c3"
+        );
+    }
 }
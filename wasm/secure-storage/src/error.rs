@@ -9,6 +9,12 @@ pub enum SecureStorageError {
     #[error("invalid password")]
     InvalidPassword,
 
+    #[error("invalid mnemonic")]
+    InvalidMnemonic,
+
+    #[error("lease held by another client")]
+    LeaseHeld,
+
     #[error("out of bounds")]
     OutOfBounds,
 
@@ -55,6 +61,8 @@ impl SecureStorageError {
     pub fn code(&self) -> &'static str {
         match self {
             Self::InvalidPassword => "INVALID_PASSWORD",
+            Self::InvalidMnemonic => "INVALID_MNEMONIC",
+            Self::LeaseHeld => "LEASE_HELD",
             Self::OutOfBounds => "OUT_OF_BOUNDS",
             Self::UnsupportedVersion(_) => "UNSUPPORTED_VERSION",
             Self::CorruptedBlock => "CORRUPTED_DATA",
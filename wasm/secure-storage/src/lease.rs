@@ -0,0 +1,185 @@
+//! Single-writer leases shared by several clients of the same storage.
+//!
+//! Browser tabs of one origin share the storage backend, but only one of
+//! them may write to it at a time. A lease names the current writer and
+//! expires unless its holder renews it (a heartbeat), so a tab that crashed
+//! or was frozen loses it after a timeout:
+//!
+//!   * [`acquire_lease`] grants the lease if it is free, already ours, or
+//!     expired — taking over from a holder that stopped renewing;
+//!   * [`renew_lease`] extends it while we still hold it;
+//!   * [`release_lease`] gives it up so another client can take it at once.
+//!
+//! These functions only decide on the next [`LeaseRecord`]; the backend
+//! stores it and must run each read-decide-write in one atomic transaction.
+
+use rand::RngCore;
+
+use crate::error::{Result, SecureStorageError};
+
+/// Size of a lease holder identifier.
+pub const LEASE_HOLDER_SIZE: usize = 16;
+
+/// Size of a serialized [`LeaseRecord`].
+pub const LEASE_RECORD_SIZE: usize = LEASE_HOLDER_SIZE + 8;
+
+/// Random identifier of a lease holder, unique per acquisition.
+pub type LeaseHolder = [u8; LEASE_HOLDER_SIZE];
+
+/// Generate a fresh holder identifier.
+#[must_use]
+pub fn generate_lease_holder() -> LeaseHolder {
+    let mut holder = [0u8; LEASE_HOLDER_SIZE];
+    rand::rngs::OsRng.fill_bytes(&mut holder);
+    holder
+}
+
+/// Stored state of a lease: who holds it and until when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaseRecord {
+    pub holder: LeaseHolder,
+    /// Expiry, in milliseconds since the Unix epoch.
+    pub expires_at_millis: u64,
+}
+
+impl LeaseRecord {
+    /// Serialize as `holder || expires_at_millis (u64 BE)`.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; LEASE_RECORD_SIZE] {
+        let mut bytes = [0u8; LEASE_RECORD_SIZE];
+        bytes[..LEASE_HOLDER_SIZE].copy_from_slice(&self.holder);
+        bytes[LEASE_HOLDER_SIZE..].copy_from_slice(&self.expires_at_millis.to_be_bytes());
+        bytes
+    }
+
+    /// Parse a record written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: &[u8; LEASE_RECORD_SIZE] = bytes
+            .try_into()
+            .map_err(|_| SecureStorageError::CorruptedBlock)?;
+        let (holder, expires_at) = bytes.split_at(LEASE_HOLDER_SIZE);
+        Ok(Self {
+            holder: holder.try_into().expect("split at the holder size"),
+            expires_at_millis: u64::from_be_bytes(expires_at.try_into().expect("8 bytes left")),
+        })
+    }
+
+    /// Whether the lease has expired at `now_millis`.
+    #[must_use]
+    pub fn is_expired(&self, now_millis: u64) -> bool {
+        now_millis >= self.expires_at_millis
+    }
+}
+
+/// Decide on an acquisition by `holder` of the lease currently in `current`.
+///
+/// Succeeds if the lease is free, held by `holder`, or expired, and returns
+/// the record to store, valid for `duration_millis`. Fails with
+/// [`SecureStorageError::LeaseHeld`] while another holder's lease runs.
+pub fn acquire_lease(
+    current: Option<&LeaseRecord>,
+    holder: &LeaseHolder,
+    now_millis: u64,
+    duration_millis: u64,
+) -> Result<LeaseRecord> {
+    if let Some(current) = current
+        && current.holder != *holder
+        && !current.is_expired(now_millis)
+    {
+        return Err(SecureStorageError::LeaseHeld);
+    }
+    Ok(LeaseRecord {
+        holder: *holder,
+        expires_at_millis: now_millis.saturating_add(duration_millis),
+    })
+}
+
+/// Decide on a renewal by `holder` of the lease currently in `current`.
+///
+/// Succeeds as long as nobody else took the lease, even past its expiry,
+/// and returns the record to store. Fails with
+/// [`SecureStorageError::LeaseHeld`] once it was released or taken over:
+/// the caller must stop writing.
+pub fn renew_lease(
+    current: Option<&LeaseRecord>,
+    holder: &LeaseHolder,
+    now_millis: u64,
+    duration_millis: u64,
+) -> Result<LeaseRecord> {
+    match current {
+        Some(current) if current.holder == *holder => Ok(LeaseRecord {
+            holder: *holder,
+            expires_at_millis: now_millis.saturating_add(duration_millis),
+        }),
+        _ => Err(SecureStorageError::LeaseHeld),
+    }
+}
+
+/// Whether a release by `holder` of the lease in `current` deletes it.
+/// Releasing a lease held by someone else leaves it untouched.
+#[must_use]
+pub fn release_lease(current: Option<&LeaseRecord>, holder: &LeaseHolder) -> bool {
+    current.is_some_and(|current| current.holder == *holder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAB_A: LeaseHolder = [1; LEASE_HOLDER_SIZE];
+    const TAB_B: LeaseHolder = [2; LEASE_HOLDER_SIZE];
+
+    #[test]
+    fn record_roundtrip() {
+        let record = LeaseRecord {
+            holder: TAB_A,
+            expires_at_millis: 1_700_000_000_000,
+        };
+        assert_eq!(LeaseRecord::from_bytes(&record.to_bytes()).unwrap(), record);
+        assert!(matches!(
+            LeaseRecord::from_bytes(&[0; LEASE_RECORD_SIZE - 1]),
+            Err(SecureStorageError::CorruptedBlock)
+        ));
+    }
+
+    #[test]
+    fn single_writer_until_expiry() {
+        let lease = acquire_lease(None, &TAB_A, 1_000, 5_000).unwrap();
+        assert_eq!(lease.expires_at_millis, 6_000);
+
+        // another tab is refused while the lease runs
+        assert!(matches!(
+            acquire_lease(Some(&lease), &TAB_B, 5_999, 5_000),
+            Err(SecureStorageError::LeaseHeld)
+        ));
+
+        // the holder renews it
+        let lease = renew_lease(Some(&lease), &TAB_A, 4_000, 5_000).unwrap();
+        assert_eq!(lease.expires_at_millis, 9_000);
+        assert!(matches!(
+            renew_lease(Some(&lease), &TAB_B, 4_000, 5_000),
+            Err(SecureStorageError::LeaseHeld)
+        ));
+
+        // once expired, another tab steals it and the old holder lost it
+        let stolen = acquire_lease(Some(&lease), &TAB_B, 9_000, 5_000).unwrap();
+        assert_eq!(stolen.holder, TAB_B);
+        assert!(matches!(
+            renew_lease(Some(&stolen), &TAB_A, 9_001, 5_000),
+            Err(SecureStorageError::LeaseHeld)
+        ));
+    }
+
+    #[test]
+    fn release_only_own_lease() {
+        let lease = acquire_lease(None, &TAB_A, 0, 5_000).unwrap();
+        assert!(!release_lease(Some(&lease), &TAB_B));
+        assert!(release_lease(Some(&lease), &TAB_A));
+        assert!(!release_lease(None, &TAB_A));
+        // a released lease cannot be renewed
+        assert!(matches!(
+            renew_lease(None, &TAB_A, 1, 5_000),
+            Err(SecureStorageError::LeaseHeld)
+        ));
+    }
+}
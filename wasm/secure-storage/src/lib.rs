@@ -12,9 +12,12 @@ mod error;
 pub mod js_num;
 mod kdf;
 mod keypair;
+mod lease;
 mod lifecycle;
+mod mnemonic;
 mod pq;
 mod read;
+mod recovery;
 pub mod storage;
 mod types;
 mod unlock;
@@ -36,6 +39,9 @@ mod native_api;
 uniffi::setup_scaffolding!();
 
 pub use block::{create_cover_block, decrypt_block, encrypt_block, rerandomize_block};
+#[cfg(target_has_atomic = "64")]
+pub use clock::MockClock;
+pub use clock::{Clock, SystemClock};
 pub use constants::{
     AEAD_TAG_SIZE, BLOCK_SIZE, DEFAULT_NAMESPACE, LENGTH_HDR_SIZE, PLAINTEXT_SIZE,
     ROOT_BLOCK_KEY_SIZE, SESSION_COUNT,
@@ -47,11 +53,23 @@ pub use domain::{
 pub use error::{Result, SecureStorageError};
 pub use kdf::{SessionKeys, derive_block_aead_key, derive_session_keys};
 pub use keypair::{KeypairFile, read_session_keypair, read_session_version_and_pk};
+pub use lease::{
+    LEASE_HOLDER_SIZE, LEASE_RECORD_SIZE, LeaseHolder, LeaseRecord, acquire_lease,
+    generate_lease_holder, release_lease, renew_lease,
+};
 pub use lifecycle::{allocate_session, cover_traffic_tick, destroy_session, provision_storage};
+pub use mnemonic::{
+    allocate_session_with_mnemonic, generate_mnemonic, mnemonic_to_password,
+    unlock_session_with_mnemonic,
+};
 pub use pq::{
     PQ_CT_SIZE, PQ_MSG_SIZE, PqPublicKey, PqSecretKey, pq_decrypt, pq_encrypt, pq_keygen, pq_rerand,
 };
 pub use read::{decrypt_session_data_block, read_session_data, read_total_length};
+pub use recovery::{
+    RECOVERY_CODE_COUNT, RECOVERY_FILE_SIZE, RecoveryCode, generate_recovery_codes,
+    pad_recovery_storage, provision_recovery_storage, unlock_with_recovery_code,
+};
 pub use types::SessionIndex;
 pub use unlock::{NamespaceState, UnlockedSession, load_namespace_state, unlock_session};
 pub use write::{
@@ -47,6 +47,7 @@ pub fn provision_storage<S: BlockStorage + KeypairStorage>(storage: &mut S) -> R
         storage.reset_blockstream(slot, DEFAULT_NAMESPACE)?;
     }
 
+    log::info!("storage provisioned with {SESSION_COUNT} slots");
     Ok(())
 }
 
@@ -128,6 +129,7 @@ pub fn allocate_session<S: BlockStorage + KeypairStorage>(
         encrypt_session_data_block(storage, domain, ns, &session, 0, pt_arr)?;
     }
 
+    log::info!("session allocated");
     Ok(session)
 }
 
@@ -191,6 +193,7 @@ pub fn destroy_session<S: BlockStorage + KeypairStorage>(
         rerandomize_all_blocks_all_slots(storage, domain, ns, Some(slot))?;
     }
 
+    log::info!("session destroyed in {} namespaces", namespaces.len());
     Ok(())
 }
 
@@ -0,0 +1,158 @@
+//! BIP39 mnemonic passwords.
+//!
+//! A 12- or 24-word BIP39 mnemonic (plus optional passphrase) is turned into
+//! its 64-byte BIP39 seed, which is then used as the session password for
+//! [`allocate_session`] / [`unlock_session`]. The checksum is validated
+//! before any KDF work so typos are reported as
+//! [`SecureStorageError::InvalidMnemonic`] rather than `InvalidPassword`.
+
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::error::{Result, SecureStorageError};
+use crate::lifecycle::allocate_session;
+use crate::storage::{BlockStorage, KeypairStorage};
+use crate::types::SessionIndex;
+use crate::unlock::{UnlockedSession, unlock_session};
+
+/// Word counts accepted for storage mnemonics.
+const WORD_COUNTS: [usize; 2] = [12, 24];
+
+/// Generate a fresh English BIP39 mnemonic of 12 or 24 words.
+pub fn generate_mnemonic(word_count: usize) -> Result<Zeroizing<String>> {
+    if !WORD_COUNTS.contains(&word_count) {
+        return Err(SecureStorageError::InvalidMnemonic);
+    }
+    // 12 words = 128 bits of entropy, 24 words = 256 bits.
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    let entropy = &mut entropy[..word_count / 3 * 4];
+    rand::rngs::OsRng.fill_bytes(entropy);
+    let mnemonic =
+        bip39::Mnemonic::from_entropy(entropy).map_err(|_| SecureStorageError::InvalidMnemonic)?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Validate a mnemonic and derive the session password from it.
+///
+/// Words may be separated by any run of whitespace; leading and trailing
+/// whitespace is ignored. Words are matched against the English list, and
+/// the checksum must be valid.
+pub fn mnemonic_to_password(phrase: &str, passphrase: &str) -> Result<Zeroizing<[u8; 64]>> {
+    let words = Zeroizing::new(phrase.split_whitespace().collect::<Vec<_>>().join(" "));
+    let mnemonic = bip39::Mnemonic::parse_in(bip39::Language::English, words.as_str())
+        .map_err(|_| SecureStorageError::InvalidMnemonic)?;
+    if !WORD_COUNTS.contains(&mnemonic.word_count()) {
+        return Err(SecureStorageError::InvalidMnemonic);
+    }
+    Ok(Zeroizing::new(mnemonic.to_seed(passphrase)))
+}
+
+/// [`allocate_session`] with a BIP39 mnemonic as the password.
+pub fn allocate_session_with_mnemonic<S: BlockStorage + KeypairStorage>(
+    storage: &mut S,
+    domain: &str,
+    slot: SessionIndex,
+    phrase: &str,
+    passphrase: &str,
+) -> Result<UnlockedSession> {
+    let password = mnemonic_to_password(phrase, passphrase)?;
+    allocate_session(storage, domain, slot, password.as_ref())
+}
+
+/// [`unlock_session`] with a BIP39 mnemonic as the password.
+pub fn unlock_session_with_mnemonic<S: BlockStorage + KeypairStorage>(
+    storage: &S,
+    domain: &str,
+    phrase: &str,
+    passphrase: &str,
+) -> Result<UnlockedSession> {
+    let password = mnemonic_to_password(phrase, passphrase)?;
+    unlock_session(storage, domain, password.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::provision_storage;
+    use crate::run_with_stack;
+    use crate::storage::MemoryStorage;
+
+    const DOMAIN: &str = "test";
+
+    /// BIP39 reference vector (all-zero entropy).
+    const VECTOR_12: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn generate_word_counts() {
+        assert_eq!(generate_mnemonic(12).unwrap().split(' ').count(), 12);
+        assert_eq!(generate_mnemonic(24).unwrap().split(' ').count(), 24);
+        assert!(matches!(
+            generate_mnemonic(15),
+            Err(SecureStorageError::InvalidMnemonic)
+        ));
+    }
+
+    #[test]
+    fn generated_mnemonic_validates() {
+        let phrase = generate_mnemonic(24).unwrap();
+        mnemonic_to_password(&phrase, "").unwrap();
+    }
+
+    #[test]
+    fn reference_vector_seed() {
+        // Trezor vector: entropy 00..00, passphrase "TREZOR".
+        let seed = mnemonic_to_password(VECTOR_12, "TREZOR").unwrap();
+        assert_eq!(seed[..8], [0xc5, 0x52, 0x57, 0xc3, 0x60, 0xc0, 0x7c, 0x72]);
+    }
+
+    #[test]
+    fn bad_checksum_rejected() {
+        let bad = VECTOR_12.replace("about", "abandon");
+        assert!(matches!(
+            mnemonic_to_password(&bad, ""),
+            Err(SecureStorageError::InvalidMnemonic)
+        ));
+    }
+
+    #[test]
+    fn unsupported_word_count_rejected() {
+        // Valid 15-word BIP39 mnemonic, but only 12/24 are accepted.
+        let phrase = bip39::Mnemonic::from_entropy(&[0u8; 20])
+            .unwrap()
+            .to_string();
+        assert!(matches!(
+            mnemonic_to_password(&phrase, ""),
+            Err(SecureStorageError::InvalidMnemonic)
+        ));
+    }
+
+    #[test]
+    fn extra_whitespace_ignored() {
+        let spaced = format!("  {}\n", VECTOR_12.replace(' ', "   "));
+        assert_eq!(
+            *mnemonic_to_password(&spaced, "").unwrap(),
+            *mnemonic_to_password(VECTOR_12, "").unwrap()
+        );
+    }
+
+    #[test]
+    fn allocate_and_unlock_with_mnemonic() {
+        run_with_stack(|| {
+            let mut storage = MemoryStorage::new();
+            provision_storage(&mut storage).unwrap();
+            let slot = SessionIndex::new(1).unwrap();
+            let phrase = generate_mnemonic(12).unwrap();
+
+            allocate_session_with_mnemonic(&mut storage, DOMAIN, slot, &phrase, "pp").unwrap();
+
+            let session = unlock_session_with_mnemonic(&storage, DOMAIN, &phrase, "pp").unwrap();
+            assert_eq!(session.session_index, slot);
+
+            // Same words, different passphrase: a different (empty) password.
+            assert!(matches!(
+                unlock_session_with_mnemonic(&storage, DOMAIN, &phrase, ""),
+                Err(SecureStorageError::InvalidPassword)
+            ));
+        });
+    }
+}
@@ -20,6 +20,7 @@ use zeroize::Zeroizing;
 
 use crate::error::SecureStorageError;
 use crate::js_num;
+use crate::recovery::RecoveryCode;
 use crate::vfs::native_vfs;
 
 // ── Global DB connection ────────────────────────────────────────────
@@ -104,6 +105,11 @@ struct UnlockArgs {
     password: String,
 }
 
+#[derive(Deserialize)]
+struct RecoveryCodeArgs {
+    code: String,
+}
+
 #[derive(Deserialize)]
 struct ExecSqlArgs {
     sql: String,
@@ -257,12 +263,29 @@ fn dispatch(method: &str, args: &str) -> Result<String> {
             allocate(a.slot, &password)?;
             Ok("null".into())
         }
+        "allocateSessionWithRecovery" => {
+            let a: AllocateArgs = parse(args)?;
+            let password = Zeroizing::new(B64.decode(a.password)?);
+            allocate(a.slot, &password)?;
+            let codes = native_vfs::regenerate_recovery_codes()?;
+            recovery_codes_json(&codes)
+        }
         "unlockSession" => {
             let a: UnlockArgs = parse(args)?;
             let password = Zeroizing::new(B64.decode(a.password)?);
             let ok = unlock(&password)?;
             Ok(serde_json::to_string(&ok)?)
         }
+        "unlockWithRecoveryCode" => {
+            let a: RecoveryCodeArgs = parse(args)?;
+            let code = Zeroizing::new(a.code);
+            let ok = unlock_with_recovery_code(&code)?;
+            Ok(serde_json::to_string(&ok)?)
+        }
+        "regenerateRecoveryCodes" => {
+            let codes = native_vfs::regenerate_recovery_codes()?;
+            recovery_codes_json(&codes)
+        }
         "lockSession" => {
             lock()?;
             Ok("null".into())
@@ -340,6 +363,12 @@ fn dispatch(method: &str, args: &str) -> Result<String> {
     }
 }
 
+/// Recovery codes as a JSON array of strings, for the user to write down.
+fn recovery_codes_json(codes: &[RecoveryCode]) -> Result<String> {
+    let codes: Vec<&str> = codes.iter().map(RecoveryCode::as_str).collect();
+    Ok(serde_json::to_string(&codes)?)
+}
+
 // ── Per-method bodies ───────────────────────────────────────────────
 
 fn init_secure_storage(path: &str, domain: &str) -> Result<()> {
@@ -402,6 +431,26 @@ fn unlock(password: &[u8]) -> Result<bool> {
     Ok(ok)
 }
 
+fn unlock_with_recovery_code(code: &str) -> Result<bool> {
+    // Same Drop-before-switch pattern as `allocate`: the unlocked session
+    // replaces the current one.
+    {
+        let mut guard = db_mutex()
+            .lock()
+            .map_err(|_| SecureStorageError::LockPoisoned)?;
+        *guard = None;
+    }
+    let ok = native_vfs::unlock_with_recovery_code(code)?;
+    if ok {
+        let conn = native_vfs::open_db()?;
+        let mut guard = db_mutex()
+            .lock()
+            .map_err(|_| SecureStorageError::LockPoisoned)?;
+        *guard = Some(conn);
+    }
+    Ok(ok)
+}
+
 fn lock() -> Result<()> {
     let mut guard = db_mutex()
         .lock()
@@ -106,7 +106,10 @@ pub fn read_total_length<S: BlockStorage>(
         0,
     ) {
         Ok(pt) => pt,
-        Err(SecureStorageError::CorruptedBlock) => return Ok(0),
+        Err(SecureStorageError::CorruptedBlock) => {
+            log::trace!("block 0 of namespace {namespace} is a cover block, length 0");
+            return Ok(0);
+        }
         Err(e) => return Err(e),
     };
     let length_bytes: [u8; 8] = plaintext[..LENGTH_HDR_SIZE]
@@ -177,7 +180,13 @@ pub fn read_session_data<S: BlockStorage>(
     let mut result = Zeroizing::new(Vec::with_capacity(length));
 
     for block_idx in first_block..=last_block {
-        let plaintext = decrypt_session_data_block(storage, domain, namespace, session, block_idx)?;
+        let plaintext = decrypt_session_data_block(storage, domain, namespace, session, block_idx)
+            .inspect_err(|e| {
+                log::warn!(
+                    "block {block_idx} of namespace {namespace} unreadable: {}",
+                    e.code()
+                );
+            })?;
 
         let block_start = block_idx
             .checked_mul(ps)
@@ -0,0 +1,427 @@
+//! One-time recovery codes for session slots.
+//!
+//! Each allocated slot can carry a recovery file of [`RECOVERY_CODE_COUNT`]
+//! fixed-size entries. Entry `i` wraps the slot's PQ secret key and root
+//! AEAD key under a key derived from recovery code `i`, so any single code
+//! unlocks the session without the password. A code is consumed on use: its
+//! entry is overwritten with random bytes.
+//!
+//! Binary format (per entry, concatenated):
+//! ```text
+//! [nonce: 16 bytes] [ct: AEAD(pq_sk || root_aead_key)]
+//! ```
+//!
+//! Recovery files are always the same size and unused entries are random
+//! bytes, so a slot with recovery codes is indistinguishable from a cover
+//! slot. [`provision_recovery_storage`] fills every slot when the storage is
+//! created, and [`pad_recovery_storage`] fills the slots of storage created
+//! before recovery codes existed; [`generate_recovery_codes`] pads before
+//! writing, so no slot ever holds the only recovery file.
+//!
+//! Recovery only restores access to the slot; its password stays the same.
+
+use rand::RngCore;
+use rand::seq::SliceRandom;
+use zeroize::Zeroizing;
+
+use crate::constants::{AEAD_TAG_SIZE, ROOT_BLOCK_KEY_SIZE, SESSION_COUNT};
+use crate::domain;
+use crate::error::{Result, SecureStorageError};
+use crate::keypair::read_session_keypair;
+use crate::pq::{PqPublicKey, PqSecretKey};
+use crate::storage::{KeypairStorage, RecoveryStorage};
+use crate::types::SessionIndex;
+use crate::unlock::UnlockedSession;
+
+/// Number of recovery codes (and entries) per slot.
+pub const RECOVERY_CODE_COUNT: usize = 8;
+
+/// Number of base32 characters in a recovery code (100 bits of entropy).
+const CODE_CHARS: usize = 20;
+
+/// Characters per dash-separated group in the displayed code.
+const CODE_GROUP: usize = 5;
+
+/// Crockford base32 alphabet (no I, L, O, U).
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+const ENTRY_PT_SIZE: usize = PqSecretKey::byte_size() + ROOT_BLOCK_KEY_SIZE;
+const ENTRY_SIZE: usize = crypto_aead::NONCE_SIZE + ENTRY_PT_SIZE + AEAD_TAG_SIZE;
+
+/// Byte size of a recovery file.
+pub const RECOVERY_FILE_SIZE: usize = ENTRY_SIZE * RECOVERY_CODE_COUNT;
+
+/// A one-time recovery code, formatted as `XXXXX-XXXXX-XXXXX-XXXXX`.
+pub struct RecoveryCode(Zeroizing<String>);
+
+impl RecoveryCode {
+    fn generate() -> Self {
+        let mut raw = Zeroizing::new([0u8; CODE_CHARS]);
+        rand::rngs::OsRng.fill_bytes(raw.as_mut());
+        let mut s = String::with_capacity(CODE_CHARS + CODE_CHARS / CODE_GROUP);
+        for (i, b) in raw.iter().enumerate() {
+            if i > 0 && i % CODE_GROUP == 0 {
+                s.push('-');
+            }
+            // 256 is a multiple of 32, so masking keeps the distribution uniform.
+            s.push(ALPHABET[(b & 0x1f) as usize] as char);
+        }
+        Self(Zeroizing::new(s))
+    }
+
+    /// The code as shown to the user.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Canonicalize user input: drop separators, uppercase, and map the
+/// Crockford look-alikes (`O` → `0`, `I`/`L` → `1`).
+fn normalize_code(code: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        code.chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            })
+            .collect(),
+    )
+}
+
+/// Derive the wrap key for a recovery code.
+///
+/// Codes carry 100 bits of entropy, so a plain HKDF is enough; there is no
+/// need for the memory-hard password KDF.
+fn derive_recovery_wrap_key(domain: &str, code: &str) -> Zeroizing<[u8; crypto_aead::KEY_SIZE]> {
+    let normalized = normalize_code(code);
+    let salt = domain::recovery_kdf_salt(domain);
+    let expander = {
+        let mut extract = crypto_kdf::Extract::new(salt.as_bytes());
+        extract.input_item(normalized.as_bytes());
+        extract.finalize()
+    };
+    let label = domain::recovery_wrap_key_label(domain);
+    let mut key = Zeroizing::new([0u8; crypto_aead::KEY_SIZE]);
+    expander.expand(label.as_bytes(), key.as_mut());
+    key
+}
+
+/// AAD for entry `code_index`, bound to the slot's current public key so
+/// re-allocating the slot invalidates every outstanding code.
+fn entry_aad(
+    domain: &str,
+    version: u32,
+    slot: SessionIndex,
+    code_index: usize,
+    pq_pk: &[u8],
+) -> Vec<u8> {
+    let mut aad = domain::recovery_aad(domain, version, slot, code_index).into_bytes();
+    aad.extend_from_slice(pq_pk);
+    aad
+}
+
+fn random_file() -> Vec<u8> {
+    let mut file = vec![0u8; RECOVERY_FILE_SIZE];
+    rand::rngs::OsRng.fill_bytes(&mut file);
+    file
+}
+
+/// Fill every slot with a random recovery file.
+///
+/// Call right after [`crate::provision_storage`] so slots with recovery
+/// codes cannot be told apart from slots without.
+pub fn provision_recovery_storage<S: RecoveryStorage>(storage: &mut S) -> Result<()> {
+    for i in 0..SESSION_COUNT as u8 {
+        let slot = SessionIndex::new(i)?;
+        storage.write_recovery(slot, &random_file())?;
+    }
+    Ok(())
+}
+
+/// Give a random recovery file to every slot without a well-formed one,
+/// e.g. in storage provisioned before recovery codes existed. Slots that
+/// have one are left untouched. Returns whether any slot was padded.
+pub fn pad_recovery_storage<S: RecoveryStorage>(storage: &mut S) -> Result<bool> {
+    let mut padded = false;
+    for i in 0..SESSION_COUNT as u8 {
+        let slot = SessionIndex::new(i)?;
+        match storage.read_recovery(slot) {
+            Ok(file) if file.len() == RECOVERY_FILE_SIZE => continue,
+            // backends report a missing file as a storage error
+            Ok(_) | Err(SecureStorageError::Storage(_)) => {}
+            Err(e) => return Err(e),
+        }
+        storage.write_recovery(slot, &random_file())?;
+        padded = true;
+    }
+    Ok(padded)
+}
+
+/// Generate a fresh set of recovery codes for an unlocked session.
+///
+/// Replaces the slot's recovery file, invalidating any codes issued before,
+/// after padding the other slots (see [`pad_recovery_storage`]). The
+/// returned codes are never stored in plaintext; the caller must show them
+/// to the user once.
+pub fn generate_recovery_codes<S: KeypairStorage + RecoveryStorage>(
+    storage: &mut S,
+    domain: &str,
+    session: &UnlockedSession,
+) -> Result<Vec<RecoveryCode>> {
+    pad_recovery_storage(storage)?;
+
+    let slot = session.session_index;
+    let pq_pk = session.pq_rerand_pk.to_bytes();
+
+    let mut pt = Zeroizing::new(Vec::with_capacity(ENTRY_PT_SIZE));
+    pt.extend_from_slice(&session.pq_rerand_sk.to_bytes());
+    pt.extend_from_slice(session.root_aead_key.as_ref());
+
+    let mut file = Vec::with_capacity(RECOVERY_FILE_SIZE);
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for code_index in 0..RECOVERY_CODE_COUNT {
+        let code = RecoveryCode::generate();
+        let wrap_key = crypto_aead::Key::from_ref(&derive_recovery_wrap_key(domain, code.as_str()));
+        let mut nonce = [0u8; crypto_aead::NONCE_SIZE];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+        let aad = entry_aad(domain, session.session_version, slot, code_index, &pq_pk);
+        let ct = crypto_aead::encrypt(&wrap_key, &crypto_aead::Nonce::from(nonce), &pt, &aad);
+        file.extend_from_slice(&nonce);
+        file.extend_from_slice(&ct);
+        codes.push(code);
+    }
+    debug_assert_eq!(file.len(), RECOVERY_FILE_SIZE);
+
+    storage.write_recovery(slot, &file)?;
+    Ok(codes)
+}
+
+/// Unlock a session with a recovery code, consuming the code.
+///
+/// Like [`crate::unlock_session`], every entry of every slot is tried
+/// regardless of whether a match was already found. On success the matching
+/// entry is overwritten with random bytes so the code cannot be used again.
+pub fn unlock_with_recovery_code<S: KeypairStorage + RecoveryStorage>(
+    storage: &mut S,
+    domain: &str,
+    code: &str,
+) -> Result<UnlockedSession> {
+    let wrap_key = crypto_aead::Key::from_ref(&derive_recovery_wrap_key(domain, code));
+
+    let mut indices: Vec<u8> = (0..SESSION_COUNT as u8).collect();
+    indices.shuffle(&mut rand::rngs::OsRng);
+
+    let mut result: Option<(UnlockedSession, usize)> = None;
+
+    for i in indices {
+        let Ok(slot) = SessionIndex::new(i) else {
+            continue;
+        };
+        let Ok(kf) = read_session_keypair(storage, slot) else {
+            continue;
+        };
+        let Ok(file) = storage.read_recovery(slot) else {
+            continue;
+        };
+        if file.len() != RECOVERY_FILE_SIZE {
+            continue;
+        }
+
+        for (code_index, entry) in file.chunks_exact(ENTRY_SIZE).enumerate() {
+            let (nonce, ct) = entry.split_at(crypto_aead::NONCE_SIZE);
+            let nonce: [u8; crypto_aead::NONCE_SIZE] = nonce.try_into().unwrap();
+            let aad = entry_aad(domain, kf.version, slot, code_index, &kf.pq_pk);
+            let Some(pt) =
+                crypto_aead::decrypt(&wrap_key, &crypto_aead::Nonce::from(nonce), ct, &aad)
+                    .map(Zeroizing::new)
+            else {
+                continue;
+            };
+            if result.is_some() || pt.len() != ENTRY_PT_SIZE {
+                continue;
+            }
+            let (sk_bytes, root_key) = pt.split_at(PqSecretKey::byte_size());
+            let (Ok(pq_rerand_sk), Ok(pq_rerand_pk)) = (
+                PqSecretKey::from_bytes(sk_bytes),
+                PqPublicKey::from_bytes(&kf.pq_pk),
+            ) else {
+                continue;
+            };
+            let mut root_aead_key = Zeroizing::new([0u8; ROOT_BLOCK_KEY_SIZE]);
+            root_aead_key.copy_from_slice(root_key);
+            result = Some((
+                UnlockedSession {
+                    session_index: slot,
+                    session_version: kf.version,
+                    pq_rerand_pk,
+                    pq_rerand_sk,
+                    root_aead_key,
+                },
+                code_index,
+            ));
+        }
+    }
+
+    let (session, code_index) = result.ok_or(SecureStorageError::InvalidPassword)?;
+
+    let mut file = Zeroizing::new(storage.read_recovery(session.session_index)?.to_vec());
+    let start = code_index * ENTRY_SIZE;
+    rand::rngs::OsRng.fill_bytes(&mut file[start..start + ENTRY_SIZE]);
+    storage.write_recovery(session.session_index, &file)?;
+
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_NAMESPACE;
+    use crate::lifecycle::{allocate_session, provision_storage};
+    use crate::read::read_session_data;
+    use crate::run_with_stack;
+    use crate::storage::MemoryStorage;
+    use crate::unlock::{NamespaceState, load_namespace_state};
+    use crate::write::write_session_data;
+
+    const DOMAIN: &str = "test";
+    const NS: u8 = DEFAULT_NAMESPACE;
+
+    fn setup() -> (MemoryStorage, Vec<RecoveryCode>) {
+        let mut storage = MemoryStorage::new();
+        provision_storage(&mut storage).unwrap();
+        provision_recovery_storage(&mut storage).unwrap();
+        let slot = SessionIndex::new(1).unwrap();
+        let session = allocate_session(&mut storage, DOMAIN, slot, b"pw").unwrap();
+        let mut ns = NamespaceState::empty();
+        write_session_data(&mut storage, DOMAIN, NS, &session, &mut ns, 0, b"secret").unwrap();
+        let codes = generate_recovery_codes(&mut storage, DOMAIN, &session).unwrap();
+        (storage, codes)
+    }
+
+    #[test]
+    fn code_format() {
+        let code = RecoveryCode::generate();
+        let s = code.as_str();
+        assert_eq!(s.len(), 23);
+        assert_eq!(s.matches('-').count(), 3);
+        assert!(s.bytes().all(|b| b == b'-' || ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn normalize_accepts_sloppy_input() {
+        assert_eq!(*normalize_code("ab0 o1-il"), "AB00111");
+    }
+
+    #[test]
+    fn each_code_unlocks_and_reads_data() {
+        run_with_stack(|| {
+            let (mut storage, codes) = setup();
+            assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+            for code in &codes {
+                let session =
+                    unlock_with_recovery_code(&mut storage, DOMAIN, code.as_str()).unwrap();
+                assert_eq!(session.session_index.as_u8(), 1);
+                let ns = load_namespace_state(&storage, DOMAIN, &session, NS).unwrap();
+                let data = read_session_data(&storage, DOMAIN, NS, &session, &ns, 0, 6).unwrap();
+                assert_eq!(&*data, b"secret");
+            }
+        });
+    }
+
+    #[test]
+    fn code_is_single_use() {
+        run_with_stack(|| {
+            let (mut storage, codes) = setup();
+            let code = codes[3].as_str().to_lowercase();
+            unlock_with_recovery_code(&mut storage, DOMAIN, &code).unwrap();
+            assert!(matches!(
+                unlock_with_recovery_code(&mut storage, DOMAIN, &code),
+                Err(SecureStorageError::InvalidPassword)
+            ));
+            // Other codes still work.
+            unlock_with_recovery_code(&mut storage, DOMAIN, codes[4].as_str()).unwrap();
+        });
+    }
+
+    #[test]
+    fn wrong_code_rejected() {
+        run_with_stack(|| {
+            let (mut storage, _codes) = setup();
+            let bogus = RecoveryCode::generate();
+            assert!(matches!(
+                unlock_with_recovery_code(&mut storage, DOMAIN, bogus.as_str()),
+                Err(SecureStorageError::InvalidPassword)
+            ));
+        });
+    }
+
+    #[test]
+    fn reallocation_invalidates_codes() {
+        run_with_stack(|| {
+            let (mut storage, codes) = setup();
+            let slot = SessionIndex::new(1).unwrap();
+            allocate_session(&mut storage, DOMAIN, slot, b"new").unwrap();
+            assert!(unlock_with_recovery_code(&mut storage, DOMAIN, codes[0].as_str()).is_err());
+        });
+    }
+
+    #[test]
+    fn regenerating_invalidates_previous_codes() {
+        run_with_stack(|| {
+            let (mut storage, old) = setup();
+            let session = crate::unlock::unlock_session(&storage, DOMAIN, b"pw").unwrap();
+            let new = generate_recovery_codes(&mut storage, DOMAIN, &session).unwrap();
+            assert!(unlock_with_recovery_code(&mut storage, DOMAIN, old[0].as_str()).is_err());
+            unlock_with_recovery_code(&mut storage, DOMAIN, new[0].as_str()).unwrap();
+        });
+    }
+
+    #[test]
+    fn generating_pads_storage_without_recovery_files() {
+        run_with_stack(|| {
+            // provisioned before recovery codes existed
+            let mut storage = MemoryStorage::new();
+            provision_storage(&mut storage).unwrap();
+            let slot = SessionIndex::new(2).unwrap();
+            let session = allocate_session(&mut storage, DOMAIN, slot, b"pw").unwrap();
+            let codes = generate_recovery_codes(&mut storage, DOMAIN, &session).unwrap();
+            for i in 0..SESSION_COUNT as u8 {
+                let slot = SessionIndex::new(i).unwrap();
+                assert_eq!(
+                    storage.read_recovery(slot).unwrap().len(),
+                    RECOVERY_FILE_SIZE
+                );
+            }
+            unlock_with_recovery_code(&mut storage, DOMAIN, codes[0].as_str()).unwrap();
+        });
+    }
+
+    #[test]
+    fn padding_keeps_existing_recovery_files() {
+        run_with_stack(|| {
+            let (mut storage, codes) = setup();
+            let slot = SessionIndex::new(1).unwrap();
+            let before = storage.read_recovery(slot).unwrap();
+            assert!(!pad_recovery_storage(&mut storage).unwrap());
+            assert_eq!(*storage.read_recovery(slot).unwrap(), *before);
+            unlock_with_recovery_code(&mut storage, DOMAIN, codes[0].as_str()).unwrap();
+        });
+    }
+
+    #[test]
+    fn recovery_files_same_size_for_all_slots() {
+        run_with_stack(|| {
+            let (storage, _codes) = setup();
+            for i in 0..SESSION_COUNT as u8 {
+                let slot = SessionIndex::new(i).unwrap();
+                assert_eq!(
+                    storage.read_recovery(slot).unwrap().len(),
+                    RECOVERY_FILE_SIZE
+                );
+            }
+        });
+    }
+}
@@ -83,8 +83,22 @@ pub trait KeypairStorage {
     fn write_keypair(&mut self, session: SessionIndex, data: &[u8]) -> Result<()>;
 }
 
+/// Recovery file storage, one fixed-size file per session slot.
+///
+/// Holds the one-time recovery entries written by
+/// [`crate::generate_recovery_codes`]. Like keypair files, recovery files
+/// are session-level and independent of namespaces.
+pub trait RecoveryStorage {
+    /// Read the raw recovery file bytes for a session.
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>>;
+
+    /// Write raw recovery file bytes for a session.
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()>;
+}
+
 pub struct MemoryStorage {
     keypairs: Vec<Zeroizing<Vec<u8>>>,
+    recovery: Vec<Zeroizing<Vec<u8>>>,
     /// `blockstreams[session][namespace] = Vec<block>`. Lazy populated:
     /// only `(session, namespace)` pairs that have been touched get an entry.
     blockstreams: Vec<HashMap<u8, Vec<Box<[u8; BLOCK_SIZE]>>>>,
@@ -103,6 +117,9 @@ impl MemoryStorage {
             keypairs: (0..SESSION_COUNT)
                 .map(|_| Zeroizing::new(Vec::new()))
                 .collect(),
+            recovery: (0..SESSION_COUNT)
+                .map(|_| Zeroizing::new(Vec::new()))
+                .collect(),
             blockstreams: (0..SESSION_COUNT).map(|_| HashMap::new()).collect(),
         }
     }
@@ -199,6 +216,23 @@ impl KeypairStorage for MemoryStorage {
     }
 }
 
+impl RecoveryStorage for MemoryStorage {
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        let data = &self.recovery[session.as_usize()];
+        if data.is_empty() {
+            return Err(SecureStorageError::Storage(
+                "recovery file not found".into(),
+            ));
+        }
+        Ok(Zeroizing::new((**data).clone()))
+    }
+
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        self.recovery[session.as_usize()] = Zeroizing::new(data.to_vec());
+        Ok(())
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 mod fs_backend {
     use super::*;
@@ -231,6 +265,12 @@ mod fs_backend {
                 .join("sessions")
                 .join(format!("session_{}.keypair", session.as_u8()))
         }
+
+        fn recovery_path(&self, session: SessionIndex) -> PathBuf {
+            self.base
+                .join("sessions")
+                .join(format!("session_{}.recovery", session.as_u8()))
+        }
     }
 
     impl BlockStorage for FsStorage {
@@ -378,6 +418,24 @@ mod fs_backend {
             Ok(())
         }
     }
+
+    impl RecoveryStorage for FsStorage {
+        fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+            let data = fs::read(self.recovery_path(session)).map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    SecureStorageError::Storage("recovery file not found".into())
+                } else {
+                    SecureStorageError::Io(e)
+                }
+            })?;
+            Ok(Zeroizing::new(data))
+        }
+
+        fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+            fs::write(self.recovery_path(session), data)?;
+            Ok(())
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
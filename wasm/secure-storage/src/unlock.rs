@@ -5,7 +5,7 @@ use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::domain;
 use crate::error::{Result, SecureStorageError};
-use crate::kdf::derive_session_keys;
+use crate::kdf::{SessionKeys, derive_session_keys};
 use crate::keypair::read_session_keypair;
 use crate::pq::{PqPublicKey, PqSecretKey};
 use crate::read::read_total_length;
@@ -84,7 +84,16 @@ pub fn unlock_session<S: BlockStorage + KeypairStorage>(
     password: &[u8],
 ) -> Result<UnlockedSession> {
     let keys = derive_session_keys(domain, password);
+    unlock_session_with_keys(storage, domain, &keys)
+}
 
+/// [`unlock_session`] once the password KDF has run, for callers that must
+/// do other work between the two steps.
+pub(crate) fn unlock_session_with_keys<S: BlockStorage + KeypairStorage>(
+    storage: &S,
+    domain: &str,
+    keys: &SessionKeys,
+) -> Result<UnlockedSession> {
     let mut indices: Vec<u8> = (0..crate::SESSION_COUNT as u8).collect();
     indices.shuffle(&mut rand::rngs::OsRng);
 
@@ -97,8 +106,12 @@ pub fn unlock_session<S: BlockStorage + KeypairStorage>(
             continue;
         };
 
-        let Ok(kf) = read_session_keypair(storage, session) else {
-            continue;
+        let kf = match read_session_keypair(storage, session) {
+            Ok(kf) => kf,
+            Err(e) => {
+                log::warn!("keypair of slot {i} unreadable: {}", e.code());
+                continue;
+            }
         };
 
         let sk_wrap_aad = domain::sk_wrap_aad(domain, kf.version, session);
@@ -114,19 +127,23 @@ pub fn unlock_session<S: BlockStorage + KeypairStorage>(
             .and_then(|bytes| PqSecretKey::from_bytes(bytes).ok());
         let pk_parse = PqPublicKey::from_bytes(&kf.pq_pk).ok();
 
-        if result.is_none() {
-            if let (Some(pq_rerand_sk), Some(pq_rerand_pk)) = (sk_parse, pk_parse) {
-                result = Some(UnlockedSession {
-                    session_index: session,
-                    session_version: kf.version,
-                    pq_rerand_pk,
-                    pq_rerand_sk,
-                    root_aead_key: keys.root_aead_key.clone(),
-                });
-            }
+        if result.is_none()
+            && let (Some(pq_rerand_sk), Some(pq_rerand_pk)) = (sk_parse, pk_parse)
+        {
+            result = Some(UnlockedSession {
+                session_index: session,
+                session_version: kf.version,
+                pq_rerand_pk,
+                pq_rerand_sk,
+                root_aead_key: keys.root_aead_key.clone(),
+            });
         }
     }
 
+    // Never log which slot matched: it would tie the password to a slot.
+    if result.is_none() {
+        log::info!("password matched no session");
+    }
     result.ok_or(SecureStorageError::InvalidPassword)
 }
 
@@ -0,0 +1,84 @@
+//! IndexedDB persistence of leases (see `crate::lease`).
+//!
+//! Leases live in their own database rather than next to the blocks: they
+//! are plaintext coordination state, must not count as provisioned data in
+//! `idbHasData`, and change far more often. IndexedDB runs readwrite
+//! transactions on the same store one at a time across all tabs of an
+//! origin, so each read-decide-write below is atomic.
+
+use indexed_db_futures::prelude::*;
+use js_sys::Uint8Array;
+use wasm_bindgen::prelude::*;
+
+use crate::error::Result;
+use crate::lease::LeaseRecord;
+
+const LEASE_DB_NAME: &str = "secure_storage_leases";
+const LEASE_STORE_NAME: &str = "leases";
+const LEASE_DB_VERSION: u32 = 1;
+
+/// What to do with a lease record.
+pub enum LeaseWrite {
+    Put(LeaseRecord),
+    Delete,
+    Keep,
+}
+
+/// Open (or create) the lease database.
+async fn open_lease_db() -> std::result::Result<IdbDatabase, JsValue> {
+    let mut req = IdbDatabase::open_u32(LEASE_DB_NAME, LEASE_DB_VERSION)?;
+    req.set_on_upgrade_needed(Some(
+        |evt: &IdbVersionChangeEvent| -> std::result::Result<(), JsValue> {
+            let db = evt.db();
+            if !db.object_store_names().any(|n| n == LEASE_STORE_NAME) {
+                db.create_object_store(LEASE_STORE_NAME)?;
+            }
+            Ok(())
+        },
+    ));
+    Ok(req.into_future().await?)
+}
+
+/// Read the lease `name`, let `decide` pick the write, and apply it, all in
+/// one readwrite transaction.
+///
+/// The outer error is an IndexedDB failure; the inner one is `decide`'s
+/// refusal, in which case nothing is written. A stored record that fails to
+/// parse counts as no lease, so a corrupted entry cannot block every client.
+pub async fn update_lease<F>(name: &str, decide: F) -> std::result::Result<Result<()>, JsValue>
+where
+    F: FnOnce(Option<&LeaseRecord>) -> Result<LeaseWrite>,
+{
+    let db = open_lease_db().await?;
+    let tx = db.transaction_on_one_with_mode(LEASE_STORE_NAME, IdbTransactionMode::Readwrite)?;
+    let store = tx.object_store(LEASE_STORE_NAME)?;
+    let key = JsValue::from_str(name);
+
+    // The continuation of this await runs in the success callback's
+    // microtask, while the transaction is still active.
+    let current = store.get(&key)?.await?.and_then(|value| {
+        let value = Uint8Array::new(&value);
+        let mut bytes = vec![0u8; value.length() as usize];
+        value.copy_to(&mut bytes);
+        LeaseRecord::from_bytes(&bytes).ok()
+    });
+
+    let write = match decide(current.as_ref()) {
+        Ok(write) => write,
+        Err(e) => {
+            tx.await.into_result()?;
+            return Ok(Err(e));
+        }
+    };
+    match write {
+        LeaseWrite::Put(record) => {
+            store.put_key_val(&key, &Uint8Array::from(record.to_bytes().as_slice()))?;
+        }
+        LeaseWrite::Delete => {
+            store.delete(&key)?;
+        }
+        LeaseWrite::Keep => {}
+    }
+    tx.await.into_result()?;
+    Ok(Ok(()))
+}
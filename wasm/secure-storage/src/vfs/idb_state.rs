@@ -24,6 +24,7 @@ use crate::error::{Result, SecureStorageError};
 ///
 ///   * `Block { session: 0, namespace: 1, idx: 42 }` ⟶ `"s:0:n:1:b:42"`
 ///   * `Keypair { session: 0 }`                       ⟶ `"s:0:kp"`
+///   * `Recovery { session: 0 }`                      ⟶ `"s:0:rc"`
 ///
 /// Always go through [`IdbKey::encode`] / [`IdbKey::parse`]; never
 /// build IDB key strings ad-hoc anywhere else in the codebase.
@@ -37,6 +38,9 @@ pub enum IdbKey {
     Keypair {
         session: u8,
     },
+    Recovery {
+        session: u8,
+    },
 }
 
 impl IdbKey {
@@ -49,6 +53,7 @@ impl IdbKey {
                 idx,
             } => format!("s:{session}:n:{namespace}:b:{idx}"),
             IdbKey::Keypair { session } => format!("s:{session}:kp"),
+            IdbKey::Recovery { session } => format!("s:{session}:rc"),
         }
     }
 
@@ -63,6 +68,9 @@ impl IdbKey {
         if rest == "kp" {
             return Some(IdbKey::Keypair { session });
         }
+        if rest == "rc" {
+            return Some(IdbKey::Recovery { session });
+        }
         let rest = rest.strip_prefix("n:")?;
         let (ns_str, rest) = rest.split_once(':')?;
         let namespace: u8 = ns_str.parse().ok()?;
@@ -109,6 +117,8 @@ pub struct IdbStorageState {
     blocks: [HashMap<u8, Vec<Option<Box<[u8; BLOCK_SIZE]>>>>; SESSION_COUNT],
     /// `keypairs[session] = Some(serialized_keypair)` if allocated.
     keypairs: [Option<Zeroizing<Vec<u8>>>; SESSION_COUNT],
+    /// `recovery[session] = Some(recovery_file)` if provisioned.
+    recovery: [Option<Zeroizing<Vec<u8>>>; SESSION_COUNT],
     /// `(session, namespace, block)` keys whose value must be PUT to IDB
     /// on next flush.
     dirty_blocks: HashSet<(u8, u8, u64)>,
@@ -117,6 +127,8 @@ pub struct IdbStorageState {
     deleted_blocks: HashSet<(u8, u8, u64)>,
     /// Sessions whose keypair must be PUT to IDB on next flush.
     dirty_keypairs: HashSet<u8>,
+    /// Sessions whose recovery file must be PUT to IDB on next flush.
+    dirty_recovery: HashSet<u8>,
 }
 
 impl Default for IdbStorageState {
@@ -131,9 +143,11 @@ impl IdbStorageState {
         Self {
             blocks: std::array::from_fn(|_| HashMap::new()),
             keypairs: std::array::from_fn(|_| None),
+            recovery: std::array::from_fn(|_| None),
             dirty_blocks: HashSet::new(),
             deleted_blocks: HashSet::new(),
             dirty_keypairs: HashSet::new(),
+            dirty_recovery: HashSet::new(),
         }
     }
 
@@ -180,6 +194,9 @@ impl IdbStorageState {
                 Some(IdbKey::Keypair { session }) => {
                     state.keypairs[session as usize] = Some(Zeroizing::new(val.to_vec()));
                 }
+                Some(IdbKey::Recovery { session }) => {
+                    state.recovery[session as usize] = Some(Zeroizing::new(val.to_vec()));
+                }
                 _ => {
                     // Unmatched entry: see the doc above for the categories.
                     skipped += 1;
@@ -195,6 +212,7 @@ impl IdbStorageState {
         self.dirty_blocks.is_empty()
             && self.deleted_blocks.is_empty()
             && self.dirty_keypairs.is_empty()
+            && self.dirty_recovery.is_empty()
     }
 
     // ── Block ops ──────────────────────────────────────────────
@@ -312,6 +330,35 @@ impl IdbStorageState {
         Ok(())
     }
 
+    // ── Recovery ops ───────────────────────────────────────────
+
+    pub fn read_recovery(&self, session: u8) -> Result<Zeroizing<Vec<u8>>> {
+        let slot = self.recovery.get(session as usize).ok_or_else(|| {
+            SecureStorageError::Storage(format!(
+                "invalid session: {session} (max {})",
+                SESSION_COUNT - 1
+            ))
+        })?;
+        match slot {
+            Some(data) => Ok(data.clone()),
+            None => Err(SecureStorageError::Storage(format!(
+                "recovery file not found: session={session}"
+            ))),
+        }
+    }
+
+    pub fn write_recovery(&mut self, session: u8, data: &[u8]) -> Result<()> {
+        let slot = self.recovery.get_mut(session as usize).ok_or_else(|| {
+            SecureStorageError::Storage(format!(
+                "invalid session: {session} (max {})",
+                SESSION_COUNT - 1
+            ))
+        })?;
+        *slot = Some(Zeroizing::new(data.to_vec()));
+        self.dirty_recovery.insert(session);
+        Ok(())
+    }
+
     // ── Drain / restore for async persistence ──────────────────
 
     /// Atomically drain all pending puts/deletes into a [`DirtySnapshot`].
@@ -333,6 +380,7 @@ impl IdbStorageState {
         let dirty_blocks = std::mem::take(&mut self.dirty_blocks);
         let deleted_blocks = std::mem::take(&mut self.deleted_blocks);
         let dirty_keypairs = std::mem::take(&mut self.dirty_keypairs);
+        let dirty_recovery = std::mem::take(&mut self.dirty_recovery);
 
         // Build the snapshot by reading current cache values for each
         // drained dirty key. Filter out any entries whose cache slot
@@ -357,10 +405,20 @@ impl IdbStorageState {
             })
             .collect();
 
+        let recovery_puts: Vec<(u8, Zeroizing<Vec<u8>>)> = dirty_recovery
+            .iter()
+            .filter_map(|&s| {
+                self.recovery[s as usize]
+                    .as_ref()
+                    .map(|d| (s, Zeroizing::new(d.to_vec())))
+            })
+            .collect();
+
         DirtySnapshot {
             block_puts,
             block_deletes: deleted_blocks.into_iter().collect(),
             keypair_puts,
+            recovery_puts,
         }
     }
 
@@ -398,6 +456,11 @@ impl IdbStorageState {
                 self.dirty_keypairs.insert(s);
             }
         }
+        for (s, _data) in snap.recovery_puts {
+            if self.recovery[s as usize].is_some() {
+                self.dirty_recovery.insert(s);
+            }
+        }
     }
 }
 
@@ -406,11 +469,15 @@ pub struct DirtySnapshot {
     pub block_puts: Vec<((u8, u8, u64), Box<[u8; BLOCK_SIZE]>)>,
     pub block_deletes: Vec<(u8, u8, u64)>,
     pub keypair_puts: Vec<(u8, Zeroizing<Vec<u8>>)>,
+    pub recovery_puts: Vec<(u8, Zeroizing<Vec<u8>>)>,
 }
 
 impl DirtySnapshot {
     pub fn is_empty(&self) -> bool {
-        self.block_puts.is_empty() && self.block_deletes.is_empty() && self.keypair_puts.is_empty()
+        self.block_puts.is_empty()
+            && self.block_deletes.is_empty()
+            && self.keypair_puts.is_empty()
+            && self.recovery_puts.is_empty()
     }
 }
 
@@ -457,6 +524,11 @@ mod tests {
         assert_eq!(IdbKey::Keypair { session: 2 }.encode(), "s:2:kp");
     }
 
+    #[test]
+    fn key_encode_recovery() {
+        assert_eq!(IdbKey::Recovery { session: 1 }.encode(), "s:1:rc");
+    }
+
     // ── IdbKey: parse ──
 
     #[test]
@@ -487,6 +559,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn key_parse_recovery() {
+        assert_eq!(
+            IdbKey::parse("s:2:rc"),
+            Some(IdbKey::Recovery { session: 2 })
+        );
+    }
+
     #[test]
     fn key_parse_rejects_malformed() {
         assert_eq!(IdbKey::parse(""), None);
@@ -528,6 +608,7 @@ mod tests {
             IdbKey::Keypair {
                 session: (SESSION_COUNT - 1) as u8,
             },
+            IdbKey::Recovery { session: 0 },
         ] {
             assert_eq!(IdbKey::parse(&k.encode()), Some(k));
         }
@@ -633,6 +714,32 @@ mod tests {
         assert!(s.read_keypair(0).is_err());
     }
 
+    #[test]
+    fn write_recovery_marks_dirty() {
+        let mut s = IdbStorageState::new();
+        s.write_recovery(1, b"codes").unwrap();
+        assert_eq!(&*s.read_recovery(1).unwrap(), b"codes");
+        let snap = s.drain_pending();
+        assert_eq!(snap.recovery_puts.len(), 1);
+        assert!(s.is_clean());
+    }
+
+    #[test]
+    fn read_missing_recovery_errors() {
+        let s = IdbStorageState::new();
+        assert!(s.read_recovery(0).is_err());
+        assert!(s.read_recovery(SESSION_COUNT as u8).is_err());
+    }
+
+    #[test]
+    fn restore_pending_recovery_after_idb_failure() {
+        let mut s = IdbStorageState::new();
+        s.write_recovery(0, b"codes").unwrap();
+        let snap = s.drain_pending();
+        s.restore_pending(snap);
+        assert_eq!(s.drain_pending().recovery_puts.len(), 1);
+    }
+
     // ── Namespace independence ──
 
     #[test]
@@ -944,6 +1051,16 @@ mod tests {
         assert_eq!(&*s.read_keypair(0).unwrap(), &[1, 2, 3, 4]);
     }
 
+    #[test]
+    fn from_entries_loads_recovery() {
+        let file = [5u8; 16];
+        let entries: Vec<(&str, &[u8])> = vec![("s:2:rc", &file[..])];
+        let (s, skipped) = IdbStorageState::from_entries(entries);
+        assert_eq!(skipped, 0);
+        assert_eq!(&*s.read_recovery(2).unwrap(), &file[..]);
+        assert!(s.is_clean());
+    }
+
     #[test]
     fn from_entries_skips_malformed() {
         let data = block(0);
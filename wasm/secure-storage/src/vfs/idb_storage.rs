@@ -6,8 +6,8 @@
 //!   * loads IDB contents at construction time (see [`Self::open`]),
 //!   * bridges sync VFS callbacks to async IDB I/O via the
 //!     drain/restore pattern (see [`Self::persist_dirty`]),
-//!   * implements the [`BlockStorage`] / [`KeypairStorage`] traits by
-//!     delegating to the state.
+//!   * implements the [`BlockStorage`] / [`KeypairStorage`] /
+//!     [`RecoveryStorage`] traits by delegating to the state.
 
 use std::cell::RefCell;
 
@@ -20,7 +20,7 @@ use zeroize::Zeroizing;
 
 use crate::constants::BLOCK_SIZE;
 use crate::error::Result;
-use crate::storage::{BlockStorage, KeypairStorage};
+use crate::storage::{BlockStorage, KeypairStorage, RecoveryStorage};
 use crate::types::SessionIndex;
 use crate::vfs::idb_state::{IdbKey, IdbStorageState};
 
@@ -183,12 +183,14 @@ impl IdbBlockStorage {
         // We do this outside the borrow so any writes that arrive
         // during the await below don't deadlock on the RefCell.
         //
-        // Capacity = block_puts.len() + keypair_puts.len() because the two
-        // sets are disjoint (different IdbKey variants encode to distinct
-        // string prefixes) — every entry in either contributes exactly one
-        // put, so the total is the sum.
-        let mut puts: Vec<(String, Uint8Array)> =
-            Vec::with_capacity(snapshot.block_puts.len() + snapshot.keypair_puts.len());
+        // Capacity = block_puts.len() + keypair_puts.len() +
+        // recovery_puts.len() because the sets are disjoint (different
+        // IdbKey variants encode to distinct string prefixes) — every entry
+        // in any of them contributes exactly one put, so the total is the
+        // sum.
+        let mut puts: Vec<(String, Uint8Array)> = Vec::with_capacity(
+            snapshot.block_puts.len() + snapshot.keypair_puts.len() + snapshot.recovery_puts.len(),
+        );
         let mut deletes: Vec<String> = Vec::with_capacity(snapshot.block_deletes.len());
         for ((session, namespace, idx), data) in &snapshot.block_puts {
             let key = IdbKey::Block {
@@ -203,6 +205,10 @@ impl IdbBlockStorage {
             let key = IdbKey::Keypair { session: *session }.encode();
             puts.push((key, Uint8Array::from(data.as_slice())));
         }
+        for (session, data) in &snapshot.recovery_puts {
+            let key = IdbKey::Recovery { session: *session }.encode();
+            puts.push((key, Uint8Array::from(data.as_slice())));
+        }
         for (session, namespace, idx) in &snapshot.block_deletes {
             let key = IdbKey::Block {
                 session: *session,
@@ -296,3 +302,15 @@ impl KeypairStorage for IdbBlockStorage {
         self.state.get_mut().write_keypair(session.as_u8(), data)
     }
 }
+
+// ── RecoveryStorage ─────────────────────────────────────────────────
+
+impl RecoveryStorage for IdbBlockStorage {
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        self.state.borrow().read_recovery(session.as_u8())
+    }
+
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        self.state.get_mut().write_recovery(session.as_u8(), data)
+    }
+}
@@ -8,6 +8,15 @@ pub(crate) mod idb_state;
 #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 pub mod idb_storage;
 
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub mod idb_lease;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub mod opfs_storage;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm-node"))]
+pub mod node_storage;
+
 #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 pub mod sqlite_vfs;
 
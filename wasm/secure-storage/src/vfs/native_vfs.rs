@@ -33,6 +33,7 @@ use rusqlite::ffi::{
 
 use crate::DEFAULT_NAMESPACE;
 use crate::error::{Result, SecureStorageError};
+use crate::recovery::RecoveryCode;
 use crate::types::SessionIndex;
 use crate::unlock::{NamespaceState, UnlockedSession, load_namespace_state};
 
@@ -224,14 +225,16 @@ pub fn has_data() -> Result<bool> {
     st.backend.has_data()
 }
 
-/// Provision all session slots.
+/// Provision all session slots, with a random recovery file each.
 pub fn provision() -> Result<()> {
     let mutex = state_mutex();
     let mut guard = mutex.lock().map_err(|_| SecureStorageError::LockPoisoned)?;
     let st = guard
         .as_mut()
         .ok_or_else(|| SecureStorageError::NotInitialized)?;
-    if let Err(e) = crate::provision_storage(&mut st.backend) {
+    if let Err(e) = crate::provision_storage(&mut st.backend)
+        .and_then(|()| crate::provision_recovery_storage(&mut st.backend))
+    {
         st.backend.discard_pending();
         return Err(e);
     }
@@ -295,6 +298,51 @@ pub fn unlock(password: &[u8]) -> Result<bool> {
     }
 }
 
+/// Unlock a session with one of its recovery codes, consuming the code.
+///
+/// The consumed code is committed before returning, so it cannot be used
+/// again even if the process dies right after.
+pub fn unlock_with_recovery_code(code: &str) -> Result<bool> {
+    let mutex = state_mutex();
+    let mut guard = mutex.lock().map_err(|_| SecureStorageError::LockPoisoned)?;
+    let st = guard
+        .as_mut()
+        .ok_or_else(|| SecureStorageError::NotInitialized)?;
+    let session = match crate::unlock_with_recovery_code(&mut st.backend, &st.domain, code) {
+        Ok(session) => session,
+        Err(crate::SecureStorageError::InvalidPassword) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    if let Err(e) = st.backend.commit() {
+        st.backend.discard_pending();
+        return Err(e);
+    }
+    let sql_state = load_namespace_state(&st.backend, &st.domain, &session, DEFAULT_NAMESPACE)?;
+    // Same reset-on-switch pattern as `allocate` above.
+    st.main_file = EncryptedFileCore::new();
+    st.namespace_states.clear();
+    st.namespace_states.insert(DEFAULT_NAMESPACE, sql_state);
+    st.session = Some(session);
+    Ok(true)
+}
+
+/// Replace the recovery codes of the unlocked session, invalidating the
+/// previous ones, and commit them.
+pub fn regenerate_recovery_codes() -> Result<Vec<RecoveryCode>> {
+    let mutex = state_mutex();
+    let mut guard = mutex.lock().map_err(|_| SecureStorageError::LockPoisoned)?;
+    let st = guard
+        .as_mut()
+        .ok_or_else(|| SecureStorageError::NotInitialized)?;
+    let session = st
+        .session
+        .as_ref()
+        .ok_or_else(|| SecureStorageError::Storage("no session".into()))?;
+    let codes = crate::generate_recovery_codes(&mut st.backend, &st.domain, session)?;
+    flush_pending_writes(st)?;
+    Ok(codes)
+}
+
 /// Permanently destroy the data of the currently unlocked slot.
 ///
 /// Atomically:
@@ -2072,4 +2120,49 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_native_vfs_recovery_code_unlock() {
+        run_with_stack(|| {
+            let _guard = test_mutex().lock().unwrap();
+            reset_state();
+            ensure_registered();
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().to_str().unwrap().to_string();
+
+            let code = {
+                init_native(&path, "test").unwrap();
+                provision().unwrap();
+                allocate(0, b"forgotten").unwrap();
+                let codes = regenerate_recovery_codes().unwrap();
+                assert_eq!(codes.len(), crate::RECOVERY_CODE_COUNT);
+                let conn = open_db().unwrap();
+                conn.execute_batch(
+                    "CREATE TABLE t (id INTEGER PRIMARY KEY, val TEXT);
+                     INSERT INTO t VALUES (1, 'recovered');",
+                )
+                .unwrap();
+                drop(conn);
+                lock().unwrap();
+                codes[0].as_str().to_string()
+            };
+
+            reset_state();
+            init_native(&path, "test").unwrap();
+            assert!(!unlock_with_recovery_code("0000-0000-0000-0000").unwrap());
+            assert!(unlock_with_recovery_code(&code).unwrap());
+            let conn = open_db().unwrap();
+            let val: String = conn
+                .query_row("SELECT val FROM t WHERE id = 1", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(val, "recovered");
+            drop(conn);
+            lock().unwrap();
+
+            // The code was consumed durably.
+            reset_state();
+            init_native(&path, "test").unwrap();
+            assert!(!unlock_with_recovery_code(&code).unwrap());
+        });
+    }
 }
@@ -0,0 +1,319 @@
+//! Node.js file-backed block storage for secure-storage (WASM on Node).
+//!
+//! Same file layout and semantics as the native `FsStorage`, through
+//! Node's synchronous `fs` API: each file is opened once and kept open, and
+//! blocks are read and written at their offset on its descriptor (pread /
+//! pwrite), so the wasm artifact shipped to browsers can run against real
+//! files in Electron and in CI.
+//!
+//! Imports the `fs` module: only built with the `wasm-node` feature, as
+//! browser bundles cannot resolve it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::constants::BLOCK_SIZE;
+use crate::error::{Result, SecureStorageError};
+use crate::storage::{BlockStorage, KeypairStorage, RecoveryStorage};
+use crate::types::SessionIndex;
+
+#[wasm_bindgen(module = "fs")]
+extern "C" {
+    type Stats;
+
+    #[wasm_bindgen(method, getter, structural)]
+    fn size(this: &Stats) -> f64;
+
+    #[wasm_bindgen(js_name = existsSync)]
+    fn exists_sync(path: &str) -> bool;
+
+    #[wasm_bindgen(js_name = mkdirSync, catch)]
+    fn mkdir_sync(path: &str, options: &JsValue) -> std::result::Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_name = readdirSync, catch)]
+    fn readdir_sync(path: &str) -> std::result::Result<js_sys::Array, JsValue>;
+
+    #[wasm_bindgen(js_name = openSync, catch)]
+    fn open_sync(path: &str, flags: &str) -> std::result::Result<f64, JsValue>;
+
+    #[wasm_bindgen(js_name = fstatSync, catch)]
+    fn fstat_sync(fd: f64) -> std::result::Result<Stats, JsValue>;
+
+    #[wasm_bindgen(js_name = readSync, catch)]
+    fn read_sync(
+        fd: f64,
+        buffer: &mut [u8],
+        offset: u32,
+        length: u32,
+        position: f64,
+    ) -> std::result::Result<f64, JsValue>;
+
+    #[wasm_bindgen(js_name = writeSync, catch)]
+    fn write_sync(
+        fd: f64,
+        buffer: &[u8],
+        offset: u32,
+        length: u32,
+        position: f64,
+    ) -> std::result::Result<f64, JsValue>;
+
+    #[wasm_bindgen(js_name = ftruncateSync, catch)]
+    fn ftruncate_sync(fd: f64, len: f64) -> std::result::Result<(), JsValue>;
+
+    #[wasm_bindgen(js_name = fsyncSync, catch)]
+    fn fsync_sync(fd: f64) -> std::result::Result<(), JsValue>;
+}
+
+fn node_err(e: JsValue) -> SecureStorageError {
+    let message = e
+        .as_string()
+        .or_else(|| {
+            e.dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+        })
+        .unwrap_or_else(|| "Node.js fs error".to_string());
+    SecureStorageError::Storage(message)
+}
+
+fn block_offset(block: u64) -> Result<u64> {
+    block
+        .checked_mul(BLOCK_SIZE as u64)
+        .ok_or(SecureStorageError::Overflow)
+}
+
+// ── NodeBlockStorage ────────────────────────────────────────────────
+
+pub struct NodeBlockStorage {
+    sessions_dir: String,
+    /// Open descriptors by file name.
+    fds: RefCell<HashMap<String, f64>>,
+}
+
+impl NodeBlockStorage {
+    /// Use the files under `base`, creating its `sessions` directory.
+    pub fn open(base: &str) -> Result<Self> {
+        let sessions_dir = format!("{}/sessions", base.trim_end_matches('/'));
+        let options = js_sys::Object::new();
+        js_sys::Reflect::set(&options, &"recursive".into(), &JsValue::TRUE).map_err(node_err)?;
+        mkdir_sync(&sessions_dir, &options).map_err(node_err)?;
+        Ok(Self {
+            sessions_dir,
+            fds: RefCell::default(),
+        })
+    }
+
+    fn path(&self, name: &str) -> String {
+        format!("{}/{name}", self.sessions_dir)
+    }
+
+    /// Descriptor of an existing file, or `None` if it does not exist.
+    fn fd(&self, name: &str) -> Result<Option<f64>> {
+        if let Some(fd) = self.fds.borrow().get(name) {
+            return Ok(Some(*fd));
+        }
+        let path = self.path(name);
+        if !exists_sync(&path) {
+            return Ok(None);
+        }
+        let fd = open_sync(&path, "r+").map_err(node_err)?;
+        self.fds.borrow_mut().insert(name.to_string(), fd);
+        Ok(Some(fd))
+    }
+
+    /// Descriptor of a file, created empty if it does not exist.
+    fn fd_or_create(&self, name: &str) -> Result<f64> {
+        if let Some(fd) = self.fd(name)? {
+            return Ok(fd);
+        }
+        // "w+" truncates, but the file does not exist
+        let fd = open_sync(&self.path(name), "w+").map_err(node_err)?;
+        self.fds.borrow_mut().insert(name.to_string(), fd);
+        Ok(fd)
+    }
+
+    /// Whether any of our files holds data.
+    pub fn has_stored_data(&self) -> Result<bool> {
+        for name in readdir_sync(&self.sessions_dir).map_err(node_err)?.iter() {
+            let Some(name) = name.as_string() else {
+                continue;
+            };
+            if name.starts_with("session_")
+                && let Some(fd) = self.fd(&name)?
+                && file_size(fd)? > 0
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn blocks_file(session: SessionIndex, namespace: u8) -> String {
+    format!("session_{}_n_{namespace}.blocks", session.as_u8())
+}
+
+fn keypair_file(session: SessionIndex) -> String {
+    format!("session_{}.keypair", session.as_u8())
+}
+
+fn recovery_file(session: SessionIndex) -> String {
+    format!("session_{}.recovery", session.as_u8())
+}
+
+fn file_size(fd: f64) -> Result<u64> {
+    Ok(fstat_sync(fd).map_err(node_err)?.size() as u64)
+}
+
+/// Read `dst.len()` bytes at `offset`; reading past the end of the file is
+/// [`SecureStorageError::OutOfBounds`].
+fn read_exact(fd: f64, offset: u64, dst: &mut [u8]) -> Result<()> {
+    let len = dst.len() as u32;
+    let read = read_sync(fd, dst, 0, len, offset as f64).map_err(node_err)?;
+    if read as usize != dst.len() {
+        return Err(SecureStorageError::OutOfBounds);
+    }
+    Ok(())
+}
+
+fn write_all(fd: f64, offset: u64, src: &[u8]) -> Result<()> {
+    let written = write_sync(fd, src, 0, src.len() as u32, offset as f64).map_err(node_err)?;
+    if written as usize != src.len() {
+        return Err(SecureStorageError::Storage("short write".into()));
+    }
+    Ok(())
+}
+
+// ── BlockStorage ────────────────────────────────────────────────────
+
+impl BlockStorage for NodeBlockStorage {
+    fn read_block(
+        &self,
+        session: SessionIndex,
+        namespace: u8,
+        block: u64,
+    ) -> Result<Box<[u8; BLOCK_SIZE]>> {
+        let fd = self
+            .fd(&blocks_file(session, namespace))?
+            .ok_or(SecureStorageError::OutOfBounds)?;
+        let mut buf = Box::new([0u8; BLOCK_SIZE]);
+        read_exact(fd, block_offset(block)?, buf.as_mut())?;
+        Ok(buf)
+    }
+
+    fn write_block(
+        &mut self,
+        session: SessionIndex,
+        namespace: u8,
+        block: u64,
+        data: &[u8; BLOCK_SIZE],
+    ) -> Result<()> {
+        if block >= self.block_count(session, namespace)? {
+            return Err(SecureStorageError::OutOfBounds);
+        }
+        let fd = self
+            .fd(&blocks_file(session, namespace))?
+            .ok_or(SecureStorageError::OutOfBounds)?;
+        write_all(fd, block_offset(block)?, data)
+    }
+
+    fn append_block(
+        &mut self,
+        session: SessionIndex,
+        namespace: u8,
+        data: &[u8; BLOCK_SIZE],
+    ) -> Result<()> {
+        let fd = self.fd_or_create(&blocks_file(session, namespace))?;
+        write_all(fd, file_size(fd)?, data)
+    }
+
+    fn block_count(&self, session: SessionIndex, namespace: u8) -> Result<u64> {
+        let Some(fd) = self.fd(&blocks_file(session, namespace))? else {
+            return Ok(0);
+        };
+        let len = file_size(fd)?;
+        if len % BLOCK_SIZE as u64 != 0 {
+            return Err(SecureStorageError::CorruptedBlock);
+        }
+        Ok(len / BLOCK_SIZE as u64)
+    }
+
+    fn fsync(&self, session: SessionIndex, namespace: u8) -> Result<()> {
+        match self.fd(&blocks_file(session, namespace))? {
+            Some(fd) => fsync_sync(fd).map_err(node_err),
+            None => Ok(()),
+        }
+    }
+
+    fn reset_blockstream(&mut self, session: SessionIndex, namespace: u8) -> Result<()> {
+        let fd = self.fd_or_create(&blocks_file(session, namespace))?;
+        ftruncate_sync(fd, 0.0).map_err(node_err)
+    }
+
+    fn namespaces_with_data(&self, session: SessionIndex) -> Result<Vec<u8>> {
+        let prefix = format!("session_{}_n_", session.as_u8());
+        let mut out = Vec::new();
+        for name in readdir_sync(&self.sessions_dir).map_err(node_err)?.iter() {
+            let Some(name) = name.as_string() else {
+                continue;
+            };
+            let Some(ns) = name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".blocks"))
+                .and_then(|ns| ns.parse::<u8>().ok())
+            else {
+                continue;
+            };
+            if let Some(fd) = self.fd(&name)?
+                && file_size(fd)? > 0
+            {
+                out.push(ns);
+            }
+        }
+        Ok(out)
+    }
+}
+
+// ── KeypairStorage ──────────────────────────────────────────────────
+
+impl KeypairStorage for NodeBlockStorage {
+    fn read_keypair(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        let fd = self
+            .fd(&keypair_file(session))?
+            .ok_or_else(|| SecureStorageError::Storage("keypair not found".into()))?;
+        let len = usize::try_from(file_size(fd)?).map_err(|_| SecureStorageError::Overflow)?;
+        let mut data = Zeroizing::new(vec![0u8; len]);
+        read_exact(fd, 0, &mut data)?;
+        Ok(data)
+    }
+
+    fn write_keypair(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        let fd = self.fd_or_create(&keypair_file(session))?;
+        ftruncate_sync(fd, 0.0).map_err(node_err)?;
+        write_all(fd, 0, data)?;
+        fsync_sync(fd).map_err(node_err)
+    }
+}
+
+// ── RecoveryStorage ─────────────────────────────────────────────────
+
+impl RecoveryStorage for NodeBlockStorage {
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        let fd = self
+            .fd(&recovery_file(session))?
+            .ok_or_else(|| SecureStorageError::Storage("recovery file not found".into()))?;
+        let len = usize::try_from(file_size(fd)?).map_err(|_| SecureStorageError::Overflow)?;
+        let mut data = Zeroizing::new(vec![0u8; len]);
+        read_exact(fd, 0, &mut data)?;
+        Ok(data)
+    }
+
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        let fd = self.fd_or_create(&recovery_file(session))?;
+        ftruncate_sync(fd, 0.0).map_err(node_err)?;
+        write_all(fd, 0, data)?;
+        fsync_sync(fd).map_err(node_err)
+    }
+}
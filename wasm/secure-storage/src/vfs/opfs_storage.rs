@@ -0,0 +1,465 @@
+//! OPFS-backed block storage for secure-storage (WASM, dedicated workers).
+//!
+//! Every file lives in one directory of the Origin Private File System,
+//! named like the files of the native `FsStorage`, and is accessed through a
+//! `FileSystemSyncAccessHandle`: blocks are read and written synchronously
+//! between Rust buffers and the file, with no in-memory copy of the store
+//! and no async persistence step.
+//!
+//! Sync access handles can only be created asynchronously, so
+//! [`OpfsBlockStorage::open`] creates one up front for every existing file
+//! and for the keypair, recovery file and default namespace blockstream of
+//! each session.
+//! Blockstreams of other namespaces first written after that are held in
+//! memory until [`OpfsBlockStorage::persist_dirty`] gives them a file.
+//!
+//! Sync access handles are exclusive: a single worker of the origin can
+//! open the storage at a time (see `crate::lease`).
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use js_sys::IteratorNext;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    File, FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
+    FileSystemGetFileOptions, FileSystemReadWriteOptions, FileSystemSyncAccessHandle,
+    WorkerGlobalScope,
+};
+use zeroize::Zeroizing;
+
+use crate::constants::{BLOCK_SIZE, DEFAULT_NAMESPACE, SESSION_COUNT};
+use crate::error::{Result, SecureStorageError};
+use crate::storage::{BlockStorage, KeypairStorage, RecoveryStorage};
+use crate::types::SessionIndex;
+
+const DIR_NAME: &str = "secure_storage";
+
+fn blocks_file(session: u8, namespace: u8) -> String {
+    format!("session_{session}_n_{namespace}.blocks")
+}
+
+fn keypair_file(session: u8) -> String {
+    format!("session_{session}.keypair")
+}
+
+fn recovery_file(session: u8) -> String {
+    format!("session_{session}.recovery")
+}
+
+/// Namespace of a blocks file of `session`, if `name` is one.
+fn blocks_file_namespace(name: &str, session: u8) -> Option<u8> {
+    name.strip_prefix(&format!("session_{session}_n_"))?
+        .strip_suffix(".blocks")?
+        .parse()
+        .ok()
+}
+
+fn is_storage_file(name: &str) -> bool {
+    name.starts_with("session_")
+        && (name.ends_with(".blocks") || name.ends_with(".keypair") || name.ends_with(".recovery"))
+}
+
+// ── Low-level OPFS helpers ──────────────────────────────────────────
+
+fn opfs_err(e: JsValue) -> SecureStorageError {
+    let message = e
+        .as_string()
+        .or_else(|| {
+            e.dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+        })
+        .unwrap_or_else(|| "OPFS error".to_string());
+    SecureStorageError::Storage(message)
+}
+
+fn at(offset: u64) -> FileSystemReadWriteOptions {
+    let options = FileSystemReadWriteOptions::new();
+    options.set_at(offset as f64);
+    options
+}
+
+fn file_size(handle: &FileSystemSyncAccessHandle) -> Result<u64> {
+    Ok(handle.get_size().map_err(opfs_err)? as u64)
+}
+
+/// Read `dst.len()` bytes at `offset`; reading past the end of the file is
+/// [`SecureStorageError::OutOfBounds`].
+fn read_exact(handle: &FileSystemSyncAccessHandle, offset: u64, dst: &mut [u8]) -> Result<()> {
+    let read = handle
+        .read_with_u8_array_and_options(dst, &at(offset))
+        .map_err(opfs_err)?;
+    if read as usize != dst.len() {
+        return Err(SecureStorageError::OutOfBounds);
+    }
+    Ok(())
+}
+
+fn write_all(handle: &FileSystemSyncAccessHandle, offset: u64, src: &[u8]) -> Result<()> {
+    let written = handle
+        .write_with_u8_array_and_options(src, &at(offset))
+        .map_err(opfs_err)?;
+    if written as usize != src.len() {
+        return Err(SecureStorageError::Storage("short write".into()));
+    }
+    Ok(())
+}
+
+fn block_offset(block: u64) -> Result<u64> {
+    block
+        .checked_mul(BLOCK_SIZE as u64)
+        .ok_or(SecureStorageError::Overflow)
+}
+
+/// Open (or create) the secure_storage directory. Fails outside of a
+/// worker, where OPFS has no sync access.
+async fn open_dir() -> std::result::Result<FileSystemDirectoryHandle, JsValue> {
+    let global: WorkerGlobalScope = js_sys::global()
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("OPFS sync access requires a dedicated worker"))?;
+    let root: FileSystemDirectoryHandle =
+        JsFuture::from(global.navigator().storage().get_directory())
+            .await?
+            .dyn_into()?;
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(true);
+    JsFuture::from(root.get_directory_handle_with_options(DIR_NAME, &options))
+        .await?
+        .dyn_into()
+}
+
+/// Names of the files of our format in the directory.
+async fn storage_file_names(
+    dir: &FileSystemDirectoryHandle,
+) -> std::result::Result<Vec<String>, JsValue> {
+    let keys = dir.keys();
+    let mut names = Vec::new();
+    loop {
+        let next: IteratorNext = JsFuture::from(keys.next()?).await?.unchecked_into();
+        if next.done() {
+            break;
+        }
+        if let Some(name) = next.value().as_string()
+            && is_storage_file(&name)
+        {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+async fn get_file(
+    dir: &FileSystemDirectoryHandle,
+    name: &str,
+) -> std::result::Result<FileSystemFileHandle, JsValue> {
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(true);
+    JsFuture::from(dir.get_file_handle_with_options(name, &options))
+        .await?
+        .dyn_into()
+}
+
+/// Open (or create) a file and take its sync access handle.
+async fn open_handle(
+    dir: &FileSystemDirectoryHandle,
+    name: &str,
+) -> std::result::Result<FileSystemSyncAccessHandle, JsValue> {
+    JsFuture::from(get_file(dir, name).await?.create_sync_access_handle())
+        .await?
+        .dyn_into()
+}
+
+// ── OpfsBlockStorage ────────────────────────────────────────────────
+
+pub struct OpfsBlockStorage {
+    dir: FileSystemDirectoryHandle,
+    /// Sync access handles by file name.
+    handles: RefCell<HashMap<String, FileSystemSyncAccessHandle>>,
+    /// Blockstreams without a file yet, by `(session, namespace)`.
+    staged: RefCell<HashMap<(u8, u8), Vec<Box<[u8; BLOCK_SIZE]>>>>,
+}
+
+impl OpfsBlockStorage {
+    /// Open the OPFS directory and take a sync access handle on each of our
+    /// files, creating the keypair, recovery and default namespace files of
+    /// every session.
+    ///
+    /// Fails if another worker holds the handles, e.g. a second tab that
+    /// did not get the storage lease.
+    pub async fn open() -> std::result::Result<Self, JsValue> {
+        let dir = open_dir().await?;
+        let mut names = storage_file_names(&dir).await?;
+        for session in 0..SESSION_COUNT as u8 {
+            for name in [
+                keypair_file(session),
+                recovery_file(session),
+                blocks_file(session, DEFAULT_NAMESPACE),
+            ] {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+
+        let mut handles = HashMap::with_capacity(names.len());
+        for name in names {
+            let handle = open_handle(&dir, &name).await?;
+            handles.insert(name, handle);
+        }
+        Ok(Self {
+            dir,
+            handles: RefCell::new(handles),
+            staged: RefCell::default(),
+        })
+    }
+
+    /// Check if OPFS has any data (used for needsUnlock detection at boot).
+    /// Static helper that reads file sizes without taking sync access
+    /// handles — used before an `OpfsBlockStorage` instance exists.
+    pub async fn has_data() -> std::result::Result<bool, JsValue> {
+        let dir = open_dir().await?;
+        for name in storage_file_names(&dir).await? {
+            let file: File = JsFuture::from(get_file(&dir, &name).await?.get_file())
+                .await?
+                .dyn_into()?;
+            if file.size() > 0.0 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether any file or in-memory blockstream holds data.
+    pub fn has_stored_data(&self) -> Result<bool> {
+        for handle in self.handles.borrow().values() {
+            if file_size(handle)? > 0 {
+                return Ok(true);
+            }
+        }
+        Ok(self
+            .staged
+            .borrow()
+            .values()
+            .any(|blocks| !blocks.is_empty()))
+    }
+
+    /// Give a file to every blockstream held in memory, and flush it.
+    ///
+    /// A stream written to while its file is being created is written out
+    /// as it is once the file is ready. On failure the stream stays in
+    /// memory and the next call retries.
+    pub async fn persist_dirty(&self) -> std::result::Result<(), JsValue> {
+        let staged: Vec<(u8, u8)> = self.staged.borrow().keys().copied().collect();
+        for (session, namespace) in staged {
+            let name = blocks_file(session, namespace);
+            let handle = open_handle(&self.dir, &name).await?;
+            let Some(blocks) = self.staged.borrow_mut().remove(&(session, namespace)) else {
+                handle.close();
+                continue;
+            };
+            let written = handle
+                .truncate_with_f64(0.0)
+                .map_err(opfs_err)
+                .and_then(|()| {
+                    blocks.iter().enumerate().try_for_each(|(index, block)| {
+                        write_all(&handle, block_offset(index as u64)?, &block[..])
+                    })
+                })
+                .and_then(|()| handle.flush().map_err(opfs_err));
+            if let Err(e) = written {
+                handle.close();
+                self.staged
+                    .borrow_mut()
+                    .insert((session, namespace), blocks);
+                return Err(JsValue::from_str(&format!("{e:?}")));
+            }
+            self.handles.borrow_mut().insert(name, handle);
+        }
+        Ok(())
+    }
+}
+
+// ── BlockStorage ────────────────────────────────────────────────────
+
+impl BlockStorage for OpfsBlockStorage {
+    fn read_block(
+        &self,
+        session: SessionIndex,
+        namespace: u8,
+        block: u64,
+    ) -> Result<Box<[u8; BLOCK_SIZE]>> {
+        if let Some(stream) = self.staged.borrow().get(&(session.as_u8(), namespace)) {
+            let idx = usize::try_from(block).map_err(|_| SecureStorageError::Overflow)?;
+            return stream
+                .get(idx)
+                .cloned()
+                .ok_or(SecureStorageError::OutOfBounds);
+        }
+        let handles = self.handles.borrow();
+        let handle = handles
+            .get(&blocks_file(session.as_u8(), namespace))
+            .ok_or(SecureStorageError::OutOfBounds)?;
+        let mut buf = Box::new([0u8; BLOCK_SIZE]);
+        read_exact(handle, block_offset(block)?, buf.as_mut())?;
+        Ok(buf)
+    }
+
+    fn write_block(
+        &mut self,
+        session: SessionIndex,
+        namespace: u8,
+        block: u64,
+        data: &[u8; BLOCK_SIZE],
+    ) -> Result<()> {
+        if let Some(stream) = self.staged.get_mut().get_mut(&(session.as_u8(), namespace)) {
+            let idx = usize::try_from(block).map_err(|_| SecureStorageError::Overflow)?;
+            let slot = stream.get_mut(idx).ok_or(SecureStorageError::OutOfBounds)?;
+            **slot = *data;
+            return Ok(());
+        }
+        if block >= self.block_count(session, namespace)? {
+            return Err(SecureStorageError::OutOfBounds);
+        }
+        let handles = self.handles.get_mut();
+        let handle = handles
+            .get(&blocks_file(session.as_u8(), namespace))
+            .ok_or(SecureStorageError::OutOfBounds)?;
+        write_all(handle, block_offset(block)?, data)
+    }
+
+    fn append_block(
+        &mut self,
+        session: SessionIndex,
+        namespace: u8,
+        data: &[u8; BLOCK_SIZE],
+    ) -> Result<()> {
+        let name = blocks_file(session.as_u8(), namespace);
+        match self.handles.get_mut().get(&name) {
+            Some(handle) => write_all(handle, file_size(handle)?, data),
+            None => {
+                self.staged
+                    .get_mut()
+                    .entry((session.as_u8(), namespace))
+                    .or_default()
+                    .push(Box::new(*data));
+                Ok(())
+            }
+        }
+    }
+
+    fn block_count(&self, session: SessionIndex, namespace: u8) -> Result<u64> {
+        if let Some(stream) = self.staged.borrow().get(&(session.as_u8(), namespace)) {
+            return Ok(stream.len() as u64);
+        }
+        let handles = self.handles.borrow();
+        let Some(handle) = handles.get(&blocks_file(session.as_u8(), namespace)) else {
+            return Ok(0);
+        };
+        let len = file_size(handle)?;
+        if len % BLOCK_SIZE as u64 != 0 {
+            return Err(SecureStorageError::CorruptedBlock);
+        }
+        Ok(len / BLOCK_SIZE as u64)
+    }
+
+    fn fsync(&self, session: SessionIndex, namespace: u8) -> Result<()> {
+        match self
+            .handles
+            .borrow()
+            .get(&blocks_file(session.as_u8(), namespace))
+        {
+            Some(handle) => handle.flush().map_err(opfs_err),
+            None => Ok(()),
+        }
+    }
+
+    fn reset_blockstream(&mut self, session: SessionIndex, namespace: u8) -> Result<()> {
+        if let Some(stream) = self.staged.get_mut().get_mut(&(session.as_u8(), namespace)) {
+            stream.clear();
+        }
+        if let Some(handle) = self
+            .handles
+            .get_mut()
+            .get(&blocks_file(session.as_u8(), namespace))
+        {
+            handle.truncate_with_f64(0.0).map_err(opfs_err)?;
+        }
+        Ok(())
+    }
+
+    fn namespaces_with_data(&self, session: SessionIndex) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for (name, handle) in self.handles.borrow().iter() {
+            if let Some(ns) = blocks_file_namespace(name, session.as_u8())
+                && file_size(handle)? > 0
+            {
+                out.push(ns);
+            }
+        }
+        for ((s, ns), blocks) in self.staged.borrow().iter() {
+            if *s == session.as_u8() && !blocks.is_empty() && !out.contains(ns) {
+                out.push(*ns);
+            }
+        }
+        Ok(out)
+    }
+}
+
+// ── KeypairStorage ──────────────────────────────────────────────────
+
+impl KeypairStorage for OpfsBlockStorage {
+    fn read_keypair(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        let handles = self.handles.borrow();
+        let handle = handles
+            .get(&keypair_file(session.as_u8()))
+            .ok_or_else(|| SecureStorageError::Storage("keypair not found".into()))?;
+        let len = usize::try_from(file_size(handle)?).map_err(|_| SecureStorageError::Overflow)?;
+        if len == 0 {
+            return Err(SecureStorageError::Storage("keypair not found".into()));
+        }
+        let mut data = Zeroizing::new(vec![0u8; len]);
+        read_exact(handle, 0, &mut data)?;
+        Ok(data)
+    }
+
+    fn write_keypair(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        let handles = self.handles.get_mut();
+        let handle = handles
+            .get(&keypair_file(session.as_u8()))
+            .ok_or_else(|| SecureStorageError::Storage("keypair file not open".into()))?;
+        handle.truncate_with_f64(0.0).map_err(opfs_err)?;
+        write_all(handle, 0, data)?;
+        handle.flush().map_err(opfs_err)
+    }
+}
+
+// ── RecoveryStorage ─────────────────────────────────────────────────
+
+impl RecoveryStorage for OpfsBlockStorage {
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        let handles = self.handles.borrow();
+        let handle = handles
+            .get(&recovery_file(session.as_u8()))
+            .ok_or_else(|| SecureStorageError::Storage("recovery file not found".into()))?;
+        let len = usize::try_from(file_size(handle)?).map_err(|_| SecureStorageError::Overflow)?;
+        if len == 0 {
+            return Err(SecureStorageError::Storage(
+                "recovery file not found".into(),
+            ));
+        }
+        let mut data = Zeroizing::new(vec![0u8; len]);
+        read_exact(handle, 0, &mut data)?;
+        Ok(data)
+    }
+
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        let handles = self.handles.get_mut();
+        let handle = handles
+            .get(&recovery_file(session.as_u8()))
+            .ok_or_else(|| SecureStorageError::Storage("recovery file not open".into()))?;
+        handle.truncate_with_f64(0.0).map_err(opfs_err)?;
+        write_all(handle, 0, data)?;
+        handle.flush().map_err(opfs_err)
+    }
+}
@@ -1,8 +1,9 @@
 //! redb-backed block & keypair storage for native targets (iOS/Android).
 //!
-//! All blocks and keypairs are stored in a single `storage.redb` file.
-//! Writes are buffered in RAM. On `commit()` every pending keypair,
-//! blockstream reset, and block write is flushed as a single ACID
+//! All blocks, keypairs and recovery files are stored in a single
+//! `storage.redb` file. Writes are buffered in RAM. On `commit()` every
+//! pending keypair, recovery file, blockstream reset, and block write is
+//! flushed as a single ACID
 //! transaction; redb handles crash safety internally, so no custom WAL
 //! is needed.
 //!
@@ -19,13 +20,14 @@ use zeroize::Zeroizing;
 
 use crate::constants::{BLOCK_SIZE, SESSION_COUNT};
 use crate::error::{Result, SecureStorageError};
-use crate::storage::{BlockStorage, KeypairStorage};
+use crate::storage::{BlockStorage, KeypairStorage, RecoveryStorage};
 use crate::types::SessionIndex;
 
 // ── redb table definitions ───────────────────────────────────────────
 
 const BLOCKS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("blocks");
 const KEYPAIRS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("keypairs");
+const RECOVERY: TableDefinition<&[u8], &[u8]> = TableDefinition::new("recovery");
 
 // ── Buffered write ───────────────────────────────────────────────────
 
@@ -56,6 +58,9 @@ pub struct RedbStorage {
     /// rotations can be atomic with the block rewrites that depend on
     /// them (notably `destroy_session`).
     pending_keypairs: HashMap<u8, Zeroizing<Vec<u8>>>,
+    /// Pending recovery file writes, drained at `commit()` time like
+    /// `pending_keypairs`.
+    pending_recovery: HashMap<u8, Zeroizing<Vec<u8>>>,
     ram_buffer: Vec<BufferedWrite>,
     /// Overlay index: maps a (session, namespace, block_id) key to the
     /// position in `ram_buffer` of the last buffered write at that key.
@@ -86,6 +91,7 @@ impl RedbStorage {
         let mut storage = Self {
             db,
             pending_keypairs: HashMap::new(),
+            pending_recovery: HashMap::new(),
             ram_buffer: Vec::new(),
             ram_overlay: HashMap::new(),
             pending_deletes: Vec::new(),
@@ -153,7 +159,8 @@ impl RedbStorage {
     /// fsync instead of two:
     ///
     ///   - Phase A: write `pending_keypairs` (session keypair rotations
-    ///     staged by `write_keypair` since the last commit).
+    ///     staged by `write_keypair` since the last commit) and
+    ///     `pending_recovery` (recovery files staged by `write_recovery`).
     ///   - Phase B: drain `pending_deletes` (blockstream resets staged
     ///     by `reset_blockstream` since the last commit).
     ///   - Phase C: insert `ram_buffer` writes, deduped via
@@ -187,6 +194,15 @@ impl RedbStorage {
                     .map_err(redb_err("insert"))?;
             }
         }
+        {
+            let mut table = txn.open_table(RECOVERY).map_err(redb_err("open table"))?;
+            for (session, data) in &self.pending_recovery {
+                let key = [*session];
+                table
+                    .insert(key.as_slice(), data.as_slice())
+                    .map_err(redb_err("insert"))?;
+            }
+        }
         {
             let mut table = txn.open_table(BLOCKS).map_err(redb_err("open table"))?;
             // Phase B: drain pending blockstream deletes.
@@ -215,6 +231,7 @@ impl RedbStorage {
         }
         txn.commit().map_err(redb_err("commit"))?;
         self.pending_keypairs.clear();
+        self.pending_recovery.clear();
         self.ram_buffer.clear();
         self.ram_overlay.clear();
         self.pending_deletes.clear();
@@ -231,8 +248,9 @@ impl RedbStorage {
     /// and corrupt the on-disk state.
     ///
     /// **Caveat**: this clears `pending_keypairs` (keypair stages),
-    /// `ram_buffer` (write-block stages), `ram_overlay` (the write-block
-    /// index), and `pending_deletes` (reset-blockstream stages). It does
+    /// `pending_recovery` (recovery file stages), `ram_buffer`
+    /// (write-block stages), `ram_overlay` (the write-block index), and
+    /// `pending_deletes` (reset-blockstream stages). It does
     /// NOT undo direct mutations to `block_counts`, which
     /// `reset_blockstream` and `append_block` apply immediately. Callers
     /// that mutate counts (i.e., call `reset_blockstream` or
@@ -240,6 +258,7 @@ impl RedbStorage {
     /// they must avoid those calls or do their own count-snapshotting.
     pub fn discard_pending(&mut self) {
         self.pending_keypairs.clear();
+        self.pending_recovery.clear();
         self.ram_buffer.clear();
         self.ram_overlay.clear();
         self.pending_deletes.clear();
@@ -442,6 +461,43 @@ impl KeypairStorage for RedbStorage {
     }
 }
 
+// ── RecoveryStorage ──────────────────────────────────────────────────
+
+impl RecoveryStorage for RedbStorage {
+    fn read_recovery(&self, session: SessionIndex) -> Result<Zeroizing<Vec<u8>>> {
+        if let Some(data) = self.pending_recovery.get(&session.as_u8()) {
+            return Ok(Zeroizing::new((**data).clone()));
+        }
+
+        let txn = self.db.begin_read().map_err(redb_err("read txn"))?;
+        let table = match txn.open_table(RECOVERY) {
+            Ok(t) => t,
+            Err(redb::TableError::TableDoesNotExist(_)) => {
+                return Err(SecureStorageError::Storage(
+                    "recovery file not found".into(),
+                ));
+            }
+            Err(e) => {
+                return Err(SecureStorageError::Storage(format!("redb open table: {e}")));
+            }
+        };
+        let key = [session.as_u8()];
+        let entry = table.get(key.as_slice()).map_err(redb_err("get"))?;
+        match entry {
+            Some(val) => Ok(Zeroizing::new(val.value().to_vec())),
+            None => Err(SecureStorageError::Storage(
+                "recovery file not found".into(),
+            )),
+        }
+    }
+
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> Result<()> {
+        self.pending_recovery
+            .insert(session.as_u8(), Zeroizing::new(data.to_vec()));
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -616,6 +672,30 @@ mod tests {
         assert!(s.read_keypair(si).is_err());
     }
 
+    // ── Recovery ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_recovery_persists_on_commit() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().to_path_buf();
+        let si = SessionIndex::new(1).unwrap();
+
+        {
+            let mut s = RedbStorage::open(&path).unwrap();
+            assert!(s.read_recovery(si).is_err());
+            s.write_recovery(si, b"recovery-file").unwrap();
+            assert_eq!(&*s.read_recovery(si).unwrap(), b"recovery-file");
+            s.commit().unwrap();
+        }
+
+        let mut s = RedbStorage::open(&path).unwrap();
+        assert_eq!(&*s.read_recovery(si).unwrap(), b"recovery-file");
+
+        s.write_recovery(si, b"discarded").unwrap();
+        s.discard_pending();
+        assert_eq!(&*s.read_recovery(si).unwrap(), b"recovery-file");
+    }
+
     // ── Persistence ──────────────────────────────────────────────────
 
     #[test]
@@ -3,8 +3,9 @@
 //!
 //! Implements the rsqlite-vfs traits (`VfsFile`, `VfsStore`, `SQLiteIoMethods`,
 //! `SQLiteVfs`). The main DB file is encrypted via [`EncryptedFileCore`] and
-//! persisted to IndexedDB via [`IdbBlockStorage`]; temp files are kept in
-//! memory via the upstream [`MemChunksFile`] helper.
+//! persisted to IndexedDB via [`IdbBlockStorage`] or to OPFS via
+//! [`OpfsBlockStorage`]; temp files are kept in memory via the upstream
+//! [`MemChunksFile`] helper.
 //!
 //! Read/write/sync/truncate/file_size require access to the unlocked session
 //! and the IDB-backed backend, both of which live alongside the open file
@@ -29,19 +30,26 @@ use sqlite_wasm_rs::utils::{
 
 use crate::BLOCK_SIZE;
 use crate::DEFAULT_NAMESPACE;
-use crate::storage::{BlockStorage, KeypairStorage, MemoryStorage};
+use crate::storage::{BlockStorage, KeypairStorage, MemoryStorage, RecoveryStorage};
 use crate::types::SessionIndex;
 use crate::unlock::{NamespaceState, UnlockedSession};
 use crate::vfs::file_core::EncryptedFileCore;
 use crate::vfs::idb_storage::IdbBlockStorage;
+#[cfg(feature = "wasm-node")]
+use crate::vfs::node_storage::NodeBlockStorage;
+use crate::vfs::opfs_storage::OpfsBlockStorage;
 
 // ── Backend ────────────────────────────────────────────────────────
 
 /// Backend used by the encrypted VFS. `Memory` is for tests, `Idb` for the
-/// production WASM path.
+/// production WASM path, `Opfs` for workers with OPFS sync access, `Node`
+/// for Node.js files.
 pub enum Backend {
     Memory(MemoryStorage),
     Idb(IdbBlockStorage),
+    Opfs(OpfsBlockStorage),
+    #[cfg(feature = "wasm-node")]
+    Node(NodeBlockStorage),
 }
 
 impl BlockStorage for Backend {
@@ -54,6 +62,9 @@ impl BlockStorage for Backend {
         match self {
             Backend::Memory(s) => s.read_block(session, namespace, block),
             Backend::Idb(s) => s.read_block(session, namespace, block),
+            Backend::Opfs(s) => s.read_block(session, namespace, block),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.read_block(session, namespace, block),
         }
     }
     fn write_block(
@@ -66,6 +77,9 @@ impl BlockStorage for Backend {
         match self {
             Backend::Memory(s) => s.write_block(session, namespace, block, data),
             Backend::Idb(s) => s.write_block(session, namespace, block, data),
+            Backend::Opfs(s) => s.write_block(session, namespace, block, data),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.write_block(session, namespace, block, data),
         }
     }
     fn append_block(
@@ -77,18 +91,27 @@ impl BlockStorage for Backend {
         match self {
             Backend::Memory(s) => s.append_block(session, namespace, data),
             Backend::Idb(s) => s.append_block(session, namespace, data),
+            Backend::Opfs(s) => s.append_block(session, namespace, data),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.append_block(session, namespace, data),
         }
     }
     fn block_count(&self, session: SessionIndex, namespace: u8) -> crate::error::Result<u64> {
         match self {
             Backend::Memory(s) => s.block_count(session, namespace),
             Backend::Idb(s) => s.block_count(session, namespace),
+            Backend::Opfs(s) => s.block_count(session, namespace),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.block_count(session, namespace),
         }
     }
     fn fsync(&self, session: SessionIndex, namespace: u8) -> crate::error::Result<()> {
         match self {
             Backend::Memory(s) => s.fsync(session, namespace),
             Backend::Idb(s) => s.fsync(session, namespace),
+            Backend::Opfs(s) => s.fsync(session, namespace),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.fsync(session, namespace),
         }
     }
     fn reset_blockstream(
@@ -99,12 +122,18 @@ impl BlockStorage for Backend {
         match self {
             Backend::Memory(s) => s.reset_blockstream(session, namespace),
             Backend::Idb(s) => s.reset_blockstream(session, namespace),
+            Backend::Opfs(s) => s.reset_blockstream(session, namespace),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.reset_blockstream(session, namespace),
         }
     }
     fn namespaces_with_data(&self, session: SessionIndex) -> crate::error::Result<Vec<u8>> {
         match self {
             Backend::Memory(s) => s.namespaces_with_data(session),
             Backend::Idb(s) => s.namespaces_with_data(session),
+            Backend::Opfs(s) => s.namespaces_with_data(session),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.namespaces_with_data(session),
         }
     }
 }
@@ -117,12 +146,42 @@ impl KeypairStorage for Backend {
         match self {
             Backend::Memory(s) => s.read_keypair(session),
             Backend::Idb(s) => s.read_keypair(session),
+            Backend::Opfs(s) => s.read_keypair(session),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.read_keypair(session),
         }
     }
     fn write_keypair(&mut self, session: SessionIndex, data: &[u8]) -> crate::error::Result<()> {
         match self {
             Backend::Memory(s) => s.write_keypair(session, data),
             Backend::Idb(s) => s.write_keypair(session, data),
+            Backend::Opfs(s) => s.write_keypair(session, data),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.write_keypair(session, data),
+        }
+    }
+}
+
+impl RecoveryStorage for Backend {
+    fn read_recovery(
+        &self,
+        session: SessionIndex,
+    ) -> crate::error::Result<zeroize::Zeroizing<Vec<u8>>> {
+        match self {
+            Backend::Memory(s) => s.read_recovery(session),
+            Backend::Idb(s) => s.read_recovery(session),
+            Backend::Opfs(s) => s.read_recovery(session),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.read_recovery(session),
+        }
+    }
+    fn write_recovery(&mut self, session: SessionIndex, data: &[u8]) -> crate::error::Result<()> {
+        match self {
+            Backend::Memory(s) => s.write_recovery(session, data),
+            Backend::Idb(s) => s.write_recovery(session, data),
+            Backend::Opfs(s) => s.write_recovery(session, data),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(s) => s.write_recovery(session, data),
         }
     }
 }
@@ -5,20 +5,37 @@
 //!
 //! Single bridge between the SDK worker and the Rust crate. Two groups:
 //!
-//!   * **Lifecycle**: `initSecureStorage`, `idbHasData`, `provisionStorage`,
-//!     `allocateSession`, `unlockSession`, `lockSession`, `coverTrafficTick`,
+//!   * **Lifecycle**: `initSecureStorage`, `idbHasData`, `opfsHasData`,
+//!     `provisionStorage`, `allocateSession`, `unlockSession`,
+//!     `unlockSessionAsync`, `lockSession`, `coverTrafficTick`,
 //!     `flushEncrypted`, `openDatabase`, `closeDatabase`.
 //!   * **SQL exec**: `execSql` runs a single SQL statement against the
 //!     embedded sqlite-wasm-rs SQLite, routing main DB I/O through our
 //!     custom encrypted VFS (see `vfs::sqlite_vfs`).
 //!
+//! [`StorageHandle`] bundles the lifecycle and namespace data exports into
+//! one class whose methods follow the sequences the JS worker expects
+//! (e.g. `lock` closes the database, flushes, then locks), so the worker
+//! only forwards calls to it.
+//!
+//! [`StorageLease`] lets the tabs of an origin sharing the storage agree on
+//! a single writer (see [`crate::lease`]).
+//!
+//! `setLogLevel` turns on a redacted diagnostic log in the console, off by
+//! default.
+//!
+//! Every fallible export throws a [`GossipError`] carrying a stable `code`
+//! (the [`SecureStorageError::code`] of storage failures), a `kind` and a
+//! message.
+//!
 //! All raw SQLite C calls are encapsulated in the [`crate::sqlite_handle`]
 //! module's RAII wrappers (`SafeDb`, `SafeStmt`); this file uses only safe
 //! Rust except for the small block in `with_app_state` that resolves the
 //! registered VFS pointer.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ffi::CStr;
+use std::rc::Rc;
 
 use js_sys::{Array, Uint8Array};
 use sqlite_wasm_rs::WasmOsCallback;
@@ -31,14 +48,23 @@ use wasm_bindgen::prelude::*;
 #[allow(unused_imports)]
 pub use wasm_bindgen_rayon::init_thread_pool;
 
-use crate::DEFAULT_NAMESPACE;
 use crate::error::SecureStorageError;
+use crate::kdf::{SessionKeys, derive_session_keys};
+use crate::lease::{
+    LeaseHolder, LeaseRecord, acquire_lease, generate_lease_holder, release_lease, renew_lease,
+};
+use crate::recovery::RecoveryCode;
 use crate::sqlite_handle::{SafeDb, SafeStmt, SqlResult, SqlValue, StepStatus};
 use crate::storage::MemoryStorage;
 use crate::types::SessionIndex;
-use crate::unlock::{NamespaceState, load_namespace_state};
+use crate::unlock::{NamespaceState, load_namespace_state, unlock_session_with_keys};
+use crate::vfs::idb_lease::{LeaseWrite, update_lease};
 use crate::vfs::idb_storage::IdbBlockStorage;
+#[cfg(feature = "wasm-node")]
+use crate::vfs::node_storage::NodeBlockStorage;
+use crate::vfs::opfs_storage::OpfsBlockStorage;
 use crate::vfs::sqlite_vfs::{AppState, Backend, EncryptedIoMethods, EncryptedVfs, VFS_NAME};
+use crate::{Clock, DEFAULT_NAMESPACE, SystemClock};
 
 // ── Global state ───────────────────────────────────────────────────
 
@@ -57,15 +83,122 @@ thread_local! {
     static DB: RefCell<Option<SafeDb>> = const { RefCell::new(None) };
 }
 
-fn map_err(e: SecureStorageError) -> JsValue {
-    JsValue::from_str(&e.to_string())
+// ── Errors ─────────────────────────────────────────────────────────
+
+/// Error thrown by every fallible export.
+///
+/// `code` is stable and meant to be switched on; `message` is for humans
+/// and its wording may change. `kind` groups the codes: `"storage"` for
+/// [`SecureStorageError`]s, `"argument"` for invalid arguments, `"state"`
+/// for calls made in the wrong lifecycle state, `"database"` for SQLite
+/// failures and `"backend"` for IndexedDB / OPFS / VFS failures.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct GossipError {
+    code: &'static str,
+    kind: &'static str,
+    message: String,
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+/** Family of a `GossipError`. */
+export type GossipErrorKind = "storage" | "argument" | "state" | "database" | "backend";
+
+/** Code of a `GossipError`. */
+export type GossipErrorCode =
+  | "INVALID_PASSWORD"
+  | "INVALID_MNEMONIC"
+  | "LEASE_HELD"
+  | "OUT_OF_BOUNDS"
+  | "UNSUPPORTED_VERSION"
+  | "CORRUPTED_DATA"
+  | "INVALID_SESSION_INDEX"
+  | "OVERFLOW"
+  | "STORAGE"
+  | "NOT_INITIALIZED"
+  | "DATABASE_NOT_OPEN"
+  | "SQLITE"
+  | "IO"
+  | "LOCK_POISONED"
+  | "THREAD_PANIC"
+  | "INVALID_ARGUMENT"
+  | "SESSION_LOCKED"
+  | "BACKEND";
+"#;
+
+#[wasm_bindgen]
+impl GossipError {
+    #[wasm_bindgen(getter, unchecked_return_type = "GossipErrorCode")]
+    pub fn code(&self) -> String {
+        self.code.to_string()
+    }
+    #[wasm_bindgen(getter, unchecked_return_type = "GossipErrorKind")]
+    pub fn kind(&self) -> String {
+        self.kind.to_string()
+    }
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        format!("{}: {}", self.code, self.message)
+    }
+}
+
+impl GossipError {
+    fn new(code: &'static str, kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::new("INVALID_ARGUMENT", "argument", message)
+    }
+
+    fn sqlite(message: impl Into<String>) -> Self {
+        Self::new("SQLITE", "database", message)
+    }
+}
+
+impl From<SecureStorageError> for GossipError {
+    fn from(e: SecureStorageError) -> Self {
+        Self::new(e.code(), "storage", e.to_string())
+    }
+}
+
+fn map_err(e: SecureStorageError) -> GossipError {
+    log::debug!("storage call failed: {}", e.code());
+    GossipError::from(e)
+}
+
+/// IndexedDB errors are JS values (usually a `DOMException`); keep their
+/// message when there is one.
+fn backend_err(e: JsValue) -> GossipError {
+    let message = e
+        .as_string()
+        .or_else(|| {
+            e.dyn_ref::<js_sys::Error>()
+                .map(|e| String::from(e.message()))
+        })
+        .unwrap_or_else(|| "IndexedDB error".to_string());
+    log::warn!("backend call failed: {message}");
+    GossipError::new("BACKEND", "backend", message)
+}
+
+fn session_locked() -> GossipError {
+    GossipError::new("SESSION_LOCKED", "state", "session not unlocked")
 }
 
 // Shared precision-aware JS `number` <-> Rust integer conversions.
 use crate::js_num::{is_js_safe_integer_i64, safe_f64_to_i64, safe_f64_to_u64};
 
-fn not_initialized() -> JsValue {
-    JsValue::from_str("secure storage not initialized")
+fn not_initialized() -> GossipError {
+    GossipError::new("NOT_INITIALIZED", "state", "secure storage not initialized")
 }
 
 /// Resolve the registered VFS pointer to its leaked `&'static AppState`.
@@ -74,9 +207,9 @@ fn not_initialized() -> JsValue {
 /// VFS pointer in `VFS_PTR`. The `pAppData` of that VFS was populated by
 /// `register_vfs` from a leaked `VfsAppData<AppState>` that lives for the
 /// program lifetime.
-fn with_app_state<F, R>(f: F) -> Result<R, JsValue>
+fn with_app_state<F, R>(f: F) -> Result<R, GossipError>
 where
-    F: FnOnce(&VfsAppData<AppState>) -> Result<R, JsValue>,
+    F: FnOnce(&VfsAppData<AppState>) -> Result<R, GossipError>,
 {
     VFS_PTR.with(|p| {
         let p = p.borrow();
@@ -87,10 +220,78 @@ where
     })
 }
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+/// Lets the event loop run pending tasks (rendering, input, worker messages)
+/// before continuing. A resolved promise would only drain microtasks.
+async fn yield_to_event_loop() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, 0);
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Calls the progress callback, if any. A throwing callback does not abort
+/// the operation it reports on.
+fn report_progress(on_progress: Option<&js_sys::Function>, progress: f64) {
+    if let Some(on_progress) = on_progress {
+        let _ = on_progress.call1(&JsValue::NULL, &JsValue::from_f64(progress));
+    }
+}
+
+// ── Diagnostics ────────────────────────────────────────────────────
+
+/// Writes `log` records to the browser console.
+struct ConsoleLogger;
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = JsValue::from(format!("[{}] {}", record.target(), record.args()));
+        match record.level() {
+            log::Level::Error => web_sys::console::error_1(&line),
+            log::Level::Warn => web_sys::console::warn_1(&line),
+            log::Level::Info => web_sys::console::info_1(&line),
+            log::Level::Debug | log::Level::Trace => web_sys::console::debug_1(&line),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CONSOLE_LOGGER: ConsoleLogger = ConsoleLogger;
+
+/// Sets the level of the diagnostic log written to the console: `"off"`
+/// (the default), `"error"`, `"warn"`, `"info"`, `"debug"` or `"trace"`.
+///
+/// The log carries error codes, counts and block positions. It never holds
+/// passwords, keys or data, nor the slot a password unlocked, which would
+/// defeat deniability.
+#[wasm_bindgen(js_name = setLogLevel)]
+pub fn set_log_level(level: &str) -> Result<(), GossipError> {
+    let filter: log::LevelFilter = level
+        .parse()
+        .map_err(|_| GossipError::invalid_argument(format!("unknown log level: {level}")))?;
+    // only fails when already installed by a previous call
+    let _ = log::set_logger(&CONSOLE_LOGGER);
+    log::set_max_level(filter);
+    Ok(())
+}
+
 // ── Lifecycle exports ──────────────────────────────────────────────
 
 #[wasm_bindgen(js_name = initSecureStorage)]
-pub async fn init_secure_storage(domain: &str, backend: &str) -> Result<(), JsValue> {
+pub async fn init_secure_storage(domain: &str, backend: &str) -> Result<(), GossipError> {
     console_error_panic_hook::set_once();
 
     // Idempotent across hot reloads: if the VFS is already registered,
@@ -101,8 +302,17 @@ pub async fn init_secure_storage(domain: &str, backend: &str) -> Result<(), JsVa
 
     let backend_inst = match backend {
         "memory" => Backend::Memory(MemoryStorage::new()),
-        "idb" => Backend::Idb(IdbBlockStorage::open().await?),
-        _ => return Err(JsValue::from_str(&format!("unknown backend: {backend}"))),
+        "idb" => Backend::Idb(IdbBlockStorage::open().await.map_err(backend_err)?),
+        "opfs" => Backend::Opfs(OpfsBlockStorage::open().await.map_err(backend_err)?),
+        #[cfg(feature = "wasm-node")]
+        _ if backend.starts_with("node:") => {
+            Backend::Node(NodeBlockStorage::open(&backend["node:".len()..]).map_err(map_err)?)
+        }
+        _ => {
+            return Err(GossipError::invalid_argument(format!(
+                "unknown backend: {backend}"
+            )));
+        }
     };
 
     let vfs = if let Ok(Some(existing)) = registered_vfs(VFS_NAME) {
@@ -110,7 +320,9 @@ pub async fn init_secure_storage(domain: &str, backend: &str) -> Result<(), JsVa
     } else {
         let app_state = AppState::new(backend_inst, domain.to_string());
         register_vfs::<EncryptedIoMethods, EncryptedVfs<WasmOsCallback>>(VFS_NAME, app_state, false)
-            .map_err(|e| JsValue::from_str(&format!("register_vfs failed: {e}")))?
+            .map_err(|e| {
+                GossipError::new("BACKEND", "backend", format!("register_vfs failed: {e}"))
+            })?
     };
 
     VFS_PTR.with(|p| *p.borrow_mut() = Some(vfs));
@@ -118,16 +330,24 @@ pub async fn init_secure_storage(domain: &str, backend: &str) -> Result<(), JsVa
 }
 
 #[wasm_bindgen(js_name = idbHasData)]
-pub async fn idb_has_data() -> Result<bool, JsValue> {
-    IdbBlockStorage::has_data().await
+pub async fn idb_has_data() -> Result<bool, GossipError> {
+    IdbBlockStorage::has_data().await.map_err(backend_err)
+}
+
+/// Same as `idbHasData` for the `opfs` backend. Call it from the worker
+/// before `initSecureStorage`, which takes the files over.
+#[wasm_bindgen(js_name = opfsHasData)]
+pub async fn opfs_has_data() -> Result<bool, GossipError> {
+    OpfsBlockStorage::has_data().await.map_err(backend_err)
 }
 
 #[wasm_bindgen(js_name = provisionStorage)]
-pub fn provision_storage() -> Result<(), JsValue> {
+pub fn provision_storage() -> Result<(), GossipError> {
     close_database_and_clear_files()?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
         crate::provision_storage(&mut state.backend).map_err(map_err)?;
+        crate::provision_recovery_storage(&mut state.backend).map_err(map_err)?;
         state.session = None;
         state.namespace_states.clear();
         Ok(())
@@ -135,7 +355,7 @@ pub fn provision_storage() -> Result<(), JsValue> {
 }
 
 #[wasm_bindgen(js_name = allocateSession)]
-pub fn allocate_session(slot: u8, password: &[u8]) -> Result<(), JsValue> {
+pub fn allocate_session(slot: u8, password: &[u8]) -> Result<(), GossipError> {
     let idx = SessionIndex::new(slot).map_err(map_err)?;
     close_database_and_clear_files()?;
     with_app_state(|app| {
@@ -153,12 +373,119 @@ pub fn allocate_session(slot: u8, password: &[u8]) -> Result<(), JsValue> {
     })
 }
 
+/// Same as `allocateSession`, also returning the session's one-time
+/// recovery codes, to show to the user once; they are durable after the
+/// next `flushEncrypted`.
+#[wasm_bindgen(
+    js_name = allocateSessionWithRecovery,
+    unchecked_return_type = "string[]"
+)]
+pub fn allocate_session_with_recovery(slot: u8, password: &[u8]) -> Result<Array, GossipError> {
+    allocate_session(slot, password)?;
+    regenerate_recovery_codes()
+}
+
+/// Unlocks the session one of its recovery codes opens, consuming the code.
+/// Returns `false`, changing nothing, if no slot matches. The code is spent
+/// for good after the next `flushEncrypted`.
+#[wasm_bindgen(js_name = unlockWithRecoveryCode)]
+pub fn unlock_with_recovery_code(code: &str) -> Result<bool, GossipError> {
+    let unlock_result = with_app_state(|app| {
+        let mut state = app.state.borrow_mut();
+        let domain = state.domain.clone();
+        match crate::unlock_with_recovery_code(&mut state.backend, &domain, code) {
+            Ok(session) => {
+                let sql_state =
+                    load_namespace_state(&state.backend, &domain, &session, DEFAULT_NAMESPACE)
+                        .map_err(map_err)?;
+                Ok(Some((session, sql_state)))
+            }
+            Err(SecureStorageError::InvalidPassword) => Ok(None),
+            Err(e) => Err(map_err(e)),
+        }
+    })?;
+    let Some((session, sql_state)) = unlock_result else {
+        return Ok(false);
+    };
+
+    close_database_and_clear_files()?;
+    with_app_state(|app| {
+        let mut state = app.state.borrow_mut();
+        state.session = Some(session);
+        state.namespace_states.clear();
+        state.namespace_states.insert(DEFAULT_NAMESPACE, sql_state);
+        Ok(())
+    })?;
+    Ok(true)
+}
+
+/// Replaces the recovery codes of the unlocked session, invalidating the
+/// previous ones. The new codes are durable after the next
+/// `flushEncrypted`.
+#[wasm_bindgen(js_name = regenerateRecoveryCodes, unchecked_return_type = "string[]")]
+pub fn regenerate_recovery_codes() -> Result<Array, GossipError> {
+    with_app_state(|app| {
+        let mut state = app.state.borrow_mut();
+        let state = &mut *state;
+        let session = state.session.as_ref().ok_or_else(session_locked)?;
+        let codes = crate::generate_recovery_codes(&mut state.backend, &state.domain, session)
+            .map_err(map_err)?;
+        Ok(recovery_codes_to_js(&codes))
+    })
+}
+
+/// Copies the codes straight into JS strings, leaving no copy on the Rust
+/// heap besides the zeroized `RecoveryCode`s.
+fn recovery_codes_to_js(codes: &[RecoveryCode]) -> Array {
+    codes
+        .iter()
+        .map(|code| JsValue::from_str(code.as_str()))
+        .collect()
+}
+
 #[wasm_bindgen(js_name = unlockSession)]
-pub fn unlock_session(password: &[u8]) -> Result<bool, JsValue> {
+pub fn unlock_session(password: &[u8]) -> Result<bool, GossipError> {
+    let keys = derive_keys(password)?;
+    unlock_with_keys(&keys)
+}
+
+/// Share of `unlockSessionAsync` progress reported once the password KDF,
+/// which takes most of the time, is done.
+const UNLOCK_KDF_PROGRESS: f64 = 0.8;
+
+/// Same as `unlockSession`, yielding to the event loop before the password
+/// KDF and before trying the slots, and reporting progress from 0 to 1 to
+/// `on_progress`.
+///
+/// The KDF itself runs in one block, as Argon2 offers no way to pause
+/// between its passes: the thread still stalls for its duration, so call
+/// this from a worker when the stall matters.
+#[wasm_bindgen(js_name = unlockSessionAsync)]
+pub async fn unlock_session_async(
+    password: Vec<u8>,
+    on_progress: Option<js_sys::Function>,
+) -> Result<bool, GossipError> {
+    let password = zeroize::Zeroizing::new(password);
+    report_progress(on_progress.as_ref(), 0.0);
+    yield_to_event_loop().await;
+    let keys = derive_keys(&password)?;
+    report_progress(on_progress.as_ref(), UNLOCK_KDF_PROGRESS);
+    yield_to_event_loop().await;
+    let unlocked = unlock_with_keys(&keys)?;
+    report_progress(on_progress.as_ref(), 1.0);
+    Ok(unlocked)
+}
+
+fn derive_keys(password: &[u8]) -> Result<SessionKeys, GossipError> {
+    let domain = with_app_state(|app| Ok(app.state.borrow().domain.clone()))?;
+    Ok(derive_session_keys(&domain, password))
+}
+
+fn unlock_with_keys(keys: &SessionKeys) -> Result<bool, GossipError> {
     let unlock_result = with_app_state(|app| {
         let state = app.state.borrow();
         let domain = state.domain.clone();
-        match crate::unlock_session(&state.backend, &domain, password) {
+        match unlock_session_with_keys(&state.backend, &domain, keys) {
             Ok(session) => {
                 // Recover total_data_length for the default namespace eagerly so
                 // SQLite reads see the right file size before any subsequent
@@ -192,7 +519,7 @@ pub fn unlock_session(password: &[u8]) -> Result<bool, JsValue> {
 }
 
 #[wasm_bindgen(js_name = lockSession)]
-pub fn lock_session() -> Result<(), JsValue> {
+pub fn lock_session() -> Result<(), GossipError> {
     close_database_and_clear_files()?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
@@ -215,14 +542,16 @@ pub fn lock_session() -> Result<(), JsValue> {
 /// flush on close lands in the buffer before destroy_session truncates
 /// the namespace. Mirrors `lockSession`'s contract.
 #[wasm_bindgen(js_name = destroySession)]
-pub fn destroy_session(namespaces: &[u8]) -> Result<(), JsValue> {
+pub fn destroy_session(namespaces: &[u8]) -> Result<(), GossipError> {
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
         let slot = state
             .session
             .as_ref()
             .ok_or_else(|| {
-                JsValue::from_str(
+                GossipError::new(
+                    "SESSION_LOCKED",
+                    "state",
                     "destroySession: secure storage is locked — no session to destroy. \
                      Call unlockSession first.",
                 )
@@ -240,7 +569,7 @@ pub fn destroy_session(namespaces: &[u8]) -> Result<(), JsValue> {
 }
 
 #[wasm_bindgen(js_name = coverTrafficTick)]
-pub fn cover_traffic_tick(namespace: u8) -> Result<(), JsValue> {
+pub fn cover_traffic_tick(namespace: u8) -> Result<(), GossipError> {
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
         let domain = state.domain.clone();
@@ -254,9 +583,9 @@ pub fn cover_traffic_tick(namespace: u8) -> Result<(), JsValue> {
 // DEFAULT_NAMESPACE is reserved for the SQLite VFS backing stream and must not
 // be accessed through these generic namespace APIs.
 
-fn reject_default_namespace(namespace: u8) -> Result<(), JsValue> {
+fn reject_default_namespace(namespace: u8) -> Result<(), GossipError> {
     if namespace == DEFAULT_NAMESPACE {
-        return Err(JsValue::from_str(
+        return Err(GossipError::invalid_argument(
             "DEFAULT_NAMESPACE is reserved for SQLite VFS access",
         ));
     }
@@ -266,14 +595,11 @@ fn reject_default_namespace(namespace: u8) -> Result<(), JsValue> {
 fn ensure_namespace_state_loaded(
     state: &mut crate::vfs::sqlite_vfs::EncryptionState,
     namespace: u8,
-) -> Result<(), JsValue> {
+) -> Result<(), GossipError> {
     if state.namespace_states.contains_key(&namespace) {
         return Ok(());
     }
-    let session = state
-        .session
-        .as_ref()
-        .ok_or_else(|| JsValue::from_str("session not unlocked"))?;
+    let session = state.session.as_ref().ok_or_else(session_locked)?;
     let domain = state.domain.clone();
     let ns_state =
         load_namespace_state(&state.backend, &domain, session, namespace).map_err(map_err)?;
@@ -282,9 +608,10 @@ fn ensure_namespace_state_loaded(
 }
 
 #[wasm_bindgen(js_name = writeNamespaceData)]
-pub fn write_namespace_data(namespace: u8, offset: f64, data: &[u8]) -> Result<(), JsValue> {
+pub fn write_namespace_data(namespace: u8, offset: f64, data: &[u8]) -> Result<(), GossipError> {
     reject_default_namespace(namespace)?;
-    let offset = safe_f64_to_u64(offset).ok_or_else(|| JsValue::from_str("invalid offset"))?;
+    let offset =
+        safe_f64_to_u64(offset).ok_or_else(|| GossipError::invalid_argument("invalid offset"))?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
         ensure_namespace_state_loaded(&mut state, namespace)?;
@@ -297,9 +624,7 @@ pub fn write_namespace_data(namespace: u8, offset: f64, data: &[u8]) -> Result<(
             namespace_states,
             domain,
         } = &mut *state;
-        let session = session
-            .as_ref()
-            .ok_or_else(|| JsValue::from_str("session not unlocked"))?;
+        let session = session.as_ref().ok_or_else(session_locked)?;
         let ns_state = namespace_states.entry(namespace).or_default();
         crate::write_session_data(backend, domain, namespace, session, ns_state, offset, data)
             .map_err(map_err)
@@ -307,16 +632,14 @@ pub fn write_namespace_data(namespace: u8, offset: f64, data: &[u8]) -> Result<(
 }
 
 #[wasm_bindgen(js_name = readNamespaceData)]
-pub fn read_namespace_data(namespace: u8, offset: f64, len: usize) -> Result<Vec<u8>, JsValue> {
+pub fn read_namespace_data(namespace: u8, offset: f64, len: usize) -> Result<Vec<u8>, GossipError> {
     reject_default_namespace(namespace)?;
-    let offset = safe_f64_to_u64(offset).ok_or_else(|| JsValue::from_str("invalid offset"))?;
+    let offset =
+        safe_f64_to_u64(offset).ok_or_else(|| GossipError::invalid_argument("invalid offset"))?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
         ensure_namespace_state_loaded(&mut state, namespace)?;
-        let session = state
-            .session
-            .as_ref()
-            .ok_or_else(|| JsValue::from_str("session not unlocked"))?;
+        let session = state.session.as_ref().ok_or_else(session_locked)?;
         let ns_state = state
             .namespace_states
             .get(&namespace)
@@ -337,7 +660,7 @@ pub fn read_namespace_data(namespace: u8, offset: f64, len: usize) -> Result<Vec
 }
 
 #[wasm_bindgen(js_name = namespaceDataLength)]
-pub fn namespace_data_length(namespace: u8) -> Result<f64, JsValue> {
+pub fn namespace_data_length(namespace: u8) -> Result<f64, GossipError> {
     reject_default_namespace(namespace)?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
@@ -352,7 +675,7 @@ pub fn namespace_data_length(namespace: u8) -> Result<f64, JsValue> {
 }
 
 #[wasm_bindgen(js_name = clearNamespace)]
-pub fn clear_namespace(namespace: u8) -> Result<(), JsValue> {
+pub fn clear_namespace(namespace: u8) -> Result<(), GossipError> {
     reject_default_namespace(namespace)?;
     with_app_state(|app| {
         let mut state = app.state.borrow_mut();
@@ -363,9 +686,7 @@ pub fn clear_namespace(namespace: u8) -> Result<(), JsValue> {
             namespace_states,
             domain,
         } = &mut *state;
-        let session = session
-            .as_ref()
-            .ok_or_else(|| JsValue::from_str("session not unlocked"))?;
+        let session = session.as_ref().ok_or_else(session_locked)?;
         let ns_state = namespace_states.entry(namespace).or_default();
         if ns_state.total_data_length > 0 {
             crate::shrink_session_data(backend, domain, namespace, session, ns_state, 0)
@@ -376,13 +697,13 @@ pub fn clear_namespace(namespace: u8) -> Result<(), JsValue> {
 }
 
 #[wasm_bindgen(js_name = flushEncrypted)]
-pub async fn flush_encrypted() -> Result<(), JsValue> {
-    // We need a &IdbBlockStorage across the .await of persist_dirty().
-    // The IdbBlockStorage lives inside RefCell<EncryptionState>, so we
+pub async fn flush_encrypted() -> Result<(), GossipError> {
+    // We need a &IdbBlockStorage (or &OpfsBlockStorage) across the .await
+    // of persist_dirty(). It lives inside RefCell<EncryptionState>, so we
     // cannot hold a RefCell borrow across .await. Instead we capture a
     // raw pointer under a short borrow.
     //
-    // SAFETY INVARIANT: the pointer targets the IdbBlockStorage inside
+    // SAFETY INVARIANT: the pointer targets the storage inside
     // the leaked AppState (program-lifetime, never deallocated). The
     // Backend enum variant is set once in init_secure_storage and never
     // replaced — no code path reassigns or moves the Backend. On
@@ -392,18 +713,35 @@ pub async fn flush_encrypted() -> Result<(), JsValue> {
     //
     // If this invariant is ever broken (e.g. a future "reinit" feature
     // that swaps backends), this code MUST be revisited.
-    let idb_ptr: Option<*const IdbBlockStorage> = with_app_state(|app| {
+    let (idb_ptr, opfs_ptr): (
+        Option<*const IdbBlockStorage>,
+        Option<*const OpfsBlockStorage>,
+    ) = with_app_state(|app| {
         let state = app.state.borrow();
         let out = match &state.backend {
-            Backend::Idb(idb) => Some(idb as *const _),
-            Backend::Memory(_) => None,
+            Backend::Idb(idb) => (Some(idb as *const _), None),
+            Backend::Opfs(opfs) => (None, Some(opfs as *const _)),
+            Backend::Memory(_) => (None, None),
+            // writes reach the files synchronously
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(_) => (None, None),
         };
         Ok(out)
     })?;
 
     if let Some(ptr) = idb_ptr {
         // SAFETY: see invariant above.
-        unsafe { &*ptr }.persist_dirty().await?;
+        unsafe { &*ptr }
+            .persist_dirty()
+            .await
+            .map_err(backend_err)?;
+    }
+    if let Some(ptr) = opfs_ptr {
+        // SAFETY: see invariant above.
+        unsafe { &*ptr }
+            .persist_dirty()
+            .await
+            .map_err(backend_err)?;
     }
     Ok(())
 }
@@ -427,34 +765,34 @@ const PRAGMAS: &CStr = c"\
 ";
 
 #[wasm_bindgen(js_name = openDatabase)]
-pub fn open_database() -> Result<(), JsValue> {
+pub fn open_database() -> Result<(), GossipError> {
     DB.with(|db| {
         let mut slot = db.borrow_mut();
         if slot.is_some() {
             return Ok(());
         }
         let handle = SafeDb::open(DB_NAME, VFS_NAME_C)
-            .map_err(|e| JsValue::from_str(&format!("SafeDb::open failed: {e}")))?;
+            .map_err(|e| GossipError::sqlite(format!("SafeDb::open failed: {e}")))?;
         handle
             .exec(PRAGMAS)
-            .map_err(|e| JsValue::from_str(&format!("PRAGMA exec failed: {e}")))?;
+            .map_err(|e| GossipError::sqlite(format!("PRAGMA exec failed: {e}")))?;
         *slot = Some(handle);
         Ok(())
     })
 }
 
 #[wasm_bindgen(js_name = closeDatabase)]
-pub fn close_database() -> Result<(), JsValue> {
+pub fn close_database() -> Result<(), GossipError> {
     close_database_and_clear_files()
 }
 
-fn close_database_and_clear_files() -> Result<(), JsValue> {
+fn close_database_and_clear_files() -> Result<(), GossipError> {
     DB.with(|db| {
         // `.take()` replaces the slot with None and drops the old SafeDb
         // synchronously on this line (not at the end of the closure). The
         // Drop impl runs sqlite3_close before we return.
         db.borrow_mut().take();
-        Ok::<(), JsValue>(())
+        Ok::<(), GossipError>(())
     })?;
     with_app_state(|app| {
         app.files.borrow_mut().clear();
@@ -494,20 +832,20 @@ impl ExecResult {
 /// Uint8Array, null, and bigint. Returns rows as a JS array of arrays
 /// (positional column values), matching the Drizzle sqlite-proxy contract.
 #[wasm_bindgen(js_name = execSql)]
-pub fn exec_sql(sql: &str, params: Array) -> Result<ExecResult, JsValue> {
+pub fn exec_sql(sql: &str, params: Array) -> Result<ExecResult, GossipError> {
     DB.with(|db| {
         let slot = db.borrow();
         let safe_db = slot
             .as_ref()
-            .ok_or_else(|| JsValue::from_str("database not open"))?;
+            .ok_or_else(|| GossipError::new("DATABASE_NOT_OPEN", "state", "database not open"))?;
         run_statement(safe_db, sql, &params)
     })
 }
 
-fn run_statement(db: &SafeDb, sql: &str, params: &Array) -> Result<ExecResult, JsValue> {
+fn run_statement(db: &SafeDb, sql: &str, params: &Array) -> Result<ExecResult, GossipError> {
     let stmt_opt = db
         .prepare(sql)
-        .map_err(|e| JsValue::from_str(&format!("prepare failed for sql {sql:?}: {e}")))?;
+        .map_err(|e| GossipError::sqlite(format!("prepare failed for sql {sql:?}: {e}")))?;
 
     let Some(stmt) = stmt_opt else {
         // Empty SQL — return empty result.
@@ -521,7 +859,7 @@ fn run_statement(db: &SafeDb, sql: &str, params: &Array) -> Result<ExecResult, J
     for (i, param) in params.iter().enumerate() {
         let idx = (i + 1) as i32;
         bind_param(&stmt, idx, &param)
-            .map_err(|e| JsValue::from_str(&format!("bind param {idx} failed: {e}")))?;
+            .map_err(|e| GossipError::sqlite(format!("bind param {idx} failed: {e}")))?;
     }
 
     // Step rows
@@ -529,7 +867,7 @@ fn run_statement(db: &SafeDb, sql: &str, params: &Array) -> Result<ExecResult, J
     loop {
         match stmt
             .step()
-            .map_err(|e| JsValue::from_str(&format!("sqlite3_step failed: {e}")))?
+            .map_err(|e| GossipError::sqlite(format!("sqlite3_step failed: {e}")))?
         {
             StepStatus::Row => rows.push(&read_row(&stmt)),
             StepStatus::Done => break,
@@ -620,3 +958,320 @@ fn sql_value_to_js(value: SqlValue) -> JsValue {
         SqlValue::Blob(b) => Uint8Array::from(b.as_slice()).into(),
     }
 }
+
+// ── Storage handle ─────────────────────────────────────────────────
+
+/// Handle on the secure storage of this worker, returned by
+/// `StorageHandle.initStorage`.
+///
+/// The storage is per-thread state (see the note on the globals above): all
+/// handles of a worker share it, and the free exports act on it too. Each
+/// method runs the sequence of exports the JS worker used to chain itself,
+/// and the ones that persist to IndexedDB return a promise that resolves
+/// once the writes are durable.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy)]
+pub struct StorageHandle {
+    _private: (),
+}
+
+#[wasm_bindgen]
+impl StorageHandle {
+    /// Registers the encrypted VFS on the given backend (`"memory"`,
+    /// `"idb"` or `"opfs"`, or `"node:<directory>"` in builds with the
+    /// `wasm-node` feature). Idempotent, like `initSecureStorage`.
+    #[wasm_bindgen(js_name = initStorage)]
+    pub async fn init_storage(
+        domain: String,
+        backend: String,
+    ) -> Result<StorageHandle, GossipError> {
+        init_secure_storage(&domain, &backend).await?;
+        Ok(StorageHandle { _private: () })
+    }
+
+    /// Whether the backend (IndexedDB unless OPFS or Node.js files) already
+    /// holds provisioned storage.
+    #[wasm_bindgen(js_name = hasData, unchecked_return_type = "Promise<boolean>")]
+    pub fn has_data(&self) -> js_sys::Promise {
+        // OPFS files are held by this worker: ask the open backend
+        let file_has_data = with_app_state(|app| match &app.state.borrow().backend {
+            Backend::Opfs(opfs) => opfs.has_stored_data().map(Some).map_err(map_err),
+            #[cfg(feature = "wasm-node")]
+            Backend::Node(node) => node.has_stored_data().map(Some).map_err(map_err),
+            _ => Ok(None),
+        });
+        to_promise(async move {
+            let has_data = match file_has_data? {
+                Some(has_data) => has_data,
+                None => idb_has_data().await?,
+            };
+            Ok(JsValue::from_bool(has_data))
+        })
+    }
+
+    /// Provisions empty storage, erasing every slot.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn provision(&self) -> js_sys::Promise {
+        let provisioned = provision_storage();
+        to_promise(async move {
+            provisioned?;
+            flush_and_resolve().await
+        })
+    }
+
+    /// Creates a session in `slot`, persists it and opens the database on
+    /// it.
+    #[wasm_bindgen(js_name = createSession, unchecked_return_type = "Promise<void>")]
+    pub fn create_session(&self, slot: u8, password: &[u8]) -> js_sys::Promise {
+        let allocated = allocate_session(slot, password);
+        to_promise(async move {
+            allocated?;
+            flush_and_resolve().await?;
+            open_database()?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Same as `createSession`, resolving to the session's one-time
+    /// recovery codes, to show to the user once.
+    #[wasm_bindgen(
+        js_name = createSessionWithRecovery,
+        unchecked_return_type = "Promise<string[]>"
+    )]
+    pub fn create_session_with_recovery(&self, slot: u8, password: &[u8]) -> js_sys::Promise {
+        let allocated = allocate_session_with_recovery(slot, password);
+        to_promise(async move {
+            let codes = allocated?;
+            flush_and_resolve().await?;
+            open_database()?;
+            Ok(codes.into())
+        })
+    }
+
+    /// Unlocks the session `password` opens and opens the database on it.
+    /// Returns `false`, changing nothing, if no slot matches.
+    #[wasm_bindgen(js_name = unlockSession)]
+    pub fn unlock_session(&self, password: &[u8]) -> Result<bool, GossipError> {
+        if !unlock_session(password)? {
+            return Ok(false);
+        }
+        open_database()?;
+        Ok(true)
+    }
+
+    /// Unlocks the session a recovery code opens, persists that the code is
+    /// spent and opens the database on it. Resolves to `false`, changing
+    /// nothing, if no slot matches.
+    #[wasm_bindgen(
+        js_name = unlockWithRecoveryCode,
+        unchecked_return_type = "Promise<boolean>"
+    )]
+    pub fn unlock_with_recovery_code(&self, code: &str) -> js_sys::Promise {
+        let unlocked = unlock_with_recovery_code(code);
+        to_promise(async move {
+            if !unlocked? {
+                return Ok(JsValue::FALSE);
+            }
+            flush_and_resolve().await?;
+            open_database()?;
+            Ok(JsValue::TRUE)
+        })
+    }
+
+    /// Replaces the recovery codes of the unlocked session and persists
+    /// them. Resolves to the new codes; the previous ones stop working.
+    #[wasm_bindgen(
+        js_name = regenerateRecoveryCodes,
+        unchecked_return_type = "Promise<string[]>"
+    )]
+    pub fn regenerate_recovery_codes(&self) -> js_sys::Promise {
+        let regenerated = regenerate_recovery_codes();
+        to_promise(async move {
+            let codes = regenerated?;
+            flush_and_resolve().await?;
+            Ok(codes.into())
+        })
+    }
+
+    /// Closes the database, persists pending writes and locks the session.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn lock(&self) -> js_sys::Promise {
+        let closed = close_database();
+        to_promise(async move {
+            closed?;
+            flush_and_resolve().await?;
+            lock_session()?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Reads `len` bytes at `offset` of a namespace of the unlocked session.
+    #[wasm_bindgen(js_name = readData)]
+    pub fn read_data(
+        &self,
+        namespace: u8,
+        offset: f64,
+        len: usize,
+    ) -> Result<Vec<u8>, GossipError> {
+        read_namespace_data(namespace, offset, len)
+    }
+
+    /// Writes `data` at `offset` of a namespace of the unlocked session. The
+    /// write is durable once `flushData` resolves.
+    #[wasm_bindgen(js_name = writeData)]
+    pub fn write_data(&self, namespace: u8, offset: f64, data: &[u8]) -> Result<(), GossipError> {
+        write_namespace_data(namespace, offset, data)
+    }
+
+    /// Length of the data in a namespace of the unlocked session.
+    #[wasm_bindgen(js_name = dataLength)]
+    pub fn data_length(&self, namespace: u8) -> Result<f64, GossipError> {
+        namespace_data_length(namespace)
+    }
+
+    /// Persists pending writes to the backend.
+    #[wasm_bindgen(js_name = flushData, unchecked_return_type = "Promise<void>")]
+    pub fn flush_data(&self) -> js_sys::Promise {
+        to_promise(flush_and_resolve())
+    }
+}
+
+// ── Leases ─────────────────────────────────────────────────────────
+
+/// Lease making this tab the single writer of the storage, negotiated with
+/// the other tabs of the origin through IndexedDB.
+///
+/// `StorageLease.acquire` grants the lease if it is free, or expired
+/// because its holder stopped renewing it; otherwise it rejects with
+/// `LEASE_HELD`. Renew it well within its duration as a heartbeat, and stop
+/// writing as soon as `renew` rejects with `LEASE_HELD`: another tab took
+/// over. `release` hands it over at once instead of after the timeout.
+///
+/// ```javascript
+/// const lease = await StorageLease.acquire("writer", 10_000);
+/// const heartbeat = setInterval(() => {
+///   lease.renew().catch(() => {
+///     clearInterval(heartbeat);
+///     becomeReadOnly();
+///   });
+/// }, 3_000);
+/// addEventListener("pagehide", () => lease.release());
+/// ```
+#[wasm_bindgen]
+pub struct StorageLease {
+    name: String,
+    holder: LeaseHolder,
+    duration_millis: u64,
+    expires_at_millis: Rc<Cell<u64>>,
+}
+
+#[wasm_bindgen]
+impl StorageLease {
+    /// Acquires the lease `name` for `duration_millis`, with a fresh holder
+    /// identity: a second `acquire` from the same tab is refused too.
+    pub async fn acquire(name: String, duration_millis: f64) -> Result<StorageLease, GossipError> {
+        let duration_millis = safe_f64_to_u64(duration_millis)
+            .filter(|&duration| duration > 0)
+            .ok_or_else(|| GossipError::invalid_argument("invalid lease duration"))?;
+        let holder = generate_lease_holder();
+        let expires_at_millis = write_lease(&name, |current, now| {
+            acquire_lease(current, &holder, now, duration_millis)
+        })
+        .await?;
+        Ok(StorageLease {
+            name,
+            holder,
+            duration_millis,
+            expires_at_millis: Rc::new(Cell::new(expires_at_millis)),
+        })
+    }
+
+    /// Extends the lease by its duration from now. Rejects with
+    /// `LEASE_HELD` if it was released or taken over; a late renewal still
+    /// succeeds as long as no other tab acquired the lease meanwhile.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn renew(&self) -> js_sys::Promise {
+        let name = self.name.clone();
+        let holder = self.holder;
+        let duration_millis = self.duration_millis;
+        let expires_at_millis = Rc::clone(&self.expires_at_millis);
+        to_promise(async move {
+            let renewed = write_lease(&name, |current, now| {
+                renew_lease(current, &holder, now, duration_millis)
+            })
+            .await?;
+            expires_at_millis.set(renewed);
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Releases the lease, if still held, so another tab can acquire it
+    /// right away.
+    #[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+    pub fn release(&self) -> js_sys::Promise {
+        let name = self.name.clone();
+        let holder = self.holder;
+        let expires_at_millis = Rc::clone(&self.expires_at_millis);
+        to_promise(async move {
+            update_lease(&name, |current| {
+                Ok(if release_lease(current, &holder) {
+                    LeaseWrite::Delete
+                } else {
+                    LeaseWrite::Keep
+                })
+            })
+            .await
+            .map_err(backend_err)?
+            .map_err(map_err)?;
+            expires_at_millis.set(0);
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// Name of the lease.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// When the lease expires unless renewed, in milliseconds since the Unix
+    /// epoch, as of the latest successful call; 0 once released.
+    #[wasm_bindgen(getter, js_name = expiresAt)]
+    pub fn expires_at(&self) -> f64 {
+        self.expires_at_millis.get() as f64
+    }
+}
+
+/// Stores the lease record `decide` returns for the current one and the
+/// time, and returns its expiry.
+async fn write_lease(
+    name: &str,
+    decide: impl FnOnce(Option<&LeaseRecord>, u64) -> crate::Result<LeaseRecord>,
+) -> Result<u64, GossipError> {
+    let now = SystemClock.now_millis() as u64;
+    let mut expires_at_millis = 0;
+    update_lease(name, |current| {
+        let record = decide(current, now)?;
+        expires_at_millis = record.expires_at_millis;
+        Ok(LeaseWrite::Put(record))
+    })
+    .await
+    .map_err(backend_err)?
+    .map_err(map_err)?;
+    Ok(expires_at_millis)
+}
+
+async fn flush_and_resolve() -> Result<JsValue, GossipError> {
+    flush_encrypted().await?;
+    Ok(JsValue::UNDEFINED)
+}
+
+/// Runs `future` as a JS promise rejecting with its [`GossipError`].
+///
+/// `StorageHandle` methods return promises rather than being `async` since
+/// an exported `async fn` cannot borrow `self`.
+fn to_promise(
+    future: impl std::future::Future<Output = Result<JsValue, GossipError>> + 'static,
+) -> js_sys::Promise {
+    wasm_bindgen_futures::future_to_promise(async move { future.await.map_err(JsValue::from) })
+}
@@ -0,0 +1,251 @@
+//! Statistical enumeration-resistance checks for secureStorage.
+//!
+//! An observer holding a storage snapshot must not be able to tell which
+//! slots hold a live session, nor how much data each one stores. These tests
+//! build storages with varied session layouts and compare the ciphertext of
+//! every slot against every other slot with simple statistics (byte
+//! histograms and lag-1 autocorrelation). They do not assume the ciphertext
+//! is uniform — only that all slots are drawn from the same distribution —
+//! so they stay valid for any block format and act as a regression gate
+//! whenever the format changes.
+//!
+//! Thresholds are set around p ≈ 1e-6 so the suite does not flake.
+
+use secureStorage::storage::{BlockStorage, KeypairStorage, MemoryStorage};
+use secureStorage::{
+    DEFAULT_NAMESPACE, NamespaceState, PLAINTEXT_SIZE, SESSION_COUNT, SessionIndex,
+    allocate_session, cover_traffic_tick, get_global_block_count, provision_storage,
+    write_session_data,
+};
+
+const DOMAIN: &str = "enum-test";
+const NS: u8 = DEFAULT_NAMESPACE;
+
+/// Chi-square critical value for 255 degrees of freedom at p ≈ 1e-6.
+const CHI2_255_CRITICAL: f64 = 380.0;
+
+/// Number of standard errors tolerated for autocorrelation comparisons.
+const Z_CRITICAL: f64 = 5.0;
+
+/// PQ crypto operations need large stack frames; run every test on a 4 MiB thread.
+fn run<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(4 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+mod stats {
+    /// Byte-value histogram.
+    pub fn histogram(bytes: &[u8]) -> [u64; 256] {
+        let mut h = [0u64; 256];
+        for &b in bytes {
+            h[b as usize] += 1;
+        }
+        h
+    }
+
+    /// Two-sample chi-square homogeneity statistic over byte histograms.
+    ///
+    /// Tests whether both samples come from the same (unknown) distribution.
+    /// Empty bins are skipped. Samples may have different totals.
+    pub fn homogeneity_chi2(a: &[u64; 256], b: &[u64; 256]) -> f64 {
+        let na: u64 = a.iter().sum();
+        let nb: u64 = b.iter().sum();
+        let (na, nb) = (na as f64, nb as f64);
+        let ka = (nb / na).sqrt();
+        let kb = (na / nb).sqrt();
+        a.iter()
+            .zip(b.iter())
+            .filter(|(x, y)| **x + **y > 0)
+            .map(|(&x, &y)| {
+                let d = ka * x as f64 - kb * y as f64;
+                d * d / (x + y) as f64
+            })
+            .sum()
+    }
+
+    /// Lag-1 autocorrelation coefficient of a byte sequence.
+    pub fn lag1_autocorrelation(bytes: &[u8]) -> f64 {
+        let n = bytes.len() as f64;
+        let mean = bytes.iter().map(|&b| f64::from(b)).sum::<f64>() / n;
+        let var = bytes
+            .iter()
+            .map(|&b| (f64::from(b) - mean).powi(2))
+            .sum::<f64>();
+        let cov = bytes
+            .windows(2)
+            .map(|w| (f64::from(w[0]) - mean) * (f64::from(w[1]) - mean))
+            .sum::<f64>();
+        cov / var
+    }
+}
+
+/// Concatenate every block of a slot's blockstream.
+fn slot_bytes(storage: &MemoryStorage, slot: SessionIndex) -> Vec<u8> {
+    let count = storage.block_count(slot, NS).unwrap();
+    let mut out = Vec::new();
+    for b in 0..count {
+        out.extend_from_slice(&storage.read_block(slot, NS, b).unwrap()[..]);
+    }
+    out
+}
+
+/// Build a storage where slot `i` holds `sizes[i]` bytes of user data, or is
+/// left provisioned-only when `sizes[i]` is `None`.
+fn build_storage(sizes: [Option<usize>; SESSION_COUNT]) -> MemoryStorage {
+    let mut storage = MemoryStorage::new();
+    provision_storage(&mut storage).unwrap();
+
+    for (i, size) in sizes.iter().enumerate() {
+        let Some(size) = size else { continue };
+        let slot = SessionIndex::new(i as u8).unwrap();
+        let password = format!("password-{i}");
+        let session = allocate_session(&mut storage, DOMAIN, slot, password.as_bytes()).unwrap();
+        let mut ns_state = NamespaceState::empty();
+        // Low-entropy plaintext makes any leak through the ciphertext obvious.
+        let data = vec![i as u8; *size];
+        write_session_data(&mut storage, DOMAIN, NS, &session, &mut ns_state, 0, &data).unwrap();
+    }
+    storage
+}
+
+/// Assert all slots are pairwise indistinguishable under the statistics above.
+fn assert_slots_indistinguishable(storage: &MemoryStorage) {
+    let slots: Vec<Vec<u8>> = (0..SESSION_COUNT as u8)
+        .map(|i| slot_bytes(storage, SessionIndex::new(i).unwrap()))
+        .collect();
+
+    // Lengths: every blockstream must have the same size.
+    for (i, bytes) in slots.iter().enumerate() {
+        assert_eq!(
+            bytes.len(),
+            slots[0].len(),
+            "slot {i} blockstream length differs from slot 0"
+        );
+    }
+
+    let histograms: Vec<[u64; 256]> = slots.iter().map(|s| stats::histogram(s)).collect();
+    let autocorr: Vec<f64> = slots
+        .iter()
+        .map(|s| stats::lag1_autocorrelation(s))
+        .collect();
+    let n = slots[0].len() as f64;
+    // Standard error of the difference of two lag-1 autocorrelations.
+    let autocorr_tolerance = Z_CRITICAL * (2.0 / n).sqrt();
+
+    for i in 0..SESSION_COUNT {
+        for j in (i + 1)..SESSION_COUNT {
+            let chi2 = stats::homogeneity_chi2(&histograms[i], &histograms[j]);
+            assert!(
+                chi2 < CHI2_255_CRITICAL,
+                "byte histograms of slots {i} and {j} differ (chi2 = {chi2:.1})"
+            );
+
+            let diff = (autocorr[i] - autocorr[j]).abs();
+            assert!(
+                diff < autocorr_tolerance,
+                "autocorrelation of slots {i} and {j} differs by {diff:.5} \
+                 (tolerance {autocorr_tolerance:.5})"
+            );
+        }
+    }
+}
+
+/// Provisioned-only storage: every slot is cover data.
+#[test]
+fn provisioned_slots_indistinguishable() {
+    run(|| {
+        let mut storage = build_storage([None; SESSION_COUNT]);
+        // Give the empty blockstreams some cover blocks to compare.
+        let slot = SessionIndex::new(0).unwrap();
+        let session = allocate_session(&mut storage, DOMAIN, slot, b"pw").unwrap();
+        let mut ns_state = NamespaceState::empty();
+        let data = vec![0u8; PLAINTEXT_SIZE * 2];
+        write_session_data(&mut storage, DOMAIN, NS, &session, &mut ns_state, 0, &data).unwrap();
+        assert_slots_indistinguishable(&storage);
+    });
+}
+
+/// One live session with several blocks of data next to unused slots.
+#[test]
+fn single_live_session_indistinguishable_from_cover() {
+    run(|| {
+        let mut sizes = [None; SESSION_COUNT];
+        sizes[1] = Some(PLAINTEXT_SIZE * 3 + 17);
+        let storage = build_storage(sizes);
+        assert!(get_global_block_count(&storage, NS).unwrap() >= 4);
+        assert_slots_indistinguishable(&storage);
+    });
+}
+
+/// Every slot live with very different data sizes — the small sessions must
+/// not reveal that most of their blocks are padding.
+#[test]
+fn varied_data_sizes_indistinguishable() {
+    run(|| {
+        let mut sizes = [None; SESSION_COUNT];
+        let per_slot = [1, PLAINTEXT_SIZE / 2, PLAINTEXT_SIZE * 4];
+        for (i, size) in sizes.iter_mut().enumerate() {
+            *size = Some(per_slot[i % per_slot.len()]);
+        }
+        let storage = build_storage(sizes);
+        assert_slots_indistinguishable(&storage);
+    });
+}
+
+/// Cover traffic must keep slots indistinguishable.
+#[test]
+fn cover_traffic_keeps_slots_indistinguishable() {
+    run(|| {
+        let mut sizes = [None; SESSION_COUNT];
+        sizes[0] = Some(PLAINTEXT_SIZE * 2);
+        let mut storage = build_storage(sizes);
+        for _ in 0..20 {
+            cover_traffic_tick(&mut storage, DOMAIN, NS).unwrap();
+        }
+        assert_slots_indistinguishable(&storage);
+    });
+}
+
+/// Keypair files of live and provisioned-only slots share the same byte
+/// distribution, not just the same length.
+#[test]
+fn keypair_files_indistinguishable() {
+    run(|| {
+        let mut sizes = [None; SESSION_COUNT];
+        sizes[0] = Some(1);
+        let storage = build_storage(sizes);
+
+        // Skip the 4-byte version header; it is compared for length only.
+        let files: Vec<Vec<u8>> = (0..SESSION_COUNT as u8)
+            .map(|i| storage.read_keypair(SessionIndex::new(i).unwrap()).unwrap()[4..].to_vec())
+            .collect();
+        for i in 1..SESSION_COUNT {
+            assert_eq!(files[i].len(), files[0].len());
+            let chi2 =
+                stats::homogeneity_chi2(&stats::histogram(&files[0]), &stats::histogram(&files[i]));
+            assert!(
+                chi2 < CHI2_255_CRITICAL,
+                "keypair files of slots 0 and {i} differ (chi2 = {chi2:.1})"
+            );
+        }
+    });
+}
+
+/// Sanity check: the harness must flag an obviously distinguishable slot.
+#[test]
+fn harness_detects_plaintext_leak() {
+    let random: Vec<u8> = (0..65536u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+    let leaky: Vec<u8> = random
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| if i % 8 == 0 { 0 } else { b })
+        .collect();
+    let chi2 = stats::homogeneity_chi2(&stats::histogram(&random), &stats::histogram(&leaky));
+    assert!(chi2 > CHI2_255_CRITICAL, "chi2 = {chi2:.1}");
+}
@@ -0,0 +1,210 @@
+//! Handshake, messaging and persistence throughput of `SessionManager`.
+//!
+//! Run with `cargo bench -p sessions`.
+
+use auth::{StaticRootSecret, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret};
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group};
+use sessions::{OutgoingInitiationRequest, SessionManager, SessionManagerConfig};
+
+/// Peer counts of the persistence benchmarks.
+const PEER_COUNTS: &[usize] = &[10, 100, 1000];
+
+/// Announcements per batch feeding benchmark.
+const BATCH_SIZE: usize = 16;
+
+/// Keys from a random root secret, skipping the password KDF.
+fn keypair() -> (UserPublicKeys, UserSecretKeys) {
+    let mut root_secret = [0u8; 32];
+    crypto_rng::fill_buffer(&mut root_secret);
+    derive_keys_from_static_root_secret(&StaticRootSecret::from_bytes(root_secret))
+}
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: u128::MAX,
+        max_incoming_message_age_millis: u128::MAX,
+        max_session_inactivity_millis: u128::MAX,
+        keep_alive_interval_millis: 60_000,
+        max_session_lag_length: 100,
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+/// A user and their session manager.
+struct User {
+    pk: UserPublicKeys,
+    sk: UserSecretKeys,
+    manager: SessionManager,
+}
+
+impl User {
+    fn new() -> Self {
+        let (pk, sk) = keypair();
+        Self {
+            pk,
+            sk,
+            manager: SessionManager::new(config()),
+        }
+    }
+
+    /// Establishes an active session between both users.
+    fn handshake(&mut self, peer: &mut User) {
+        let to_peer = self
+            .manager
+            .establish_outgoing_session(&peer.pk, &self.pk, &self.sk, vec![])
+            .unwrap();
+        let to_self = peer
+            .manager
+            .establish_outgoing_session(&self.pk, &peer.pk, &peer.sk, vec![])
+            .unwrap();
+        peer.manager
+            .feed_incoming_announcement(&to_peer, &peer.pk, &peer.sk)
+            .unwrap();
+        self.manager
+            .feed_incoming_announcement(&to_self, &self.pk, &self.sk)
+            .unwrap();
+    }
+}
+
+fn bench_announcements(c: &mut Criterion) {
+    let (alice_pk, alice_sk) = keypair();
+    let (bob_pk, bob_sk) = keypair();
+
+    let mut group = c.benchmark_group("announcement");
+    group.bench_function("create", |b| {
+        b.iter(|| OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]))
+    });
+    let (announcement, _) = OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
+    group.bench_function("parse", |b| {
+        b.iter_batched(
+            || SessionManager::new(config()),
+            |mut manager| {
+                manager
+                    .feed_incoming_announcement(&announcement, &bob_pk, &bob_sk)
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_session_establishment(c: &mut Criterion) {
+    c.bench_function("session/establish", |b| {
+        b.iter_batched(
+            || (User::new(), User::new()),
+            |(mut alice, mut bob)| alice.handshake(&mut bob),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_round_trip(c: &mut Criterion) {
+    let mut alice = User::new();
+    let mut bob = User::new();
+    alice.handshake(&mut bob);
+    let alice_id = alice.pk.derive_id();
+    let bob_id = bob.pk.derive_id();
+
+    let mut group = c.benchmark_group("message");
+    for size in [64, 4096] {
+        let message = vec![0x42u8; size];
+        group.throughput(Throughput::Bytes(2 * size as u64));
+        group.bench_with_input(BenchmarkId::new("round_trip", size), &message, |b, m| {
+            // each side answers, so that sessions stay within their lag budget
+            b.iter(|| {
+                let sent = alice.manager.send_message(&bob_id, m).unwrap();
+                bob.manager
+                    .feed_incoming_message_board_read(&sent.seeker, &sent.data, &bob.sk)
+                    .unwrap();
+                let reply = bob.manager.send_message(&alice_id, m).unwrap();
+                alice
+                    .manager
+                    .feed_incoming_message_board_read(&reply.seeker, &reply.data, &alice.sk)
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_batch_announcements(c: &mut Criterion) {
+    let (alice_pk, alice_sk) = keypair();
+    let announcements: Vec<Vec<u8>> = (0..BATCH_SIZE)
+        .map(|_| {
+            let (peer_pk, peer_sk) = keypair();
+            OutgoingInitiationRequest::new(&peer_pk, &peer_sk, &alice_pk, vec![]).0
+        })
+        .collect();
+    let announcements: Vec<&[u8]> = announcements.iter().map(Vec::as_slice).collect();
+
+    let mut group = c.benchmark_group("announcement_batch");
+    group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+    group.bench_function("sequential", |b| {
+        b.iter_batched(
+            || SessionManager::new(config()),
+            |mut manager| {
+                for announcement in &announcements {
+                    manager
+                        .feed_incoming_announcement(announcement, &alice_pk, &alice_sk)
+                        .unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("parallel", |b| {
+        b.iter_batched(
+            || SessionManager::new(config()),
+            |mut manager| {
+                manager.feed_incoming_announcements_parallel(&announcements, &alice_pk, &alice_sk)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_persistence(c: &mut Criterion) {
+    let key = crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE]);
+
+    let mut group = c.benchmark_group("to_encrypted_blob");
+    group.sample_size(10);
+    for &peer_count in PEER_COUNTS {
+        let mut alice = User::new();
+        for _ in 0..peer_count {
+            alice.handshake(&mut User::new());
+        }
+        group.throughput(Throughput::Elements(peer_count as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(peer_count),
+            &alice.manager,
+            |b, manager| b.iter(|| manager.to_encrypted_blob(&key).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_announcements,
+    bench_session_establishment,
+    bench_round_trip,
+    bench_batch_announcements,
+    bench_persistence
+);
+
+/// PQ crypto operations need large stack frames; run on a 16 MiB thread.
+fn main() {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(|| {
+            benches();
+            Criterion::default().configure_from_args().final_summary();
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
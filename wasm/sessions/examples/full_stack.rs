@@ -0,0 +1,124 @@
+//! Two users talking over an in-memory board, with their session state kept
+//! in secure storage across a lock and an unlock.
+//!
+//! Run with `cargo run -p sessions --example full_stack --features secure-storage`.
+
+use std::collections::HashMap;
+
+use auth::{StaticRootSecret, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret};
+use secure_storage::storage::MemoryStorage;
+use secure_storage::{SessionIndex, allocate_session, provision_storage, unlock_session};
+use sessions::{SessionManager, SessionManagerConfig, SessionStore};
+
+const DOMAIN: &str = "full-stack-example";
+const NAMESPACE: u8 = 3;
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+fn keys(name: &str) -> (UserPublicKeys, UserSecretKeys) {
+    derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(name.as_bytes()))
+}
+
+/// Reads every message the manager expects from the board, printing them.
+fn read_board(
+    name: &str,
+    manager: &mut SessionManager,
+    sk: &UserSecretKeys,
+    board: &HashMap<Vec<u8>, Vec<u8>>,
+) {
+    for seeker in manager.get_message_board_read_keys() {
+        if let Some(data) = board.get(&seeker)
+            && let Some(msg) = manager.feed_incoming_message_board_read(&seeker, data, sk)
+            && !msg.message.is_empty()
+        {
+            println!(
+                "{name} received {:?}",
+                String::from_utf8_lossy(&msg.message)
+            );
+        }
+    }
+}
+
+fn main() {
+    let (alice_pk, alice_sk) = keys("alice");
+    let (bob_pk, bob_sk) = keys("bob");
+    let alice_id = alice_pk.derive_id();
+    let bob_id = bob_pk.derive_id();
+
+    // announcements and messages of the on-chain board
+    let mut announcements: Vec<Vec<u8>> = Vec::new();
+    let mut board: HashMap<Vec<u8>, Vec<u8>> = HashMap::new();
+
+    let mut alice = SessionManager::new(config());
+    let mut bob = SessionManager::new(config());
+
+    announcements.push(
+        alice
+            .establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![])
+            .expect("alice announces"),
+    );
+    announcements.push(
+        bob.establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![])
+            .expect("bob answers"),
+    );
+    for announcement in &announcements {
+        alice.feed_incoming_announcement(announcement, &alice_pk, &alice_sk);
+        bob.feed_incoming_announcement(announcement, &bob_pk, &bob_sk);
+    }
+    println!("alice sees bob as {:?}", alice.peer_session_status(&bob_id));
+
+    let sent = alice
+        .send_message(&bob_id, b"hello bob")
+        .expect("session is active");
+    board.insert(sent.seeker.clone(), sent.data.clone());
+    read_board("bob", &mut bob, &bob_sk, &board);
+
+    // bob saves his state into secure storage and locks it
+    let password = b"bob password";
+    let key = crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE]);
+    let mut storage = MemoryStorage::new();
+    provision_storage(&mut storage).expect("storage provisioned");
+    let session = allocate_session(
+        &mut storage,
+        DOMAIN,
+        SessionIndex::new(0).unwrap(),
+        password,
+    )
+    .expect("session allocated");
+    let mut store =
+        SessionStore::open(&storage, DOMAIN, &session, NAMESPACE).expect("store opened");
+    store
+        .save(&mut storage, DOMAIN, &session, &mut bob, &key)
+        .expect("state saved");
+    drop(session);
+    drop(bob);
+    println!("bob locked his storage");
+
+    // alice keeps writing meanwhile
+    let sent = alice
+        .send_message(&bob_id, b"are you there?")
+        .expect("session is active");
+    board.insert(sent.seeker.clone(), sent.data.clone());
+
+    // bob unlocks and resumes
+    let session = unlock_session(&storage, DOMAIN, password).expect("storage unlocked");
+    let store = SessionStore::open(&storage, DOMAIN, &session, NAMESPACE).expect("store opened");
+    let mut bob = store
+        .load(&storage, DOMAIN, &session, &key)
+        .expect("state readable")
+        .expect("state saved before");
+    println!("bob unlocked his storage");
+    read_board("bob", &mut bob, &bob_sk, &board);
+
+    let sent = bob
+        .send_message(&alice_id, b"back again")
+        .expect("session is active");
+    board.insert(sent.seeker.clone(), sent.data.clone());
+    read_board("alice", &mut alice, &alice_sk, &board);
+}
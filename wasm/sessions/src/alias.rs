@@ -0,0 +1,90 @@
+//! Alias identities: identities of our own, one per peer.
+//!
+//! A peer contacted under an alias only learns the alias, so two peers
+//! comparing notes cannot tell they talk to the same user. Each alias comes
+//! from a random seed, not from our long-term keys, so aliases stay
+//! unlinkable even if those keys leak, and survive identity rotations.
+
+use crate::collections::HashMap;
+use alloc::boxed::Box;
+use auth::{StaticRootSecret, UserId, UserPublicKeys, UserSecretKeys};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Keys of an alias, derived from its seed.
+type AliasKeys = Box<(UserPublicKeys, UserSecretKeys)>;
+
+/// The aliases we use, by the peer they are used with.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Aliases {
+    seeds: HashMap<UserId, [u8; 32]>,
+    /// Keys derived from the seeds; not persisted
+    #[serde(skip)]
+    keys: HashMap<UserId, AliasKeys>,
+}
+
+impl Aliases {
+    /// Returns whether we use an alias with the peer.
+    pub(crate) fn contains(&self, peer_id: &UserId) -> bool {
+        self.seeds.contains_key(peer_id)
+    }
+
+    /// Creates a new alias for the peer, replacing any previous one.
+    pub(crate) fn create(&mut self, peer_id: &UserId) {
+        let mut seed = [0u8; 32];
+        crypto_rng::fill_buffer(&mut seed);
+        self.remove(peer_id);
+        self.seeds.insert(peer_id.clone(), seed);
+    }
+
+    /// Stops using an alias with the peer.
+    pub(crate) fn remove(&mut self, peer_id: &UserId) {
+        if let Some(mut seed) = self.seeds.remove(peer_id) {
+            seed.zeroize();
+        }
+        self.keys.remove(peer_id);
+    }
+
+    /// Keeps the alias of a peer that moved to a new identity.
+    pub(crate) fn rename(&mut self, peer_id: &UserId, new_peer_id: &UserId) {
+        if let Some(seed) = self.seeds.remove(peer_id) {
+            self.seeds.insert(new_peer_id.clone(), seed);
+        }
+        if let Some(keys) = self.keys.remove(peer_id) {
+            self.keys.insert(new_peer_id.clone(), keys);
+        }
+    }
+
+    /// Derives the keys of every alias that were not derived yet.
+    pub(crate) fn derive_keys(&mut self) {
+        for (peer_id, seed) in &self.seeds {
+            if !self.keys.contains_key(peer_id) {
+                let root_secret = StaticRootSecret::from_bytes(*seed);
+                let keys = auth::derive_keys_from_static_root_secret(&root_secret);
+                self.keys.insert(peer_id.clone(), Box::new(keys));
+            }
+        }
+    }
+
+    /// Returns the keys of the alias used with the peer, once derived with
+    /// [`derive_keys`](Self::derive_keys).
+    pub(crate) fn keys(&self, peer_id: &UserId) -> Option<(&UserPublicKeys, &UserSecretKeys)> {
+        self.keys.get(peer_id).map(|keys| (&keys.0, &keys.1))
+    }
+
+    /// Iterates over the derived aliases, with the peer each one is used with.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&UserId, &UserPublicKeys, &UserSecretKeys)> {
+        self.keys
+            .iter()
+            .map(|(peer_id, keys)| (peer_id, &keys.0, &keys.1))
+    }
+
+    /// Forgets every alias.
+    pub(crate) fn clear(&mut self) {
+        for seed in self.seeds.values_mut() {
+            seed.zeroize();
+        }
+        self.seeds.clear();
+        self.keys.clear();
+    }
+}
@@ -0,0 +1,144 @@
+//! Recently seen announcements.
+//!
+//! Boards are re-scanned, so the same announcement bytes reach
+//! `feed_incoming_announcement` many times. Announcements are keyed by the
+//! hash of their bytes, since the announcer is only known after decryption,
+//! so duplicates are rejected before any cryptographic work.
+
+use crate::collections::HashMap;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct AnnouncementCache {
+    /// Digest of each cached announcement, with the time it was seen
+    seen: HashMap<[u8; 32], u128>,
+    /// Digests in insertion order, for eviction
+    order: VecDeque<[u8; 32]>,
+}
+
+impl AnnouncementCache {
+    pub(crate) fn digest(announcement_bytes: &[u8]) -> [u8; 32] {
+        *massa_hash::Hash::compute_from(announcement_bytes).to_bytes()
+    }
+
+    /// Returns true if the digest was seen less than `max_age_millis` ago.
+    pub(crate) fn contains(&self, digest: &[u8; 32], now: u128, max_age_millis: u128) -> bool {
+        self.seen
+            .get(digest)
+            .is_some_and(|seen_at| now.saturating_sub(*seen_at) < max_age_millis)
+    }
+
+    /// Records a digest, evicting expired entries and the oldest ones beyond
+    /// `capacity`.
+    pub(crate) fn insert(
+        &mut self,
+        digest: [u8; 32],
+        now: u128,
+        capacity: usize,
+        max_age_millis: u128,
+    ) {
+        if capacity == 0 {
+            return;
+        }
+        if self.seen.insert(digest, now).is_none() {
+            self.order.push_back(digest);
+        }
+        while let Some(oldest) = self.order.front() {
+            let expired = self
+                .seen
+                .get(oldest)
+                .is_none_or(|seen_at| now.saturating_sub(*seen_at) >= max_age_millis);
+            if !expired && self.order.len() <= capacity {
+                break;
+            }
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+    }
+
+    /// Adds the entries of another cache, e.g. one saved after this state,
+    /// keeping the latest time each digest was seen.
+    pub(crate) fn merge(
+        &mut self,
+        other: AnnouncementCache,
+        now: u128,
+        capacity: usize,
+        max_age_millis: u128,
+    ) {
+        for (digest, seen_at) in other.seen {
+            let entry = self.seen.entry(digest).or_insert(seen_at);
+            *entry = (*entry).max(seen_at);
+        }
+        let mut entries: Vec<([u8; 32], u128)> = self.seen.drain().collect();
+        entries.sort_by_key(|(_, seen_at)| *seen_at);
+        self.order.clear();
+        let keep = entries
+            .iter()
+            .filter(|(_, seen_at)| now.saturating_sub(*seen_at) < max_age_millis)
+            .count()
+            .min(capacity);
+        for (digest, seen_at) in entries.into_iter().rev().take(keep).rev() {
+            self.seen.insert(digest, seen_at);
+            self.order.push_back(digest);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that entries expire and the cache stays within capacity
+    #[test]
+    fn test_eviction() {
+        let mut cache = AnnouncementCache::default();
+        let a = AnnouncementCache::digest(b"a");
+        let b = AnnouncementCache::digest(b"b");
+        let c = AnnouncementCache::digest(b"c");
+
+        cache.insert(a, 0, 2, 100);
+        cache.insert(b, 10, 2, 100);
+        assert!(cache.contains(&a, 50, 100));
+        assert!(!cache.contains(&a, 100, 100));
+
+        // capacity evicts the oldest
+        cache.insert(c, 20, 2, 100);
+        assert!(!cache.contains(&a, 20, 100));
+        assert!(cache.contains(&b, 20, 100));
+        assert!(cache.contains(&c, 20, 100));
+
+        // age evicts on insert
+        cache.insert(a, 115, 2, 100);
+        assert_eq!(cache.order.len(), 2);
+        assert!(!cache.seen.contains_key(&b));
+    }
+
+    /// Tests that merging keeps the most recent entries within bounds
+    #[test]
+    fn test_merge() {
+        let a = AnnouncementCache::digest(b"a");
+        let b = AnnouncementCache::digest(b"b");
+        let c = AnnouncementCache::digest(b"c");
+
+        let mut restored = AnnouncementCache::default();
+        restored.insert(a, 0, 2, 100);
+        let mut saved = AnnouncementCache::default();
+        saved.insert(a, 30, 2, 100);
+        saved.insert(b, 40, 2, 100);
+        saved.insert(c, 50, 2, 100);
+
+        restored.merge(saved, 50, 2, 100);
+        assert_eq!(restored.order.len(), 2);
+        assert!(restored.contains(&b, 120, 100));
+        assert!(restored.contains(&c, 120, 100));
+        assert!(!restored.contains(&a, 50, 100));
+    }
+}
@@ -0,0 +1,7 @@
+//! Hash maps and sets of the standard library, or of `hashbrown` without
+//! `std`.
+
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{HashMap, HashSet};
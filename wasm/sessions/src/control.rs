@@ -0,0 +1,87 @@
+//! Protocol traffic carried inside session messages.
+//!
+//! Control messages travel encrypted like user messages, marked in the message
+//! header with a `u16` kind so they never reach applications as payloads.
+//! Receivers skip kinds they do not know, so new kinds can be added without
+//! closing sessions with peers running this version or later. Keep-alives stay
+//! empty user messages on the wire, for older peers, and are reported as
+//! [`ControlMessage::KeepAlive`].
+
+use alloc::vec::Vec;
+
+use crate::transfer::MessagePart;
+
+/// Protocol traffic received in place of a user payload, set in
+/// [`FeedIncomingMessageOutput::control`](crate::FeedIncomingMessageOutput::control).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ControlMessage {
+    /// Empty message sent by the peer to keep the session alive
+    KeepAlive,
+    /// Liveness probe, answered automatically
+    Ping,
+    /// Answer to one of our pings
+    Pong,
+    /// The peer read our messages up to this message ID
+    ReadReceipt { up_to_message_id: u64 },
+    /// The peer closed the session with `SessionManager::close_session`
+    Close,
+    /// Control message of a kind introduced by a later version, ignored
+    Unknown { kind: u16 },
+}
+
+/// Control payload handled by the crate, with its wire kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ControlPayload {
+    Ping { nonce: u64 },
+    Pong { nonce: u64 },
+    ReadReceipt { up_to_message_id: u64 },
+    Close,
+}
+
+impl ControlPayload {
+    const PING: u16 = 1;
+    const PONG: u16 = 2;
+    const READ_RECEIPT: u16 = 3;
+    pub(crate) const CLOSE: u16 = 4;
+
+    /// Returns the message header and contents carrying the payload.
+    pub(crate) fn encode(&self) -> (MessagePart, Vec<u8>) {
+        let (kind, contents) = match *self {
+            Self::Ping { nonce } => (Self::PING, nonce.to_le_bytes().to_vec()),
+            Self::Pong { nonce } => (Self::PONG, nonce.to_le_bytes().to_vec()),
+            Self::ReadReceipt { up_to_message_id } => {
+                (Self::READ_RECEIPT, up_to_message_id.to_le_bytes().to_vec())
+            }
+            Self::Close => (Self::CLOSE, Vec::new()),
+        };
+        (MessagePart::Control { kind }, contents)
+    }
+
+    /// Parses the contents of a control message, or returns `None` if the
+    /// kind is unknown or the contents malformed.
+    pub(crate) fn decode(kind: u16, contents: &[u8]) -> Option<Self> {
+        let value = || contents.try_into().ok().map(u64::from_le_bytes);
+        match kind {
+            Self::PING => Some(Self::Ping { nonce: value()? }),
+            Self::PONG => Some(Self::Pong { nonce: value()? }),
+            Self::READ_RECEIPT => Some(Self::ReadReceipt {
+                up_to_message_id: value()?,
+            }),
+            Self::CLOSE => contents.is_empty().then_some(Self::Close),
+            _ => None,
+        }
+    }
+
+    /// Returns how the payload is reported to the application.
+    pub(crate) fn message(&self) -> ControlMessage {
+        match self {
+            Self::Ping { .. } => ControlMessage::Ping,
+            Self::Pong { .. } => ControlMessage::Pong,
+            Self::ReadReceipt { up_to_message_id } => ControlMessage::ReadReceipt {
+                up_to_message_id: *up_to_message_id,
+            },
+            Self::Close => ControlMessage::Close,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Multi-device support for a single identity.
+//!
+//! Several devices can run their own `SessionManager` with the same
+//! `UserPublicKeys`. Each manager gets a random [`DeviceId`] and links with
+//! its sibling devices through a self-addressed announcement (an
+//! announcement encrypted to our own KEM key, whose user data carries the
+//! sender's device id). Once two devices have seen each other's link
+//! announcement they share a regular session, exactly like two peers.
+//!
+//! # Mirroring
+//!
+//! Peer sessions stay per device: a peer's ratchet state cannot be shared.
+//! Instead every message a device sends to or receives from a peer is
+//! mirrored to the linked devices as a [`DeviceSyncMessage`] over the device
+//! sessions, so the conversation is readable everywhere.
+//!
+//! # Conflict rules
+//!
+//! A peer keeps only the most recent announcement it received for a user ID,
+//! so when two of our devices announce to the same peer, the later
+//! announcement wins and the earlier device's session is dead on the peer's
+//! side. Whenever a device establishes a peer session it broadcasts a claim
+//! carrying its announcement timestamp; a device receiving a claim newer than
+//! its own announcement to that peer drops its session for that peer. The
+//! losing device keeps receiving the conversation through mirroring.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Size of a [`DeviceId`] in bytes.
+pub const DEVICE_ID_SIZE: usize = 16;
+
+/// Marker prefixed to device-link user data so that arbitrary self-addressed
+/// announcements are not mistaken for link requests.
+const DEVICE_LINK_MAGIC: [u8; 8] = *b"gsp.dlnk";
+
+/// Random identifier of one device (one `SessionManager` instance).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Zeroize)]
+pub struct DeviceId([u8; DEVICE_ID_SIZE]);
+
+impl DeviceId {
+    /// Generates a fresh random device ID.
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; DEVICE_ID_SIZE];
+        crypto_rng::fill_buffer(&mut bytes);
+        Self(bytes)
+    }
+
+    /// Returns the device ID as a byte slice.
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Creates a `DeviceId` from a byte array.
+    pub const fn from_bytes(bytes: [u8; DEVICE_ID_SIZE]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// User data carried by a device-link announcement.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DeviceLinkPayload {
+    magic: [u8; 8],
+    pub(crate) device_id: DeviceId,
+}
+
+impl DeviceLinkPayload {
+    pub(crate) fn new(device_id: DeviceId) -> Self {
+        Self {
+            magic: DEVICE_LINK_MAGIC,
+            device_id,
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .expect("Failed to serialize device link payload")
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let payload: Self = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .ok()?
+            .0;
+        (payload.magic == DEVICE_LINK_MAGIC).then_some(payload)
+    }
+}
+
+/// Message exchanged between linked devices.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) enum DeviceSyncMessage {
+    /// Copy of a message this device sent to a peer
+    Sent {
+        peer_id: Vec<u8>,
+        timestamp: u128,
+        contents: Vec<u8>,
+        ttl_millis: Option<u64>,
+    },
+    /// Copy of a message this device received from a peer
+    Received {
+        peer_id: Vec<u8>,
+        timestamp: u128,
+        contents: Vec<u8>,
+        ttl_millis: Option<u64>,
+    },
+    /// This device holds the session with `peer_id`, established by an
+    /// announcement made at `announcement_timestamp`
+    PeerClaim {
+        peer_id: Vec<u8>,
+        announcement_timestamp: u128,
+    },
+    /// Keeps the device session alive
+    KeepAlive,
+}
+
+/// Kind of a message received from a linked device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeviceSyncKind {
+    /// The linked device sent this message to the peer
+    Sent,
+    /// The linked device received this message from the peer
+    Received,
+    /// Internal device-to-device traffic with no user-visible message
+    Control,
+}
+
+/// Origin of a message that arrived through a linked device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceSync {
+    /// The linked device the message came from
+    pub device_id: DeviceId,
+    /// What the message represents
+    pub kind: DeviceSyncKind,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that a link payload round-trips and that foreign user data is rejected
+    #[test]
+    fn test_device_link_payload_roundtrip() {
+        let device_id = DeviceId::generate();
+        let bytes = DeviceLinkPayload::new(device_id).to_bytes();
+        let parsed = DeviceLinkPayload::from_bytes(&bytes).expect("valid payload");
+        assert_eq!(parsed.device_id, device_id);
+
+        assert!(DeviceLinkPayload::from_bytes(b"contact_request").is_none());
+        assert!(DeviceLinkPayload::from_bytes(&[]).is_none());
+    }
+
+    /// Tests that generated device IDs are distinct
+    #[test]
+    fn test_device_id_generate_unique() {
+        assert_ne!(DeviceId::generate(), DeviceId::generate());
+    }
+}
@@ -0,0 +1,140 @@
+//! Error type for the sessions API.
+
+/// Reason why an announcement, message, or send was rejected.
+///
+/// Display messages are short and stable enough to show to users; use
+/// [`code`](Self::code) to switch on the failure mode programmatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum SessionError {
+    /// The data could not be decrypted or parsed: it is malformed or not
+    /// addressed to us
+    #[error("malformed or undecryptable data")]
+    Undecryptable,
+
+    /// The data decrypted but its signature does not verify
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// The timestamp is older than the configured maximum age
+    #[error("too old")]
+    TooOld,
+
+    /// The timestamp is further in the future than the configured tolerance
+    #[error("timestamp in the future")]
+    TooFarInFuture,
+
+    /// The announcement or message is not newer than the latest one already
+    /// processed for this peer
+    #[error("replayed or out of order")]
+    Replay,
+
+    /// Our own announcement read back from the board
+    #[error("own announcement")]
+    OwnAnnouncement,
+
+    /// The peer is not known to the session manager
+    #[error("unknown peer")]
+    UnknownPeer,
+
+    /// There is no active session with the peer
+    #[error("no active session")]
+    NoSession,
+
+    /// The session was closed with
+    /// [`close_session`](crate::SessionManager::close_session): the message
+    /// belongs to it, or the announcement predates the close
+    #[error("session closed")]
+    Closed,
+
+    /// The session has too many unacknowledged messages
+    #[error("session saturated")]
+    Saturated,
+
+    /// No active session reads at the given seeker
+    #[error("unknown seeker")]
+    UnknownSeeker,
+
+    /// A chunked transfer part is out of order, oversized, or the reassembled
+    /// payload does not match its manifest; the transfer is dropped but the
+    /// session stays open
+    #[error("invalid chunked transfer")]
+    InvalidTransfer,
+
+    /// The announcement comes from a blocked peer
+    #[error("blocked peer")]
+    Blocked,
+
+    /// Too many announcements in the current rate limiting interval; the
+    /// announcement was not examined
+    #[error("rate limited")]
+    RateLimited,
+
+    /// The entry was read ahead of earlier messages of its session and is
+    /// held until they are fed
+    #[error("buffered until earlier messages arrive")]
+    Buffered,
+
+    /// The announcement comes from a new identity signed with the wallet key
+    /// of a known peer; it is accepted once approved
+    #[error("keys changed for a known identity")]
+    KeyChanged,
+
+    /// The announcement was revoked by its signer
+    #[error("announcement revoked")]
+    Revoked,
+
+    /// The announcement user data exceeds the configured limit
+    #[error("announcement user data too large")]
+    UserDataTooLarge,
+
+    /// The peer has no session request waiting for our answer
+    #[error("no pending session request")]
+    NoRequest,
+
+    /// The announcement comes from an unknown peer that is not on the
+    /// allowlist
+    #[error("peer not allowed")]
+    NotAllowed,
+
+    /// The announcement comes from a new peer while the peer table is full of
+    /// peers that cannot be evicted
+    #[error("too many peers")]
+    TooManyPeers,
+
+    /// Too many messages sent to the peer in a short time; the message was
+    /// not sent and can be sent again after `retry_after_millis`
+    #[error("throttled, retry in {retry_after_millis} ms")]
+    Throttled { retry_after_millis: u128 },
+}
+
+impl SessionError {
+    /// Stable, machine-discriminable code for this error variant, for use at
+    /// the FFI boundary.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Undecryptable => "UNDECRYPTABLE",
+            Self::InvalidSignature => "INVALID_SIGNATURE",
+            Self::TooOld => "TOO_OLD",
+            Self::TooFarInFuture => "TOO_FAR_IN_FUTURE",
+            Self::Replay => "REPLAY",
+            Self::OwnAnnouncement => "OWN_ANNOUNCEMENT",
+            Self::UnknownPeer => "UNKNOWN_PEER",
+            Self::NoSession => "NO_SESSION",
+            Self::Closed => "CLOSED",
+            Self::Saturated => "SATURATED",
+            Self::UnknownSeeker => "UNKNOWN_SEEKER",
+            Self::InvalidTransfer => "INVALID_TRANSFER",
+            Self::Blocked => "BLOCKED",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::Buffered => "BUFFERED",
+            Self::KeyChanged => "KEY_CHANGED",
+            Self::Revoked => "REVOKED",
+            Self::UserDataTooLarge => "USER_DATA_TOO_LARGE",
+            Self::NoRequest => "NO_REQUEST",
+            Self::NotAllowed => "NOT_ALLOWED",
+            Self::TooManyPeers => "TOO_MANY_PEERS",
+            Self::Throttled { .. } => "THROTTLED",
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Session lifecycle events.
+//!
+//! Observers registered with `SessionManager::subscribe` are notified
+//! synchronously, from within the call that caused the change, so UIs can
+//! react to state transitions instead of polling `peer_session_status` for
+//! every peer.
+
+use crate::error::SessionError;
+use alloc::vec::Vec;
+use auth::UserId;
+
+/// A change in the state of a peer session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionEvent {
+    /// A session with the peer became active (new or replacing a previous one)
+    SessionEstablished { peer_id: UserId },
+    /// The session was dropped by `refresh` after too long without incoming messages
+    SessionExpired { peer_id: UserId },
+    /// Our announcement to the peer went unanswered until it became too old
+    /// to be accepted; reported by `refresh`
+    RequestExpired { peer_id: UserId },
+    /// The unanswered request of the peer was discarded to make room for a
+    /// new peer under `max_peers`
+    RequestEvicted { peer_id: UserId },
+    /// The session was closed because an incoming message was rejected
+    SessionKilled {
+        peer_id: UserId,
+        reason: SessionError,
+    },
+    /// The session reached `max_session_lag_length` unacknowledged messages;
+    /// sending is blocked until the peer acknowledges some of them
+    PeerSaturated { peer_id: UserId },
+    /// An announcement from the peer was accepted
+    AnnouncementAccepted {
+        peer_id: UserId,
+        timestamp_millis: u128,
+    },
+    /// An announcement from a new identity signed with the wallet key of a
+    /// known peer was rejected with `SessionError::KeyChanged`; approve it with
+    /// `SessionManager::approve_key_change`
+    KeyChanged {
+        peer_id: UserId,
+        previous_peer_id: UserId,
+    },
+    /// The peer moved to new long-term keys; its session and state now belong
+    /// to `peer_id`
+    PeerKeysRotated {
+        previous_peer_id: UserId,
+        peer_id: UserId,
+    },
+    /// The peer acknowledged messages we sent, identified by their seekers
+    MessageAcknowledged {
+        peer_id: UserId,
+        seekers: Vec<Vec<u8>>,
+    },
+    /// A chunked transfer from the peer was fully received and verified; the
+    /// payload is in the `FeedIncomingMessageOutput` of the last chunk
+    TransferCompleted {
+        peer_id: UserId,
+        transfer_id: u64,
+        total_bytes: u64,
+    },
+    /// The session was closed deliberately with
+    /// `SessionManager::close_session`, by us or by the peer
+    SessionClosed { peer_id: UserId, by_peer: bool },
+    /// The peer read our messages up to `up_to_message_id`, the
+    /// `message_id` of a `SendOutgoingMessageOutput`
+    MessagesRead {
+        peer_id: UserId,
+        up_to_message_id: u64,
+    },
+}
+
+/// Receiver of [`SessionEvent`]s.
+///
+/// Implemented for any `FnMut(&SessionEvent) + Send` closure.
+pub trait SessionObserver: Send {
+    fn on_event(&mut self, event: &SessionEvent);
+}
+
+impl<F: FnMut(&SessionEvent) + Send> SessionObserver for F {
+    fn on_event(&mut self, event: &SessionEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,137 @@
+//! Contact verification fingerprints.
+//!
+//! Both parties derive the same fingerprint from their two sets of public
+//! keys and compare it out of band, by reading the digits or emoji aloud or
+//! by scanning the binary encoding as a QR code. A match rules out a
+//! man-in-the-middle substituting keys.
+
+use alloc::{format, string::String, vec::Vec};
+use auth::UserPublicKeys;
+
+/// Version byte of the binary encoding.
+pub const FINGERPRINT_VERSION: u8 = 0;
+
+/// Size of the fingerprint hash.
+pub const FINGERPRINT_SIZE: usize = 32;
+
+/// Emoji alphabet, one per 6 bits.
+const EMOJI: [&str; 64] = [
+    "🐶", "🐱", "🐭", "🐹", "🐰", "🦊", "🐻", "🐼", "🐨", "🐯", "🦁", "🐮", "🐷", "🐸", "🐵", "🐔",
+    "🐧", "🐦", "🦆", "🦉", "🐴", "🦄", "🐝", "🐛", "🦋", "🐌", "🐞", "🐢", "🐍", "🐙", "🦀", "🐬",
+    "🐳", "🦈", "🐊", "🦒", "🐘", "🦔", "🌵", "🌲", "🌻", "🍄", "🌙", "⭐", "🔥", "🌈", "❄", "🍎",
+    "🍌", "🍇", "🍓", "🍒", "🍍", "🥕", "🌽", "🍕", "🎈", "🎁", "🔑", "🔔", "🎸", "🚀", "⚓", "⏰",
+];
+
+/// Number of emoji in [`VerificationFingerprint::emoji`].
+const EMOJI_COUNT: usize = 8;
+
+/// Number of 5-digit groups in [`VerificationFingerprint::digits`].
+const DIGIT_GROUPS: usize = 6;
+
+/// Fingerprint of a pair of identities, identical on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationFingerprint([u8; FINGERPRINT_SIZE]);
+
+impl VerificationFingerprint {
+    /// Derives the fingerprint of two identities; the argument order does not
+    /// matter.
+    pub fn derive(a: &UserPublicKeys, b: &UserPublicKeys) -> Self {
+        let mut ids = [a.derive_id(), b.derive_id()];
+        ids.sort_by(|x, y| x.as_bytes().cmp(y.as_bytes()));
+
+        let mut kdf = crypto_kdf::Extract::new(b"session.fingerprint.kdf.salt----");
+        kdf.input_item(ids[0].as_bytes());
+        kdf.input_item(ids[1].as_bytes());
+        let expander = kdf.finalize();
+        let mut fingerprint = [0u8; FINGERPRINT_SIZE];
+        expander.expand(b"session.fingerprint", &mut fingerprint);
+        Self(fingerprint)
+    }
+
+    /// Returns 30 digits in groups of 5 separated by spaces.
+    pub fn digits(&self) -> String {
+        self.0
+            .chunks_exact(5)
+            .take(DIGIT_GROUPS)
+            .map(|chunk| {
+                let mut bytes = [0u8; 8];
+                bytes[..5].copy_from_slice(chunk);
+                format!("{:05}", u64::from_le_bytes(bytes) % 100_000)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns a sequence of 8 emoji.
+    pub fn emoji(&self) -> String {
+        let mut bits = u64::from_le_bytes(self.0[..8].try_into().expect("8 bytes"));
+        let mut emoji = String::new();
+        for _ in 0..EMOJI_COUNT {
+            emoji.push_str(EMOJI[(bits & 0x3f) as usize]);
+            bits >>= 6;
+        }
+        emoji
+    }
+
+    /// Compact binary encoding for QR codes:
+    /// `[FINGERPRINT_VERSION, fingerprint (32 bytes)]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + FINGERPRINT_SIZE);
+        bytes.push(FINGERPRINT_VERSION);
+        bytes.extend_from_slice(&self.0);
+        bytes
+    }
+
+    /// Parses the encoding of [`to_bytes`](Self::to_bytes), e.g. from a
+    /// scanned QR code. Returns `None` for an unknown version or length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&version, fingerprint) = bytes.split_first()?;
+        if version != FINGERPRINT_VERSION {
+            return None;
+        }
+        Some(Self(fingerprint.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_test_public_keys() -> UserPublicKeys {
+        let mut passphrase = [0u8; 32];
+        crypto_rng::fill_buffer(&mut passphrase);
+        let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+        auth::derive_keys_from_static_root_secret(&root_secret).0
+    }
+
+    /// Tests that both parties derive the same fingerprint and encodings
+    #[test]
+    fn test_fingerprint_symmetric() {
+        let alice_pk = generate_test_public_keys();
+        let bob_pk = generate_test_public_keys();
+        let carol_pk = generate_test_public_keys();
+
+        let alice_side = VerificationFingerprint::derive(&alice_pk, &bob_pk);
+        let bob_side = VerificationFingerprint::derive(&bob_pk, &alice_pk);
+        assert_eq!(alice_side, bob_side);
+        assert_eq!(alice_side.digits(), bob_side.digits());
+        assert_eq!(alice_side.emoji(), bob_side.emoji());
+        assert_ne!(
+            alice_side,
+            VerificationFingerprint::derive(&alice_pk, &carol_pk)
+        );
+
+        let digits = alice_side.digits();
+        assert_eq!(digits.len(), DIGIT_GROUPS * 6 - 1);
+        assert!(digits.chars().all(|c| c.is_ascii_digit() || c == ' '));
+        assert_eq!(alice_side.emoji().chars().count(), EMOJI_COUNT);
+
+        let bytes = alice_side.to_bytes();
+        assert_eq!(bytes.len(), 1 + FINGERPRINT_SIZE);
+        assert_eq!(
+            VerificationFingerprint::from_bytes(&bytes),
+            Some(alice_side)
+        );
+        assert_eq!(VerificationFingerprint::from_bytes(&bytes[1..]), None);
+    }
+}
@@ -0,0 +1,64 @@
+//! Incremental persistence.
+//!
+//! [`SessionManager::to_encrypted_blob`](crate::SessionManager::to_encrypted_blob)
+//! serializes and encrypts every peer, every time. An incremental snapshot
+//! instead holds one encrypted record per peer that changed since the previous
+//! snapshot, plus a manifest with the rest of the manager state and the list
+//! of peers. Storage keeps the latest record of every peer and the latest
+//! manifest, and loads them back with
+//! [`SessionManager::from_incremental_snapshot`](crate::SessionManager::from_incremental_snapshot).
+
+use crate::collections::HashSet;
+use alloc::vec::Vec;
+use auth::UserId;
+
+/// Encrypted state of one peer.
+pub struct PeerRecord {
+    /// Peer the record belongs to, to key it in storage. The same ID is
+    /// encrypted in the blob and checked on load.
+    pub peer_id: UserId,
+    /// Encrypted peer state
+    pub blob: Vec<u8>,
+}
+
+/// Changes since the previous incremental snapshot.
+pub struct IncrementalSnapshot {
+    /// Encrypted manager state without the peers, replacing the stored one
+    pub manifest: Vec<u8>,
+    /// Records of the peers that changed, replacing their stored records
+    pub updated_peers: Vec<PeerRecord>,
+    /// Peers whose stored records must be deleted
+    pub removed_peers: Vec<UserId>,
+}
+
+/// Peers changed since the previous incremental snapshot.
+#[derive(Default)]
+pub(crate) struct PeerChanges {
+    updated: HashSet<UserId>,
+    removed: HashSet<UserId>,
+}
+
+impl PeerChanges {
+    pub(crate) fn touch(&mut self, peer_id: &UserId) {
+        self.removed.remove(peer_id);
+        self.updated.insert(peer_id.clone());
+    }
+
+    pub(crate) fn remove(&mut self, peer_id: &UserId) {
+        self.updated.remove(peer_id);
+        self.removed.insert(peer_id.clone());
+    }
+
+    pub(crate) fn updated(&self) -> impl Iterator<Item = &UserId> {
+        self.updated.iter()
+    }
+
+    pub(crate) fn removed(&self) -> Vec<UserId> {
+        self.removed.iter().cloned().collect()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.updated.clear();
+        self.removed.clear();
+    }
+}
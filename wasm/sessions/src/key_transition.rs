@@ -0,0 +1,107 @@
+//! Long-term identity key rotation.
+//!
+//! A user who replaces their long-term keys, e.g. after a suspected
+//! compromise, sends a key transition over every active session. The
+//! transition is signed by both the old and the new keys over both
+//! identities, so the peer can move the conversation to the new identity
+//! without a new handshake. Messages the peer sent before reading the
+//! transition are still encrypted to the old keys: the rotating side keeps
+//! them until every notified peer has switched.
+
+use crate::error::SessionError;
+use alloc::vec::Vec;
+use auth::{AuthBlob, UserId, UserPublicKeys, UserSecretKeys};
+use serde::{Deserialize, Serialize};
+
+/// Secret payload of both signatures. There is no shared secret to bind: the
+/// transition travels inside an authenticated session.
+const KEY_TRANSITION_CONTEXT: &[u8] = b"sessions.key_transition";
+
+/// Statement that an identity moves to new keys.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct KeyTransition {
+    /// Signature of the old keys over both identities
+    old_keys: AuthBlob,
+    /// Signature of the new keys over both identities, proving possession
+    new_keys: AuthBlob,
+}
+
+impl KeyTransition {
+    /// Signs the transition from the old keys to the new ones.
+    pub(crate) fn new(
+        old_pk: &UserPublicKeys,
+        old_sk: &UserSecretKeys,
+        new_pk: &UserPublicKeys,
+        new_sk: &UserSecretKeys,
+    ) -> Self {
+        let ids = Self::signed_ids(&old_pk.derive_id(), &new_pk.derive_id());
+        Self {
+            old_keys: AuthBlob::new(old_pk.clone(), old_sk, ids.clone(), KEY_TRANSITION_CONTEXT),
+            new_keys: AuthBlob::new(new_pk.clone(), new_sk, ids, KEY_TRANSITION_CONTEXT),
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .expect("Failed to serialize key transition")
+    }
+
+    /// Parses a transition away from `old_id` and checks both signatures,
+    /// returning the new keys.
+    pub(crate) fn verify(bytes: &[u8], old_id: &UserId) -> Result<UserPublicKeys, SessionError> {
+        let (transition, _): (Self, _) =
+            bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+                .map_err(|_| SessionError::Undecryptable)?;
+        let new_pk = transition.new_keys.public_keys();
+        let ids = Self::signed_ids(old_id, &new_pk.derive_id());
+        if transition.old_keys.public_keys().derive_id() != *old_id
+            || transition.old_keys.public_payload() != ids.as_slice()
+            || transition.new_keys.public_payload() != ids.as_slice()
+            || !transition.old_keys.verify(KEY_TRANSITION_CONTEXT)
+            || !transition.new_keys.verify(KEY_TRANSITION_CONTEXT)
+        {
+            return Err(SessionError::InvalidSignature);
+        }
+        Ok(new_pk.clone())
+    }
+
+    /// Both identities, as signed by both keys.
+    fn signed_ids(old_id: &UserId, new_id: &UserId) -> Vec<u8> {
+        let mut ids = old_id.as_bytes().to_vec();
+        ids.extend_from_slice(new_id.as_bytes());
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_test_keypair() -> (UserPublicKeys, UserSecretKeys) {
+        let mut passphrase = [0u8; 32];
+        crypto_rng::fill_buffer(&mut passphrase);
+        let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+        auth::derive_keys_from_static_root_secret(&root_secret)
+    }
+
+    /// Tests that a transition only verifies for the identity it moves away from
+    #[test]
+    fn test_transition_binds_both_identities() {
+        let (old_pk, old_sk) = generate_test_keypair();
+        let (new_pk, new_sk) = generate_test_keypair();
+        let (other_pk, _) = generate_test_keypair();
+
+        let bytes = KeyTransition::new(&old_pk, &old_sk, &new_pk, &new_sk).to_bytes();
+        let keys = KeyTransition::verify(&bytes, &old_pk.derive_id()).unwrap();
+        assert_eq!(keys.derive_id(), new_pk.derive_id());
+        assert_eq!(
+            KeyTransition::verify(&bytes, &other_pk.derive_id()).err(),
+            Some(SessionError::InvalidSignature)
+        );
+
+        // signed by someone else than the old identity
+        let (forger_pk, forger_sk) = generate_test_keypair();
+        let forged = KeyTransition::new(&forger_pk, &forger_sk, &new_pk, &new_sk).to_bytes();
+        assert!(KeyTransition::verify(&forged, &old_pk.derive_id()).is_err());
+    }
+}
@@ -55,9 +55,13 @@
 //!
 //! - **Clock Synchronization**: The timestamp validation relies on reasonably synchronized clocks.
 //!   Configure `max_incoming_announcement_future_millis` and `max_incoming_message_future_millis`
-//!   based on expected clock drift.
+//!   based on expected clock drift. `max_clock_skew_compensation_millis` additionally tolerates
+//!   peers whose clock runs ahead, estimating the offset of every peer from its messages;
+//!   announcement timestamps are not compensated.
 //! - **Denial of Service**: The `max_session_lag_length` configuration prevents memory exhaustion
-//!   from unacknowledged messages.
+//!   from unacknowledged messages. `max_incoming_announcement_bytes` and
+//!   `max_incoming_announcements_per_interval` bound the CPU spent validating junk announcements;
+//!   `announcement_stats()` reports how much load was shed.
 //! - **Session Expiry**: Configure `max_session_inactivity_millis` appropriately to balance security
 //!   (shorter = less time for attacks) vs usability (longer = fewer re-establishments).
 //! - **Key Material**: All sensitive key material uses `zeroize` to clear memory on drop.
@@ -87,7 +91,9 @@
 //! ## Basic Example
 //!
 //! ```rust,no_run
-//! use sessions::{SessionManager, SessionManagerConfig, SessionStatus};
+//! use sessions::{
+//!     PaddingPolicy, SeekerNamespace, SessionManager, SessionManagerConfig, SessionStatus,
+//! };
 //! use auth::{UserPublicKeys, UserSecretKeys, UserId, derive_keys_from_static_root_secret, StaticRootSecret};
 //!
 //! // Initialize your identity
@@ -107,10 +113,26 @@
 //!     max_incoming_announcement_future_millis: 5_000,     // 5 seconds
 //!     max_incoming_message_age_millis: 300_000,           // 5 minutes
 //!     max_incoming_message_future_millis: 5_000,          // 5 seconds
+//!     max_clock_skew_compensation_millis: Some(600_000),  // tolerate peer clocks 10 minutes ahead
 //!     max_session_inactivity_millis: 3_600_000,           // 1 hour
 //!     keep_alive_interval_millis: 60_000,                 // 1 minute
 //!     max_session_lag_length: 100,                        // max unacknowledged messages
-//!     max_keep_alive_peer_lag_length: 8,                  // trigger keep-alive on peer lag
+//!     near_saturation_lag_length: Some(80),               // report near saturation from 80
+//!     send_burst_size: Some(20),                          // send 20 messages back to back...
+//!     send_interval_millis: 500,                          // ...then at most one per 500 ms
+//!     rehandshake_killed_sessions: true,                  // announce again on killed sessions
+//!     rehandshake_saturated_after_millis: Some(600_000),  // or saturated for 10 minutes
+//!     renew_requests_before_expiry_millis: Some(10_000),  // renew contact requests 10 s before expiry
+//!     max_peers: Some(10_000),                            // evict stale requests beyond 10000 peers
+//!     max_incoming_announcement_bytes: Some(65_536),      // drop oversized announcements
+//!     max_announcement_user_data_bytes: Some(1_024),      // cap announcement user data
+//!     announcement_padding: PaddingPolicy::Buckets(vec![4_096]), // pad to multiples of 4 KiB
+//!     max_incoming_announcements_per_interval: Some(500), // validate at most 500...
+//!     announcement_rate_interval_millis: 1_000,           // ...announcements per second
+//!     announcement_cache_max_age_millis: 86_400_000,      // for 1 day
+//!     seeker_lookahead: 4,                                // let peers prefetch 4 messages ahead
+//!     seeker_namespace: SeekerNamespace::new(*b"my-app"), // keep our seekers apart from other apps
+//!     ..Default::default()
 //! };
 //!
 //! let mut session_manager = SessionManager::new(config);
@@ -123,7 +145,7 @@
 //!     &our_pk,
 //!     &our_sk,
 //!     user_data.to_vec(),
-//! );
+//! ).unwrap();
 //! // Publish `announcement` to the blockchain announcement board
 //!
 //! // Main event loop
@@ -177,19 +199,20 @@
 //!         blockchain_write_fn(&output.seeker, &output.data);
 //!     }
 //!
-//!     // 5. Refresh sessions and send keep-alive messages
-//!     let keep_alive_peers = session_manager.refresh();
-//!     for peer_id in keep_alive_peers {
-//!         let keep_alive_msg = b"";  // Empty message for keep-alive
-//!         if let Some(output) = session_manager.send_message(&peer_id, keep_alive_msg) {
-//!             blockchain_write_fn(&output.seeker, &output.data);
-//!         }
+//!     // 5. Refresh sessions and post keep-alive messages
+//!     let (report, keep_alives) = session_manager.refresh_with_keepalives();
+//!     for (_peer_id, output) in keep_alives {
+//!         blockchain_write_fn(&output.seeker, &output.data);
+//!     }
+//!     for _peer_id in report.expired_sessions {
+//!         /* Session dropped for inactivity, now Killed */
 //!     }
 //!
 //!     // 6. Check session statuses
 //!     for peer_id in session_manager.peer_list() {
 //!         match session_manager.peer_session_status(&peer_id) {
 //!             SessionStatus::Active => { /* Session is healthy */ },
+//!             SessionStatus::NearSaturation => { /* Lag is building up, slow down */ },
 //!             SessionStatus::Saturated => { /* Too much lag, wait for acks */ },
 //!             SessionStatus::PeerRequested => {
 //!                 // Peer wants session, respond with our announcement
@@ -199,7 +222,7 @@
 //!                     &our_pk,
 //!                     &our_sk,
 //!                     user_data.to_vec(),
-//!                 );
+//!                 ).unwrap();
 //!             },
 //!             _ => { /* Handle other states */ },
 //!         }
@@ -212,17 +235,229 @@
 //!
 //! 1. **Initiation**: Either peer calls `establish_outgoing_session()` and publishes the announcement
 //! 2. **Handshake**: When both peers have sent announcements, `feed_incoming_announcement()` creates the session
+//!    Requests from peers we have not announced to are listed by `pending_requests()` and answered with
+//!    `accept_request()`, `reject_request()` or `ignore_request()`. `parse_announcement_peek()` reads
+//!    the announcer's keys and user data without feeding the announcement, e.g. to ask the user first
 //! 3. **Active Communication**: Use `send_message()` and `feed_incoming_message_board_read()` to exchange messages
-//! 4. **Keep-Alive**: Call `refresh()` periodically and send keep-alive messages to prevent expiry
+//! 4. **Keep-Alive**: Call `refresh_with_keepalives()` periodically and post the keep-alive messages it
+//!    returns to prevent expiry (or call `refresh()` and send them yourself). `ping()` probes a peer;
+//!    `peer_liveness()` reports the measured round trip and when the peer was last seen
 //! 5. **Termination**: Sessions expire after `max_session_inactivity_millis` of inactivity, or can be manually
-//!    closed with `peer_discard()`
+//!    closed with `close_session()`, which tells the peer and leaves both sides `Closed`, or dropped
+//!    with `peer_discard()`. `refresh()` reports expired sessions, and unanswered announcements that
+//!    became too old to be accepted, in its `RefreshReport`
+//! 6. **Recovery**: With `rehandshake_killed_sessions` or `rehandshake_saturated_after_millis` set,
+//!    `refresh_with_rehandshakes()` announces again to peers whose session was killed or stayed saturated,
+//!    returning the announcements to publish
+//!
+//! Each session with a peer has an epoch, returned by `session_epoch()`, that grows by one whenever a
+//! new pair of announcements replaces it. Epochs are counted locally and may differ between peers. The outputs of sent and received messages carry the epoch of
+//! their session, to tell which handshake a message belongs to when re-establishments race.
+//!
+//! ## Error Reporting
+//!
+//! `feed_incoming_announcement()`, `send_message()` and `feed_incoming_message_board_read()` return
+//! `None` on any failure. Their `try_` counterparts return a [`SessionError`] instead, telling apart
+//! undecryptable data, bad signatures, stale or replayed input, missing and saturated sessions.
+//!
+//! With `send_burst_size` set, messages to a peer beyond the burst must be `send_interval_millis`
+//! apart: earlier ones fail with `SessionError::Throttled`, which tells when to retry, so a runaway
+//! send loop cannot spend the `max_session_lag_length` budget at once. Keep-alives and other control
+//! messages are not counted.
+//!
+//! `debug_transcript()` returns the latest events about a peer, such as state transitions, lag and
+//! rejection reasons, without keys or message contents, to attach to bug reports.
+//!
+//! ## Control Messages
+//!
+//! Keep-alives, pings and their answers arrive through `feed_incoming_message_board_read()` like
+//! any message, with the output's `control` set and an empty `message`: they are not payloads of
+//! the peer's application. Empty user messages are reserved for keep-alives. Control kinds added by
+//! later versions are reported as `ControlMessage::Unknown` rather than closing the session.
+//!
+//! `send_read_receipt()` tells a peer which of its messages were read. The peer's manager emits
+//! `SessionEvent::MessagesRead` with the highest read `message_id`, so applications can show read
+//! state next to the delivery state of `message_status()`.
+//!
+//! ## Large Messages
+//!
+//! `send_large_message()` splits a payload into a manifest and ordered chunks, one message-board
+//! entry each. The receiver reassembles them across `feed_incoming_message_board_read()` calls:
+//! each output reports its `transfer` progress, and the output of the last chunk carries the whole
+//! payload once its hash matches the manifest.
+//!
+//! ## Message Padding
+//!
+//! By default the size of a message-board entry follows the size of the message, which also tells
+//! keep-alives apart from real messages. `set_padding_policy()` pads every outgoing message to
+//! fixed size buckets or by a random Pareto-distributed amount before encryption; receivers strip
+//! the padding without any configuration.
+//!
+//! Announcements likewise reveal how much user data they carry. The `announcement_padding`
+//! configuration pads them the same way; receivers ignore the padding, including older versions.
+//!
+//! ## Reading Ahead
+//!
+//! With `seeker_lookahead` set, every message also announces the seekers of the following messages.
+//! `get_message_board_lookahead_keys()` returns them so entries can be fetched in parallel or out of
+//! order. An entry fed before its predecessors is buffered (`SessionError::Buffered`) and decrypted
+//! once they arrive; collect those messages with `take_reordered_messages()`. The ratchet still needs
+//! every message: an entry that is never found blocks the ones after it.
+//!
+//! ## Contact Verification
+//!
+//! `SessionManager::verification_fingerprint()` derives a fingerprint from both parties' public
+//! keys, identical on both sides. Users compare its digits or emoji out of band, or scan its binary
+//! encoding as a QR code, to rule out substituted keys.
+//!
+//! ## Key Changes
+//!
+//! A `UserId` is a hash of all of a user's public keys, so the keys first seen for a peer stay
+//! pinned to its ID: new keys always come with a new ID. Announcements are also signed with the
+//! user's Massa key (wallet address). An announcement from a new ID signed with the Massa key of a
+//! known peer means that peer's messaging keys changed, or that their wallet key leaked: it is
+//! rejected with `SessionError::KeyChanged` and a `SessionEvent::KeyChanged` naming both IDs. After
+//! the user confirms, `approve_key_change()` lets the next announcement from the new ID through.
+//!
+//! A user who replaces their keys on purpose calls `rotate_identity()` instead: it sends every peer
+//! with an active session a transition signed by both the old and the new keys. Peers move the
+//! session to the new ID (`SessionEvent::PeerKeysRotated`) and the conversation carries on.
+//!
+//! ## Alias Identities
+//!
+//! Peers we contact under the same keys can tell they talk to the same user. With
+//! `alias_identities` set in the config, each new contact is announced to under a random alias with
+//! its own `UserId` instead, and the session with that peer runs on the alias keys; messages are fed
+//! with our own keys as usual. `alias_public_keys()` returns the alias a peer knows us by, and
+//! `reveal_identity()` switches a peer back to our own identity at the next announcement.
+//!
+//! ## Prekey Bundles
+//!
+//! A session normally forms once both peers have read each other's announcement. A user can instead
+//! publish a prekey bundle from `create_prekey_bundle()`, a fresh ML-KEM key signed by their
+//! long-term keys, e.g. next to their public profile. `establish_outgoing_session_with_prekey()`
+//! starts an active session from the bundle right away: the initiator can send before the owner is
+//! even online, and the owner joins the session when it reads the initiator's announcement.
+//!
+//! ## Announcement User Data
+//!
+//! The user data of an announcement is encrypted to the recipient's long-term keys only, so it has no
+//! post-compromise secrecy. `establish_outgoing_session_with()` and `UserDataMode::Session` hold it
+//! back until the session is established and send it as its first message instead, under keys
+//! derived from both sides' ephemeral key exchanges. The recipient reads it from the
+//! `FeedIncomingMessageOutput` flagged `announcement_user_data`, one round trip later.
+//!
+//! The user data is free-form. Clients sharing profile information encode an `AnnouncementUserData`
+//! (protocol version, display name, avatar hash and capability flags) with `to_bytes()` and parse
+//! it back with `AnnouncementUserData::from_bytes()`, so that different clients understand each other.
+//!
+//! ## Revocation
+//!
+//! After losing a device, a user calls `create_announcement_revocation()` and publishes the signed
+//! record it returns. Peers feed it to `feed_announcement_revocation()`: announcements the user
+//! created up to that time are rejected with `SessionError::Revoked`, and sessions formed with them
+//! are closed until the user announces again. Revocations are persisted with the manager state.
+//!
+//! ## Time
+//!
+//! Every timestamp and time window (announcement and message age, keep-alive, expiry, rate limits)
+//! reads the manager's `Clock`, the system clock by default. `set_clock()` replaces it, e.g. with a
+//! `MockClock` in tests, to move time without sleeping. Both come from the `clock` crate, whose
+//! `Clock` the secure storage crate reads as well.
+//!
+//! Random bytes all come from `crypto_rng`, whose `deterministic` feature seeds them. With a
+//! `MockClock` as well, runs are reproducible: `tests/test_vectors.rs` writes test vectors of the
+//! wire formats for other implementations to check against. For a single announcement, this crate's
+//! own `deterministic` feature adds `OutgoingInitiationRequest::new_deterministic()`, taking the seed
+//! and timestamp directly.
+//!
+//! `testing::InMemoryBoard` stands in for the blockchain board in tests, with configurable loss,
+//! duplication, reordering and latency driven by a `MockClock`, and `testing::BoardUser` runs a
+//! manager against it: it posts what the manager sends and feeds it what it can read.
+//!
+//! ## `no_std`
+//!
+//! With the default `std` feature disabled, the crate only needs `alloc`, for embedded and
+//! secure-element targets. There is no system clock, so every constructor takes a `Clock`:
+//! `with_clock()`, `from_encrypted_blob_with_clock()` and `from_incremental_snapshot_with_clock()`;
+//! `new()`, `from_encrypted_blob()` and `from_incremental_snapshot()` need `std`. Hash maps come
+//! from `hashbrown`, and batch feeding runs sequentially. The `auth` and `crypto-*` crates it builds on still need `std` for now.
+//!
+//! ## Incremental Persistence
+//!
+//! `to_encrypted_blob()` re-encrypts every peer on each save. Clients with many contacts can instead
+//! store one record per peer: `take_incremental_snapshot()` returns a manifest, the encrypted records
+//! of the peers that changed since the previous snapshot and the peers to delete. The latest
+//! manifest and records load back with `SessionManager::from_incremental_snapshot()`.
+//!
+//! Peers marked with `set_peer_ephemeral()` are left out of both, for conversations that must leave
+//! no trace in persisted state: they only live in memory until discarded or until the manager is
+//! dropped.
+//!
+//! With the `secure-storage` feature, `SessionStore` writes these snapshots into a namespace of an
+//! unlocked `secureStorage` session: `SessionStore::save()` only rewrites the manifest and the
+//! changed peers, and `SessionStore::load()` restores the manager. A save interrupted midway leaves
+//! the previously saved state readable.
+//!
+//! ## Replay Protection
+//!
+//! Announcements older than the latest one accepted from a peer are rejected as replays, even after
+//! the peer is discarded. Restoring an older state would accept again the announcements processed
+//! since it was saved: save the small blob from `export_replay_guard()` after every accepted
+//! announcement and pass it to `merge_replay_guard()` after restoring.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod alias;
+mod announcement_cache;
+mod collections;
+mod control;
+mod device;
+mod error;
+mod events;
+mod fingerprint;
+mod incremental;
+mod key_transition;
+mod padding;
+mod prekey;
+mod revocation;
+#[cfg(feature = "secure-storage")]
+mod secure_store;
+mod seeker_index;
 mod session;
 mod session_manager;
-mod utils;
+#[cfg(target_has_atomic = "64")]
+pub mod testing;
+mod transcript;
+mod transfer;
+mod user_data;
 
-pub use session::{FeedIncomingMessageOutput, SendOutgoingMessageOutput};
-pub use session::{IncomingInitiationRequest, OutgoingInitiationRequest, Session};
+pub use clock::Clock;
+#[cfg(target_has_atomic = "64")]
+pub use clock::MockClock;
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+pub use control::ControlMessage;
+pub use device::{DEVICE_ID_SIZE, DeviceId, DeviceSync, DeviceSyncKind};
+pub use error::SessionError;
+pub use events::{SessionEvent, SessionObserver};
+pub use fingerprint::{FINGERPRINT_SIZE, FINGERPRINT_VERSION, VerificationFingerprint};
+pub use incremental::{IncrementalSnapshot, PeerRecord};
+pub use padding::PaddingPolicy;
+pub use prekey::MAX_PREKEYS;
+#[cfg(feature = "secure-storage")]
+pub use secure_store::{SessionStore, StoreError};
+pub use session::{FeedIncomingMessageOutput, MessageHandle, SendOutgoingMessageOutput};
+pub use session::{IncomingInitiationRequest, OutgoingInitiationRequest, SeekerNamespace, Session};
 pub use session_manager::{
-    AnnouncementResult, SessionManager, SessionManagerConfig, SessionStatus,
+    AnnouncementResult, AnnouncementStats, MessageStatus, PeerLag, PeerLiveness, PeerStats,
+    PeerSummary, PendingRequest, PostingCost, PrunedPeer, RefreshReport, STATE_FORMAT_VERSION,
+    SessionManager, SessionManagerConfig, SessionStatus, UserDataMode, parse_announcement_peek,
+};
+pub use transcript::{MAX_TRANSCRIPT_ENTRIES, TranscriptEntry, TranscriptEvent};
+pub use transfer::{DEFAULT_CHUNK_SIZE, MAX_LARGE_MESSAGE_SIZE, TransferProgress};
+pub use user_data::{
+    ANNOUNCEMENT_USER_DATA_VERSION, AVATAR_HASH_SIZE, AnnouncementUserData, MAX_DISPLAY_NAME_BYTES,
 };
@@ -0,0 +1,114 @@
+//! Message padding.
+//!
+//! The length of a message-board entry follows the length of its plaintext,
+//! which tells keep-alives apart from real messages and leaks message sizes.
+//! A [`PaddingPolicy`] pads the serialized message before agraphon encryption.
+//! The padding follows the serialized message inside the ciphertext and is
+//! ignored when the receiver deserializes it, so no length field is needed
+//! and padded and unpadded peers interoperate.
+
+use alloc::{vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// How outgoing messages are padded before encryption.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum PaddingPolicy {
+    /// No padding (default)
+    #[default]
+    None,
+    /// Pad to the smallest bucket size that fits the message; messages larger
+    /// than every bucket are padded to a multiple of the largest one
+    Buckets(Vec<u32>),
+    /// Append a random amount of padding following a Pareto (Lomax)
+    /// distribution with the given scale in bytes and shape, capped at the
+    /// larger of the message size and the scale. Lower shapes give heavier
+    /// tails.
+    Pareto { scale: u32, shape: f64 },
+}
+
+impl PaddingPolicy {
+    /// Returns the padded length for a serialized message of `len` bytes.
+    pub(crate) fn padded_len(&self, len: usize) -> usize {
+        match self {
+            Self::None => len,
+            Self::Buckets(buckets) => {
+                let Some(&largest) = buckets.iter().max() else {
+                    return len;
+                };
+                if let Some(&bucket) = buckets.iter().filter(|&&b| b as usize >= len).min() {
+                    return bucket as usize;
+                }
+                let largest = (largest as usize).max(1);
+                len.div_ceil(largest) * largest
+            }
+            Self::Pareto { scale, shape } => {
+                if *scale == 0 || !shape.is_finite() || *shape <= 0.0 {
+                    return len;
+                }
+                // uniform in (0, 1]
+                let mut bytes = [0u8; 8];
+                crypto_rng::fill_buffer(&mut bytes);
+                let u = ((u64::from_le_bytes(bytes) >> 11) + 1) as f64 / (1u64 << 53) as f64;
+                let extra = *scale as f64 * (u.powf(-1.0 / shape) - 1.0);
+                let cap = len.max(*scale as usize);
+                len + (extra as usize).min(cap)
+            }
+        }
+    }
+
+    /// Returns the largest padded length for a serialized message of `len`
+    /// bytes: the padded length, or the cap of Pareto padding.
+    pub(crate) fn max_padded_len(&self, len: usize) -> usize {
+        match self {
+            Self::Pareto { scale, shape } if *scale != 0 && shape.is_finite() && *shape > 0.0 => {
+                len + len.max(*scale as usize)
+            }
+            Self::Pareto { .. } => len,
+            _ => self.padded_len(len),
+        }
+    }
+
+    /// Returns a padded copy of serialized message bytes, allocated once so
+    /// no unzeroized copy is left behind by a reallocation.
+    pub(crate) fn pad(&self, bytes: &[u8]) -> Zeroizing<Vec<u8>> {
+        let mut padded = Zeroizing::new(vec![0u8; self.padded_len(bytes.len())]);
+        padded[..bytes.len()].copy_from_slice(bytes);
+        padded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that buckets pick the smallest fitting size
+    #[test]
+    fn test_bucket_padding() {
+        let policy = PaddingPolicy::Buckets(vec![1024, 256, 4096]);
+        assert_eq!(policy.padded_len(0), 256);
+        assert_eq!(policy.padded_len(256), 256);
+        assert_eq!(policy.padded_len(257), 1024);
+        assert_eq!(policy.padded_len(4000), 4096);
+        assert_eq!(policy.padded_len(5000), 8192);
+        assert_eq!(PaddingPolicy::Buckets(vec![]).padded_len(10), 10);
+        assert_eq!(PaddingPolicy::None.padded_len(10), 10);
+    }
+
+    /// Tests that Pareto padding never shrinks and stays within its cap
+    #[test]
+    fn test_pareto_padding() {
+        let policy = PaddingPolicy::Pareto {
+            scale: 512,
+            shape: 1.5,
+        };
+        for len in [0, 100, 2000] {
+            for _ in 0..200 {
+                let padded = policy.padded_len(len);
+                assert!(padded >= len);
+                assert!(padded <= len + len.max(512));
+                assert!(padded <= policy.max_padded_len(len));
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Prekey bundles for asynchronous session establishment.
+//!
+//! A session normally needs both peers' announcements, so an initiator has to
+//! wait for the peer to announce back before sending anything. A user can
+//! instead publish a prekey bundle ahead of time: a fresh ML-KEM key signed by
+//! their long-term keys. An initiator that fetched the bundle establishes a
+//! session on its own and sends right away; its announcement carries what the
+//! bundle owner needs to join the same session.
+
+use crate::error::SessionError;
+use alloc::vec::Vec;
+use auth::{AuthBlob, UserId, UserPublicKeys, UserSecretKeys};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Number of prekeys kept by
+/// [`SessionManager::create_prekey_bundle`](crate::SessionManager::create_prekey_bundle).
+pub const MAX_PREKEYS: usize = 4;
+
+/// Secret payload of the bundle signature. Bundles are public: there is no
+/// shared secret to bind.
+const PREKEY_BUNDLE_CONTEXT: &[u8] = b"sessions.prekey_bundle";
+
+/// Prekey signed in a bundle.
+#[derive(Serialize, Deserialize)]
+struct PrekeyBundlePayload {
+    prekey_id: u64,
+    public_key: crypto_kem::PublicKey,
+}
+
+/// A prekey we published, kept to join the sessions initiators build on it.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct Prekey {
+    #[zeroize(skip)]
+    id: u64,
+    secret_key: crypto_kem::SecretKey,
+    public_key: crypto_kem::PublicKey,
+}
+
+impl Prekey {
+    pub(crate) fn generate() -> Self {
+        let mut id = [0u8; 8];
+        crypto_rng::fill_buffer(&mut id);
+        let mut randomness = [0u8; crypto_kem::KEY_GENERATION_RANDOMNESS_SIZE];
+        crypto_rng::fill_buffer(&mut randomness);
+        let (secret_key, public_key) = crypto_kem::generate_key_pair(randomness);
+        randomness.zeroize();
+        Self {
+            id: u64::from_le_bytes(id),
+            secret_key,
+            public_key,
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Signs the bundle to publish.
+    pub(crate) fn bundle(&self, our_pk: &UserPublicKeys, our_sk: &UserSecretKeys) -> Vec<u8> {
+        let payload = PrekeyBundlePayload {
+            prekey_id: self.id,
+            public_key: self.public_key.clone(),
+        };
+        let payload = bincode::serde::encode_to_vec(&payload, bincode::config::standard())
+            .expect("Failed to serialize prekey bundle");
+        let blob = AuthBlob::new(our_pk.clone(), our_sk, payload, PREKEY_BUNDLE_CONTEXT);
+        bincode::serde::encode_to_vec(&blob, bincode::config::standard())
+            .expect("Failed to serialize prekey bundle")
+    }
+
+    /// Rebuilds our side of a session an initiator built on this prekey.
+    pub(crate) fn accept(
+        &self,
+        prekey_use: &PrekeyUse,
+    ) -> Result<crypto_agraphon::OutgoingAnnouncement, SessionError> {
+        let ciphertext: [u8; crypto_kem::CIPHERTEXT_SIZE] = prekey_use
+            .ciphertext
+            .as_slice()
+            .try_into()
+            .map_err(|_| SessionError::Undecryptable)?;
+        Ok(crypto_agraphon::OutgoingAnnouncement::from_prekey(
+            &self.secret_key,
+            &self.public_key,
+            &crypto_kem::Ciphertext::from(ciphertext),
+        ))
+    }
+}
+
+/// Parses a bundle published by `peer_id` and checks its signature,
+/// returning the prekey ID and key.
+pub(crate) fn verify_bundle(
+    bytes: &[u8],
+    peer_id: &UserId,
+) -> Result<(u64, crypto_kem::PublicKey), SessionError> {
+    let (blob, _): (AuthBlob, _) =
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map_err(|_| SessionError::Undecryptable)?;
+    if blob.public_keys().derive_id() != *peer_id || !blob.verify(PREKEY_BUNDLE_CONTEXT) {
+        return Err(SessionError::InvalidSignature);
+    }
+    let (payload, _): (PrekeyBundlePayload, _) =
+        bincode::serde::decode_from_slice(blob.public_payload(), bincode::config::standard())
+            .map_err(|_| SessionError::Undecryptable)?;
+    Ok((payload.prekey_id, payload.public_key))
+}
+
+/// What an announcement built on a prekey carries for the bundle owner,
+/// signed along with the rest of the session initialization payload.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct PrekeyUse {
+    /// Prekey the initiator used
+    #[zeroize(skip)]
+    pub(crate) prekey_id: u64,
+    /// Encapsulation to the prekey
+    pub(crate) ciphertext: Vec<u8>,
+    /// Seed of the bundle owner's initial seeker keypair, chosen by the
+    /// initiator since the owner did not announce one
+    pub(crate) seeker_seed: [u8; 32],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_test_keypair() -> (UserPublicKeys, UserSecretKeys) {
+        let mut passphrase = [0u8; 32];
+        crypto_rng::fill_buffer(&mut passphrase);
+        let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+        auth::derive_keys_from_static_root_secret(&root_secret)
+    }
+
+    /// Tests that a bundle only verifies for the identity that signed it
+    #[test]
+    fn test_bundle_signed_by_owner() {
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let (eve_pk, _) = generate_test_keypair();
+
+        let prekey = Prekey::generate();
+        let bundle = prekey.bundle(&bob_pk, &bob_sk);
+        let (prekey_id, public_key) = verify_bundle(&bundle, &bob_pk.derive_id()).unwrap();
+        assert_eq!(prekey_id, prekey.id());
+        assert_eq!(public_key.as_bytes(), prekey.public_key.as_bytes());
+        assert_eq!(
+            verify_bundle(&bundle, &eve_pk.derive_id()).err(),
+            Some(SessionError::InvalidSignature)
+        );
+        assert!(verify_bundle(&bundle[1..], &bob_pk.derive_id()).is_err());
+    }
+}
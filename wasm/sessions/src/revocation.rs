@@ -0,0 +1,83 @@
+//! Announcement revocation.
+//!
+//! Announcements stay on the board and keep their session material. A user
+//! who lost a device, or suspects its state leaked, publishes a revocation
+//! signed by their long-term keys: every announcement they created up to the
+//! revocation time is rejected from then on, and peers close the sessions
+//! formed with them. Sessions resume once the user announces again.
+
+use crate::error::SessionError;
+use alloc::vec::Vec;
+use auth::{AuthBlob, UserId, UserPublicKeys, UserSecretKeys};
+use serde::{Deserialize, Serialize};
+
+/// Secret payload of the revocation signature. Revocations are public: there
+/// is no shared secret to bind.
+const REVOCATION_CONTEXT: &[u8] = b"sessions.announcement_revocation";
+
+/// Announcements revoked by a revocation record.
+#[derive(Serialize, Deserialize)]
+struct RevocationPayload {
+    /// Announcements created at or before this time are revoked
+    revoked_until_millis: u128,
+}
+
+/// Signs a revocation of our announcements created at or before
+/// `revoked_until_millis`.
+pub(crate) fn create(
+    our_pk: &UserPublicKeys,
+    our_sk: &UserSecretKeys,
+    revoked_until_millis: u128,
+) -> Vec<u8> {
+    let payload = bincode::serde::encode_to_vec(
+        RevocationPayload {
+            revoked_until_millis,
+        },
+        bincode::config::standard(),
+    )
+    .expect("Failed to serialize announcement revocation");
+    let blob = AuthBlob::new(our_pk.clone(), our_sk, payload, REVOCATION_CONTEXT);
+    bincode::serde::encode_to_vec(&blob, bincode::config::standard())
+        .expect("Failed to serialize announcement revocation")
+}
+
+/// Parses a revocation and checks its signature, returning its signer and the
+/// time up to which their announcements are revoked.
+pub(crate) fn verify(bytes: &[u8]) -> Result<(UserId, u128), SessionError> {
+    let (blob, _): (AuthBlob, _) =
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map_err(|_| SessionError::Undecryptable)?;
+    if !blob.verify(REVOCATION_CONTEXT) {
+        return Err(SessionError::InvalidSignature);
+    }
+    let (payload, _): (RevocationPayload, _) =
+        bincode::serde::decode_from_slice(blob.public_payload(), bincode::config::standard())
+            .map_err(|_| SessionError::Undecryptable)?;
+    Ok((blob.public_keys().derive_id(), payload.revoked_until_millis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_test_keypair() -> (UserPublicKeys, UserSecretKeys) {
+        let mut passphrase = [0u8; 32];
+        crypto_rng::fill_buffer(&mut passphrase);
+        let root_secret = auth::StaticRootSecret::from_passphrase(&passphrase);
+        auth::derive_keys_from_static_root_secret(&root_secret)
+    }
+
+    /// Tests that a revocation verifies for its signer only
+    #[test]
+    fn test_revocation_roundtrip() {
+        let (bob_pk, bob_sk) = generate_test_keypair();
+        let revocation = create(&bob_pk, &bob_sk, 42);
+        assert_eq!(verify(&revocation), Ok((bob_pk.derive_id(), 42)));
+        assert!(verify(&revocation[1..]).is_err());
+
+        let mut tampered = revocation.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(verify(&tampered).is_err());
+    }
+}
@@ -0,0 +1,457 @@
+//! Persistence into a secure storage session.
+//!
+//! With the `secure-storage` feature, a [`SessionStore`] keeps the
+//! incremental snapshots of a [`SessionManager`] in one namespace of an
+//! unlocked `secureStorage` session. The namespace holds a header, the
+//! manifest, one slot per peer record and a directory of the slots. A save
+//! writes only the manifest, the changed peers, the directory and the header,
+//! so block-level writes stay proportional to what changed.
+//!
+//! Saves never overwrite data the stored directory points to: new contents
+//! go to free or appended space, and the header is switched to the new
+//! directory last. An interrupted save leaves the previous state readable.
+//! Space freed by a save is reused by the following ones.
+
+use crate::incremental::{IncrementalSnapshot, PeerRecord};
+use crate::session_manager::SessionManager;
+use alloc::vec::Vec;
+use auth::UserId;
+use secure_storage::storage::{BlockStorage, KeypairStorage};
+use secure_storage::{
+    NamespaceState, PLAINTEXT_SIZE, SecureStorageError, UnlockedSession, load_namespace_state,
+    read_session_data, write_session_data,
+};
+use serde::{Deserialize, Serialize};
+
+/// First bytes of a namespace written by a [`SessionStore`].
+const MAGIC: [u8; 4] = *b"GSSM";
+
+/// Version of the namespace layout.
+const LAYOUT_VERSION: u8 = 1;
+
+/// Size of the header at offset 0: magic, layout version, 3 reserved bytes,
+/// then the offset, length and capacity of the directory as big-endian u64.
+const HEADER_SIZE: u64 = 32;
+
+/// Error of a [`SessionStore`] operation.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum StoreError {
+    /// The underlying storage failed
+    #[error("storage error: {0}")]
+    Storage(#[from] SecureStorageError),
+    /// The namespace does not hold a session store, or its directory is
+    /// inconsistent
+    #[error("corrupted session store")]
+    Corrupted,
+    /// The manager state could not be serialized
+    #[error("session state could not be serialized")]
+    Serialization,
+    /// The stored state could not be decrypted with the given key
+    #[error("session state could not be decrypted")]
+    Undecryptable,
+}
+
+/// What a slot holds.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum SlotOwner {
+    Manifest,
+    Peer(UserId),
+    Free,
+}
+
+/// A range of the namespace.
+#[derive(Clone, Serialize, Deserialize)]
+struct Slot {
+    owner: SlotOwner,
+    offset: u64,
+    capacity: u64,
+    len: u64,
+}
+
+impl Slot {
+    fn end(&self) -> u64 {
+        self.offset + self.capacity
+    }
+}
+
+/// [`SessionManager`] state kept in one namespace of an unlocked storage
+/// session.
+///
+/// The namespace must be dedicated to the store. Storage, domain and session
+/// are passed to every call, like to the `secureStorage` functions.
+pub struct SessionStore {
+    namespace: u8,
+    ns_state: NamespaceState,
+    slots: Vec<Slot>,
+    /// Location of the stored directory; `None` before the first save
+    directory: Option<Slot>,
+}
+
+impl SessionStore {
+    /// Opens the store kept in `namespace`, empty if the namespace holds no
+    /// data yet.
+    pub fn open<S: BlockStorage>(
+        storage: &S,
+        domain: &str,
+        session: &UnlockedSession,
+        namespace: u8,
+    ) -> Result<Self, StoreError> {
+        let ns_state = load_namespace_state(storage, domain, session, namespace)?;
+        let mut store = Self {
+            namespace,
+            ns_state,
+            slots: Vec::new(),
+            directory: None,
+        };
+        if ns_state.total_data_length == 0 {
+            return Ok(store);
+        }
+
+        let header = store.read(storage, domain, session, 0, HEADER_SIZE)?;
+        if header.iter().all(|&byte| byte == 0) {
+            // the first save was interrupted before its header was written
+            return Ok(store);
+        }
+        if header[..4] != MAGIC || header[4] != LAYOUT_VERSION {
+            log::warn!("namespace {namespace} holds no session store");
+            return Err(StoreError::Corrupted);
+        }
+        let field = |index: usize| {
+            let start = 8 + 8 * index;
+            u64::from_be_bytes(header[start..start + 8].try_into().unwrap())
+        };
+        let directory = Slot {
+            owner: SlotOwner::Free,
+            offset: field(0),
+            len: field(1),
+            capacity: field(2),
+        };
+        let encoded = store.read(storage, domain, session, directory.offset, directory.len)?;
+        store.slots = bincode::serde::decode_from_slice(&encoded, bincode::config::standard())
+            .map_err(|_| StoreError::Corrupted)?
+            .0;
+        store.directory = Some(directory);
+        Ok(store)
+    }
+
+    /// Returns whether nothing was saved yet.
+    pub fn is_empty(&self) -> bool {
+        self.directory.is_none()
+    }
+
+    /// Loads the stored manager, or returns `None` if nothing was saved yet.
+    pub fn load<S: BlockStorage>(
+        &self,
+        storage: &S,
+        domain: &str,
+        session: &UnlockedSession,
+        key: &crypto_aead::Key,
+    ) -> Result<Option<SessionManager>, StoreError> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        let mut manifest = None;
+        let mut peer_records = Vec::new();
+        for slot in &self.slots {
+            match &slot.owner {
+                SlotOwner::Manifest => {
+                    manifest = Some(self.read(storage, domain, session, slot.offset, slot.len)?);
+                }
+                SlotOwner::Peer(peer_id) => peer_records.push(PeerRecord {
+                    peer_id: peer_id.clone(),
+                    blob: self.read(storage, domain, session, slot.offset, slot.len)?,
+                }),
+                SlotOwner::Free => {}
+            }
+        }
+        let manifest = manifest.ok_or(StoreError::Corrupted)?;
+        let manager = SessionManager::from_incremental_snapshot(&manifest, &peer_records, key);
+        if manager.is_none() {
+            log::warn!("stored session state could not be decrypted");
+        }
+        manager.map(Some).ok_or(StoreError::Undecryptable)
+    }
+
+    /// Saves the changes of `manager` since its previous incremental
+    /// snapshot.
+    ///
+    /// On error the changes stay pending in `manager`, and the previously
+    /// saved state stays readable.
+    pub fn save<S: BlockStorage + KeypairStorage>(
+        &mut self,
+        storage: &mut S,
+        domain: &str,
+        session: &UnlockedSession,
+        manager: &mut SessionManager,
+        key: &crypto_aead::Key,
+    ) -> Result<(), StoreError> {
+        let snapshot = manager
+            .take_incremental_snapshot(key)
+            .ok_or(StoreError::Serialization)?;
+        let result = self.write_snapshot(storage, domain, session, &snapshot);
+        match &result {
+            Ok(()) => log::debug!(
+                "session store saved: {} updated and {} removed peers",
+                snapshot.updated_peers.len(),
+                snapshot.removed_peers.len()
+            ),
+            Err(e) => {
+                log::warn!("session store save failed: {e}");
+                manager.unmark_stored(&snapshot);
+            }
+        }
+        result
+    }
+
+    fn write_snapshot<S: BlockStorage + KeypairStorage>(
+        &mut self,
+        storage: &mut S,
+        domain: &str,
+        session: &UnlockedSession,
+        snapshot: &IncrementalSnapshot,
+    ) -> Result<(), StoreError> {
+        // slots the stored directory points to are only free after the switch
+        let mut slots = self.slots.clone();
+        let mut released = Vec::new();
+        let mut end = self.end();
+        for peer_id in &snapshot.removed_peers {
+            released.extend(take_owned(&mut slots, &SlotOwner::Peer(peer_id.clone())));
+        }
+        let writes = core::iter::once((SlotOwner::Manifest, &snapshot.manifest)).chain(
+            snapshot
+                .updated_peers
+                .iter()
+                .map(|record| (SlotOwner::Peer(record.peer_id.clone()), &record.blob)),
+        );
+        for (owner, data) in writes {
+            released.extend(take_owned(&mut slots, &owner));
+            let mut slot = allocate(&mut slots, &mut end, data.len() as u64);
+            self.write(storage, domain, session, slot.offset, data)?;
+            slot.owner = owner;
+            slot.len = data.len() as u64;
+            slots.push(slot);
+        }
+        released.extend(self.directory.clone());
+
+        // the directory lists the released slots as free; taking its own
+        // slot out of the list only shrinks it
+        let encode = |slots: &[Slot], released: &[Slot]| {
+            let all: Vec<&Slot> = slots.iter().chain(released).collect();
+            bincode::serde::encode_to_vec(&all, bincode::config::standard())
+                .map_err(|_| StoreError::Serialization)
+        };
+        let upper_bound = encode(&slots, &released)?.len() as u64;
+        let mut directory = allocate(&mut slots, &mut end, upper_bound);
+        slots.extend(released.into_iter().map(|mut slot| {
+            slot.owner = SlotOwner::Free;
+            slot.len = 0;
+            slot
+        }));
+        let encoded = encode(&slots, &[])?;
+        directory.len = encoded.len() as u64;
+        self.write(storage, domain, session, directory.offset, &encoded)?;
+
+        // switch to the new directory
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = LAYOUT_VERSION;
+        for (index, value) in [directory.offset, directory.len, directory.capacity]
+            .into_iter()
+            .enumerate()
+        {
+            header[8 + 8 * index..16 + 8 * index].copy_from_slice(&value.to_be_bytes());
+        }
+        self.write(storage, domain, session, 0, &header)?;
+
+        self.slots = slots;
+        self.directory = Some(directory);
+        Ok(())
+    }
+
+    /// Returns the end of the space in use, past the header.
+    fn end(&self) -> u64 {
+        self.slots
+            .iter()
+            .chain(&self.directory)
+            .map(Slot::end)
+            .fold(HEADER_SIZE, u64::max)
+    }
+
+    fn read<S: BlockStorage>(
+        &self,
+        storage: &S,
+        domain: &str,
+        session: &UnlockedSession,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        let len = usize::try_from(len).map_err(|_| StoreError::Corrupted)?;
+        let data = read_session_data(
+            storage,
+            domain,
+            self.namespace,
+            session,
+            &self.ns_state,
+            offset,
+            len,
+        )
+        .map_err(|e| match e {
+            SecureStorageError::OutOfBounds => StoreError::Corrupted,
+            e => StoreError::Storage(e),
+        })?;
+        Ok(data.to_vec())
+    }
+
+    fn write<S: BlockStorage + KeypairStorage>(
+        &mut self,
+        storage: &mut S,
+        domain: &str,
+        session: &UnlockedSession,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), StoreError> {
+        write_session_data(
+            storage,
+            domain,
+            self.namespace,
+            session,
+            &mut self.ns_state,
+            offset,
+            data,
+        )?;
+        Ok(())
+    }
+}
+
+/// Removes the slot of `owner` from `slots`, if any.
+fn take_owned(slots: &mut Vec<Slot>, owner: &SlotOwner) -> Option<Slot> {
+    let index = slots.iter().position(|slot| slot.owner == *owner)?;
+    Some(slots.swap_remove(index))
+}
+
+/// Takes the smallest free slot that fits `len` bytes, or appends a new one
+/// with room to grow, rounded up to whole blocks.
+fn allocate(slots: &mut Vec<Slot>, end: &mut u64, len: u64) -> Slot {
+    let best_fit = slots
+        .iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.owner == SlotOwner::Free && slot.capacity >= len)
+        .min_by_key(|(_, slot)| slot.capacity)
+        .map(|(index, _)| index);
+    if let Some(index) = best_fit {
+        return slots.swap_remove(index);
+    }
+    let capacity = (len + len / 4)
+        .max(1)
+        .next_multiple_of(PLAINTEXT_SIZE as u64);
+    let slot = Slot {
+        owner: SlotOwner::Free,
+        offset: *end,
+        capacity,
+        len: 0,
+    };
+    *end += capacity;
+    slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionStatus;
+    use crate::session_manager::SessionManagerConfig;
+    use auth::{StaticRootSecret, derive_keys_from_static_root_secret};
+    use secure_storage::storage::MemoryStorage;
+    use secure_storage::{SessionIndex, allocate_session, provision_storage};
+
+    const DOMAIN: &str = "test";
+    const NAMESPACE: u8 = 7;
+
+    fn test_session(storage: &mut MemoryStorage) -> UnlockedSession {
+        provision_storage(storage).unwrap();
+        allocate_session(storage, DOMAIN, SessionIndex::new(0).unwrap(), b"password").unwrap()
+    }
+
+    fn test_config() -> SessionManagerConfig {
+        SessionManagerConfig {
+            max_incoming_announcement_age_millis: 60_000,
+            max_incoming_message_age_millis: 60_000,
+            max_session_inactivity_millis: 3_600_000,
+            keep_alive_interval_millis: 60_000,
+            max_session_lag_length: 100,
+            announcement_cache_size: 0,
+            announcement_cache_max_age_millis: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Tests that saved managers load back, that later saves replace the
+    /// stored peers, and that freed space is reused
+    #[test]
+    fn test_save_and_load() {
+        let mut storage = MemoryStorage::new();
+        let session = test_session(&mut storage);
+        let key = crypto_aead::Key::from([1u8; crypto_aead::KEY_SIZE]);
+        let (alice_pk, alice_sk) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(b"alice"));
+        let (bob_pk, _) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(b"bob"));
+        let bob_id = bob_pk.derive_id();
+
+        let mut store = SessionStore::open(&storage, DOMAIN, &session, NAMESPACE).unwrap();
+        assert!(store.is_empty());
+        assert!(
+            store
+                .load(&storage, DOMAIN, &session, &key)
+                .unwrap()
+                .is_none()
+        );
+
+        let mut manager = SessionManager::new(test_config());
+        manager
+            .establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![])
+            .unwrap();
+        store
+            .save(&mut storage, DOMAIN, &session, &mut manager, &key)
+            .unwrap();
+
+        let store = SessionStore::open(&storage, DOMAIN, &session, NAMESPACE).unwrap();
+        let mut loaded = store
+            .load(&storage, DOMAIN, &session, &key)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            loaded.peer_session_status(&bob_id),
+            SessionStatus::SelfRequested
+        ));
+        let wrong_key = crypto_aead::Key::from([2u8; crypto_aead::KEY_SIZE]);
+        assert!(matches!(
+            store.load(&storage, DOMAIN, &session, &wrong_key),
+            Err(StoreError::Undecryptable)
+        ));
+
+        // saving again only rewrites what changed, in freed space
+        let mut store = store;
+        loaded.peer_discard(&bob_id);
+        store
+            .save(&mut storage, DOMAIN, &session, &mut loaded, &key)
+            .unwrap();
+        let end = store.end();
+        for _ in 0..4 {
+            store
+                .save(&mut storage, DOMAIN, &session, &mut loaded, &key)
+                .unwrap();
+        }
+        assert_eq!(store.end(), end);
+
+        let store = SessionStore::open(&storage, DOMAIN, &session, NAMESPACE).unwrap();
+        let reloaded = store
+            .load(&storage, DOMAIN, &session, &key)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            reloaded.peer_session_status(&bob_id),
+            SessionStatus::UnknownPeer
+        ));
+    }
+}
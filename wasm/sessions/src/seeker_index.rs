@@ -0,0 +1,133 @@
+//! Seeker to peer index.
+//!
+//! Every message board read carries the seeker it was read at, and the
+//! manager needs the peer whose session expects it. Recomputing each
+//! session's next seeker on every read is linear in the number of peers, so
+//! the index maps each peer's next seeker to the peer and is updated whenever
+//! a session is created, advanced or closed. It is persisted with the manager
+//! so that restoring thousands of sessions does not derive every seeker
+//! again; incremental snapshots, whose peer records are written separately,
+//! rebuild it instead. Entries of ephemeral peers are left out of persisted
+//! state, like the peers themselves.
+
+use crate::collections::{HashMap, HashSet};
+use alloc::vec::Vec;
+use auth::UserId;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Default)]
+pub(crate) struct SeekerIndex {
+    /// Next seeker of each peer session, to its peer
+    peers: HashMap<Vec<u8>, UserId>,
+    /// Indexed seeker of each peer, to replace it when the session moves on
+    seekers: HashMap<UserId, Vec<u8>>,
+    /// Indexed peers that are not persisted
+    ephemeral: HashSet<UserId>,
+}
+
+impl SeekerIndex {
+    pub(crate) fn get(&self, seeker: &[u8]) -> Option<&UserId> {
+        self.peers.get(seeker)
+    }
+
+    /// Sets the next seeker of a peer, or removes the peer if it has no
+    /// active session.
+    pub(crate) fn set(&mut self, peer_id: &UserId, seeker: Option<Vec<u8>>, ephemeral: bool) {
+        if let Some(old_seeker) = self.seekers.remove(peer_id) {
+            self.peers.remove(&old_seeker);
+        }
+        self.ephemeral.remove(peer_id);
+        if let Some(seeker) = seeker {
+            if ephemeral {
+                self.ephemeral.insert(peer_id.clone());
+            }
+            self.peers.insert(seeker.clone(), peer_id.clone());
+            self.seekers.insert(peer_id.clone(), seeker);
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Vec<u8>, &UserId)> {
+        self.peers.iter()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.peers.clear();
+        self.seekers.clear();
+        self.ephemeral.clear();
+    }
+}
+
+/// Serialized as the seeker to peer map of persistent peers; the reverse map
+/// is derived.
+impl Serialize for SeekerIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // bincode needs the length upfront
+        let persistent: Vec<_> = self
+            .peers
+            .iter()
+            .filter(|(_, peer_id)| !self.ephemeral.contains(*peer_id))
+            .collect();
+        serializer.collect_map(persistent)
+    }
+}
+
+impl<'de> Deserialize<'de> for SeekerIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let peers = HashMap::<Vec<u8>, UserId>::deserialize(deserializer)?;
+        let seekers = peers
+            .iter()
+            .map(|(seeker, peer_id)| (peer_id.clone(), seeker.clone()))
+            .collect();
+        Ok(Self {
+            peers,
+            seekers,
+            ephemeral: HashSet::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that moving or removing a peer's seeker drops the old entry
+    #[test]
+    fn test_set_replaces_previous_seeker() {
+        let alice = UserId::from_bytes([1u8; 32]);
+        let bob = UserId::from_bytes([2u8; 32]);
+        let mut index = SeekerIndex::default();
+
+        index.set(&alice, Some(b"a1".to_vec()), false);
+        index.set(&bob, Some(b"b1".to_vec()), false);
+        assert_eq!(index.get(b"a1"), Some(&alice));
+
+        index.set(&alice, Some(b"a2".to_vec()), false);
+        assert_eq!(index.get(b"a1"), None);
+        assert_eq!(index.get(b"a2"), Some(&alice));
+
+        index.set(&bob, None, false);
+        assert_eq!(index.get(b"b1"), None);
+        assert_eq!(index.iter().count(), 1);
+    }
+
+    /// Tests that a deserialized index still replaces previous seekers and
+    /// leaves out ephemeral peers
+    #[test]
+    fn test_serialization_roundtrip() {
+        let alice = UserId::from_bytes([1u8; 32]);
+        let bob = UserId::from_bytes([2u8; 32]);
+        let mut index = SeekerIndex::default();
+        index.set(&alice, Some(b"a1".to_vec()), false);
+        index.set(&bob, Some(b"b1".to_vec()), true);
+
+        let bytes = bincode::serde::encode_to_vec(&index, bincode::config::standard()).unwrap();
+        let (mut restored, _): (SeekerIndex, usize) =
+            bincode::serde::decode_from_slice(&bytes, bincode::config::standard()).unwrap();
+        assert_eq!(restored.get(b"a1"), Some(&alice));
+        assert_eq!(restored.get(b"b1"), None);
+
+        restored.set(&alice, Some(b"a2".to_vec()), false);
+        assert_eq!(restored.get(b"a1"), None);
+        assert_eq!(restored.iter().count(), 1);
+    }
+}
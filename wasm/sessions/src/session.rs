@@ -71,6 +71,15 @@
 //! assert_eq!(received.message, b"Hello Bob!");
 //! ```
 
+use crate::error::SessionError;
+use crate::padding::PaddingPolicy;
+use crate::prekey::{Prekey, PrekeyUse};
+use crate::transfer::{MessagePart, TransferProgress};
+#[cfg(feature = "std")]
+use crate::{Clock, SystemClock};
+use alloc::collections::VecDeque;
+use alloc::{vec, vec::Vec};
+use core::ops::RangeInclusive;
 use serde::{Deserialize, Serialize};
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
@@ -79,6 +88,53 @@ use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 /// where hash_bytes is the massa_hash of the seeker's public key.
 const MESSAGE_SEEKER_DB_KEY: &[u8] = &[1u8];
 
+/// Domain separation of message seekers.
+///
+/// Seekers are public keys of the message board, and by default each one is
+/// the hash of the seeker public key posted with the message: anyone can
+/// check that relation and single out gossip traffic, and applications or
+/// networks sharing a board share one seeker space. A non-empty namespace is
+/// hashed in front of the public key, so that seekers of different namespaces
+/// never collide and cannot be recognized without knowing the namespace.
+/// Both sides of a session must use the same namespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SeekerNamespace(Vec<u8>);
+
+impl SeekerNamespace {
+    /// Creates a namespace from domain separation bytes. Empty bytes give the
+    /// default namespace, whose seekers are the hash of the public key alone.
+    pub fn new(domain: impl Into<Vec<u8>>) -> Self {
+        Self(domain.into())
+    }
+
+    /// Returns the domain separation bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Whether this is the default namespace.
+    pub fn is_default(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Hashes a seeker public key in this namespace.
+    fn hash(&self, public_key_bytes: &[u8]) -> Vec<u8> {
+        let hash = if self.is_default() {
+            massa_hash::Hash::compute_from(public_key_bytes)
+        } else {
+            massa_hash::Hash::compute_from(
+                &[
+                    (self.0.len() as u32).to_le_bytes().as_slice(),
+                    &self.0,
+                    public_key_bytes,
+                ]
+                .concat(),
+            )
+        };
+        hash.to_bytes().to_vec()
+    }
+}
+
 /// Session initialization payload embedded in announcements.
 ///
 /// This is serialized, encrypted in an auth blob, and included in the announcement.
@@ -109,21 +165,54 @@ pub(crate) struct AuthPayload {
 pub(crate) struct Message {
     /// Timestamp when the message was created (milliseconds since Unix epoch)
     pub timestamp: u128,
+    /// Position of the message among the sender's messages in the session,
+    /// starting at 1
+    pub id: u64,
     /// Next Massa keypair for future seeker generation (part of the ratchet)
     #[zeroize(skip)]
     pub seeker_massa_keypair_next: massa_signature::KeyPair,
     /// Actual message contents provided by the user
     pub contents: Vec<u8>,
+    /// Sender's requested lifetime for message plaintexts, in milliseconds
+    pub ttl_millis: Option<u64>,
+    /// Set when the message is part of a chunked transfer
+    pub part: Option<MessagePart>,
+    /// Seeker public keys of the messages after the next one, in order, so
+    /// the peer can fetch them ahead of time
+    #[zeroize(skip)]
+    pub seeker_public_keys_ahead: Vec<massa_signature::PublicKey>,
+}
+
+/// Identifies a message sent through a `SessionManager`, for tracking its
+/// delivery with `SessionManager::message_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MessageHandle(pub(crate) u64);
+
+impl MessageHandle {
+    /// Returns the handle as an integer, e.g. to store it alongside the message.
+    pub const fn to_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Rebuilds a handle from [`to_u64`](Self::to_u64).
+    pub const fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
 }
 
 /// Output from sending a message.
 ///
 /// Contains the seeker (database lookup key) and the encrypted message data
 /// that should be posted to the public message board.
-#[derive(Zeroize, ZeroizeOnDrop)]
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct SendOutgoingMessageOutput {
     /// Message timestamp (milliseconds since Unix epoch)
     pub timestamp: u128,
+    /// Message ID, increasing by one with every message of the session
+    /// starting at 1; the peer receives it in
+    /// [`FeedIncomingMessageOutput::message_id`]
+    #[zeroize(skip)]
+    pub message_id: u64,
     /// Seeker bytes - database key for message lookup on the message board
     /// Format: [hash_length, hash_bytes..., MESSAGE_SEEKER_DB_KEY]
     /// where hash_bytes is the massa_hash of the seeker public key
@@ -131,6 +220,28 @@ pub struct SendOutgoingMessageOutput {
     /// Encrypted message data to post to the message board
     /// Format: [seeker_pubkey_len, seeker_pubkey, sig_len, signature, encrypted_agraphon_message]
     pub data: Vec<u8>,
+    /// Delivery tracking handle, set when sent through a `SessionManager`
+    #[zeroize(skip)]
+    pub handle: Option<MessageHandle>,
+    /// Epoch of the session the message was sent in, set when sent through a
+    /// `SessionManager` (see `SessionManager::session_epoch`); not
+    /// persisted, so `None` for outputs held across a save and reload
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub session_epoch: Option<u64>,
+    /// Lag of the session once the message was sent: our messages the peer
+    /// has not acknowledged yet, as compared to
+    /// `SessionManagerConfig::max_session_lag_length`. Set when sent through
+    /// a `SessionManager`; not persisted
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub lag_length: Option<u64>,
+    /// Messages that can still be sent before the session saturates, set
+    /// along with [`lag_length`](Self::lag_length); at 0, further messages
+    /// fail with `SessionError::Saturated` until the peer acknowledges some
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub remaining_lag_budget: Option<u64>,
 }
 
 /// Output from successfully decrypting an incoming message.
@@ -138,6 +249,10 @@ pub struct SendOutgoingMessageOutput {
 pub struct FeedIncomingMessageOutput {
     /// Message timestamp (milliseconds since Unix epoch)
     pub timestamp: u128,
+    /// ID the sender gave the message, increasing by one with every message
+    /// of the session starting at 1
+    #[zeroize(skip)]
+    pub message_id: u64,
     /// Decrypted message contents
     pub message: Vec<u8>,
     /// List of seekers for our messages that were acknowledged by this message
@@ -145,6 +260,38 @@ pub struct FeedIncomingMessageOutput {
     pub newly_acknowledged_self_seekers: Vec<Vec<u8>>,
     /// User Id of the peer that sent the message
     pub user_id: Vec<u8>,
+    /// Set when the message was mirrored by one of our linked devices rather
+    /// than received from the peer directly
+    #[zeroize(skip)]
+    pub device_sync: Option<crate::DeviceSync>,
+    /// Lifetime agreed with the peer for message plaintexts, in milliseconds
+    /// from `timestamp`; `None` if neither side set one
+    pub ttl_millis: Option<u64>,
+    /// Reassembly progress when the message is part of a chunked transfer.
+    /// `message` is empty until the transfer completes, then holds the
+    /// whole payload
+    #[zeroize(skip)]
+    pub transfer: Option<TransferProgress>,
+    /// Set when `message` is the user data of the peer's announcement,
+    /// deferred to the session with [`UserDataMode::Session`](crate::UserDataMode::Session)
+    #[zeroize(skip)]
+    pub announcement_user_data: bool,
+    /// Set when the message ID was already delivered; such a message only
+    /// carries its acknowledgments and is not delivered again
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub(crate) duplicate: bool,
+    /// Set when the message is protocol traffic handled by the crate rather
+    /// than a payload of the peer's application; `message` is then empty
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub control: Option<crate::ControlMessage>,
+    /// Epoch of the session the message was received in, set when received
+    /// through a `SessionManager` (see `SessionManager::session_epoch`); not
+    /// persisted, so `None` for messages held across a save and reload
+    #[serde(skip)]
+    #[zeroize(skip)]
+    pub session_epoch: Option<u64>,
 }
 
 /// Incoming session initiation request from a peer.
@@ -181,13 +328,40 @@ impl IncomingInitiationRequest {
         our_pk: &auth::UserPublicKeys,
         our_sk: &auth::UserSecretKeys,
     ) -> Option<(Self, Vec<u8>)> {
+        Self::parse(bytes, our_pk, our_sk)
+            .ok()
+            .map(|(request, user_data, _)| (request, user_data))
+    }
+
+    /// Same as [`try_from`](Self::try_from), reporting why parsing failed,
+    /// and also returning the prekey use of an announcement built on one of
+    /// our prekey bundles.
+    pub(crate) fn parse(
+        bytes: &[u8],
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+    ) -> Result<(Self, Vec<u8>, Option<PrekeyUse>), SessionError> {
+        Self::parse_within(bytes, our_pk, our_sk, None)
+    }
+
+    /// Same as [`parse`](Self::parse), rejecting announcements timestamped
+    /// outside `timestamp_window` with [`SessionError::TooOld`] or
+    /// [`SessionError::TooFarInFuture`] before their signature is verified,
+    /// the costliest step once an announcement decrypted.
+    pub(crate) fn parse_within(
+        bytes: &[u8],
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+        timestamp_window: Option<&RangeInclusive<u128>>,
+    ) -> Result<(Self, Vec<u8>, Option<PrekeyUse>), SessionError> {
         // parse announcement precursor
         let incoming_announcement_precursor =
             crypto_agraphon::IncomingAnnouncementPrecursor::try_from_incoming_announcement_bytes(
                 bytes,
                 &our_pk.kem_public_key,
                 &our_sk.kem_secret_key,
-            )?;
+            )
+            .ok_or(SessionError::Undecryptable)?;
 
         // get auth payload and key
         let auth_payload = incoming_announcement_precursor.auth_payload();
@@ -196,27 +370,50 @@ impl IncomingInitiationRequest {
         // deserialize announcement contents
         let auth_payload: AuthPayload =
             bincode::serde::decode_from_slice(auth_payload, bincode::config::standard())
-                .ok()?
+                .map_err(|_| SessionError::Undecryptable)?
                 .0;
 
+        // drop stale or future announcements before verifying them; a
+        // malformed payload fails the signature check below instead
+        if let Some(timestamp_window) = timestamp_window
+            && let Ok((init_payload, _)) = bincode::serde::decode_from_slice::<SessionInitPayload, _>(
+                auth_payload.auth_blob.public_payload(),
+                bincode::config::standard(),
+            )
+        {
+            if init_payload.unix_timestamp_millis < *timestamp_window.start() {
+                return Err(SessionError::TooOld);
+            }
+            if init_payload.unix_timestamp_millis > *timestamp_window.end() {
+                return Err(SessionError::TooFarInFuture);
+            }
+        }
+
         // verify auth blob
         if !auth_payload.auth_blob.verify(auth_key) {
-            return None;
+            return Err(SessionError::InvalidSignature);
         }
 
-        // deserialize inner data
-        let init_payload: SessionInitPayload = bincode::serde::decode_from_slice(
-            auth_payload.auth_blob.public_payload(),
-            bincode::config::standard(),
-        )
-        .ok()?
-        .0;
+        // deserialize inner data, followed by the prekey use if any
+        let signed_payload = auth_payload.auth_blob.public_payload();
+        let (init_payload, init_payload_len): (SessionInitPayload, _) =
+            bincode::serde::decode_from_slice(signed_payload, bincode::config::standard())
+                .map_err(|_| SessionError::Undecryptable)?;
+        let prekey_use = match &signed_payload[init_payload_len..] {
+            [] => None,
+            bytes => Some(
+                bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+                    .map_err(|_| SessionError::Undecryptable)?
+                    .0,
+            ),
+        };
 
         // finalize agraphon announcement
         let agraphon_announcement = incoming_announcement_precursor
-            .finalize(auth_payload.auth_blob.public_keys().kem_public_key.clone())?;
+            .finalize(auth_payload.auth_blob.public_keys().kem_public_key.clone())
+            .ok_or(SessionError::Undecryptable)?;
 
-        Some((
+        Ok((
             Self {
                 agraphon_announcement: agraphon_announcement.clone(),
                 origin_public_keys: auth_payload.auth_blob.public_keys().clone(),
@@ -224,8 +421,24 @@ impl IncomingInitiationRequest {
                 seeker_seed: init_payload.seeker_seed,
             },
             auth_payload.user_data.clone(),
+            prekey_use,
         ))
     }
+
+    /// Joins the session an initiator built on one of our prekeys, returning
+    /// our side of the handshake in place of the announcement we did not
+    /// send.
+    pub(crate) fn accept_prekey(
+        &self,
+        prekey: &Prekey,
+        prekey_use: &PrekeyUse,
+    ) -> Result<OutgoingInitiationRequest, SessionError> {
+        Ok(OutgoingInitiationRequest {
+            agraphon_announcement: prekey.accept(prekey_use)?,
+            timestamp_millis: self.timestamp_millis,
+            seeker_seed: prekey_use.seeker_seed,
+        })
+    }
 }
 
 /// Outgoing session initiation request.
@@ -242,15 +455,109 @@ pub struct OutgoingInitiationRequest {
 }
 
 impl OutgoingInitiationRequest {
+    #[cfg(feature = "std")]
     pub fn new(
         our_pk: &auth::UserPublicKeys,
         our_sk: &auth::UserSecretKeys,
         peer_pk: &auth::UserPublicKeys,
         user_data: Vec<u8>,
     ) -> (Vec<u8>, Self) {
-        // get current timestamp
-        let timestamp_millis = crate::utils::timestamp_millis();
+        Self::new_at(
+            our_pk,
+            our_sk,
+            peer_pk,
+            user_data,
+            SystemClock.now_millis(),
+            None,
+            &PaddingPolicy::None,
+        )
+    }
+
+    /// Same as [`new`](Self::new), drawing every random byte from the
+    /// keystream of `seed` (see `crypto_rng::with_seed`) and timestamped with
+    /// `timestamp_millis`: the same inputs yield byte-identical
+    /// announcements, so test harnesses and implementations in other
+    /// languages can compare them.
+    ///
+    /// The announcement is only as secret as `seed`; never use it outside
+    /// tests.
+    #[cfg(any(test, feature = "deterministic"))]
+    pub fn new_deterministic(
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+        peer_pk: &auth::UserPublicKeys,
+        user_data: Vec<u8>,
+        seed: [u8; 32],
+        timestamp_millis: u128,
+    ) -> (Vec<u8>, Self) {
+        crypto_rng::with_seed(seed, || {
+            Self::new_at(
+                our_pk,
+                our_sk,
+                peer_pk,
+                user_data,
+                timestamp_millis,
+                None,
+                &PaddingPolicy::None,
+            )
+        })
+    }
+
+    /// Same as [`new_at`](Self::new_at), building on a prekey published by
+    /// the peer: also returns the peer's side of the handshake, so a session
+    /// can start without waiting for the peer's announcement.
+    pub(crate) fn new_with_prekey_at(
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+        peer_pk: &auth::UserPublicKeys,
+        (prekey_id, prekey_pk): (u64, &crypto_kem::PublicKey),
+        user_data: Vec<u8>,
+        timestamp_millis: u128,
+        padding: &PaddingPolicy,
+    ) -> (Vec<u8>, Self, IncomingInitiationRequest) {
+        let (ciphertext, agraphon_announcement) =
+            crypto_agraphon::IncomingAnnouncement::from_prekey(
+                peer_pk.kem_public_key.clone(),
+                prekey_pk,
+            );
+        let mut seeker_seed = [0u8; 32];
+        crypto_rng::fill_buffer(&mut seeker_seed);
+        let prekey_use = PrekeyUse {
+            prekey_id,
+            ciphertext: ciphertext.as_bytes().to_vec(),
+            seeker_seed,
+        };
+
+        let (announcement_bytes, outgoing) = Self::new_at(
+            our_pk,
+            our_sk,
+            peer_pk,
+            user_data,
+            timestamp_millis,
+            Some(&prekey_use),
+            padding,
+        );
+        let incoming = IncomingInitiationRequest {
+            agraphon_announcement,
+            origin_public_keys: peer_pk.clone(),
+            timestamp_millis,
+            seeker_seed,
+        };
+        (announcement_bytes, outgoing, incoming)
+    }
 
+    /// Same as [`new`](Self::new), timestamped with `timestamp_millis`
+    /// instead of the system clock, with the prekey use of an announcement
+    /// built on a prekey bundle, and padding the encrypted payload.
+    pub(crate) fn new_at(
+        our_pk: &auth::UserPublicKeys,
+        our_sk: &auth::UserSecretKeys,
+        peer_pk: &auth::UserPublicKeys,
+        user_data: Vec<u8>,
+        timestamp_millis: u128,
+        prekey_use: Option<&PrekeyUse>,
+        padding: &PaddingPolicy,
+    ) -> (Vec<u8>, Self) {
         // prepare agraphon outgoing announcement precursor
         let agraphon_announcement_precursor =
             crypto_agraphon::OutgoingAnnouncementPrecursor::new(&peer_pk.kem_public_key);
@@ -271,9 +578,16 @@ impl OutgoingInitiationRequest {
             seeker_seed,
             unix_timestamp_millis: timestamp_millis,
         };
-        let session_init_payload_bytes =
+        let mut session_init_payload_bytes =
             bincode::serde::encode_to_vec(&session_init_payload, bincode::config::standard())
                 .expect("Failed to serialize outgoing session initiation request");
+        // appended so that receivers without prekey support still parse the payload
+        if let Some(prekey_use) = prekey_use {
+            session_init_payload_bytes.extend(
+                bincode::serde::encode_to_vec(prekey_use, bincode::config::standard())
+                    .expect("Failed to serialize prekey use"),
+            );
+        }
 
         // create auth payload
         let auth_payload = AuthPayload {
@@ -285,10 +599,11 @@ impl OutgoingInitiationRequest {
             ),
             user_data,
         };
-        let auth_payload_bytes = Zeroizing::new(
+        // the padding follows the auth payload and is ignored when parsing it
+        let auth_payload_bytes = padding.pad(&Zeroizing::new(
             bincode::serde::encode_to_vec(&auth_payload, bincode::config::standard())
                 .expect("Failed to serialize auth blob"),
-        );
+        ));
 
         // finalize announcement
         let (announcement_bytes, announcement) =
@@ -303,6 +618,51 @@ impl OutgoingInitiationRequest {
             },
         )
     }
+
+    /// Returns the size of the announcement [`new_at`](Self::new_at) builds
+    /// for `user_data_len` bytes of user data, without a prekey. Pareto
+    /// padding counts at its cap and the announcer's Massa public key at its
+    /// longest text, the size is then an upper bound.
+    pub(crate) fn announcement_size(
+        user_data_len: usize,
+        timestamp_millis: u128,
+        padding: &PaddingPolicy,
+    ) -> usize {
+        // length prefix, `P` and the base58check of the versioned key, at most
+        const MAX_MASSA_PUBLIC_KEY_LEN: usize = 53;
+
+        // any identity has the key and signature sizes of ours, but the Massa
+        // public key is serialized as text, whose length varies between keys
+        let (our_pk, our_sk) = auth::derive_keys_from_static_root_secret(
+            &auth::StaticRootSecret::from_bytes([0u8; auth::STATIC_ROOT_SECRET_SIZE]),
+        );
+        let massa_key_len = serialized_len(&our_pk.massa_public_key);
+        let session_init_payload = SessionInitPayload {
+            seeker_seed: [0u8; 32],
+            unix_timestamp_millis: timestamp_millis,
+        };
+        let session_init_payload_bytes =
+            bincode::serde::encode_to_vec(&session_init_payload, bincode::config::standard())
+                .expect("Failed to serialize outgoing session initiation request");
+        let auth_payload = AuthPayload {
+            auth_blob: auth::AuthBlob::new(our_pk, &our_sk, session_init_payload_bytes, &[0u8; 32]),
+            user_data: Vec::new(),
+        };
+
+        // the user data adds its bytes and grows its length prefix
+        let auth_payload_len = serialized_len(&auth_payload) - serialized_len(&0u64)
+            + serialized_len(&(user_data_len as u64))
+            + user_data_len
+            + (MAX_MASSA_PUBLIC_KEY_LEN - massa_key_len);
+        crypto_agraphon::ANNOUNCEMENT_OVERHEAD + padding.max_padded_len(auth_payload_len)
+    }
+}
+
+/// Returns the length of `value` serialized as in announcements and messages.
+fn serialized_len<T: Serialize>(value: &T) -> usize {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .expect("Failed to serialize value")
+        .len()
 }
 
 /// An established session between two peers.
@@ -326,6 +686,17 @@ pub struct Session {
     /// Current Massa keypair for the next message we will send to the peer
     #[zeroize(skip)]
     self_seeker_massa_keypair: massa_signature::KeyPair,
+    /// Lifetime we request for message plaintexts, sent with every message
+    self_ttl_millis: Option<u64>,
+    /// Lifetime the peer requested in its latest message
+    peer_ttl_millis: Option<u64>,
+    /// Keypairs of the messages we will send after the next one, in order
+    #[zeroize(skip)]
+    self_seeker_lookahead: VecDeque<massa_signature::KeyPair>,
+    /// Seeker public keys of the messages the peer will send after the next
+    /// one, as announced in its latest message
+    #[zeroize(skip)]
+    peer_seeker_lookahead: Vec<massa_signature::PublicKey>,
 }
 
 impl Session {
@@ -406,14 +777,29 @@ impl Session {
             peer_public_keys: incoming_initiation_request.origin_public_keys.clone(),
             peer_seeker_massa_keypair,
             self_seeker_massa_keypair,
+            self_ttl_millis: None,
+            peer_ttl_millis: None,
+            self_seeker_lookahead: VecDeque::new(),
+            peer_seeker_lookahead: Vec::new(),
         }
     }
 
-    fn compute_seeker(seeker_public_key: &massa_signature::PublicKey) -> Vec<u8> {
+    /// Generates a seeker keypair from `crypto_rng`, so that seeded test
+    /// vectors reproduce it.
+    fn generate_seeker_keypair() -> massa_signature::KeyPair {
+        // Massa keypair format: [version_byte, 32_secret_key_bytes]
+        let mut seeker_key = Zeroizing::new([0u8; 33]);
+        crypto_rng::fill_buffer(&mut seeker_key[1..]);
+        massa_signature::KeyPair::from_bytes(seeker_key.as_slice())
+            .expect("Failed to generate seeker keypair")
+    }
+
+    fn compute_seeker(
+        namespace: &SeekerNamespace,
+        seeker_public_key: &massa_signature::PublicKey,
+    ) -> Vec<u8> {
         // Hash the public key bytes to get a fixed-size identifier
-        let public_key_bytes = seeker_public_key.to_bytes();
-        let hash = massa_hash::Hash::compute_from(&public_key_bytes);
-        let hash_bytes = hash.to_bytes();
+        let hash_bytes = namespace.hash(&seeker_public_key.to_bytes());
 
         [
             &[hash_bytes.len() as u8],
@@ -447,36 +833,68 @@ impl Session {
     ///
     /// A [`SendOutgoingMessageOutput`] containing the seeker (database key) and encrypted data
     /// that should be posted to the message board.
+    #[cfg(feature = "std")]
     pub fn send_outgoing_message(&mut self, message: &[u8]) -> SendOutgoingMessageOutput {
-        // get timestamp
-        let timestamp = crate::utils::timestamp_millis();
+        self.send_outgoing_message_part(
+            message,
+            None,
+            &PaddingPolicy::None,
+            SystemClock.now_millis(),
+            &SeekerNamespace::default(),
+        )
+    }
 
-        // generate seeker for next message on our side
-        let mut seeker_keypair =
-            massa_signature::KeyPair::generate(0).expect("Failed to generate seeker keypair");
+    /// Same as [`send_outgoing_message`](Self::send_outgoing_message), marking
+    /// the message as part of a chunked transfer, padding it before
+    /// encryption, timestamping it with `timestamp` and posting it at a
+    /// seeker of `namespace`.
+    pub(crate) fn send_outgoing_message_part(
+        &mut self,
+        message: &[u8],
+        part: Option<MessagePart>,
+        padding: &PaddingPolicy,
+        timestamp: u128,
+        namespace: &SeekerNamespace,
+    ) -> SendOutgoingMessageOutput {
+        // generate seeker for next message on our side, or for the end of the
+        // lookahead window which then provides the next one
+        let mut seeker_keypair = Self::generate_seeker_keypair();
+        if let Some(next_keypair) = self.self_seeker_lookahead.pop_front() {
+            self.self_seeker_lookahead.push_back(seeker_keypair);
+            seeker_keypair = next_keypair;
+        }
 
         // flip with the current seeker
-        std::mem::swap(&mut seeker_keypair, &mut self.self_seeker_massa_keypair);
+        core::mem::swap(&mut seeker_keypair, &mut self.self_seeker_massa_keypair);
         // seeker_keypair is now the "current" seeker
 
         // compute ephemeral seeker public key
         let seeker_public_key = seeker_keypair.get_public_key();
 
         // assemble seeker datastore key
-        let seeker = Self::compute_seeker(&seeker_public_key);
+        let seeker = Self::compute_seeker(namespace, &seeker_public_key);
 
-        // create message
+        // create message, the announcement being the first at height 1
+        let id = self.agraphon_instance.self_height();
         let msg = Message {
             timestamp,
+            id,
             seeker_massa_keypair_next: self.self_seeker_massa_keypair.clone(),
             contents: message.to_vec(),
+            ttl_millis: self.self_ttl_millis,
+            part,
+            seeker_public_keys_ahead: self
+                .self_seeker_lookahead
+                .iter()
+                .map(|keypair| keypair.get_public_key())
+                .collect(),
         };
 
-        // serialize message
-        let msg_bytes: Zeroizing<Vec<u8>> = Zeroizing::new(
+        // serialize and pad message
+        let msg_bytes: Zeroizing<Vec<u8>> = padding.pad(&Zeroizing::new(
             bincode::serde::encode_to_vec(&msg, bincode::config::standard())
                 .expect("Failed to serialize message"),
-        );
+        ));
 
         // feed agraphon
         let agraphon_message_bytes = self.agraphon_instance.send_outgoing_message(
@@ -513,11 +931,65 @@ impl Session {
 
         SendOutgoingMessageOutput {
             timestamp,
+            message_id: id,
             seeker: seeker.to_vec(),
             data,
+            handle: None,
+            session_epoch: None,
+            lag_length: None,
+            remaining_lag_budget: None,
         }
     }
 
+    /// Returns the sizes of the seeker and of the data of a message with
+    /// `contents_len` bytes of contents, as posted by
+    /// [`send_outgoing_message_part`](Self::send_outgoing_message_part) with
+    /// `lookahead` seekers ahead. Message IDs and lifetimes count at their
+    /// largest and Pareto padding at its cap, so the data size is an upper
+    /// bound.
+    pub(crate) fn outgoing_message_size(
+        contents_len: usize,
+        part: Option<MessagePart>,
+        lookahead: usize,
+        padding: &PaddingPolicy,
+        timestamp: u128,
+        namespace: &SeekerNamespace,
+    ) -> (usize, usize) {
+        // any seeker keypair has the key and signature sizes of a random one
+        let seeker_keypair = massa_signature::KeyPair::from_bytes(&[0u8; 33])
+            .expect("Failed to build seeker keypair");
+        let seeker_public_key = seeker_keypair.get_public_key();
+        let seeker = Self::compute_seeker(namespace, &seeker_public_key);
+        let seeker_public_key_len = seeker_public_key.to_bytes().len();
+        let signature_len = seeker_keypair
+            .sign(&massa_hash::Hash::compute_from(&seeker))
+            .expect("Failed to sign message")
+            .to_bytes()
+            .len();
+
+        let msg = Message {
+            timestamp,
+            id: u64::MAX,
+            seeker_massa_keypair_next: seeker_keypair,
+            contents: Vec::new(),
+            ttl_millis: Some(u64::MAX),
+            part,
+            seeker_public_keys_ahead: vec![seeker_public_key; lookahead],
+        };
+
+        // the contents add their bytes and grow their length prefix
+        let msg_len = serialized_len(&msg) - serialized_len(&0u64)
+            + serialized_len(&(contents_len as u64))
+            + contents_len;
+        let data_len = 1
+            + seeker_public_key_len
+            + 1
+            + signature_len
+            + crypto_agraphon::MESSAGE_OVERHEAD
+            + padding.max_padded_len(msg_len);
+        (seeker.len(), data_len)
+    }
+
     /// Returns the seeker (database key) for the next message from the peer.
     ///
     /// Use this to look up the peer's next message on the message board. The seeker
@@ -526,7 +998,44 @@ impl Session {
     /// After successfully receiving a message via [`try_feed_incoming_message`](Self::try_feed_incoming_message),
     /// this seeker will be updated to point to the subsequent message.
     pub fn next_peer_message_seeker(&self) -> Vec<u8> {
-        Self::compute_seeker(&self.peer_seeker_massa_keypair.get_public_key())
+        self.next_peer_message_seeker_in(&SeekerNamespace::default())
+    }
+
+    /// Same as [`next_peer_message_seeker`](Self::next_peer_message_seeker),
+    /// in `namespace`.
+    pub(crate) fn next_peer_message_seeker_in(&self, namespace: &SeekerNamespace) -> Vec<u8> {
+        Self::compute_seeker(namespace, &self.peer_seeker_massa_keypair.get_public_key())
+    }
+
+    /// Returns the seekers of the messages the peer will send after the next
+    /// one, in order, as far as the peer announced them.
+    ///
+    /// Entries at these seekers can be fetched ahead of time, but only decrypt
+    /// once every earlier message was fed.
+    pub fn peer_message_seekers_ahead(&self) -> Vec<Vec<u8>> {
+        self.peer_message_seekers_ahead_in(&SeekerNamespace::default())
+    }
+
+    /// Same as [`peer_message_seekers_ahead`](Self::peer_message_seekers_ahead),
+    /// in `namespace`.
+    pub(crate) fn peer_message_seekers_ahead_in(
+        &self,
+        namespace: &SeekerNamespace,
+    ) -> Vec<Vec<u8>> {
+        self.peer_seeker_lookahead
+            .iter()
+            .map(|public_key| Self::compute_seeker(namespace, public_key))
+            .collect()
+    }
+
+    /// Sets how many seekers beyond the next one we announce to the peer with
+    /// every message (0 by default).
+    pub fn set_seeker_lookahead(&mut self, window: usize) {
+        self.self_seeker_lookahead.truncate(window);
+        while self.self_seeker_lookahead.len() < window {
+            self.self_seeker_lookahead
+                .push_back(Self::generate_seeker_keypair());
+        }
     }
 
     /// Attempts to decrypt and process an incoming message from the peer.
@@ -551,36 +1060,52 @@ impl Session {
         seeker: &[u8],
         message: &[u8],
     ) -> Option<FeedIncomingMessageOutput> {
+        self.feed_incoming_message(self_static_sk, seeker, message, &SeekerNamespace::default())
+            .ok()
+            .map(|(output, _)| output)
+    }
+
+    /// Same as [`try_feed_incoming_message`](Self::try_feed_incoming_message),
+    /// reporting why the message was rejected and returning its chunked
+    /// transfer marker, if any. The seeker must be of `namespace`.
+    pub(crate) fn feed_incoming_message(
+        &mut self,
+        self_static_sk: &auth::UserSecretKeys,
+        seeker: &[u8],
+        message: &[u8],
+        namespace: &SeekerNamespace,
+    ) -> Result<(FeedIncomingMessageOutput, Option<MessagePart>), SessionError> {
         // decompose seeker
-        let hash_len = *seeker.first()? as usize;
-        let hash_bytes = seeker.get(1..1 + hash_len)?;
+        let malformed = SessionError::Undecryptable;
+        let hash_len = *seeker.first().ok_or(malformed)? as usize;
+        let hash_bytes = seeker.get(1..1 + hash_len).ok_or(malformed)?;
         if seeker.get(1 + hash_len..) != Some(MESSAGE_SEEKER_DB_KEY) {
-            return None;
+            return Err(malformed);
         }
 
         // decompose the data
-        let seeker_public_key_len = *message.first()? as usize;
-        let seeker_public_key_bytes = message.get(1..1 + seeker_public_key_len)?;
-        let seeker_public_key =
-            massa_signature::PublicKey::from_bytes(seeker_public_key_bytes).ok()?;
+        let seeker_public_key_len = *message.first().ok_or(malformed)? as usize;
+        let seeker_public_key_bytes = message.get(1..1 + seeker_public_key_len).ok_or(malformed)?;
+        let seeker_public_key = massa_signature::PublicKey::from_bytes(seeker_public_key_bytes)
+            .map_err(|_| malformed)?;
 
         let signature_offset = 1 + seeker_public_key_len;
-        let signature_len = *message.get(signature_offset)? as usize;
-        let signature_bytes =
-            message.get(signature_offset + 1..signature_offset + 1 + signature_len)?;
-        let signature = massa_signature::Signature::from_bytes(signature_bytes).ok()?;
+        let signature_len = *message.get(signature_offset).ok_or(malformed)? as usize;
+        let signature_bytes = message
+            .get(signature_offset + 1..signature_offset + 1 + signature_len)
+            .ok_or(malformed)?;
+        let signature =
+            massa_signature::Signature::from_bytes(signature_bytes).map_err(|_| malformed)?;
 
         let message_bytes = message
-            .get(signature_offset + 1 + signature_len..)?
+            .get(signature_offset + 1 + signature_len..)
+            .ok_or(malformed)?
             .to_vec();
 
         // check that the hash derives from the seeker public key by recomputing it
-        let public_key_bytes = seeker_public_key.to_bytes();
-        let expected_hash = massa_hash::Hash::compute_from(&public_key_bytes);
-        let expected_hash_bytes = expected_hash.to_bytes();
-
+        let expected_hash_bytes = namespace.hash(&seeker_public_key.to_bytes());
         if hash_bytes != expected_hash_bytes.as_slice() {
-            return None;
+            return Err(malformed);
         }
 
         // check that the signature is valid
@@ -590,36 +1115,51 @@ impl Session {
             .verify_signature(&hash_to_verify, &signature)
             .is_err()
         {
-            return None;
+            return Err(SessionError::InvalidSignature);
         }
 
         // try to read message from agraphon
+        let last_message_id = self.agraphon_instance.peer_height() - 1;
         let agraphon_result = self
             .agraphon_instance
-            .try_feed_incoming_message(&self_static_sk.kem_secret_key, &message_bytes)?;
+            .try_feed_incoming_message(&self_static_sk.kem_secret_key, &message_bytes)
+            .ok_or(malformed)?;
 
         // deserialize the message
         let message: Message = bincode::serde::decode_from_slice(
             &agraphon_result.message_bytes,
             bincode::config::standard(),
         )
-        .ok()?
+        .map_err(|_| malformed)?
         .0;
 
         // update peer seeker keypair for next message
         self.peer_seeker_massa_keypair = message.seeker_massa_keypair_next.clone();
+        self.peer_seeker_lookahead = message.seeker_public_keys_ahead.clone();
+
+        // the peer's latest TTL request replaces the previous one
+        self.peer_ttl_millis = message.ttl_millis;
 
         // get user id of the peer that sent the message
         let user_id = self.peer_public_keys.derive_id();
 
-        Some(FeedIncomingMessageOutput {
+        let output = FeedIncomingMessageOutput {
             timestamp: message.timestamp,
+            message_id: message.id,
             message: message.contents.clone(),
             newly_acknowledged_self_seekers: agraphon_result
                 .newly_acknowledged_self_seekers
                 .clone(),
             user_id: user_id.as_bytes().to_vec(),
-        })
+            device_sync: None,
+            ttl_millis: self.message_ttl(),
+            transfer: None,
+            announcement_user_data: false,
+            duplicate: message.id <= last_message_id,
+            control: None,
+            session_epoch: None,
+        };
+        Ok((output, message.part.clone()))
     }
 
     /// Returns the number of unacknowledged self messages sent by this session.
@@ -631,6 +1171,50 @@ impl Session {
         self.agraphon_instance.self_lag_length()
     }
 
+    /// Returns the seekers of our messages the peer has not acknowledged yet,
+    /// oldest first.
+    pub fn unacknowledged_self_seekers(&self) -> Vec<Vec<u8>> {
+        self.agraphon_instance.unacknowledged_self_seekers()
+    }
+
+    /// Returns the number of messages we sent in the session.
+    pub fn sent_message_count(&self) -> u64 {
+        self.agraphon_instance.self_height() - 1
+    }
+
+    /// Returns the number of messages received from the peer in the session.
+    pub fn received_message_count(&self) -> u64 {
+        self.agraphon_instance.peer_height() - 1
+    }
+
+    /// Sets the lifetime we request for message plaintexts.
+    ///
+    /// The request travels encrypted with every outgoing message, so the peer
+    /// learns it with our next message.
+    pub fn set_message_ttl(&mut self, ttl_millis: Option<u64>) {
+        self.self_ttl_millis = ttl_millis;
+    }
+
+    /// Returns the agreed lifetime for message plaintexts: the shorter of our
+    /// request and the peer's latest one, or `None` if neither side set one.
+    pub fn message_ttl(&self) -> Option<u64> {
+        match (self.self_ttl_millis, self.peer_ttl_millis) {
+            (Some(ours), Some(theirs)) => Some(ours.min(theirs)),
+            (ours, theirs) => ours.or(theirs),
+        }
+    }
+
+    /// Returns the peer's long-term public keys.
+    pub(crate) fn peer_public_keys(&self) -> &auth::UserPublicKeys {
+        &self.peer_public_keys
+    }
+
+    /// Replaces the peer's long-term public keys after a key transition;
+    /// later messages are encrypted to the new keys.
+    pub(crate) fn set_peer_public_keys(&mut self, peer_public_keys: auth::UserPublicKeys) {
+        self.peer_public_keys = peer_public_keys;
+    }
+
     /// Returns how many peer messages are not yet acknowledged by our latest outgoing message.
     ///
     /// This value increases when we receive messages without replying, and drops to `0`
@@ -643,6 +1227,31 @@ impl Session {
     }
 }
 
+/// `Session` as persisted before state format versioning, without message
+/// lifetimes and seeker lookahead.
+#[derive(Deserialize)]
+pub(crate) struct SessionV0 {
+    agraphon_instance: crypto_agraphon::Agraphon,
+    peer_public_keys: auth::UserPublicKeys,
+    peer_seeker_massa_keypair: massa_signature::KeyPair,
+    self_seeker_massa_keypair: massa_signature::KeyPair,
+}
+
+impl From<SessionV0> for Session {
+    fn from(v0: SessionV0) -> Self {
+        Self {
+            agraphon_instance: v0.agraphon_instance,
+            peer_public_keys: v0.peer_public_keys,
+            peer_seeker_massa_keypair: v0.peer_seeker_massa_keypair,
+            self_seeker_massa_keypair: v0.self_seeker_massa_keypair,
+            self_ttl_millis: None,
+            peer_ttl_millis: None,
+            self_seeker_lookahead: VecDeque::new(),
+            peer_seeker_lookahead: Vec::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -659,10 +1268,14 @@ mod tests {
     /// Helper function to create a test message with given contents
     fn create_test_message(contents: &[u8]) -> Message {
         Message {
-            timestamp: crate::utils::timestamp_millis(),
+            timestamp: SystemClock.now_millis(),
+            id: 0,
             seeker_massa_keypair_next: massa_signature::KeyPair::generate(0)
                 .expect("Failed to generate placeholder keypair"),
             contents: contents.to_vec(),
+            ttl_millis: None,
+            part: None,
+            seeker_public_keys_ahead: Vec::new(),
         }
     }
 
@@ -682,6 +1295,49 @@ mod tests {
         assert!(outgoing_req.timestamp_millis > 0);
     }
 
+    /// Tests that seeded announcements are byte-identical and parse with
+    /// their injected timestamp
+    #[test]
+    fn test_outgoing_initiation_request_deterministic() {
+        let (our_pk, our_sk) = generate_test_keypair();
+        let (peer_pk, peer_sk) = generate_test_keypair();
+        let timestamp_millis = 1_700_000_000_000;
+
+        let (first, _) = OutgoingInitiationRequest::new_deterministic(
+            &our_pk,
+            &our_sk,
+            &peer_pk,
+            b"hi".to_vec(),
+            [1; 32],
+            timestamp_millis,
+        );
+        let (second, outgoing_req) = OutgoingInitiationRequest::new_deterministic(
+            &our_pk,
+            &our_sk,
+            &peer_pk,
+            b"hi".to_vec(),
+            [1; 32],
+            timestamp_millis,
+        );
+        assert_eq!(first, second);
+        assert_eq!(outgoing_req.timestamp_millis, timestamp_millis);
+
+        let (other_seed, _) = OutgoingInitiationRequest::new_deterministic(
+            &our_pk,
+            &our_sk,
+            &peer_pk,
+            b"hi".to_vec(),
+            [2; 32],
+            timestamp_millis,
+        );
+        assert_ne!(first, other_seed);
+
+        let (incoming_req, user_data) =
+            IncomingInitiationRequest::try_from(&first, &peer_pk, &peer_sk).unwrap();
+        assert_eq!(user_data, b"hi");
+        assert_eq!(incoming_req.timestamp_millis, timestamp_millis);
+    }
+
     /// Tests that an incoming initiation request can be parsed from announcement bytes
     /// and contains the expected public keys and timestamp
     #[test]
@@ -776,7 +1432,7 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         // Establish sessions
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, bob_outgoing) =
@@ -820,7 +1476,7 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         // Establish sessions
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, bob_outgoing) =
@@ -875,7 +1531,7 @@ mod tests {
         let (eve_pk, eve_sk) = generate_test_keypair();
 
         // Establish session between Alice and Bob
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, _bob_outgoing) =
@@ -977,7 +1633,7 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         // Establish sessions
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, bob_outgoing) =
@@ -1030,7 +1686,7 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         // Establish sessions
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, bob_outgoing) =
@@ -1066,7 +1722,7 @@ mod tests {
         let (bob_pk, bob_sk) = generate_test_keypair();
 
         // Establish sessions
-        let _timestamp = crate::utils::timestamp_millis();
+        let _timestamp = SystemClock.now_millis();
         let (alice_announcement, alice_outgoing) =
             OutgoingInitiationRequest::new(&alice_pk, &alice_sk, &bob_pk, vec![]);
         let (bob_announcement, bob_outgoing) =
@@ -0,0 +1,350 @@
+//! Simulated message board for tests.
+//!
+//! Managers normally exchange announcements and messages through the
+//! blockchain board, which drops nothing but is read late, out of order and
+//! sometimes twice by clients. [`InMemoryBoard`] models that for tests: every
+//! posted entry becomes readable after a random latency, may be lost, and
+//! reads may return entries shuffled or twice, as set by [`BoardConditions`].
+//! Time is that of a [`MockClock`], shared with the managers.
+//!
+//! [`BoardUser`] drives a manager against the board: it posts what the
+//! manager sends and feeds it what it can read, so two managers can talk
+//! without piping outputs directly. Faults are drawn from a seeded generator,
+//! so a failing run can be replayed.
+
+use crate::collections::{HashMap, HashSet};
+use crate::error::SessionError;
+use crate::session::{FeedIncomingMessageOutput, SendOutgoingMessageOutput};
+use crate::session_manager::SessionManager;
+use crate::{Clock, MockClock};
+use alloc::vec::Vec;
+use auth::UserId;
+
+/// Faults of an [`InMemoryBoard`]; the default delivers everything at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BoardConditions {
+    /// Probability that a posted entry never becomes readable
+    pub loss: f64,
+    /// Probability that a read entry is returned twice
+    pub duplication: f64,
+    /// Probability that a batch of read entries is returned shuffled
+    pub reordering: f64,
+    /// Minimum delay before a posted entry becomes readable
+    pub min_latency_millis: u64,
+    /// Maximum delay before a posted entry becomes readable
+    pub max_latency_millis: u64,
+}
+
+/// Entry posted on the board.
+struct Entry {
+    data: Vec<u8>,
+    /// When the entry becomes readable
+    readable_at_millis: u128,
+}
+
+/// In-memory message board with simulated network faults.
+pub struct InMemoryBoard {
+    conditions: BoardConditions,
+    clock: MockClock,
+    /// State of the fault generator
+    rng_state: u64,
+    announcements: Vec<Entry>,
+    messages: HashMap<Vec<u8>, Entry>,
+}
+
+impl InMemoryBoard {
+    /// Creates an empty board reading time from `clock`, drawing faults from
+    /// `seed`.
+    pub fn new(conditions: BoardConditions, clock: MockClock, seed: u64) -> Self {
+        Self {
+            conditions,
+            clock,
+            rng_state: seed,
+            announcements: Vec::new(),
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Returns the clock of the board, to share with the managers.
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    /// Posts an announcement.
+    pub fn post_announcement(&mut self, announcement: Vec<u8>) {
+        if let Some(entry) = self.entry(announcement) {
+            self.announcements.push(entry);
+        }
+    }
+
+    /// Posts a message at its seeker, replacing any entry already there.
+    pub fn post_message(&mut self, output: &SendOutgoingMessageOutput) {
+        if let Some(entry) = self.entry(output.data.clone()) {
+            self.messages.insert(output.seeker.clone(), entry);
+        }
+    }
+
+    /// Returns the readable announcements whose index is not in `read`,
+    /// adding them to it.
+    pub fn read_announcements(&mut self, read: &mut HashSet<usize>) -> Vec<Vec<u8>> {
+        let now = self.clock.now_millis();
+        let entries: Vec<Vec<u8>> = self
+            .announcements
+            .iter()
+            .enumerate()
+            .filter(|(index, entry)| entry.readable_at_millis <= now && read.insert(*index))
+            .map(|(_, entry)| entry.data.clone())
+            .collect();
+        self.deliver(entries)
+    }
+
+    /// Returns the readable messages at `seekers`, with their seeker.
+    pub fn read_messages(&mut self, seekers: &[Vec<u8>]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let now = self.clock.now_millis();
+        let entries = seekers
+            .iter()
+            .filter_map(|seeker| {
+                let entry = self.messages.get(seeker)?;
+                (entry.readable_at_millis <= now).then(|| (seeker.clone(), entry.data.clone()))
+            })
+            .collect();
+        self.deliver(entries)
+    }
+
+    /// Returns the entry to store for `data`, or `None` if it is lost.
+    fn entry(&mut self, data: Vec<u8>) -> Option<Entry> {
+        if self.chance(self.conditions.loss) {
+            return None;
+        }
+        let min = self.conditions.min_latency_millis;
+        let spread = self.conditions.max_latency_millis.saturating_sub(min);
+        let latency = min + self.next_u64() % spread.saturating_add(1);
+        Some(Entry {
+            data,
+            readable_at_millis: self.clock.now_millis() + u128::from(latency),
+        })
+    }
+
+    /// Applies duplication and reordering to a batch of reads.
+    fn deliver<T: Clone>(&mut self, entries: Vec<T>) -> Vec<T> {
+        let mut delivered = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if self.chance(self.conditions.duplication) {
+                delivered.push(entry.clone());
+            }
+            delivered.push(entry);
+        }
+        if self.chance(self.conditions.reordering) {
+            for i in (1..delivered.len()).rev() {
+                let j = (self.next_u64() % (i as u64 + 1)) as usize;
+                delivered.swap(i, j);
+            }
+        }
+        delivered
+    }
+
+    /// Returns whether an event of probability `p` happens.
+    fn chance(&mut self, p: f64) -> bool {
+        // 53 random bits, uniform in [0, 1)
+        p > 0.0 && ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// Next output of the fault generator (SplitMix64).
+    fn next_u64(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// A manager and its keys, reading and writing an [`InMemoryBoard`].
+pub struct BoardUser {
+    pub manager: SessionManager,
+    pub pk: auth::UserPublicKeys,
+    pub sk: auth::UserSecretKeys,
+    /// Indices of the announcements already read
+    announcements_read: HashSet<usize>,
+}
+
+impl BoardUser {
+    /// Wraps a manager, which should read time from the board's clock.
+    pub fn new(
+        manager: SessionManager,
+        pk: auth::UserPublicKeys,
+        sk: auth::UserSecretKeys,
+    ) -> Self {
+        Self {
+            manager,
+            pk,
+            sk,
+            announcements_read: HashSet::new(),
+        }
+    }
+
+    /// Returns the identity of the user.
+    pub fn id(&self) -> UserId {
+        self.pk.derive_id()
+    }
+
+    /// Announces to `peer`, posting the announcement.
+    pub fn announce(
+        &mut self,
+        board: &mut InMemoryBoard,
+        peer_pk: &auth::UserPublicKeys,
+    ) -> Result<(), SessionError> {
+        let announcement =
+            self.manager
+                .establish_outgoing_session(peer_pk, &self.pk, &self.sk, Vec::new())?;
+        board.post_announcement(announcement);
+        Ok(())
+    }
+
+    /// Sends a message to `peer_id`, posting it.
+    pub fn send(
+        &mut self,
+        board: &mut InMemoryBoard,
+        peer_id: &UserId,
+        message: &[u8],
+    ) -> Result<(), SessionError> {
+        let output = self.manager.try_send_message(peer_id, message)?;
+        board.post_message(&output);
+        Ok(())
+    }
+
+    /// Refreshes the manager, posting the keep-alives it produces.
+    pub fn refresh(&mut self, board: &mut InMemoryBoard) {
+        for (_, keep_alive) in self.manager.refresh_with_keepalives().1 {
+            board.post_message(&keep_alive);
+        }
+    }
+
+    /// Feeds the manager everything it can read from the board, returning the
+    /// messages accepted. Rejected entries, such as duplicates, are skipped.
+    pub fn poll(&mut self, board: &mut InMemoryBoard) -> Vec<FeedIncomingMessageOutput> {
+        for announcement in board.read_announcements(&mut self.announcements_read) {
+            self.manager
+                .feed_incoming_announcement(&announcement, &self.pk, &self.sk);
+        }
+
+        // every fed message moves its session to the next seeker; entries
+        // read ahead are buffered by the manager, so each seeker is read once
+        let mut received = Vec::new();
+        let mut attempted = HashSet::new();
+        loop {
+            let seekers: Vec<Vec<u8>> = self
+                .manager
+                .get_message_board_read_keys()
+                .into_iter()
+                .filter(|seeker| !attempted.contains(seeker))
+                .collect();
+            let reads = board.read_messages(&seekers);
+            if reads.is_empty() {
+                received.append(&mut self.manager.take_reordered_messages());
+                return received;
+            }
+            for (seeker, data) in reads {
+                if let Ok(msg) = self
+                    .manager
+                    .try_feed_incoming_message_board_read(&seeker, &data, &self.sk)
+                {
+                    received.push(msg);
+                }
+                attempted.insert(seeker);
+            }
+        }
+    }
+}
+
+/// Announces both ways between `a` and `b`, waits out the board latency and
+/// polls both, so they end up with a session unless the board lost an
+/// announcement. Returns whether both sides have an active session.
+pub fn handshake(
+    board: &mut InMemoryBoard,
+    a: &mut BoardUser,
+    b: &mut BoardUser,
+) -> Result<bool, SessionError> {
+    let a_pk = a.pk.clone();
+    let b_pk = b.pk.clone();
+    a.announce(board, &b_pk)?;
+    b.announce(board, &a_pk)?;
+    board.clock.advance(board.conditions.max_latency_millis);
+    a.poll(board);
+    b.poll(board);
+    Ok(
+        a.manager.peer_session_status(&b.id()) == crate::SessionStatus::Active
+            && b.manager.peer_session_status(&a.id()) == crate::SessionStatus::Active,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(conditions: BoardConditions) -> InMemoryBoard {
+        InMemoryBoard::new(conditions, MockClock::new(1_000), 7)
+    }
+
+    /// Tests that entries become readable after their latency, once per reader
+    #[test]
+    fn test_latency() {
+        let mut board = board(BoardConditions {
+            min_latency_millis: 100,
+            max_latency_millis: 200,
+            ..Default::default()
+        });
+        let mut read = HashSet::new();
+        board.post_announcement(vec![1]);
+        assert!(board.read_announcements(&mut read).is_empty());
+        board.clock().advance(200);
+        assert_eq!(board.read_announcements(&mut read), vec![vec![1]]);
+        assert!(board.read_announcements(&mut read).is_empty());
+        assert_eq!(board.read_announcements(&mut HashSet::new()), vec![vec![1]]);
+    }
+
+    /// Tests that lost entries are never read and duplicated ones are read
+    /// twice
+    #[test]
+    fn test_loss_and_duplication() {
+        let mut lossy = board(BoardConditions {
+            loss: 1.0,
+            ..Default::default()
+        });
+        lossy.post_announcement(vec![1]);
+        assert!(lossy.read_announcements(&mut HashSet::new()).is_empty());
+
+        let mut duplicating = board(BoardConditions {
+            duplication: 1.0,
+            ..Default::default()
+        });
+        duplicating.post_announcement(vec![1]);
+        assert_eq!(
+            duplicating.read_announcements(&mut HashSet::new()),
+            vec![vec![1], vec![1]]
+        );
+    }
+
+    /// Tests that reordering shuffles reads without losing any, and that the
+    /// same seed gives the same order
+    #[test]
+    fn test_reordering() {
+        let conditions = BoardConditions {
+            reordering: 1.0,
+            ..Default::default()
+        };
+        let reads = |seed| {
+            let mut board = InMemoryBoard::new(conditions, MockClock::new(1_000), seed);
+            for i in 0..32u8 {
+                board.post_announcement(vec![i]);
+            }
+            board.read_announcements(&mut HashSet::new())
+        };
+        let shuffled = reads(3);
+        assert_eq!(shuffled, reads(3));
+        let mut sorted = shuffled.clone();
+        sorted.sort();
+        assert_ne!(shuffled, sorted);
+        assert_eq!(sorted, (0..32u8).map(|i| vec![i]).collect::<Vec<_>>());
+    }
+}
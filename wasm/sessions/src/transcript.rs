@@ -0,0 +1,219 @@
+//! Redacted per-peer debug transcripts.
+//!
+//! A transcript records what happened with a peer: announcements, session
+//! transitions, lag and rejection reasons, with their times. It holds no keys,
+//! seekers, user data or message contents, so users can attach it to bug
+//! reports. Transcripts live in memory only and keep the latest
+//! [`MAX_TRANSCRIPT_ENTRIES`] entries per peer.
+//!
+//! Every entry also goes to the [`log`] facade, so an application that
+//! installs a logger sees the same redacted events for all peers, without
+//! the peer IDs.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::SessionError;
+use crate::events::SessionEvent;
+use auth::UserId;
+
+/// Number of entries kept per peer; older entries are dropped first.
+pub const MAX_TRANSCRIPT_ENTRIES: usize = 256;
+
+/// Something that happened with a peer, without secrets or plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TranscriptEvent {
+    /// We made an announcement to the peer
+    AnnouncementSent,
+    /// An announcement from the peer was accepted
+    AnnouncementAccepted { announcement_timestamp_millis: u128 },
+    /// An announcement from the peer was rejected
+    AnnouncementRejected { reason: SessionError },
+    /// A session with the peer became active
+    SessionEstablished,
+    /// The session was dropped after too long without incoming messages
+    SessionExpired,
+    /// The session was closed deliberately, by us or by the peer
+    SessionClosed { by_peer: bool },
+    /// Our announcement to the peer became too old to be accepted
+    RequestExpired,
+    /// The session was closed because an incoming message was rejected
+    SessionKilled { reason: SessionError },
+    /// The session reached the lag limit
+    PeerSaturated,
+    /// An announcement from a new identity signed with the peer's wallet key
+    /// awaits approval
+    KeyChanged,
+    /// The peer moved to new long-term keys
+    PeerKeysRotated,
+    /// We sent a message; `lag` is our unacknowledged message count after it
+    MessageSent { lag: u64 },
+    /// We received a message; `lag` is our unacknowledged message count after
+    /// its acknowledgments
+    MessageReceived { lag: u64 },
+    /// An incoming message from the peer was rejected
+    MessageRejected { reason: SessionError },
+    /// The peer acknowledged `count` of our messages
+    MessagesAcknowledged { count: usize },
+    /// A chunked transfer from the peer completed
+    TransferCompleted { total_bytes: u64 },
+    /// The peer read our messages up to this message ID
+    MessagesRead { up_to_message_id: u64 },
+}
+
+impl TranscriptEvent {
+    /// Level at which the event is logged: rejections and killed sessions
+    /// are warnings, everything else is routine.
+    pub(crate) fn log_level(&self) -> log::Level {
+        match self {
+            Self::AnnouncementRejected { .. }
+            | Self::SessionKilled { .. }
+            | Self::MessageRejected { .. } => log::Level::Warn,
+            Self::SessionEstablished
+            | Self::SessionExpired
+            | Self::SessionClosed { .. }
+            | Self::RequestExpired
+            | Self::PeerSaturated
+            | Self::KeyChanged
+            | Self::PeerKeysRotated => log::Level::Info,
+            _ => log::Level::Debug,
+        }
+    }
+
+    /// Returns the peer a session event is about and its redacted form.
+    pub(crate) fn from_session_event(event: &SessionEvent) -> Option<(&UserId, Self)> {
+        let entry = match event {
+            SessionEvent::SessionEstablished { peer_id } => (peer_id, Self::SessionEstablished),
+            SessionEvent::SessionExpired { peer_id } => (peer_id, Self::SessionExpired),
+            SessionEvent::SessionClosed { peer_id, by_peer } => {
+                (peer_id, Self::SessionClosed { by_peer: *by_peer })
+            }
+            SessionEvent::RequestExpired { peer_id } => (peer_id, Self::RequestExpired),
+            SessionEvent::SessionKilled { peer_id, reason } => {
+                (peer_id, Self::SessionKilled { reason: *reason })
+            }
+            SessionEvent::PeerSaturated { peer_id } => (peer_id, Self::PeerSaturated),
+            SessionEvent::AnnouncementAccepted {
+                peer_id,
+                timestamp_millis,
+            } => (
+                peer_id,
+                Self::AnnouncementAccepted {
+                    announcement_timestamp_millis: *timestamp_millis,
+                },
+            ),
+            // the new identity is unknown until approved; log it on the known one
+            SessionEvent::KeyChanged {
+                previous_peer_id, ..
+            } => (previous_peer_id, Self::KeyChanged),
+            SessionEvent::PeerKeysRotated { peer_id, .. } => (peer_id, Self::PeerKeysRotated),
+            SessionEvent::MessageAcknowledged { peer_id, seekers } => (
+                peer_id,
+                Self::MessagesAcknowledged {
+                    count: seekers.len(),
+                },
+            ),
+            SessionEvent::TransferCompleted {
+                peer_id,
+                total_bytes,
+                ..
+            } => (
+                peer_id,
+                Self::TransferCompleted {
+                    total_bytes: *total_bytes,
+                },
+            ),
+            SessionEvent::MessagesRead {
+                peer_id,
+                up_to_message_id,
+            } => (
+                peer_id,
+                Self::MessagesRead {
+                    up_to_message_id: *up_to_message_id,
+                },
+            ),
+            // the peer is gone along with its transcript
+            SessionEvent::RequestEvicted { .. } => return None,
+        };
+        Some(entry)
+    }
+}
+
+impl fmt::Display for TranscriptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AnnouncementSent => write!(f, "announcement_sent"),
+            Self::AnnouncementAccepted {
+                announcement_timestamp_millis,
+            } => write!(
+                f,
+                "announcement_accepted timestamp={announcement_timestamp_millis}"
+            ),
+            Self::AnnouncementRejected { reason } => {
+                write!(f, "announcement_rejected reason={}", reason.code())
+            }
+            Self::SessionEstablished => write!(f, "session_established"),
+            Self::SessionExpired => write!(f, "session_expired"),
+            Self::SessionClosed { by_peer } => write!(f, "session_closed by_peer={by_peer}"),
+            Self::RequestExpired => write!(f, "request_expired"),
+            Self::SessionKilled { reason } => {
+                write!(f, "session_killed reason={}", reason.code())
+            }
+            Self::PeerSaturated => write!(f, "peer_saturated"),
+            Self::KeyChanged => write!(f, "key_changed"),
+            Self::PeerKeysRotated => write!(f, "peer_keys_rotated"),
+            Self::MessageSent { lag } => write!(f, "message_sent lag={lag}"),
+            Self::MessageReceived { lag } => write!(f, "message_received lag={lag}"),
+            Self::MessageRejected { reason } => {
+                write!(f, "message_rejected reason={}", reason.code())
+            }
+            Self::MessagesAcknowledged { count } => {
+                write!(f, "messages_acknowledged count={count}")
+            }
+            Self::TransferCompleted { total_bytes } => {
+                write!(f, "transfer_completed bytes={total_bytes}")
+            }
+            Self::MessagesRead { up_to_message_id } => {
+                write!(f, "messages_read up_to={up_to_message_id}")
+            }
+        }
+    }
+}
+
+/// A [`TranscriptEvent`] and when it happened.
+///
+/// Displays as one line, `<timestamp_millis> <event> [key=value...]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// Local time of the event, in milliseconds since the Unix epoch
+    pub timestamp_millis: u128,
+    pub event: TranscriptEvent,
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.timestamp_millis, self.event)
+    }
+}
+
+/// Bounded log of the latest entries about a peer.
+#[derive(Default)]
+pub(crate) struct Transcript(VecDeque<TranscriptEntry>);
+
+impl Transcript {
+    pub(crate) fn record(&mut self, timestamp_millis: u128, event: TranscriptEvent) {
+        if self.0.len() >= MAX_TRANSCRIPT_ENTRIES {
+            self.0.pop_front();
+        }
+        self.0.push_back(TranscriptEntry {
+            timestamp_millis,
+            event,
+        });
+    }
+
+    pub(crate) fn entries(&self) -> Vec<TranscriptEntry> {
+        self.0.iter().copied().collect()
+    }
+}
@@ -0,0 +1,241 @@
+//! Large message chunking.
+//!
+//! Payloads too large for a single message-board entry are sent as a
+//! manifest message followed by ordered chunk messages. The manifest and
+//! chunk markers travel in the encrypted message header, so chunks are
+//! indistinguishable from regular messages on the board. Messages within a
+//! session are read in order, so the receiver reassembles by appending and
+//! checks the result against the hash in the manifest.
+
+use crate::error::SessionError;
+use alloc::{vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// Default chunk size for [`SessionManager::send_large_message`](crate::SessionManager::send_large_message).
+pub const DEFAULT_CHUNK_SIZE: usize = 32 * 1024;
+
+/// Largest payload a receiver accepts for a chunked transfer.
+pub const MAX_LARGE_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Marker of a message that belongs to a chunked transfer, or carries a key
+/// transition, deferred announcement user data or a liveness probe.
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) enum MessagePart {
+    /// Announces a transfer; the message contents are empty
+    Manifest {
+        transfer_id: u64,
+        chunk_count: u32,
+        total_len: u64,
+        hash: [u8; 32],
+    },
+    /// One chunk of a transfer; the message contents are the chunk bytes
+    Chunk { transfer_id: u64, index: u32 },
+    /// The sender moved to new long-term keys; the message contents are the
+    /// signed `KeyTransition`
+    KeyTransition,
+    /// The message contents are the user data of the sender's announcement,
+    /// sent in the session rather than in the announcement
+    UserData,
+    /// Protocol traffic of the given `ControlPayload` kind; the message
+    /// contents are its encoded fields
+    Control { kind: u16 },
+}
+
+impl MessagePart {
+    /// Splits `contents` into a manifest followed by its chunks.
+    pub(crate) fn split(contents: &[u8], chunk_size: usize) -> Vec<(MessagePart, &[u8])> {
+        let mut transfer_id = [0u8; 8];
+        crypto_rng::fill_buffer(&mut transfer_id);
+        let transfer_id = u64::from_le_bytes(transfer_id);
+
+        let chunks: Vec<&[u8]> = contents.chunks(chunk_size.max(1)).collect();
+        let manifest = MessagePart::Manifest {
+            transfer_id,
+            chunk_count: chunks.len() as u32,
+            total_len: contents.len() as u64,
+            hash: payload_hash(contents),
+        };
+
+        let mut parts = vec![(manifest, &[][..])];
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let part = MessagePart::Chunk {
+                transfer_id,
+                index: index as u32,
+            };
+            parts.push((part, chunk));
+        }
+        parts
+    }
+}
+
+/// Reassembly progress of an incoming chunked transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Zeroize)]
+pub struct TransferProgress {
+    /// Identifier of the transfer, shared by its manifest and chunks
+    pub transfer_id: u64,
+    /// Payload bytes received so far
+    pub received_bytes: u64,
+    /// Total payload size announced by the manifest
+    pub total_bytes: u64,
+}
+
+impl TransferProgress {
+    /// Returns true once the whole payload was received and verified.
+    pub fn is_complete(&self) -> bool {
+        self.received_bytes == self.total_bytes
+    }
+}
+
+/// Reassembly state of an incoming chunked transfer.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub(crate) struct IncomingTransfer {
+    transfer_id: u64,
+    chunk_count: u32,
+    total_len: u64,
+    hash: [u8; 32],
+    next_index: u32,
+    payload: Vec<u8>,
+}
+
+impl IncomingTransfer {
+    /// Starts a transfer from its manifest.
+    pub(crate) fn from_manifest(part: &MessagePart) -> Result<Self, SessionError> {
+        let MessagePart::Manifest {
+            transfer_id,
+            chunk_count,
+            total_len,
+            hash,
+        } = part
+        else {
+            return Err(SessionError::InvalidTransfer);
+        };
+        if *total_len > MAX_LARGE_MESSAGE_SIZE
+            || (*chunk_count == 0 && (*total_len != 0 || *hash != payload_hash(&[])))
+        {
+            return Err(SessionError::InvalidTransfer);
+        }
+        Ok(Self {
+            transfer_id: *transfer_id,
+            chunk_count: *chunk_count,
+            total_len: *total_len,
+            hash: *hash,
+            next_index: 0,
+            payload: Vec::new(),
+        })
+    }
+
+    /// Appends the next chunk.
+    pub(crate) fn push(&mut self, part: &MessagePart, chunk: &[u8]) -> Result<(), SessionError> {
+        let MessagePart::Chunk { transfer_id, index } = part else {
+            return Err(SessionError::InvalidTransfer);
+        };
+        if *transfer_id != self.transfer_id
+            || *index != self.next_index
+            || self.next_index >= self.chunk_count
+            || chunk.is_empty()
+            || self.payload.len() as u64 + chunk.len() as u64 > self.total_len
+        {
+            return Err(SessionError::InvalidTransfer);
+        }
+        self.payload.extend_from_slice(chunk);
+        self.next_index += 1;
+
+        // verify once complete
+        if self.next_index == self.chunk_count
+            && (self.payload.len() as u64 != self.total_len
+                || payload_hash(&self.payload) != self.hash)
+        {
+            return Err(SessionError::InvalidTransfer);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn progress(&self) -> TransferProgress {
+        TransferProgress {
+            transfer_id: self.transfer_id,
+            received_bytes: self.payload.len() as u64,
+            total_bytes: self.total_len,
+        }
+    }
+
+    /// Returns true once every chunk was pushed.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.next_index == self.chunk_count
+    }
+
+    /// Takes the reassembled payload.
+    pub(crate) fn take_payload(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.payload)
+    }
+}
+
+fn payload_hash(payload: &[u8]) -> [u8; 32] {
+    *massa_hash::Hash::compute_from(payload).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reassemble(parts: &[(MessagePart, &[u8])]) -> Result<IncomingTransfer, SessionError> {
+        let mut transfer = IncomingTransfer::from_manifest(&parts[0].0)?;
+        for (part, chunk) in &parts[1..] {
+            transfer.push(part, chunk)?;
+        }
+        Ok(transfer)
+    }
+
+    /// Tests that a payload split into chunks is reassembled identically
+    #[test]
+    fn test_split_and_reassemble() {
+        let payload: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let parts = MessagePart::split(&payload, 3_000);
+        assert_eq!(parts.len(), 5);
+
+        let mut transfer = reassemble(&parts).unwrap();
+        assert!(transfer.is_complete());
+        assert!(transfer.progress().is_complete());
+        assert_eq!(transfer.take_payload(), payload);
+    }
+
+    /// Tests that an empty payload is a manifest with no chunks
+    #[test]
+    fn test_empty_payload() {
+        let parts = MessagePart::split(&[], 100);
+        assert_eq!(parts.len(), 1);
+        let transfer = reassemble(&parts).unwrap();
+        assert!(transfer.is_complete());
+    }
+
+    /// Tests that out-of-order, foreign or tampered chunks are rejected
+    #[test]
+    fn test_invalid_chunks_rejected() {
+        let payload = vec![7u8; 1_000];
+        let mut parts = MessagePart::split(&payload, 300);
+
+        // out of order
+        parts.swap(1, 2);
+        assert_eq!(
+            reassemble(&parts).err(),
+            Some(SessionError::InvalidTransfer)
+        );
+        parts.swap(1, 2);
+
+        // tampered chunk
+        let tampered = vec![8u8; parts[2].1.len()];
+        parts[2].1 = &tampered;
+        assert_eq!(
+            reassemble(&parts).err(),
+            Some(SessionError::InvalidTransfer)
+        );
+
+        // chunk from another transfer
+        let other = MessagePart::split(&payload, 300);
+        let mut transfer = IncomingTransfer::from_manifest(&parts[0].0).unwrap();
+        assert_eq!(
+            transfer.push(&other[1].0, other[1].1).err(),
+            Some(SessionError::InvalidTransfer)
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Structured announcement user data.
+//!
+//! The user data of an announcement is free-form bytes. Clients that share
+//! profile information through it use [`AnnouncementUserData`], so that they
+//! read each other's: a version byte followed by a canonical bincode encoding
+//! of bounded fields.
+
+use crate::error::SessionError;
+use alloc::{string::String, vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// Version byte of the encoding.
+pub const ANNOUNCEMENT_USER_DATA_VERSION: u8 = 1;
+
+/// Maximum size of [`AnnouncementUserData::display_name`] in bytes.
+pub const MAX_DISPLAY_NAME_BYTES: usize = 64;
+
+/// Size of [`AnnouncementUserData::avatar_hash`].
+pub const AVATAR_HASH_SIZE: usize = 32;
+
+/// Profile information carried in announcement user data.
+///
+/// Fields added by later revisions of the encoding are appended, so that
+/// older clients still parse it and ignore them; the version byte only
+/// changes for incompatible revisions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnouncementUserData {
+    /// Version of the application protocol spoken by the announcer
+    pub protocol_version: u32,
+    /// Name to show for the announcer, at most [`MAX_DISPLAY_NAME_BYTES`]
+    /// bytes of UTF-8
+    pub display_name: String,
+    /// Hash of the announcer's avatar image, fetched out of band
+    pub avatar_hash: Option<[u8; AVATAR_HASH_SIZE]>,
+    /// Features supported by the announcer, as `CAPABILITY_*` bits
+    pub capabilities: u64,
+}
+
+impl AnnouncementUserData {
+    /// Receives chunked transfers of large messages.
+    pub const CAPABILITY_TRANSFERS: u64 = 1 << 0;
+    /// Publishes prekey bundles.
+    pub const CAPABILITY_PREKEYS: u64 = 1 << 1;
+    /// Reads user data sent as the first session message.
+    pub const CAPABILITY_SESSION_USER_DATA: u64 = 1 << 2;
+    /// Skips unknown control messages and answers pings sent with
+    /// `SessionManager::ping`.
+    pub const CAPABILITY_PINGS: u64 = 1 << 3;
+    /// Sends read receipts with `SessionManager::send_read_receipt`.
+    pub const CAPABILITY_READ_RECEIPTS: u64 = 1 << 4;
+
+    /// Returns whether all the given capability bits are set.
+    pub fn has_capability(&self, capability: u64) -> bool {
+        self.capabilities & capability == capability
+    }
+
+    /// Encodes as `[ANNOUNCEMENT_USER_DATA_VERSION, bincode fields]`, to pass
+    /// as announcement user data.
+    ///
+    /// Fails with [`SessionError::UserDataTooLarge`] if the display name is
+    /// longer than [`MAX_DISPLAY_NAME_BYTES`].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SessionError> {
+        if self.display_name.len() > MAX_DISPLAY_NAME_BYTES {
+            return Err(SessionError::UserDataTooLarge);
+        }
+        let mut bytes = vec![ANNOUNCEMENT_USER_DATA_VERSION];
+        bytes.extend(
+            bincode::serde::encode_to_vec(self, bincode::config::standard())
+                .expect("Failed to serialize announcement user data"),
+        );
+        Ok(bytes)
+    }
+
+    /// Parses the encoding of [`to_bytes`](Self::to_bytes), e.g. from
+    /// [`AnnouncementResult::user_data`](crate::AnnouncementResult::user_data).
+    /// Returns `None` for other user data, an unknown version or an oversized
+    /// display name.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&version, body) = bytes.split_first()?;
+        if version != ANNOUNCEMENT_USER_DATA_VERSION {
+            return None;
+        }
+        let (user_data, _): (Self, _) =
+            bincode::serde::decode_from_slice(body, bincode::config::standard()).ok()?;
+        if user_data.display_name.len() > MAX_DISPLAY_NAME_BYTES {
+            return None;
+        }
+        Some(user_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the encoding roundtrip and its limits
+    #[test]
+    fn test_announcement_user_data_roundtrip() {
+        let user_data = AnnouncementUserData {
+            protocol_version: 3,
+            display_name: "Alice".to_string(),
+            avatar_hash: Some([7u8; AVATAR_HASH_SIZE]),
+            capabilities: AnnouncementUserData::CAPABILITY_TRANSFERS
+                | AnnouncementUserData::CAPABILITY_PREKEYS,
+        };
+        let bytes = user_data.to_bytes().unwrap();
+        assert_eq!(bytes[0], ANNOUNCEMENT_USER_DATA_VERSION);
+        assert_eq!(bytes, user_data.to_bytes().unwrap());
+        let parsed = AnnouncementUserData::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, user_data);
+        assert!(parsed.has_capability(AnnouncementUserData::CAPABILITY_PREKEYS));
+        assert!(!parsed.has_capability(AnnouncementUserData::CAPABILITY_SESSION_USER_DATA));
+
+        // fields appended by later revisions are ignored
+        let mut extended = bytes.clone();
+        extended.extend_from_slice(&[1, 2, 3]);
+        assert_eq!(AnnouncementUserData::from_bytes(&extended), Some(user_data));
+
+        assert_eq!(AnnouncementUserData::from_bytes(b"{\"name\":\"x\"}"), None);
+        assert_eq!(
+            AnnouncementUserData::from_bytes(&bytes[..bytes.len() - 1]),
+            None
+        );
+
+        let oversized = AnnouncementUserData {
+            display_name: "x".repeat(MAX_DISPLAY_NAME_BYTES + 1),
+            ..AnnouncementUserData::default()
+        };
+        assert_eq!(oversized.to_bytes(), Err(SessionError::UserDataTooLarge));
+    }
+}
@@ -0,0 +1,134 @@
+//! Compatibility of persisted `SessionManager` blobs.
+//!
+//! `tests/fixtures/session_manager_v<N>.bin` are blobs written by format
+//! version `N`: Alice's manager with an active session with Bob, encrypted
+//! with [`fixture_key`]. Every format version must keep loading. To add the
+//! fixture of a new version, run
+//! `cargo test -p sessions --test blob_compat -- --ignored`. The version 0
+//! fixture comes from a build of the release before versioning, see
+//! `scripts/generate-session-fixture-v0.sh`.
+
+use auth::{StaticRootSecret, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret};
+use sessions::{SessionManager, SessionManagerConfig, SessionStatus};
+
+/// PQ crypto operations need large stack frames; run every test on a 16 MiB thread.
+fn run<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn fixture_key() -> crypto_aead::Key {
+    crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE])
+}
+
+fn keypair(passphrase: &[u8]) -> (UserPublicKeys, UserSecretKeys) {
+    derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(passphrase))
+}
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: u128::MAX,
+        max_incoming_message_age_millis: u128::MAX,
+        max_session_inactivity_millis: u128::MAX,
+        keep_alive_interval_millis: 60_000,
+        max_session_lag_length: 100,
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+/// Reads the fixture of format `version`.
+fn fixture(version: u8) -> Vec<u8> {
+    let path = format!(
+        "{}/tests/fixtures/session_manager_v{version}.bin",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    std::fs::read(&path).unwrap_or_else(|_| match version {
+        0 => panic!("{path} is missing, run scripts/generate-session-fixture-v0.sh"),
+        _ => panic!(
+            "{path} is missing, run `cargo test -p sessions --test blob_compat -- --ignored`"
+        ),
+    })
+}
+
+/// Tests that blobs of every format version load, and save as the current one
+#[test]
+fn fixtures_load() {
+    run(|| {
+        for version in 0..=sessions::STATE_FORMAT_VERSION {
+            check_fixture(version, &fixture(version));
+        }
+    });
+}
+
+/// Loads a fixture of format `version`, checks Alice's session with Bob, and
+/// round trips it through the current format.
+fn check_fixture(version: u8, blob: &[u8]) {
+    let (bob_pk, _) = keypair(b"bob");
+    let bob_id = bob_pk.derive_id();
+
+    let mut manager = SessionManager::from_encrypted_blob(blob, &fixture_key())
+        .unwrap_or_else(|| panic!("fixture v{version} failed to load"));
+    assert_eq!(manager.peer_list(), vec![bob_id.clone()]);
+    assert!(matches!(
+        manager.peer_session_status(&bob_id),
+        SessionStatus::Active
+    ));
+    assert!(manager.send_message(&bob_id, b"hello").is_some());
+
+    // round trip through the current format
+    let blob = manager.to_encrypted_blob(&fixture_key()).unwrap();
+    let manager = SessionManager::from_encrypted_blob(&blob, &fixture_key()).unwrap();
+    assert_eq!(manager.peer_list(), vec![bob_id]);
+}
+
+/// Tests that blobs of an unknown future version are rejected
+#[test]
+fn future_version_rejected() {
+    run(|| {
+        let manager = SessionManager::new(config());
+        let blob = manager.to_encrypted_blob(&fixture_key()).unwrap();
+        assert!(SessionManager::from_encrypted_blob(&blob, &fixture_key()).is_some());
+
+        let nonce = crypto_aead::Nonce::from([0u8; crypto_aead::NONCE_SIZE]);
+        let plaintext = [0xff, sessions::STATE_FORMAT_VERSION + 1, 0];
+        let ciphertext = crypto_aead::encrypt(&fixture_key(), &nonce, &plaintext, b"");
+        let blob = [nonce.as_bytes().as_slice(), &ciphertext].concat();
+        assert!(SessionManager::from_encrypted_blob(&blob, &fixture_key()).is_none());
+    });
+}
+
+/// Writes the fixture of the current format version.
+#[test]
+#[ignore]
+fn generate_fixture() {
+    run(|| {
+        let (alice_pk, alice_sk) = keypair(b"alice");
+        let (bob_pk, bob_sk) = keypair(b"bob");
+        let mut alice_manager = SessionManager::new(config());
+        let mut bob_manager = SessionManager::new(config());
+
+        let to_bob = alice_manager
+            .establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![])
+            .unwrap();
+        let to_alice = bob_manager
+            .establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![])
+            .unwrap();
+        bob_manager.feed_incoming_announcement(&to_bob, &bob_pk, &bob_sk);
+        alice_manager.feed_incoming_announcement(&to_alice, &alice_pk, &alice_sk);
+
+        let blob = alice_manager.to_encrypted_blob(&fixture_key()).unwrap();
+        let dir = format!("{}/tests/fixtures", env!("CARGO_MANIFEST_DIR"));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = format!(
+            "{dir}/session_manager_v{}.bin",
+            sessions::STATE_FORMAT_VERSION
+        );
+        std::fs::write(path, blob).unwrap();
+    });
+}
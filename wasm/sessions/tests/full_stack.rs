@@ -0,0 +1,169 @@
+//! End-to-end test of two `SessionManager`s persisted in secure storage.
+//!
+//! Alice and Bob handshake and talk over an `InMemoryBoard`, save their
+//! managers into a `secureStorage` session with `SessionStore`, lock it,
+//! unlock it again and resume the conversation from the loaded managers.
+
+use auth::{StaticRootSecret, derive_keys_from_static_root_secret};
+use secure_storage::storage::MemoryStorage;
+use secure_storage::{
+    SessionIndex, UnlockedSession, allocate_session, provision_storage, unlock_session,
+};
+use sessions::testing::{BoardConditions, BoardUser, InMemoryBoard, handshake};
+use sessions::{MockClock, SessionManager, SessionManagerConfig, SessionStatus, SessionStore};
+
+const DOMAIN: &str = "full-stack";
+const NAMESPACE: u8 = 3;
+
+/// PQ crypto operations need large stack frames; run every test on a 16 MiB thread.
+fn run<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: 60_000,
+        max_incoming_message_age_millis: 60_000,
+        max_session_inactivity_millis: 3_600_000,
+        keep_alive_interval_millis: 60_000,
+        max_session_lag_length: 100,
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+/// A user with its own device storage.
+struct User {
+    board_user: BoardUser,
+    password: &'static [u8],
+    storage: MemoryStorage,
+    /// State encryption key of the manager
+    key: crypto_aead::Key,
+}
+
+impl User {
+    fn new(board: &InMemoryBoard, seed: u8, password: &'static [u8]) -> Self {
+        let (pk, sk) =
+            derive_keys_from_static_root_secret(&StaticRootSecret::from_bytes([seed; 32]));
+        let mut storage = MemoryStorage::new();
+        provision_storage(&mut storage).unwrap();
+        allocate_session(
+            &mut storage,
+            DOMAIN,
+            SessionIndex::new(0).unwrap(),
+            password,
+        )
+        .unwrap();
+        let mut manager = SessionManager::new(config());
+        manager.set_clock(Box::new(board.clock().clone()));
+        Self {
+            board_user: BoardUser::new(manager, pk, sk),
+            password,
+            storage,
+            key: crypto_aead::Key::from([seed; crypto_aead::KEY_SIZE]),
+        }
+    }
+
+    fn unlock(&self) -> UnlockedSession {
+        unlock_session(&self.storage, DOMAIN, self.password).unwrap()
+    }
+
+    /// Reads the board, returning the contents of the messages received.
+    fn read_messages(&mut self, board: &mut InMemoryBoard) -> Vec<Vec<u8>> {
+        self.board_user
+            .poll(board)
+            .iter()
+            .filter(|msg| !msg.message.is_empty())
+            .map(|msg| msg.message.clone())
+            .collect()
+    }
+
+    /// Saves the manager, locks the storage session and unlocks it again to
+    /// load the manager back.
+    fn save_lock_and_resume(&mut self, board: &InMemoryBoard) {
+        let session = self.unlock();
+        let mut store = SessionStore::open(&self.storage, DOMAIN, &session, NAMESPACE).unwrap();
+        store
+            .save(
+                &mut self.storage,
+                DOMAIN,
+                &session,
+                &mut self.board_user.manager,
+                &self.key,
+            )
+            .unwrap();
+        drop(session);
+        self.board_user.manager = SessionManager::new(config());
+
+        let session = self.unlock();
+        let store = SessionStore::open(&self.storage, DOMAIN, &session, NAMESPACE).unwrap();
+        let mut manager = store
+            .load(&self.storage, DOMAIN, &session, &self.key)
+            .unwrap()
+            .unwrap();
+        manager.set_clock(Box::new(board.clock().clone()));
+        self.board_user.manager = manager;
+    }
+}
+
+#[test]
+fn test_conversation_survives_lock_and_unlock() {
+    run(|| {
+        let conditions = BoardConditions {
+            min_latency_millis: 500,
+            max_latency_millis: 2_000,
+            ..Default::default()
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let mut board = InMemoryBoard::new(conditions, MockClock::new(now), 1);
+        let mut alice = User::new(&board, 1, b"alice password");
+        let mut bob = User::new(&board, 2, b"bob password");
+        let alice_id = alice.board_user.id();
+        let bob_id = bob.board_user.id();
+
+        assert!(handshake(&mut board, &mut alice.board_user, &mut bob.board_user).unwrap());
+        alice
+            .board_user
+            .send(&mut board, &bob_id, b"hello bob")
+            .unwrap();
+        board.clock().advance(conditions.max_latency_millis);
+        assert_eq!(bob.read_messages(&mut board), vec![b"hello bob".to_vec()]);
+
+        // both lock their storage and come back
+        alice.save_lock_and_resume(&board);
+        bob.save_lock_and_resume(&board);
+        assert!(matches!(
+            alice.board_user.manager.peer_session_status(&bob_id),
+            SessionStatus::Active
+        ));
+        assert!(matches!(
+            bob.board_user.manager.peer_session_status(&alice_id),
+            SessionStatus::Active
+        ));
+
+        // the conversation resumes where it stopped
+        bob.board_user
+            .send(&mut board, &alice_id, b"hi alice")
+            .unwrap();
+        board.clock().advance(conditions.max_latency_millis);
+        assert_eq!(alice.read_messages(&mut board), vec![b"hi alice".to_vec()]);
+        alice
+            .board_user
+            .send(&mut board, &bob_id, b"still there")
+            .unwrap();
+        board.clock().advance(conditions.max_latency_millis);
+        assert_eq!(bob.read_messages(&mut board), vec![b"still there".to_vec()]);
+
+        // and a wrong password does not unlock the storage
+        assert!(unlock_session(&alice.storage, DOMAIN, b"wrong password").is_err());
+    });
+}
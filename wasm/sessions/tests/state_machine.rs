@@ -0,0 +1,251 @@
+//! Property-based tests of two `SessionManager`s talking over a lossy board.
+//!
+//! Random sequences of handshakes, sends, refreshes, discards and clock steps
+//! run against Alice and Bob, who read the board in any order, read entries
+//! twice or never. After every step the managers must still be consistent.
+
+use std::collections::HashSet;
+
+use auth::{StaticRootSecret, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret};
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestRunner};
+use sessions::{MockClock, SessionManager, SessionManagerConfig, SessionStatus};
+
+/// Number of random sequences to run.
+const CASES: u32 = 64;
+
+/// Maximum number of steps in a sequence.
+const MAX_STEPS: usize = 60;
+
+const MAX_SESSION_LAG_LENGTH: u64 = 4;
+
+/// PQ crypto operations need large stack frames; run every test on a 16 MiB thread.
+fn run<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+/// Keys from a random root secret, skipping the password KDF.
+fn keypair() -> (UserPublicKeys, UserSecretKeys) {
+    let mut root_secret = [0u8; 32];
+    crypto_rng::fill_buffer(&mut root_secret);
+    derive_keys_from_static_root_secret(&StaticRootSecret::from_bytes(root_secret))
+}
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_incoming_announcement_age_millis: 60_000,
+        max_incoming_message_age_millis: 60_000,
+        max_session_inactivity_millis: 60_000,
+        keep_alive_interval_millis: 20_000,
+        max_session_lag_length: MAX_SESSION_LAG_LENGTH,
+        max_keep_alive_peer_lag_length: 2,
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+/// Step applied to one of the two users (`0` is Alice, `1` is Bob).
+#[derive(Debug, Clone)]
+enum Step {
+    /// Posts an announcement to the other user
+    Establish(usize),
+    /// Sends a message to the other user
+    Send(usize),
+    /// Refreshes, posting the keep-alives it produces
+    Refresh(usize),
+    /// Forgets the other user
+    Discard(usize),
+    /// Reads a board entry, picked modulo the board length; entries are never
+    /// removed, so they can be read again, out of order or never
+    Read(usize),
+    /// Moves the shared clock forward
+    Advance(u64),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        2 => (0..2usize).prop_map(Step::Establish),
+        4 => (0..2usize).prop_map(Step::Send),
+        1 => (0..2usize).prop_map(Step::Refresh),
+        1 => (0..2usize).prop_map(Step::Discard),
+        6 => any::<usize>().prop_map(Step::Read),
+        1 => (0..40_000u64).prop_map(Step::Advance),
+    ]
+}
+
+/// Entry posted on the board for a user.
+enum Post {
+    Announcement(Vec<u8>),
+    Message { seeker: Vec<u8>, data: Vec<u8> },
+}
+
+struct User {
+    pk: UserPublicKeys,
+    sk: UserSecretKeys,
+    manager: SessionManager,
+    /// Messages received so far, to detect duplicates
+    received: HashSet<Vec<u8>>,
+}
+
+struct World {
+    users: [User; 2],
+    /// Board entries with the index of the user they are addressed to
+    board: Vec<(usize, Post)>,
+    clock: MockClock,
+    next_message: u64,
+}
+
+impl World {
+    fn new() -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let clock = MockClock::new(now);
+        let users = [(), ()].map(|_| {
+            let (pk, sk) = keypair();
+            let mut manager = SessionManager::new(config());
+            manager.set_clock(Box::new(clock.clone()));
+            User {
+                pk,
+                sk,
+                manager,
+                received: HashSet::new(),
+            }
+        });
+        Self {
+            users,
+            board: Vec::new(),
+            clock,
+            next_message: 0,
+        }
+    }
+
+    fn apply(&mut self, step: &Step) -> Result<(), TestCaseError> {
+        match *step {
+            Step::Establish(side) => {
+                let peer_pk = self.users[1 - side].pk.clone();
+                let user = &mut self.users[side];
+                if let Ok(announcement) =
+                    user.manager
+                        .establish_outgoing_session(&peer_pk, &user.pk, &user.sk, vec![])
+                {
+                    self.board
+                        .push((1 - side, Post::Announcement(announcement)));
+                }
+            }
+            Step::Send(side) => {
+                let peer_id = self.users[1 - side].pk.derive_id();
+                let message = format!("{side}-{}", self.next_message).into_bytes();
+                self.next_message += 1;
+                if let Ok(sent) = self.users[side]
+                    .manager
+                    .try_send_message(&peer_id, &message)
+                {
+                    self.board.push((
+                        1 - side,
+                        Post::Message {
+                            seeker: sent.seeker.clone(),
+                            data: sent.data.clone(),
+                        },
+                    ));
+                }
+            }
+            Step::Refresh(side) => {
+                let (_, keep_alives) = self.users[side].manager.refresh_with_keepalives();
+                for (_, sent) in keep_alives {
+                    self.board.push((
+                        1 - side,
+                        Post::Message {
+                            seeker: sent.seeker.clone(),
+                            data: sent.data.clone(),
+                        },
+                    ));
+                }
+            }
+            Step::Discard(side) => {
+                let peer_id = self.users[1 - side].pk.derive_id();
+                self.users[side].manager.peer_discard(&peer_id);
+            }
+            Step::Read(index) => {
+                if self.board.is_empty() {
+                    return Ok(());
+                }
+                let (side, post) = &self.board[index % self.board.len()];
+                let user = &mut self.users[*side];
+                match post {
+                    Post::Announcement(announcement) => {
+                        let _ = user.manager.try_feed_incoming_announcement(
+                            announcement,
+                            &user.pk,
+                            &user.sk,
+                        );
+                    }
+                    Post::Message { seeker, data } => {
+                        if let Ok(output) = user
+                            .manager
+                            .try_feed_incoming_message_board_read(seeker, data, &user.sk)
+                            && output.control.is_none()
+                        {
+                            prop_assert!(
+                                user.received.insert(output.message.clone()),
+                                "message received twice"
+                            );
+                        }
+                    }
+                }
+            }
+            Step::Advance(millis) => self.clock.advance(millis),
+        }
+        Ok(())
+    }
+
+    fn check_invariants(&self) -> Result<(), TestCaseError> {
+        for (side, user) in self.users.iter().enumerate() {
+            let peer_id = self.users[1 - side].pk.derive_id();
+            let read_keys = user.manager.get_message_board_read_keys_map();
+            if matches!(
+                user.manager.peer_session_status(&peer_id),
+                SessionStatus::Active
+            ) {
+                prop_assert!(
+                    read_keys.values().any(|id| *id == peer_id),
+                    "active session without a seeker"
+                );
+            }
+            prop_assert!(read_keys.values().all(|id| *id == peer_id));
+            if let Some(lag) = user.manager.peer_lag(&peer_id) {
+                prop_assert!(lag.unacked_count <= MAX_SESSION_LAG_LENGTH);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tests that random interleavings keep both managers consistent
+#[test]
+fn random_interleavings_keep_invariants() {
+    run(|| {
+        let mut runner = TestRunner::new(Config {
+            cases: CASES,
+            failure_persistence: None,
+            ..Config::default()
+        });
+        runner
+            .run(&prop::collection::vec(step(), 1..MAX_STEPS), |steps| {
+                let mut world = World::new();
+                for step in &steps {
+                    world.apply(step)?;
+                    world.check_invariants()?;
+                }
+                Ok(())
+            })
+            .unwrap();
+    });
+}
@@ -0,0 +1,111 @@
+//! Deterministic test vectors of the wire formats.
+//!
+//! Every random byte comes from the seeded keystream of
+//! `crypto_rng::with_seed` (ChaCha20 of RFC 8439 keyed with [`SEED`], zero
+//! nonce, counter from 0) and every timestamp from a clock stopped at
+//! [`NOW_MILLIS`], so the same steps replayed by another implementation must
+//! yield the same bytes. Alice and Bob derive their keys from the passphrases
+//! `alice` and `bob`, Alice announces to Bob, Bob announces back, Alice sends
+//! `hello`, and her state is saved encrypted with the key `[7; 32]`.
+//!
+//! `tests/vectors/wire_formats.txt` holds one `name: hex` line per vector.
+//! Write it with `cargo test -p sessions --test test_vectors -- --ignored`
+//! whenever a wire format changes.
+
+use auth::{StaticRootSecret, UserPublicKeys, UserSecretKeys, derive_keys_from_static_root_secret};
+use sessions::{MockClock, SessionManager, SessionManagerConfig};
+
+const SEED: [u8; 32] = [0x42; 32];
+const NOW_MILLIS: u64 = 1_700_000_000_000;
+
+/// PQ crypto operations need large stack frames; run every test on a 16 MiB thread.
+fn run<F: FnOnce() + Send + 'static>(f: F) {
+    std::thread::Builder::new()
+        .stack_size(16 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn keypair(passphrase: &[u8]) -> (UserPublicKeys, UserSecretKeys) {
+    derive_keys_from_static_root_secret(&StaticRootSecret::from_passphrase(passphrase))
+}
+
+fn config() -> SessionManagerConfig {
+    SessionManagerConfig {
+        max_session_lag_length: 100,
+        announcement_cache_size: 0,
+        announcement_cache_max_age_millis: 0,
+        ..Default::default()
+    }
+}
+
+/// Returns the named vectors, in file order.
+fn vectors() -> Vec<(&'static str, Vec<u8>)> {
+    crypto_rng::with_seed(SEED, || {
+        let (alice_pk, alice_sk) = keypair(b"alice");
+        let (bob_pk, bob_sk) = keypair(b"bob");
+        let clock = MockClock::new(NOW_MILLIS);
+        let mut alice_manager = SessionManager::with_clock(config(), Box::new(clock.clone()));
+        let mut bob_manager = SessionManager::with_clock(config(), Box::new(clock.clone()));
+
+        let to_bob = alice_manager
+            .establish_outgoing_session(&bob_pk, &alice_pk, &alice_sk, vec![])
+            .unwrap();
+        clock.advance(1_000);
+        let to_alice = bob_manager
+            .establish_outgoing_session(&alice_pk, &bob_pk, &bob_sk, vec![])
+            .unwrap();
+        bob_manager.feed_incoming_announcement(&to_bob, &bob_pk, &bob_sk);
+        alice_manager.feed_incoming_announcement(&to_alice, &alice_pk, &alice_sk);
+
+        clock.advance(1_000);
+        let message = alice_manager
+            .send_message(&bob_pk.derive_id(), b"hello")
+            .unwrap();
+        let key = crypto_aead::Key::from([7u8; crypto_aead::KEY_SIZE]);
+        let blob = alice_manager.to_encrypted_blob(&key).unwrap();
+
+        vec![
+            ("alice_user_id", alice_pk.derive_id().as_bytes().to_vec()),
+            ("alice_public_keys", alice_pk.to_bytes()),
+            ("bob_user_id", bob_pk.derive_id().as_bytes().to_vec()),
+            ("bob_public_keys", bob_pk.to_bytes()),
+            ("announcement_alice_to_bob", to_bob),
+            ("announcement_bob_to_alice", to_alice),
+            ("message_seeker", message.seeker.clone()),
+            ("message_data", message.data.clone()),
+            ("session_manager_blob", blob),
+        ]
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Tests that the vectors do not depend on anything but the seed and clock
+#[test]
+fn vectors_are_deterministic() {
+    run(|| {
+        let first = vectors();
+        assert_eq!(first, vectors());
+        assert!(first.iter().all(|(_, bytes)| !bytes.is_empty()));
+    });
+}
+
+/// Writes `tests/vectors/wire_formats.txt`.
+#[test]
+#[ignore]
+fn generate_vectors() {
+    run(|| {
+        let mut contents = String::new();
+        for (name, bytes) in vectors() {
+            contents.push_str(&format!("{name}: {}\n", to_hex(&bytes)));
+        }
+        let dir = format!("{}/tests/vectors", env!("CARGO_MANIFEST_DIR"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(format!("{dir}/wire_formats.txt"), contents).unwrap();
+    });
+}